@@ -2,8 +2,17 @@
 //! runs Wasm components.
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use greentic_types::TenantCtx;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, de};
+
+use crate::error::ExecError;
+use crate::host_calls::HostCallTape;
+use crate::kv::KvStore;
+use crate::retry_store::RetryStore;
 use crate::store::ToolStore;
 
 /// Configuration for a single executor invocation.
@@ -12,11 +21,24 @@ pub struct ExecConfig {
     pub store: ToolStore,
     pub security: VerifyPolicy,
     pub runtime: RuntimePolicy,
+    /// Policies used instead of `runtime` for requests from these tenants, keyed by
+    /// tenant id.
+    pub tenant_runtime: HashMap<String, RuntimePolicy>,
     pub http_enabled: bool,
 }
 
+impl ExecConfig {
+    /// Runtime policy for a request from `tenant`: its override, if it has one.
+    pub fn runtime_for(&self, tenant: Option<&TenantCtx>) -> &RuntimePolicy {
+        tenant
+            .and_then(|tenant| self.tenant_runtime.get(tenant.tenant_id.as_str()))
+            .unwrap_or(&self.runtime)
+    }
+}
+
 /// Policy describing how artifacts must be verified prior to execution.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct VerifyPolicy {
     /// Whether artifacts without a matching digest/signature are still allowed.
     pub allow_unverified: bool,
@@ -32,9 +54,38 @@ pub struct RuntimePolicy {
     pub fuel: Option<u64>,
     pub max_memory: Option<u64>,
     pub wallclock_timeout: Duration,
+    /// Upper bound on a single attempt.
     pub per_call_timeout: Duration,
+    /// Upper bound on a whole retried invocation, including backoff.
+    pub total_timeout: Option<Duration>,
     pub max_attempts: u32,
     pub base_backoff: Duration,
+    /// How the delay between attempts grows from `base_backoff`.
+    pub retry_policy: RetryPolicy,
+    /// Stop retrying once this much time has passed since the first attempt started.
+    pub max_retry_duration: Option<Duration>,
+    /// Budget shared by every call using this policy; retries are refused once spent.
+    pub retry_budget: Option<Arc<RetryBudget>>,
+    /// Integrator rules deciding which errors are safe to retry.
+    pub retry_classifier: RetryClassifier,
+    /// Receives a [`RetryEvent`] for every step of the retry loop.
+    pub retry_observer: Option<RetryObserver>,
+    /// Also place the invocation's idempotency key in object args as
+    /// `_meta.idempotency_key`, so guests can deduplicate side effects across retries.
+    pub inject_idempotency_key: bool,
+    /// Pass the request's tenant to the guest as `_meta.tenant` in object args.
+    pub inject_tenant: bool,
+    /// Persists retry progress so invocations with a caller-supplied idempotency key
+    /// can resume after a restart.
+    pub retry_store: Option<Arc<dyn RetryStore>>,
+    /// Backend for the `kv_get`/`kv_put` host functions; without one reads miss and
+    /// writes are dropped.
+    pub kv_store: Option<Arc<dyn KvStore>>,
+    /// Hosts the `http_request` host function may reach; any host when unset. Give
+    /// tenants their own list through [`ExecConfig::tenant_runtime`].
+    pub http_allowlist: Option<HttpAllowlist>,
+    /// Records the guest's host calls, or answers them from an earlier recording.
+    pub host_calls: Option<Arc<HostCallTape>>,
 }
 
 impl Default for RuntimePolicy {
@@ -44,8 +95,581 @@ impl Default for RuntimePolicy {
             max_memory: None,
             wallclock_timeout: Duration::from_secs(30),
             per_call_timeout: Duration::from_secs(10),
+            total_timeout: None,
             max_attempts: 1,
             base_backoff: Duration::from_millis(100),
+            retry_policy: RetryPolicy::default(),
+            max_retry_duration: None,
+            retry_budget: None,
+            retry_classifier: RetryClassifier::default(),
+            retry_observer: None,
+            inject_idempotency_key: false,
+            inject_tenant: false,
+            retry_store: None,
+            kv_store: None,
+            http_allowlist: None,
+            host_calls: None,
+        }
+    }
+}
+
+/// Hosts reachable through the `http_request` host function.
+///
+/// Entries match a host exactly, or with a `*.` prefix any subdomain of it, e.g.
+/// `*.crm.example.com`. Hosts are compared case-insensitively.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpAllowlist {
+    hosts: Vec<String>,
+}
+
+impl HttpAllowlist {
+    pub fn new<I, S>(hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            hosts: hosts
+                .into_iter()
+                .map(|host| host.into().to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Whether `host` matches one of the entries.
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.hosts
+            .iter()
+            .any(|entry| match entry.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                None => *entry == host,
+            })
+    }
+}
+
+/// How retry delays are computed: a growth strategy plus the jitter applied on top.
+///
+/// Serialized flat, e.g. `{ strategy: exponential_capped, max_delay_ms: 2000, jitter: full }`.
+/// Unknown keys are rejected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct RetryPolicy {
+    #[serde(flatten)]
+    pub backoff: BackoffStrategy,
+    /// Randomization applied to each delay; `proportional` (±50%) when unset.
+    #[serde(default, skip_serializing_if = "Jitter::is_default")]
+    pub jitter: Jitter,
+}
+
+impl RetryPolicy {
+    /// Policy using `backoff` with the default jitter.
+    pub fn new(backoff: BackoffStrategy) -> Self {
+        Self {
+            backoff,
+            jitter: Jitter::default(),
+        }
+    }
+
+    /// Replace the jitter strategy.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delay before retry number `attempt` (zero-based), without jitter.
+    pub fn delay(&self, base: Duration, attempt: u32) -> Duration {
+        self.backoff.delay(base, attempt)
+    }
+
+    /// Upper bound on any delay this policy produces, if it has one.
+    pub fn max_delay(&self) -> Option<Duration> {
+        self.backoff.max_delay()
+    }
+}
+
+impl From<BackoffStrategy> for RetryPolicy {
+    fn from(backoff: BackoffStrategy) -> Self {
+        Self::new(backoff)
+    }
+}
+
+impl<'de> Deserialize<'de> for RetryPolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawRetryPolicy::deserialize(deserializer)?;
+        Ok(Self {
+            backoff: raw.backoff()?,
+            jitter: raw.jitter.unwrap_or_default(),
+        })
+    }
+}
+
+/// Strategy for growing the delay between retry attempts from a base backoff.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum BackoffStrategy {
+    /// Wait the base backoff before every retry.
+    Fixed,
+    /// Wait `base * n` before the n-th retry.
+    Linear,
+    /// Double the delay after every attempt.
+    #[default]
+    Exponential,
+    /// Double the delay after every attempt, never waiting longer than `max_delay_ms`.
+    ExponentialCapped { max_delay_ms: u64 },
+    /// Grow the delay along the Fibonacci sequence (`base * 1, 1, 2, 3, 5, ...`).
+    Fibonacci,
+}
+
+impl BackoffStrategy {
+    /// Delay before retry number `attempt` (zero-based), without jitter.
+    pub fn delay(&self, base: Duration, attempt: u32) -> Duration {
+        let factor: u32 = match self {
+            BackoffStrategy::Fixed => 1,
+            BackoffStrategy::Linear => attempt.saturating_add(1),
+            BackoffStrategy::Exponential | BackoffStrategy::ExponentialCapped { .. } => {
+                1u32 << attempt.min(16)
+            }
+            BackoffStrategy::Fibonacci => fibonacci(attempt.saturating_add(1)),
+        };
+        let delay = base.saturating_mul(factor);
+        match self {
+            BackoffStrategy::ExponentialCapped { max_delay_ms } => {
+                delay.min(Duration::from_millis(*max_delay_ms))
+            }
+            _ => delay,
+        }
+    }
+
+    /// Upper bound on any delay this strategy produces, if it has one.
+    pub fn max_delay(&self) -> Option<Duration> {
+        match self {
+            BackoffStrategy::ExponentialCapped { max_delay_ms } => {
+                Some(Duration::from_millis(*max_delay_ms))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BackoffStrategy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawRetryPolicy::deserialize(deserializer)?;
+        if raw.jitter.is_some() {
+            return Err(de::Error::unknown_field(
+                "jitter",
+                &["strategy", "max_delay_ms"],
+            ));
+        }
+        raw.backoff()
+    }
+}
+
+/// Wire form of [`RetryPolicy`] and [`BackoffStrategy`]. Serde cannot reject unknown
+/// keys through `flatten` or unit variants of a tagged enum, so both read this.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRetryPolicy {
+    strategy: StrategyName,
+    #[serde(default)]
+    max_delay_ms: Option<u64>,
+    #[serde(default)]
+    jitter: Option<Jitter>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StrategyName {
+    Fixed,
+    Linear,
+    Exponential,
+    ExponentialCapped,
+    Fibonacci,
+}
+
+impl RawRetryPolicy {
+    fn backoff<E: de::Error>(&self) -> Result<BackoffStrategy, E> {
+        let backoff = match (self.strategy, self.max_delay_ms) {
+            (StrategyName::ExponentialCapped, Some(max_delay_ms)) => {
+                return Ok(BackoffStrategy::ExponentialCapped { max_delay_ms });
+            }
+            (StrategyName::ExponentialCapped, None) => {
+                return Err(E::missing_field("max_delay_ms"));
+            }
+            (_, Some(_)) => {
+                return Err(E::custom(
+                    "`max_delay_ms` only applies to the `exponential_capped` strategy",
+                ));
+            }
+            (StrategyName::Fixed, None) => BackoffStrategy::Fixed,
+            (StrategyName::Linear, None) => BackoffStrategy::Linear,
+            (StrategyName::Exponential, None) => BackoffStrategy::Exponential,
+            (StrategyName::Fibonacci, None) => BackoffStrategy::Fibonacci,
+        };
+        Ok(backoff)
+    }
+}
+
+/// Randomization applied to retry delays so that clients failing together do not
+/// retry in lockstep.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Jitter {
+    /// Use the computed delay as is.
+    None,
+    /// Scale the delay by a random factor in `[0.5, 1.5]`.
+    #[default]
+    Proportional,
+    /// Pick uniformly from `[0, delay]`.
+    Full,
+    /// Keep half the delay and pick the other half uniformly from `[0, delay / 2]`.
+    Equal,
+    /// Pick uniformly from `[base, previous * 3]`, ignoring the growth strategy except
+    /// for its cap.
+    Decorrelated,
+}
+
+impl Jitter {
+    fn is_default(&self) -> bool {
+        *self == Jitter::default()
+    }
+}
+
+/// Token bucket limiting retries to a fraction of overall calls.
+///
+/// Every call deposits `ratio` tokens and every retry spends one, so over time retries
+/// stay below `ratio` of the call volume plus `burst`. Share one budget (via `Arc`) across
+/// everything that should be limited together.
+#[derive(Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    burst: f64,
+    tokens: Mutex<f64>,
+}
+
+impl RetryBudget {
+    /// Allow retries for up to `ratio` (e.g. `0.2`) of calls, plus an initial `burst`.
+    pub fn new(ratio: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            ratio: ratio.max(0.0),
+            burst,
+            tokens: Mutex::new(burst),
+        }
+    }
+
+    /// Record a first attempt, earning retry tokens.
+    pub fn record_call(&self) {
+        let mut tokens = self.tokens.lock().expect("retry budget lock poisoned");
+        *tokens = (*tokens + self.ratio).min(self.burst);
+    }
+
+    /// Spend a token for a retry, returning `false` when the budget is exhausted.
+    pub fn try_acquire(&self) -> bool {
+        let mut tokens = self.tokens.lock().expect("retry budget lock poisoned");
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Tokens currently available.
+    pub fn available(&self) -> f64 {
+        *self.tokens.lock().expect("retry budget lock poisoned")
+    }
+}
+
+type ClassifyFn = dyn Fn(&ExecError) -> Option<bool> + Send + Sync;
+
+/// Decides whether an error may be retried, ahead of the built-in rules.
+///
+/// Code rules match the `code` of [`ExecError::Tool`] exactly, or by prefix when they end
+/// in `*` (e.g. `upstream.*`). The first matching rule wins; a custom function, when set,
+/// is consulted before the rules. Errors nobody claims fall back to the executor defaults.
+#[derive(Clone, Default)]
+pub struct RetryClassifier {
+    rules: Vec<(String, bool)>,
+    custom: Option<Arc<ClassifyFn>>,
+}
+
+impl RetryClassifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat tool errors whose code matches `pattern` as retryable.
+    pub fn retry_code(mut self, pattern: impl Into<String>) -> Self {
+        self.rules.push((pattern.into(), true));
+        self
+    }
+
+    /// Never retry tool errors whose code matches `pattern`.
+    pub fn never_retry_code(mut self, pattern: impl Into<String>) -> Self {
+        self.rules.push((pattern.into(), false));
+        self
+    }
+
+    /// Consult `classify` first; returning `None` defers to the code rules and defaults.
+    pub fn with_fn<F>(mut self, classify: F) -> Self
+    where
+        F: Fn(&ExecError) -> Option<bool> + Send + Sync + 'static,
+    {
+        self.custom = Some(Arc::new(classify));
+        self
+    }
+
+    /// Whether `err` is retryable, or `None` when no rule applies.
+    pub fn classify(&self, err: &ExecError) -> Option<bool> {
+        if let Some(verdict) = self.custom.as_ref().and_then(|custom| custom(err)) {
+            return Some(verdict);
+        }
+        let ExecError::Tool { code, .. } = err else {
+            return None;
+        };
+        self.rules
+            .iter()
+            .find(|(pattern, _)| match pattern.strip_suffix('*') {
+                Some(prefix) => code.starts_with(prefix),
+                None => code == pattern,
+            })
+            .map(|(_, retry)| *retry)
+    }
+}
+
+impl std::fmt::Debug for RetryClassifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryClassifier")
+            .field("rules", &self.rules)
+            .field("custom", &self.custom.is_some())
+            .finish()
+    }
+}
+
+/// Step in the life of a retried invocation. Attempts are numbered from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RetryEvent {
+    AttemptStarted {
+        tool: String,
+        attempt: u32,
+    },
+    AttemptFailed {
+        tool: String,
+        attempt: u32,
+        /// Error class, e.g. a tool error code, `timeout`, or `transient`.
+        class: String,
+        retryable: bool,
+        message: String,
+    },
+    BackingOff {
+        tool: String,
+        attempt: u32,
+        delay: Duration,
+    },
+    Succeeded {
+        tool: String,
+        attempts: u32,
+    },
+    GaveUp {
+        tool: String,
+        attempts: u32,
+        reason: GiveUpReason,
+    },
+}
+
+/// Why a retry loop stopped without a successful attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GiveUpReason {
+    /// The error was not classified as retryable.
+    NotRetryable,
+    /// Every allowed attempt failed.
+    AttemptsExhausted,
+    /// Another attempt would not finish before the retry deadline.
+    Deadline,
+    /// The shared [`RetryBudget`] had no tokens left.
+    Budget,
+}
+
+impl GiveUpReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GiveUpReason::NotRetryable => "not_retryable",
+            GiveUpReason::AttemptsExhausted => "attempts_exhausted",
+            GiveUpReason::Deadline => "deadline",
+            GiveUpReason::Budget => "budget",
+        }
+    }
+}
+
+/// Callback receiving [`RetryEvent`]s, e.g. to feed dashboards.
+#[derive(Clone)]
+pub struct RetryObserver(Arc<dyn Fn(&RetryEvent) + Send + Sync>);
+
+impl RetryObserver {
+    pub fn new<F>(observe: F) -> Self
+    where
+        F: Fn(&RetryEvent) + Send + Sync + 'static,
+    {
+        Self(Arc::new(observe))
+    }
+
+    pub fn notify(&self, event: &RetryEvent) {
+        (self.0)(event)
+    }
+}
+
+impl std::fmt::Debug for RetryObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RetryObserver(..)")
+    }
+}
+
+fn fibonacci(n: u32) -> u32 {
+    let (mut current, mut next) = (0u32, 1u32);
+    for _ in 0..n {
+        (current, next) = (next, current.saturating_add(next));
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_policy_delays() {
+        let base = Duration::from_millis(100);
+        let delays = |policy: BackoffStrategy| {
+            (0..5)
+                .map(|attempt| policy.delay(base, attempt).as_millis())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(delays(BackoffStrategy::Fixed), [100, 100, 100, 100, 100]);
+        assert_eq!(delays(BackoffStrategy::Linear), [100, 200, 300, 400, 500]);
+        assert_eq!(
+            delays(BackoffStrategy::Exponential),
+            [100, 200, 400, 800, 1600]
+        );
+        assert_eq!(
+            delays(BackoffStrategy::ExponentialCapped { max_delay_ms: 500 }),
+            [100, 200, 400, 500, 500]
+        );
+        assert_eq!(
+            delays(BackoffStrategy::Fibonacci),
+            [100, 100, 200, 300, 500]
+        );
+    }
+
+    #[test]
+    fn retry_policy_serializes_flat() {
+        let policy = RetryPolicy::new(BackoffStrategy::ExponentialCapped { max_delay_ms: 500 })
+            .with_jitter(Jitter::Decorrelated);
+        let value = serde_json::to_value(policy).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "strategy": "exponential_capped",
+                "max_delay_ms": 500,
+                "jitter": "decorrelated"
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<RetryPolicy>(value).unwrap(),
+            policy
+        );
+
+        let plain: RetryPolicy =
+            serde_json::from_value(serde_json::json!({ "strategy": "fixed" })).unwrap();
+        assert_eq!(plain, RetryPolicy::new(BackoffStrategy::Fixed));
+        assert_eq!(plain.jitter, Jitter::Proportional);
+
+        let rejected = [
+            serde_json::json!({ "strategy": "fixed", "jiter": "full" }),
+            serde_json::json!({ "strategy": "linear", "max_delay_ms": 500 }),
+            serde_json::json!({ "strategy": "exponential_capped" }),
+        ];
+        for value in rejected {
+            assert!(
+                serde_json::from_value::<RetryPolicy>(value.clone()).is_err(),
+                "{value}"
+            );
+        }
+        let err = serde_json::from_value::<BackoffStrategy>(
+            serde_json::json!({ "strategy": "fixed", "jitter": "full" }),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("jitter"), "{err}");
+        assert_eq!(
+            serde_json::from_value::<BackoffStrategy>(serde_json::json!({ "strategy": "linear" }))
+                .unwrap(),
+            BackoffStrategy::Linear
+        );
+    }
+
+    #[test]
+    fn classifier_matches_codes_in_order() {
+        let classifier = RetryClassifier::new()
+            .never_retry_code("upstream.auth")
+            .retry_code("upstream.*")
+            .retry_code("rate_limited");
+        let tool = |code: &str| ExecError::tool_error("c", "a", code, serde_json::Value::Null);
+
+        assert_eq!(classifier.classify(&tool("rate_limited")), Some(true));
+        assert_eq!(classifier.classify(&tool("upstream.503")), Some(true));
+        assert_eq!(classifier.classify(&tool("upstream.auth")), Some(false));
+        assert_eq!(classifier.classify(&tool("bad_input")), None);
+
+        let custom = classifier.with_fn(|_| Some(false));
+        assert_eq!(custom.classify(&tool("rate_limited")), Some(false));
+    }
+
+    #[test]
+    fn tenants_get_their_runtime_override() {
+        let premium = RuntimePolicy {
+            fuel: Some(1_000_000),
+            ..RuntimePolicy::default()
+        };
+        let cfg = ExecConfig {
+            store: ToolStore::LocalDir(std::env::temp_dir()),
+            security: VerifyPolicy::default(),
+            runtime: RuntimePolicy {
+                fuel: Some(1_000),
+                ..RuntimePolicy::default()
+            },
+            tenant_runtime: HashMap::from([("acme".to_string(), premium)]),
+            http_enabled: false,
+        };
+        let tenant = |id: &str| TenantCtx::new("dev".try_into().unwrap(), id.try_into().unwrap());
+
+        assert_eq!(cfg.runtime_for(Some(&tenant("acme"))).fuel, Some(1_000_000));
+        assert_eq!(cfg.runtime_for(Some(&tenant("free"))).fuel, Some(1_000));
+        assert_eq!(cfg.runtime_for(None).fuel, Some(1_000));
+    }
+
+    #[test]
+    fn http_allowlist_matches_hosts_and_subdomains() {
+        let allowlist = HttpAllowlist::new(["api.example.com", "*.crm.example.com"]);
+        assert!(allowlist.allows("API.example.com"));
+        assert!(allowlist.allows("eu.crm.example.com"));
+        assert!(!allowlist.allows("crm.example.com"));
+        assert!(!allowlist.allows("evilcrm.example.com"));
+        assert!(!allowlist.allows("example.com"));
+    }
+
+    #[test]
+    fn retry_budget_limits_retries_to_ratio_of_calls() {
+        let budget = RetryBudget::new(0.2, 2);
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+
+        for _ in 0..5 {
+            budget.record_call();
         }
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
     }
 }
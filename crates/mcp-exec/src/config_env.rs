@@ -0,0 +1,171 @@
+//! `GREENTIC_MCP_*` environment overrides layered over an [`ExecConfig`], so
+//! container deployments can be tuned without rebuilding or editing files.
+//!
+//! | Variable                            | Overrides                              |
+//! |-------------------------------------|----------------------------------------|
+//! | `GREENTIC_MCP_STORE_DIR`            | `store`, replaced by a local directory |
+//! | `GREENTIC_MCP_CACHE_DIR`            | `cache_dir` of an HTTP store           |
+//! | `GREENTIC_MCP_HTTP_ENABLED`         | `http_enabled`                         |
+//! | `GREENTIC_MCP_PER_CALL_TIMEOUT_MS`  | `per_call_timeout`                     |
+//! | `GREENTIC_MCP_TOTAL_TIMEOUT_MS`     | `total_timeout`                        |
+//! | `GREENTIC_MCP_MAX_ATTEMPTS`         | `max_attempts`                         |
+//!
+//! Runtime variables apply to `runtime` and to every `tenant_runtime` policy.
+//! Empty variables are ignored.
+
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+use crate::config::{ExecConfig, RuntimePolicy};
+use crate::store::ToolStore;
+
+const ENV_PREFIX: &str = "GREENTIC_MCP_";
+
+impl ExecConfig {
+    /// Apply `GREENTIC_MCP_*` environment variables on top of this config, e.g.
+    /// one returned by [`ExecConfig::from_path`]. Unparsable values are errors
+    /// naming the variable.
+    pub fn with_env_overrides(self) -> Result<Self> {
+        self.with_overrides_from(|name| std::env::var(name).ok())
+    }
+
+    fn with_overrides_from(mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| {
+            lookup(&format!("{ENV_PREFIX}{name}")).filter(|value| !value.trim().is_empty())
+        };
+
+        if let Some(dir) = var("STORE_DIR") {
+            self.store = ToolStore::LocalDir(PathBuf::from(dir));
+        }
+        if let (Some(dir), ToolStore::HttpSingleFile { cache_dir, .. }) =
+            (var("CACHE_DIR"), &mut self.store)
+        {
+            *cache_dir = PathBuf::from(dir);
+        }
+        if let Some(value) = var("HTTP_ENABLED") {
+            self.http_enabled = parse_bool(&value)
+                .ok_or_else(|| invalid("HTTP_ENABLED", &value, "expected true or false"))?;
+        }
+
+        let per_call_timeout =
+            parse::<u64>(&var, "PER_CALL_TIMEOUT_MS")?.map(Duration::from_millis);
+        let total_timeout = parse::<u64>(&var, "TOTAL_TIMEOUT_MS")?.map(Duration::from_millis);
+        let max_attempts = parse::<u32>(&var, "MAX_ATTEMPTS")?;
+        let apply = |policy: &mut RuntimePolicy| {
+            if let Some(timeout) = per_call_timeout {
+                policy.per_call_timeout = timeout;
+            }
+            if let Some(timeout) = total_timeout {
+                policy.total_timeout = Some(timeout);
+            }
+            if let Some(max_attempts) = max_attempts {
+                policy.max_attempts = max_attempts;
+            }
+        };
+        apply(&mut self.runtime);
+        self.tenant_runtime.values_mut().for_each(apply);
+        Ok(self)
+    }
+}
+
+fn parse<T>(var: impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    var(name)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|err| invalid(name, &value, err))
+        })
+        .transpose()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn invalid(name: &str, value: &str, reason: impl Display) -> anyhow::Error {
+    anyhow!("invalid {ENV_PREFIX}{name} `{value}`: {reason}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::VerifyPolicy;
+
+    fn config() -> ExecConfig {
+        ExecConfig {
+            store: ToolStore::HttpSingleFile {
+                name: "weather_api".into(),
+                url: "https://example.invalid/weather_api.wasm".into(),
+                cache_dir: "/tmp/cache".into(),
+            },
+            security: VerifyPolicy::default(),
+            runtime: RuntimePolicy::default(),
+            tenant_runtime: [("acme".to_string(), RuntimePolicy::default())].into(),
+            http_enabled: false,
+        }
+    }
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn environment_overrides_config() {
+        let cfg = config()
+            .with_overrides_from(env(&[
+                ("GREENTIC_MCP_CACHE_DIR", "/var/cache/tools"),
+                ("GREENTIC_MCP_HTTP_ENABLED", "true"),
+                ("GREENTIC_MCP_PER_CALL_TIMEOUT_MS", "2500"),
+                ("GREENTIC_MCP_MAX_ATTEMPTS", "4"),
+                ("GREENTIC_MCP_TOTAL_TIMEOUT_MS", ""),
+            ]))
+            .unwrap();
+        assert!(matches!(
+            &cfg.store,
+            ToolStore::HttpSingleFile { cache_dir, .. }
+                if cache_dir.as_path() == Path::new("/var/cache/tools")
+        ));
+        assert!(cfg.http_enabled);
+        for policy in [&cfg.runtime, &cfg.tenant_runtime["acme"]] {
+            assert_eq!(policy.per_call_timeout, Duration::from_millis(2500));
+            assert_eq!(policy.max_attempts, 4);
+            assert_eq!(policy.total_timeout, None);
+        }
+
+        let cfg = config()
+            .with_overrides_from(env(&[("GREENTIC_MCP_STORE_DIR", "/srv/tools")]))
+            .unwrap();
+        assert!(matches!(
+            &cfg.store,
+            ToolStore::LocalDir(dir) if dir.as_path() == Path::new("/srv/tools")
+        ));
+
+        let err = config()
+            .with_overrides_from(env(&[("GREENTIC_MCP_MAX_ATTEMPTS", "many")]))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("GREENTIC_MCP_MAX_ATTEMPTS"),
+            "{err}"
+        );
+    }
+}
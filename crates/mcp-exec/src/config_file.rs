@@ -0,0 +1,233 @@
+//! [`ExecConfig`] loaded from a YAML or JSON file, so operators manage executor
+//! policy as configuration rather than Rust code.
+//!
+//! ```yaml
+//! store:
+//!   local_dir: ./tools
+//! security:
+//!   required_digests:
+//!     weather_api: 6f1c...
+//! runtime:
+//!   per_call_timeout_ms: 5000
+//!   max_attempts: 3
+//!   retry_policy: { strategy: exponential, jitter: full }
+//!   http_allowlist: ["api.weather.example"]
+//! tenant_runtime:
+//!   acme:
+//!     max_attempts: 5
+//! http_enabled: true
+//! ```
+//!
+//! Relative store paths are resolved against the file's directory. Tenant entries
+//! override fields of `runtime`. Retry budgets, observers, and other values that
+//! are code rather than data keep their defaults; set them on the loaded config.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::{ExecConfig, HttpAllowlist, RetryPolicy, RuntimePolicy, VerifyPolicy};
+use crate::store::ToolStore;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExecConfigFile {
+    store: StoreFile,
+    #[serde(default)]
+    security: VerifyPolicy,
+    #[serde(default)]
+    runtime: RuntimeFile,
+    #[serde(default)]
+    tenant_runtime: HashMap<String, RuntimeFile>,
+    #[serde(default)]
+    http_enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum StoreFile {
+    LocalDir(PathBuf),
+    HttpSingleFile {
+        name: String,
+        url: String,
+        cache_dir: PathBuf,
+    },
+}
+
+/// Fields of a [`RuntimePolicy`] that can be written down; unset fields keep the
+/// value they are applied to.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuntimeFile {
+    fuel: Option<u64>,
+    max_memory: Option<u64>,
+    wallclock_timeout_ms: Option<u64>,
+    per_call_timeout_ms: Option<u64>,
+    total_timeout_ms: Option<u64>,
+    max_attempts: Option<u32>,
+    base_backoff_ms: Option<u64>,
+    retry_policy: Option<RetryPolicy>,
+    max_retry_duration_ms: Option<u64>,
+    inject_idempotency_key: Option<bool>,
+    inject_tenant: Option<bool>,
+    http_allowlist: Option<Vec<String>>,
+}
+
+impl RuntimeFile {
+    fn apply(&self, policy: &mut RuntimePolicy) {
+        let ms = Duration::from_millis;
+        if let Some(fuel) = self.fuel {
+            policy.fuel = Some(fuel);
+        }
+        if let Some(max_memory) = self.max_memory {
+            policy.max_memory = Some(max_memory);
+        }
+        if let Some(timeout) = self.wallclock_timeout_ms {
+            policy.wallclock_timeout = ms(timeout);
+        }
+        if let Some(timeout) = self.per_call_timeout_ms {
+            policy.per_call_timeout = ms(timeout);
+        }
+        if let Some(timeout) = self.total_timeout_ms {
+            policy.total_timeout = Some(ms(timeout));
+        }
+        if let Some(max_attempts) = self.max_attempts {
+            policy.max_attempts = max_attempts;
+        }
+        if let Some(backoff) = self.base_backoff_ms {
+            policy.base_backoff = ms(backoff);
+        }
+        if let Some(retry_policy) = self.retry_policy {
+            policy.retry_policy = retry_policy;
+        }
+        if let Some(duration) = self.max_retry_duration_ms {
+            policy.max_retry_duration = Some(ms(duration));
+        }
+        if let Some(inject) = self.inject_idempotency_key {
+            policy.inject_idempotency_key = inject;
+        }
+        if let Some(inject) = self.inject_tenant {
+            policy.inject_tenant = inject;
+        }
+        if let Some(hosts) = &self.http_allowlist {
+            policy.http_allowlist = Some(HttpAllowlist::new(hosts));
+        }
+    }
+}
+
+impl ExecConfig {
+    /// Load a config from `path`: JSON for `.json` files, YAML otherwise.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read exec config {}", path.display()))?;
+        let document: Value = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(anyhow::Error::from)
+        } else {
+            serde_yaml_bw::from_str(&content).map_err(anyhow::Error::from)
+        }
+        .with_context(|| format!("failed to parse exec config {}", path.display()))?;
+        let file: ExecConfigFile = serde_json::from_value(document)
+            .with_context(|| format!("invalid exec config {}", path.display()))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let store = match file.store {
+            StoreFile::LocalDir(dir) => ToolStore::LocalDir(base.join(dir)),
+            StoreFile::HttpSingleFile {
+                name,
+                url,
+                cache_dir,
+            } => ToolStore::HttpSingleFile {
+                name,
+                url,
+                cache_dir: base.join(cache_dir),
+            },
+        };
+        let mut runtime = RuntimePolicy::default();
+        file.runtime.apply(&mut runtime);
+        let tenant_runtime = file
+            .tenant_runtime
+            .iter()
+            .map(|(tenant, overrides)| {
+                let mut policy = runtime.clone();
+                overrides.apply(&mut policy);
+                (tenant.clone(), policy)
+            })
+            .collect();
+        Ok(Self {
+            store,
+            security: file.security,
+            runtime,
+            tenant_runtime,
+            http_enabled: file.http_enabled,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BackoffStrategy;
+
+    #[test]
+    fn loads_yaml_and_json_configs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let yaml = tmp.path().join("exec.yaml");
+        std::fs::write(
+            &yaml,
+            r#"
+store:
+  local_dir: tools
+security:
+  required_digests:
+    weather_api: abc123
+runtime:
+  per_call_timeout_ms: 5000
+  max_attempts: 3
+  retry_policy: { strategy: fibonacci, jitter: none }
+  http_allowlist: ["*.weather.example"]
+tenant_runtime:
+  acme:
+    max_attempts: 5
+http_enabled: true
+"#,
+        )
+        .unwrap();
+
+        let cfg = ExecConfig::from_path(&yaml).unwrap();
+        assert!(matches!(
+            &cfg.store,
+            ToolStore::LocalDir(dir) if *dir == tmp.path().join("tools")
+        ));
+        assert_eq!(cfg.security.required_digests["weather_api"], "abc123");
+        assert_eq!(cfg.runtime.per_call_timeout, Duration::from_secs(5));
+        assert_eq!(cfg.runtime.max_attempts, 3);
+        assert_eq!(cfg.runtime.retry_policy.backoff, BackoffStrategy::Fibonacci);
+        assert!(
+            cfg.runtime
+                .http_allowlist
+                .as_ref()
+                .unwrap()
+                .allows("api.weather.example")
+        );
+        let acme = &cfg.tenant_runtime["acme"];
+        assert_eq!(
+            (acme.max_attempts, acme.per_call_timeout),
+            (5, Duration::from_secs(5))
+        );
+        assert!(cfg.http_enabled);
+
+        let json = tmp.path().join("exec.json");
+        std::fs::write(
+            &json,
+            r#"{ "store": { "local_dir": "/srv/tools" }, "runtim": {} }"#,
+        )
+        .unwrap();
+        let err = ExecConfig::from_path(&json).unwrap_err();
+        assert!(format!("{err:#}").contains("runtim"), "{err:#}");
+    }
+}
@@ -1,39 +1,87 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
 use anyhow::{Context, Result};
 use serde_json::Value;
 
+use crate::resolve::ResolvedArtifact;
 use crate::{ExecConfig, ExecError, ExecRequest, exec};
 
 #[cfg(feature = "describe-v1")]
 const DESCRIBE_INTERFACE: &str = "greentic:component/describe-v1@1.0.0";
 #[cfg(feature = "describe-v1")]
-const DESCRIBE_EXPORT: &str = "greentic:component/describe-v1@1.0.0#describe-json";
+const DESCRIBE_FUNC: &str = "describe-json";
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Maybe<T> {
     Data(T),
     Unsupported,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ToolDescribe {
     pub describe_v1: Option<Value>,
     pub capabilities: Maybe<Vec<String>>,
     pub secrets: Maybe<Value>,
     pub config_schema: Maybe<Value>,
+    /// JSON Schema of the tool's input, from the `input_schema` action or the newest
+    /// version in the `describe-v1` document.
+    pub input_schema: Maybe<Value>,
 }
 
+/// Describe results by component digest; a component's answers never change.
+type DescribeCache = Mutex<HashMap<String, ToolDescribe>>;
+
+fn describe_cache() -> &'static DescribeCache {
+    static CACHE: OnceLock<DescribeCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Describe the component `name` through its `describe-v1` export, or else by probing
+/// the `capabilities`, `list_secrets`, `config_schema`, and `input_schema` actions.
+///
+/// Results are cached per component digest, so repeated calls (e.g. for every tool
+/// listing) only resolve the artifact.
 pub fn describe_tool(name: &str, cfg: &ExecConfig) -> Result<ToolDescribe> {
+    let resolved =
+        crate::resolve::resolve(name, &cfg.store).map_err(|err| ExecError::resolve(name, err))?;
+    let digest = resolved.digest.clone();
+    let cached = describe_cache()
+        .lock()
+        .expect("describe cache poisoned")
+        .get(&digest)
+        .cloned();
+    if let Some(describe) = cached {
+        return Ok(describe);
+    }
+
+    let describe = probe(name, resolved, cfg)?;
+    describe_cache()
+        .lock()
+        .expect("describe cache poisoned")
+        .insert(digest, describe.clone());
+    Ok(describe)
+}
+
+fn probe(name: &str, resolved: ResolvedArtifact, cfg: &ExecConfig) -> Result<ToolDescribe> {
     #[cfg(feature = "describe-v1")]
     {
-        if let Some(document) = try_describe_v1(name, cfg)? {
+        if let Some(document) = try_describe_v1(name, resolved, cfg)? {
+            let input_schema = match describe_v1_input_schema(&document, None) {
+                Some(schema) => Maybe::Data(schema.clone()),
+                None => Maybe::Unsupported,
+            };
             return Ok(ToolDescribe {
                 describe_v1: Some(document),
                 capabilities: Maybe::Unsupported,
                 secrets: Maybe::Unsupported,
                 config_schema: Maybe::Unsupported,
+                input_schema,
             });
         }
     }
+    #[cfg(not(feature = "describe-v1"))]
+    let _ = resolved;
 
     fn try_action(name: &str, action: &str, cfg: &ExecConfig) -> Result<Maybe<Value>> {
         let req = ExecRequest {
@@ -41,6 +89,8 @@ pub fn describe_tool(name: &str, cfg: &ExecConfig) -> Result<ToolDescribe> {
             action: action.to_string(),
             args: Value::Object(Default::default()),
             tenant: None,
+            correlation_id: None,
+            identity: None,
         };
 
         match exec(req, cfg) {
@@ -57,6 +107,7 @@ pub fn describe_tool(name: &str, cfg: &ExecConfig) -> Result<ToolDescribe> {
     let capabilities_value = try_action(name, "capabilities", cfg)?;
     let secrets = try_action(name, "list_secrets", cfg)?;
     let config_schema = try_action(name, "config_schema", cfg)?;
+    let input_schema = try_action(name, "input_schema", cfg)?;
 
     let capabilities = match capabilities_value {
         Maybe::Data(value) => {
@@ -78,56 +129,108 @@ pub fn describe_tool(name: &str, cfg: &ExecConfig) -> Result<ToolDescribe> {
         capabilities,
         secrets,
         config_schema,
+        input_schema,
     })
 }
 
-#[cfg(feature = "describe-v1")]
-fn try_describe_v1(name: &str, cfg: &ExecConfig) -> Result<Option<Value>> {
-    use wasmtime::component::{Component, Linker};
-    use wasmtime::{Config, Engine, Store};
+/// Input schema of the entry for `version` in a `describe-v1` document
+/// (`{ name, versions: [{ version, schema, defaults? }] }`), or of the newest entry
+/// when `version` is `None` or not listed.
+pub fn describe_v1_input_schema<'a>(
+    document: &'a Value,
+    version: Option<&str>,
+) -> Option<&'a Value> {
+    let versions = document.get("versions")?.as_array()?;
+    let matching = version.and_then(|version| {
+        versions
+            .iter()
+            .find(|entry| entry.get("version").and_then(Value::as_str) == Some(version))
+    });
+    matching
+        .or_else(|| versions.last())?
+        .get("schema")
+        .filter(|schema| schema.is_object())
+}
 
-    let resolved =
-        crate::resolve::resolve(name, &cfg.store).map_err(|err| ExecError::resolve(name, err))?;
+#[cfg(feature = "describe-v1")]
+fn try_describe_v1(
+    name: &str,
+    resolved: ResolvedArtifact,
+    cfg: &ExecConfig,
+) -> Result<Option<Value>> {
     let verified = crate::verify::verify(name, resolved, &cfg.security)
         .map_err(|err| ExecError::verification(name, err))?;
 
+    describe_component(verified.resolved.bytes.as_ref())
+}
+
+/// Call the `describe-v1` export of an already fetched component.
+///
+/// Returns `Ok(None)` when the bytes are not a component or it does not export the
+/// describe interface.
+#[cfg(feature = "describe-v1")]
+pub fn describe_component(bytes: &[u8]) -> Result<Option<Value>> {
+    use wasmtime::component::{Component, Linker};
+    use wasmtime::{Config, Engine, Store};
+
     let mut config = Config::new();
     config.wasm_component_model(true);
     config.async_support(false);
     config.epoch_interruption(true);
 
     let engine = Engine::new(&config)?;
-    let component = match Component::from_binary(&engine, verified.resolved.bytes.as_ref()) {
+    let component = match Component::from_binary(&engine, bytes) {
         Ok(component) => component,
         Err(_) => return Ok(None),
     };
     let linker = Linker::new(&engine);
     let mut store = Store::new(&engine, ());
+    // Nothing advances this engine's epoch; a zero deadline would trap on entry.
+    store.set_epoch_deadline(1);
 
     let instance = match linker.instantiate(&mut store, &component) {
         Ok(instance) => instance,
         Err(_) => return Ok(None),
     };
-    if instance
-        .get_export(&mut store, None, DESCRIBE_INTERFACE)
-        .is_none()
-    {
+    // The function lives inside the exported interface instance, so look it up there.
+    let Some(interface) = instance.get_export_index(&mut store, None, DESCRIBE_INTERFACE) else {
+        return Ok(None);
+    };
+    let Some(export) = instance.get_export_index(&mut store, Some(&interface), DESCRIBE_FUNC)
+    else {
         return Ok(None);
-    }
-
-    let func = match instance.get_typed_func::<(), (String,)>(&mut store, DESCRIBE_EXPORT) {
-        Ok(func) => func,
-        Err(err) => {
-            let msg = err.to_string();
-            if msg.contains("unknown export") {
-                return Ok(None);
-            }
-            return Err(err);
-        }
     };
+    let func = instance.get_typed_func::<(), (String,)>(&mut store, &export)?;
 
     let (raw,) = func.call(&mut store, ())?;
     let value: Value =
         serde_json::from_str(&raw).with_context(|| "describe-json returned invalid JSON")?;
     Ok(Some(value))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn picks_the_schema_of_the_requested_or_newest_version() {
+        let document = json!({
+            "name": "weather",
+            "versions": [
+                { "version": "1.0.0", "schema": { "required": ["city"] } },
+                { "version": "2.0.0", "schema": { "required": ["lat", "lon"] } },
+                { "version": "3.0.0-rc", "schema": "not a schema" },
+            ],
+        });
+        let required =
+            |version| describe_v1_input_schema(&document, version).map(|s| &s["required"]);
+        assert_eq!(required(Some("1.0.0")), Some(&json!(["city"])));
+        assert_eq!(required(Some("9.9.9")), None);
+        assert_eq!(required(None), None);
+
+        let document = json!({ "versions": [{ "version": "1", "schema": { "type": "object" } }] });
+        assert!(describe_v1_input_schema(&document, Some("2")).is_some());
+        assert!(describe_v1_input_schema(&json!({}), None).is_none());
+    }
+}
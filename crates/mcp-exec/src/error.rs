@@ -1,12 +1,282 @@
 //! Structured error types produced across the resolution, verification, and runtime pipeline.
 
+use std::borrow::Cow;
+use std::fmt;
 use std::io;
+use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::Error as AnyError;
-use serde_json::Value;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
 use thiserror::Error;
 
+use crate::signing::SignatureError;
+
+/// Stable, machine-readable identifier of an error, shared by [`ExecError`] and
+/// `greentic-mcp`'s `McpError`.
+///
+/// Codes are dotted strings grouped by pipeline stage, e.g. `resolve.not_found` or
+/// `runner.timeout`; errors reported by the tool itself are `tool.<code>`, such as
+/// `tool.transient.rate_limited`. They serialize as those strings and never change
+/// once published, so downstream systems can branch on them instead of on messages.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// `resolve.not_found`: the component is not in any configured store.
+    ResolveNotFound,
+    /// `resolve.io`: the component could not be read.
+    ResolveIo,
+    /// `resolve.store`: the store failed, e.g. a download error.
+    ResolveStore,
+    /// `verify.digest_mismatch`: the component does not match its pinned digest.
+    VerifyDigestMismatch,
+    /// `verify.unsigned`: policy requires a signature the component lacks.
+    VerifyUnsigned,
+    /// `verify.bad_signature`: the component's signature does not match it.
+    VerifyBadSignature,
+    /// `runner.timeout`: an attempt ran out of time.
+    RunnerTimeout,
+    /// `runner.total_timeout`: the invocation, retries included, ran out of time.
+    RunnerTotalTimeout,
+    /// `runner.cancelled`: the caller cancelled the invocation.
+    RunnerCancelled,
+    /// `runner.deadline_exceeded`: retries stopped at the retry deadline.
+    RunnerDeadlineExceeded,
+    /// `runner.action_not_found`: the component does not export the action.
+    RunnerActionNotFound,
+    /// `runner.transient`: the guest trapped; another attempt may succeed.
+    RunnerTransient,
+    /// `runner.failed`: the component could not be compiled, instantiated, or run.
+    RunnerFailed,
+    /// `tool.<code>`: the tool returned an error with its own code.
+    Tool(String),
+    /// `map.tool_not_found`: no tool with the requested name is configured.
+    ToolNotFound,
+    /// `map.tool_disabled`: the tool is configured but disabled.
+    ToolDisabled,
+    /// `input.invalid`: the arguments were rejected before invoking.
+    InvalidInput,
+    /// `auth.unauthorized`: the caller is not allowed to connect or call.
+    Unauthorized,
+    /// `auth.forbidden`: the caller's tenant may not use the tool.
+    Forbidden,
+    /// `quota.rate_limited`: the tenant is over its rate limit; retry later.
+    RateLimited,
+    /// `config.invalid`: a tool map configuration could not be loaded.
+    ConfigInvalid,
+    /// `config.secret_unavailable`: a secret referenced by configuration is missing.
+    SecretUnavailable,
+    /// `internal`: a host-side failure not caused by the tool or the caller.
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> Cow<'_, str> {
+        Cow::Borrowed(match self {
+            ErrorCode::ResolveNotFound => "resolve.not_found",
+            ErrorCode::ResolveIo => "resolve.io",
+            ErrorCode::ResolveStore => "resolve.store",
+            ErrorCode::VerifyDigestMismatch => "verify.digest_mismatch",
+            ErrorCode::VerifyUnsigned => "verify.unsigned",
+            ErrorCode::VerifyBadSignature => "verify.bad_signature",
+            ErrorCode::RunnerTimeout => "runner.timeout",
+            ErrorCode::RunnerTotalTimeout => "runner.total_timeout",
+            ErrorCode::RunnerCancelled => "runner.cancelled",
+            ErrorCode::RunnerDeadlineExceeded => "runner.deadline_exceeded",
+            ErrorCode::RunnerActionNotFound => "runner.action_not_found",
+            ErrorCode::RunnerTransient => "runner.transient",
+            ErrorCode::RunnerFailed => "runner.failed",
+            ErrorCode::Tool(code) => return Cow::Owned(format!("tool.{code}")),
+            ErrorCode::ToolNotFound => "map.tool_not_found",
+            ErrorCode::ToolDisabled => "map.tool_disabled",
+            ErrorCode::InvalidInput => "input.invalid",
+            ErrorCode::Unauthorized => "auth.unauthorized",
+            ErrorCode::Forbidden => "auth.forbidden",
+            ErrorCode::RateLimited => "quota.rate_limited",
+            ErrorCode::ConfigInvalid => "config.invalid",
+            ErrorCode::SecretUnavailable => "config.secret_unavailable",
+            ErrorCode::Internal => "internal",
+        })
+    }
+
+    /// Whether the error means the invocation ran out of time.
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RunnerTimeout
+                | ErrorCode::RunnerTotalTimeout
+                | ErrorCode::RunnerDeadlineExceeded
+        )
+    }
+
+    /// Whether another attempt of the same call may succeed: timeouts of a single
+    /// attempt, traps, rate limits, and `tool.transient.*` errors.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ErrorCode::RunnerTimeout | ErrorCode::RunnerTransient | ErrorCode::RateLimited => true,
+            ErrorCode::Tool(code) => code.starts_with("transient."),
+            _ => false,
+        }
+    }
+
+    /// Coarse category of the code; see [`ErrorKind`].
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ErrorCode::ResolveNotFound
+            | ErrorCode::RunnerActionNotFound
+            | ErrorCode::ToolNotFound
+            | ErrorCode::ToolDisabled => ErrorKind::NotFound,
+            ErrorCode::InvalidInput => ErrorKind::InvalidInput,
+            ErrorCode::Unauthorized | ErrorCode::Forbidden => ErrorKind::Denied,
+            ErrorCode::RunnerTimeout
+            | ErrorCode::RunnerTotalTimeout
+            | ErrorCode::RunnerDeadlineExceeded => ErrorKind::Timeout,
+            ErrorCode::RunnerCancelled => ErrorKind::Cancelled,
+            ErrorCode::RunnerTransient | ErrorCode::RateLimited => ErrorKind::Transient,
+            ErrorCode::Tool(code) if code.starts_with("transient.") => ErrorKind::Transient,
+            ErrorCode::Tool(_) => ErrorKind::Tool,
+            ErrorCode::VerifyDigestMismatch
+            | ErrorCode::VerifyUnsigned
+            | ErrorCode::VerifyBadSignature => ErrorKind::Integrity,
+            ErrorCode::ConfigInvalid | ErrorCode::SecretUnavailable => ErrorKind::Config,
+            ErrorCode::ResolveIo
+            | ErrorCode::ResolveStore
+            | ErrorCode::RunnerFailed
+            | ErrorCode::Internal => ErrorKind::Internal,
+        }
+    }
+}
+
+/// Coarse category of an error, shared by [`ExecError`] and `greentic-mcp`'s
+/// `McpError`, for hosts that handle errors by kind rather than by [`ErrorCode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The tool, component, or action does not exist or is disabled.
+    NotFound,
+    /// The arguments were rejected.
+    InvalidInput,
+    /// The caller may not make the call.
+    Denied,
+    /// The invocation ran out of time.
+    Timeout,
+    /// The caller cancelled the invocation.
+    Cancelled,
+    /// Another attempt may succeed: traps, `tool.transient.*` errors, rate limits.
+    Transient,
+    /// The tool returned an error of its own.
+    Tool,
+    /// The component failed digest or signature verification.
+    Integrity,
+    /// Configuration could not be loaded.
+    Config,
+    /// The component could not be loaded or run, or the host failed.
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::Denied => "denied",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Transient => "transient",
+            ErrorKind::Tool => "tool",
+            ErrorKind::Integrity => "integrity",
+            ErrorKind::Config => "config",
+            ErrorKind::Internal => "internal",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_str())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Ok(match code {
+            "resolve.not_found" => ErrorCode::ResolveNotFound,
+            "resolve.io" => ErrorCode::ResolveIo,
+            "resolve.store" => ErrorCode::ResolveStore,
+            "verify.digest_mismatch" => ErrorCode::VerifyDigestMismatch,
+            "verify.unsigned" => ErrorCode::VerifyUnsigned,
+            "verify.bad_signature" => ErrorCode::VerifyBadSignature,
+            "runner.timeout" => ErrorCode::RunnerTimeout,
+            "runner.total_timeout" => ErrorCode::RunnerTotalTimeout,
+            "runner.cancelled" => ErrorCode::RunnerCancelled,
+            "runner.deadline_exceeded" => ErrorCode::RunnerDeadlineExceeded,
+            "runner.action_not_found" => ErrorCode::RunnerActionNotFound,
+            "runner.transient" => ErrorCode::RunnerTransient,
+            "runner.failed" => ErrorCode::RunnerFailed,
+            "map.tool_not_found" => ErrorCode::ToolNotFound,
+            "map.tool_disabled" => ErrorCode::ToolDisabled,
+            "input.invalid" => ErrorCode::InvalidInput,
+            "auth.unauthorized" => ErrorCode::Unauthorized,
+            "auth.forbidden" => ErrorCode::Forbidden,
+            "quota.rate_limited" => ErrorCode::RateLimited,
+            "config.invalid" => ErrorCode::ConfigInvalid,
+            "config.secret_unavailable" => ErrorCode::SecretUnavailable,
+            "internal" => ErrorCode::Internal,
+            other => match other.strip_prefix("tool.") {
+                Some(code) if !code.is_empty() => ErrorCode::Tool(code.to_string()),
+                _ => return Err(format!("unknown error code `{other}`")),
+            },
+        })
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Structured, serializable form of an error, for MCP results, audit logs, and HTTP
+/// APIs alike. Built by [`ExecError::report`] and `greentic-mcp`'s `McpError::report`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Component or tool the error is about, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// See [`ErrorCode::is_retryable`].
+    pub retryable: bool,
+    /// Error-specific data, such as the payload of a tool error; `null` when there is none.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl From<&ExecError> for ErrorReport {
+    fn from(err: &ExecError) -> Self {
+        err.report()
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ExecError {
     #[error("failed to resolve component `{component}`: {source}")]
@@ -36,6 +306,13 @@ pub enum ExecError {
         code: String,
         payload: Value,
     },
+    #[error("retry deadline for `{component}` exceeded after {elapsed:?}: {last}")]
+    DeadlineExceeded {
+        component: String,
+        elapsed: Duration,
+        #[source]
+        last: Box<ExecError>,
+    },
 }
 
 impl ExecError {
@@ -67,6 +344,108 @@ impl ExecError {
         }
     }
 
+    /// Short class used when reporting the error, e.g. the tool error code or `timeout`.
+    pub fn class(&self) -> &str {
+        match self {
+            ExecError::Resolve { .. } => "resolve",
+            ExecError::Verification { .. } => "verification",
+            ExecError::Runner { source, .. } => match source {
+                RunnerError::Timeout { .. } => "timeout",
+                RunnerError::TotalTimeout { .. } => "total_timeout",
+                RunnerError::ToolTransient { .. } => "transient",
+                _ => "runner",
+            },
+            ExecError::NotFound { .. } => "not_found",
+            ExecError::Tool { code, .. } => code,
+            ExecError::DeadlineExceeded { .. } => "deadline_exceeded",
+        }
+    }
+
+    /// Stable code of the error; see [`ErrorCode`].
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ExecError::Resolve { source, .. } => match source {
+                ResolveError::NotFound => ErrorCode::ResolveNotFound,
+                ResolveError::Io(_) => ErrorCode::ResolveIo,
+                ResolveError::Store(_) => ErrorCode::ResolveStore,
+            },
+            ExecError::Verification { source, .. } => match source {
+                VerificationError::DigestMismatch { .. } => ErrorCode::VerifyDigestMismatch,
+                VerificationError::UnsignedRejected => ErrorCode::VerifyUnsigned,
+                VerificationError::BadSignature(_) => ErrorCode::VerifyBadSignature,
+            },
+            ExecError::Runner { source, .. } => match source {
+                RunnerError::Timeout { .. } => ErrorCode::RunnerTimeout,
+                RunnerError::TotalTimeout { .. } => ErrorCode::RunnerTotalTimeout,
+                RunnerError::ActionNotFound { .. } => ErrorCode::RunnerActionNotFound,
+                RunnerError::ToolTransient { .. } => ErrorCode::RunnerTransient,
+                _ => ErrorCode::RunnerFailed,
+            },
+            ExecError::NotFound { .. } => ErrorCode::RunnerActionNotFound,
+            ExecError::Tool { code, .. } => ErrorCode::Tool(code.clone()),
+            ExecError::DeadlineExceeded { .. } => ErrorCode::RunnerDeadlineExceeded,
+        }
+    }
+
+    /// Coarse category of the error; see [`ErrorKind`].
+    pub fn kind(&self) -> ErrorKind {
+        self.code().kind()
+    }
+
+    /// Name of the component the error is about.
+    pub fn component(&self) -> &str {
+        match self {
+            ExecError::Resolve { component, .. }
+            | ExecError::Verification { component, .. }
+            | ExecError::Runner { component, .. }
+            | ExecError::NotFound { component, .. }
+            | ExecError::Tool { component, .. }
+            | ExecError::DeadlineExceeded { component, .. } => component,
+        }
+    }
+
+    /// Action that failed, for errors that know it.
+    pub fn action(&self) -> Option<&str> {
+        match self {
+            ExecError::NotFound { action, .. } | ExecError::Tool { action, .. } => Some(action),
+            ExecError::DeadlineExceeded { last, .. } => last.action(),
+            _ => None,
+        }
+    }
+
+    /// Serializable form of the error; see [`ErrorReport`].
+    pub fn report(&self) -> ErrorReport {
+        let details = match self {
+            ExecError::Tool { payload, .. } => payload.clone(),
+            ExecError::DeadlineExceeded { elapsed, last, .. } => json!({
+                "elapsed_ms": elapsed.as_millis() as u64,
+                "last": last.report(),
+            }),
+            _ => Value::Null,
+        };
+        let code = self.code();
+        ErrorReport {
+            retryable: code.is_retryable(),
+            code,
+            message: self.to_string(),
+            component: Some(self.component().to_string()),
+            action: self.action().map(str::to_string),
+            details,
+        }
+    }
+
+    pub fn deadline_exceeded(
+        component: impl Into<String>,
+        elapsed: Duration,
+        last: ExecError,
+    ) -> Self {
+        Self::DeadlineExceeded {
+            component: component.into(),
+            elapsed,
+            last: Box::new(last),
+        }
+    }
+
     pub fn tool_error(
         component: impl Into<String>,
         action: impl Into<String>,
@@ -98,12 +477,16 @@ pub enum VerificationError {
     DigestMismatch { expected: String, actual: String },
     #[error("artifact is unsigned and policy does not allow it")]
     UnsignedRejected,
+    #[error("bad signature: {0}")]
+    BadSignature(#[source] SignatureError),
 }
 
 #[derive(Debug, Error)]
 pub enum RunnerError {
     #[error("wasm execution timed out after {elapsed:?}")]
     Timeout { elapsed: Duration },
+    #[error("invocation timed out after {elapsed:?}, retries included")]
+    TotalTimeout { elapsed: Duration },
     #[error("wasmtime error: {0}")]
     Wasmtime(#[from] wasmtime::Error),
     #[error("serde error: {0}")]
@@ -117,3 +500,95 @@ pub enum RunnerError {
     #[error("runner is not implemented for this configuration")]
     NotImplemented,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_know_their_kind_and_action() {
+        let err = ExecError::deadline_exceeded(
+            "echo",
+            Duration::from_secs(1),
+            ExecError::tool_error("echo", "run", "transient.rate_limited", Value::Null),
+        );
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert_eq!((err.component(), err.action()), ("echo", Some("run")));
+        assert_eq!(
+            ErrorCode::Tool("transient.busy".into()).kind(),
+            ErrorKind::Transient
+        );
+        assert_eq!(ErrorCode::Tool("bad_city".into()).kind(), ErrorKind::Tool);
+        assert_eq!(
+            serde_json::to_value(ErrorKind::InvalidInput).unwrap(),
+            "invalid_input"
+        );
+    }
+
+    #[test]
+    fn reports_serialize_with_details() {
+        let err = ExecError::tool_error(
+            "weather",
+            "forecast",
+            "transient.rate_limited",
+            json!({ "retry_in": 5 }),
+        );
+        let report = serde_json::to_value(err.report()).unwrap();
+        assert_eq!(
+            report,
+            json!({
+                "code": "tool.transient.rate_limited",
+                "message": "tool `weather` returned error `transient.rate_limited` for action `forecast`",
+                "component": "weather",
+                "action": "forecast",
+                "retryable": true,
+                "details": { "retry_in": 5 },
+            })
+        );
+        let report: ErrorReport = serde_json::from_value(report).unwrap();
+        assert_eq!(report, ErrorReport::from(&err));
+
+        let err = ExecError::deadline_exceeded("weather", Duration::from_secs(2), err);
+        let report = err.report();
+        assert!(!report.retryable);
+        assert_eq!(report.details["elapsed_ms"], 2000);
+        assert_eq!(
+            report.details["last"]["code"],
+            "tool.transient.rate_limited"
+        );
+    }
+
+    #[test]
+    fn error_codes_round_trip_as_strings() {
+        let err = ExecError::tool_error("echo", "run", "transient.rate_limited", Value::Null);
+        assert_eq!(err.code().to_string(), "tool.transient.rate_limited");
+        let err = ExecError::runner(
+            "echo",
+            RunnerError::Timeout {
+                elapsed: Duration::from_secs(1),
+            },
+        );
+        assert_eq!(err.code(), ErrorCode::RunnerTimeout);
+        let err = ExecError::runner(
+            "echo",
+            RunnerError::TotalTimeout {
+                elapsed: Duration::from_secs(1),
+            },
+        );
+        assert_eq!(err.code().to_string(), "runner.total_timeout");
+        assert!(err.code().is_timeout() && !err.code().is_retryable());
+
+        for code in [
+            ErrorCode::ResolveNotFound,
+            ErrorCode::Tool("transient.rate_limited".into()),
+            ErrorCode::RateLimited,
+            ErrorCode::Internal,
+        ] {
+            let json = serde_json::to_value(&code).unwrap();
+            assert_eq!(json, Value::String(code.to_string()));
+            assert_eq!(serde_json::from_value::<ErrorCode>(json).unwrap(), code);
+        }
+        assert!("tool.".parse::<ErrorCode>().is_err());
+        assert!("nope".parse::<ErrorCode>().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Recording and replay of the host calls a guest makes.
+//!
+//! Set [`RuntimePolicy::host_calls`](crate::RuntimePolicy::host_calls) to a
+//! [`HostCallTape`] and the runner logs every `http_request`, `secret_get`,
+//! `kv_get`, and `kv_put` with its result. A replaying tape answers the same calls
+//! from the log instead, without reaching the network, the secrets backend, or
+//! the KV store, so a tool can be tested hermetically against recorded traffic.
+
+use std::fmt;
+use std::sync::Mutex;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// One host call and its result, as seen by the guest.
+///
+/// HTTP headers and request bodies are not kept, so credentials passed in them
+/// never end up in recordings. KV namespaces are the guest's, without the tenant
+/// prefix.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum HostCall {
+    Http {
+        method: String,
+        url: String,
+        result: Result<Vec<u8>, String>,
+    },
+    Secret {
+        name: String,
+        result: Result<String, String>,
+    },
+    KvGet {
+        namespace: String,
+        key: String,
+        value: Option<String>,
+    },
+    KvPut {
+        namespace: String,
+        key: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for HostCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostCall::Http { method, url, .. } => write!(f, "http_request {method} {url}"),
+            HostCall::Secret { name, .. } => write!(f, "secret_get {name}"),
+            HostCall::KvGet { namespace, key, .. } => write!(f, "kv_get {namespace}/{key}"),
+            HostCall::KvPut { namespace, key, .. } => write!(f, "kv_put {namespace}/{key}"),
+        }
+    }
+}
+
+/// Log of host calls, either being recorded or being served back.
+#[derive(Debug)]
+pub struct HostCallTape {
+    replaying: bool,
+    state: Mutex<TapeState>,
+}
+
+#[derive(Debug, Default)]
+struct TapeState {
+    calls: Vec<HostCall>,
+    /// Calls served so far, when replaying.
+    served: usize,
+}
+
+impl HostCallTape {
+    /// Make host calls as usual and log them.
+    pub fn recording() -> Self {
+        Self {
+            replaying: false,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Answer host calls with `calls`, in order. Calls that differ from the next
+    /// recorded one trap the guest.
+    pub fn replaying(calls: Vec<HostCall>) -> Self {
+        Self {
+            replaying: true,
+            state: Mutex::new(TapeState { calls, served: 0 }),
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// Calls recorded so far, or the calls a replaying tape serves.
+    pub fn calls(&self) -> Vec<HostCall> {
+        self.state().calls.clone()
+    }
+
+    /// Recorded calls a replaying tape has not served yet.
+    pub fn remaining(&self) -> usize {
+        let state = self.state();
+        state.calls.len() - state.served
+    }
+
+    pub(crate) fn record(&self, call: HostCall) {
+        self.state().calls.push(call);
+    }
+
+    /// Serve the next recorded call, if `matches` accepts it, as its result.
+    pub(crate) fn replay<T>(&self, matches: impl FnOnce(&HostCall) -> Option<T>) -> Result<T> {
+        let mut state = self.state();
+        let served = state.served;
+        let Some(recorded) = state.calls.get(served) else {
+            bail!("host call beyond the end of the recording ({served} calls)");
+        };
+        let Some(result) = matches(recorded) else {
+            bail!("host call does not match recorded call #{served}: {recorded}");
+        };
+        state.served += 1;
+        Ok(result)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, TapeState> {
+        self.state.lock().expect("host call tape poisoned")
+    }
+}
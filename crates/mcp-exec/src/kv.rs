@@ -0,0 +1,77 @@
+//! Key-value storage backing the `kv_get`/`kv_put` host functions.
+//!
+//! Namespaces used by a tool invoked for a tenant are prefixed with the tenant id
+//! (see [`tenant_namespace`]), so tenants never see each other's entries. Tools may
+//! not use `/` in namespaces, so no namespace they ask for names a tenant's entries
+//! (see [`scoped_namespace`]).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use anyhow::{Result, bail};
+
+/// Namespaced string storage shared between tools and the host.
+///
+/// Tools reach it through the runner host imports; hosts can read the same entries,
+/// e.g. to publish tool output as MCP resources.
+pub trait KvStore: fmt::Debug + Send + Sync {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>>;
+    fn put(&self, namespace: &str, key: &str, value: String) -> Result<()>;
+    /// Keys stored under `namespace`, in ascending order.
+    fn keys(&self, namespace: &str) -> Result<Vec<String>>;
+}
+
+/// Namespace in the store behind `namespace` as seen by tools invoked for `tenant`.
+pub fn tenant_namespace(tenant: &str, namespace: &str) -> String {
+    format!("{tenant}/{namespace}")
+}
+
+/// Namespace in the store behind the `namespace` a tool asked for, scoped to `tenant`
+/// when the call has one. Namespaces containing `/` are refused, since
+/// `acme/reports` would otherwise reach tenant `acme`'s entries.
+pub fn scoped_namespace(tenant: Option<&str>, namespace: &str) -> Result<String> {
+    if namespace.contains('/') {
+        bail!("invalid-kv-namespace: `{namespace}` contains `/`");
+    }
+    Ok(match tenant {
+        Some(tenant) => tenant_namespace(tenant, namespace),
+        None => namespace.to_string(),
+    })
+}
+
+/// Process-local [`KvStore`].
+#[derive(Debug, Default)]
+pub struct MemoryKvStore {
+    entries: Mutex<BTreeMap<(String, String), String>>,
+}
+
+impl MemoryKvStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KvStore for MemoryKvStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<String>> {
+        let entries = self.entries.lock().expect("kv store lock poisoned");
+        Ok(entries
+            .get(&(namespace.to_string(), key.to_string()))
+            .cloned())
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: String) -> Result<()> {
+        let mut entries = self.entries.lock().expect("kv store lock poisoned");
+        entries.insert((namespace.to_string(), key.to_string()), value);
+        Ok(())
+    }
+
+    fn keys(&self, namespace: &str) -> Result<Vec<String>> {
+        let entries = self.entries.lock().expect("kv store lock poisoned");
+        Ok(entries
+            .keys()
+            .filter(|(ns, _)| ns == namespace)
+            .map(|(_, key)| key.clone())
+            .collect())
+    }
+}
@@ -3,19 +3,37 @@
 //! runtime constraints to enforce, then call [`exec`] with a structured request.
 
 mod config;
+mod config_env;
+mod config_file;
 pub mod describe;
 mod error;
+mod host_calls;
+mod kv;
 mod resolve;
+mod retry_store;
 mod runner;
+pub mod signing;
 mod store;
+pub mod telemetry;
+pub mod tenant;
 mod verify;
 
-pub use config::{ExecConfig, RuntimePolicy, VerifyPolicy};
-pub use error::{ExecError, RunnerError};
+pub use config::{
+    BackoffStrategy, ExecConfig, GiveUpReason, HttpAllowlist, Jitter, RetryBudget, RetryClassifier,
+    RetryEvent, RetryObserver, RetryPolicy, RuntimePolicy, VerifyPolicy,
+};
+pub use error::{ErrorCode, ErrorKind, ErrorReport, ExecError, RunnerError};
+pub use host_calls::{HostCall, HostCallTape};
+pub use kv::{KvStore, MemoryKvStore, scoped_namespace, tenant_namespace};
+pub use retry_store::{FileRetryStore, MemoryRetryStore, RetryRequest, RetryState, RetryStore};
 pub use store::{ToolInfo, ToolStore};
+pub use tenant::TenantIdentity;
+
+use std::time::Instant;
 
 use greentic_types::TenantCtx;
 use serde_json::{Value, json};
+use tracing::Instrument;
 
 use crate::runner::Runner;
 
@@ -25,6 +43,12 @@ pub struct ExecRequest {
     pub action: String,
     pub args: Value,
     pub tenant: Option<TenantCtx>,
+    /// Id tying this call to the user action behind it. It is attached to every event
+    /// of the call and passed to the guest as `_meta.correlation_id`.
+    pub correlation_id: Option<String>,
+    /// Session, locale, and other caller identity passed to the guest with the
+    /// tenant; see [`tenant`].
+    pub identity: Option<TenantIdentity>,
 }
 
 /// Execute a single action exported by an MCP component.
@@ -32,24 +56,154 @@ pub struct ExecRequest {
 /// Resolution, verification, and runtime enforcement are performed in sequence,
 /// with detailed errors surfaced through [`ExecError`].
 pub fn exec(req: ExecRequest, cfg: &ExecConfig) -> Result<Value, ExecError> {
-    let resolved = resolve::resolve(&req.component, &cfg.store)
-        .map_err(|err| ExecError::resolve(&req.component, err))?;
-
-    let verified = verify::verify(&req.component, resolved, &cfg.security)
-        .map_err(|err| ExecError::verification(&req.component, err))?;
+    exec_span(&req).in_scope(|| {
+        let started = Instant::now();
+        let mut digest = None;
+        let result = exec_attempt(&req, cfg, &mut digest);
+        record_attempt(&req, digest.as_deref(), started, &result);
+        result
+    })
+}
 
-    let runner = runner::DefaultRunner::new(&cfg.runtime)
-        .map_err(|err| ExecError::runner(&req.component, err))?;
+fn exec_attempt(
+    req: &ExecRequest,
+    cfg: &ExecConfig,
+    digest: &mut Option<String>,
+) -> Result<Value, ExecError> {
+    let (verified, runner) = prepare(req, cfg)?;
+    *digest = Some(verified.resolved.digest.clone());
 
     let result = runner.run(
-        &req,
+        req,
         &verified,
         runner::ExecutionContext {
-            runtime: &cfg.runtime,
+            runtime: cfg.runtime_for(req.tenant.as_ref()),
             http_enabled: cfg.http_enabled,
         },
     );
 
+    interpret(req, result)
+}
+
+/// Async variant of [`exec`] for callers already running on Tokio.
+///
+/// Resolution and the Wasm call run on Tokio's blocking pool and the per-call timeout is
+/// enforced with a Tokio timer, so no dedicated thread is spawned per call. A guest that
+/// outlives the timeout is interrupted at its epoch deadline, returning its blocking
+/// thread to the pool. Must be called from within a Tokio runtime.
+pub async fn exec_async(req: ExecRequest, cfg: &ExecConfig) -> Result<Value, ExecError> {
+    let span = exec_span(&req);
+    async {
+        let started = Instant::now();
+        let mut digest = None;
+        let result = exec_attempt_async(&req, cfg, &mut digest).await;
+        record_attempt(&req, digest.as_deref(), started, &result);
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+/// Span around one call, carrying its correlation id to every nested event.
+fn exec_span(req: &ExecRequest) -> tracing::Span {
+    tracing::info_span!(
+        "exec",
+        component = %req.component,
+        correlation_id = req.correlation_id.as_deref()
+    )
+}
+
+async fn exec_attempt_async(
+    req: &ExecRequest,
+    cfg: &ExecConfig,
+    digest: &mut Option<String>,
+) -> Result<Value, ExecError> {
+    let cfg = cfg.clone();
+    let prepare_req = req.clone();
+    let span = tracing::Span::current();
+    let (verified, runner, cfg) = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        prepare(&prepare_req, &cfg).map(|(verified, runner)| (verified, runner, cfg))
+    })
+    .await
+    .map_err(|err| ExecError::runner(&req.component, join_error(err)))??;
+    *digest = Some(verified.resolved.digest.clone());
+
+    let timeout = cfg.runtime_for(req.tenant.as_ref()).per_call_timeout;
+    let call = {
+        let req = req.clone();
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _span = span.enter();
+            runner.run_inline(
+                &req,
+                &verified,
+                runner::ExecutionContext {
+                    runtime: cfg.runtime_for(req.tenant.as_ref()),
+                    http_enabled: cfg.http_enabled,
+                },
+            )
+        })
+    };
+    let result = match tokio::time::timeout(timeout, call).await {
+        Ok(joined) => joined.unwrap_or_else(|err| Err(join_error(err))),
+        Err(_) => Err(RunnerError::Timeout { elapsed: timeout }),
+    };
+
+    interpret(req, result)
+}
+
+/// Emit the shared `attempt finished` event for `req`.
+fn record_attempt(
+    req: &ExecRequest,
+    digest: Option<&str>,
+    started: Instant,
+    result: &Result<Value, ExecError>,
+) {
+    let error_code = result.as_ref().err().map(ExecError::code);
+    let error_code = error_code.as_ref().map(ErrorCode::as_str);
+    telemetry::AttemptRecord {
+        tool: &req.component,
+        tenant_id: req.tenant.as_ref().map(|tenant| tenant.tenant_id.as_str()),
+        correlation_id: req.correlation_id.as_deref(),
+        digest,
+        attempt: req.tenant.as_ref().map_or(1, |tenant| tenant.attempt + 1),
+        duration: started.elapsed(),
+        outcome: match result {
+            Ok(_) => "ok",
+            Err(err) if err.code().is_timeout() => "timeout",
+            Err(_) => "error",
+        },
+        error_code: error_code.as_deref(),
+    }
+    .emit();
+}
+
+/// Resolve and verify the requested component and build the runner that will execute it.
+fn prepare(
+    req: &ExecRequest,
+    cfg: &ExecConfig,
+) -> Result<(verify::VerifiedArtifact, runner::DefaultRunner), ExecError> {
+    let resolved = tracing::info_span!("resolve", component = %req.component)
+        .in_scope(|| resolve::resolve(&req.component, &cfg.store))
+        .map_err(|err| ExecError::resolve(&req.component, err))?;
+
+    let verified = tracing::info_span!("verify")
+        .in_scope(|| verify::verify(&req.component, resolved, &cfg.security))
+        .map_err(|err| ExecError::verification(&req.component, err))?;
+
+    let runner = runner::DefaultRunner::new(cfg.runtime_for(req.tenant.as_ref()))
+        .map_err(|err| ExecError::runner(&req.component, err))?;
+
+    Ok((verified, runner))
+}
+
+fn join_error(err: tokio::task::JoinError) -> RunnerError {
+    RunnerError::Internal(format!("blocking runner task failed: {err}"))
+}
+
+/// Map the runner outcome (and any error object returned by the tool) onto [`ExecError`].
+fn interpret(req: &ExecRequest, result: Result<Value, RunnerError>) -> Result<Value, ExecError> {
     let value = match result {
         Ok(v) => v,
         Err(RunnerError::ActionNotFound { .. }) => {
@@ -82,11 +236,14 @@ pub fn exec(req: ExecRequest, cfg: &ExecConfig) -> Result<Value, ExecError> {
         .map(str::to_owned)
     {
         if code == "iface-error.not-found" {
-            return Err(ExecError::not_found(req.component, req.action));
+            return Err(ExecError::not_found(
+                req.component.clone(),
+                req.action.clone(),
+            ));
         } else {
             return Err(ExecError::tool_error(
-                req.component,
-                req.action,
+                req.component.clone(),
+                req.action.clone(),
                 code,
                 value,
             ));
@@ -129,6 +286,45 @@ mod tests {
         }
     }
 
+    fn mock_config(dir: &std::path::Path) -> ExecConfig {
+        ExecConfig {
+            store: ToolStore::LocalDir(PathBuf::from(dir)),
+            security: VerifyPolicy {
+                allow_unverified: true,
+                ..VerifyPolicy::default()
+            },
+            runtime: RuntimePolicy::default(),
+            tenant_runtime: Default::default(),
+            http_enabled: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn exec_async_runs_mock_component() {
+        let tempdir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(
+            tempdir.path().join("mock.component.wasm"),
+            r#"{"_mock_mcp_exec": true, "responses": {"greet": {"text": "hi"}}}"#,
+        )
+        .expect("write");
+        let cfg = mock_config(tempdir.path());
+        let request = |action: &str| ExecRequest {
+            component: "mock.component".into(),
+            action: action.into(),
+            args: json!({}),
+            tenant: None,
+            correlation_id: None,
+            identity: None,
+        };
+
+        let value = exec_async(request("greet"), &cfg).await.expect("exec");
+        assert_eq!(value, json!({"text": "hi"}));
+        assert_eq!(exec(request("greet"), &cfg).expect("exec"), value);
+
+        let err = exec_async(request("missing"), &cfg).await.unwrap_err();
+        assert!(matches!(err, ExecError::NotFound { .. }), "{err:?}");
+    }
+
     #[test]
     fn local_resolve_and_verify_success() {
         let tempdir = tempfile::tempdir().expect("tempdir");
@@ -153,6 +349,7 @@ mod tests {
                 trusted_signers: Vec::new(),
             },
             runtime: RuntimePolicy::default(),
+            tenant_runtime: Default::default(),
             http_enabled: false,
         };
 
@@ -161,6 +358,8 @@ mod tests {
             action: "noop".into(),
             args: json!({"message": "hello"}),
             tenant: None,
+            correlation_id: None,
+            identity: None,
         };
 
         // Inject our mock runner to exercise pipeline without executing wasm.
@@ -184,4 +383,92 @@ mod tests {
             Some(digest.as_str())
         );
     }
+
+    /// Component whose `exec` never returns.
+    fn spin_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (core module $Tool
+                    (memory (export "memory") 1)
+                    (func (export "exec") (param i32 i32 i32 i32) (result i32)
+                        (loop $spin (br $spin))
+                        (unreachable))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (i32.const 1024)))
+                (core instance $tool (instantiate $Tool))
+                (func (export "exec") (param "action" string) (param "args" string) (result string)
+                    (canon lift (core func $tool "exec") (memory $tool "memory")
+                        (realloc (func $tool "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    #[test]
+    fn timed_out_guests_release_their_blocking_thread() {
+        let tempdir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(tempdir.path().join("spin.component.wasm"), spin_component())
+            .expect("write");
+        std::fs::write(
+            tempdir.path().join("mock.component.wasm"),
+            r#"{"_mock_mcp_exec": true, "responses": {"greet": {"text": "hi"}}}"#,
+        )
+        .expect("write");
+        let cfg = ExecConfig {
+            runtime: RuntimePolicy {
+                per_call_timeout: std::time::Duration::from_millis(100),
+                ..RuntimePolicy::default()
+            },
+            ..mock_config(tempdir.path())
+        };
+        let request = |component: &str, action: &str| ExecRequest {
+            component: component.into(),
+            action: action.into(),
+            args: json!({}),
+            tenant: None,
+            correlation_id: None,
+            identity: None,
+        };
+        // With a single blocking thread, a guest that kept spinning after its timeout
+        // would leave nothing to run the next call on.
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .max_blocking_threads(1)
+            .build()
+            .expect("runtime");
+
+        runtime.block_on(async {
+            for _ in 0..3 {
+                let err = exec_async(request("spin.component", "run"), &cfg)
+                    .await
+                    .unwrap_err();
+                assert_eq!(err.code(), ErrorCode::RunnerTimeout, "{err:?}");
+            }
+            let value = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                exec_async(request("mock.component", "greet"), &cfg),
+            )
+            .await
+            .expect("the blocking thread was released")
+            .expect("exec");
+            assert_eq!(value, json!({"text": "hi"}));
+        });
+
+        let runner = runner::DefaultRunner::new(&cfg.runtime).expect("runner");
+        let resolved = crate::resolve::resolve("spin.component", &cfg.store).expect("resolve");
+        let verified =
+            crate::verify::verify("spin.component", resolved, &cfg.security).expect("verify");
+        let started = std::time::Instant::now();
+        let err = runner
+            .run_inline(
+                &request("spin.component", "run"),
+                &verified,
+                runner::ExecutionContext {
+                    runtime: &cfg.runtime,
+                    http_enabled: false,
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, RunnerError::Timeout { .. }), "{err:?}");
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
 }
@@ -10,7 +10,6 @@ use crate::store::{self, ToolInfo, ToolStore};
 
 #[derive(Clone, Debug)]
 pub struct ResolvedArtifact {
-    #[allow(dead_code)]
     pub info: ToolInfo,
     pub bytes: Arc<[u8]>,
     pub digest: String,
@@ -23,7 +22,10 @@ pub fn resolve(component: &str, store_ref: &ToolStore) -> Result<ResolvedArtifac
         Err(err) => return Err(ResolveError::Store(err)),
     };
 
-    let bytes = fs::read(&info.path).map_err(ResolveError::Io)?;
+    let bytes = match store_ref.memory_bytes(component) {
+        Some(bytes) => bytes,
+        None => Arc::from(fs::read(&info.path).map_err(ResolveError::Io)?),
+    };
     let digest = info
         .sha256
         .clone()
@@ -31,7 +33,7 @@ pub fn resolve(component: &str, store_ref: &ToolStore) -> Result<ResolvedArtifac
 
     Ok(ResolvedArtifact {
         info,
-        bytes: Arc::from(bytes),
+        bytes,
         digest,
     })
 }
@@ -61,6 +63,21 @@ mod tests {
         assert_eq!(artifact.digest, compute_digest(b"payload"));
     }
 
+    #[test]
+    fn resolves_memory_component_without_files() {
+        let bytes: Arc<[u8]> = Arc::from(&b"payload"[..]);
+        let store = ToolStore::Memory([("tool".to_string(), bytes.clone())].into());
+        let artifact = resolve("tool", &store).expect("resolve");
+
+        assert_eq!(artifact.info.path, PathBuf::from("memory:tool"));
+        assert_eq!(artifact.digest, compute_digest(b"payload"));
+        assert!(Arc::ptr_eq(&artifact.bytes, &bytes));
+        assert!(matches!(
+            resolve("missing", &store),
+            Err(ResolveError::NotFound)
+        ));
+    }
+
     #[test]
     fn fails_when_component_missing() {
         let tmp = tempfile::tempdir().expect("tempdir");
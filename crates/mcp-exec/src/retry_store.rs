@@ -0,0 +1,224 @@
+//! Persistence hook for retry progress, letting a restarted host resume an invocation
+//! mid-backoff instead of starting over or losing it.
+//!
+//! After a restart, [`RetryStore::pending`] lists the invocations that were waiting to
+//! be retried, with the request each was made with. Submitting that request again
+//! under the same idempotency key resumes its retries.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Progress of a logical invocation that is waiting to be retried.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryState {
+    /// Attempts already made.
+    pub attempts: u32,
+    /// When the first attempt started, so retry deadlines span restarts.
+    pub started_at: SystemTime,
+    /// Error message of the last attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Earliest time the next attempt may start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_retry_at: Option<SystemTime>,
+    /// What was invoked, so the invocation can be submitted again after a restart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<RetryRequest>,
+}
+
+/// Invocation a [`RetryState`] belongs to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryRequest {
+    /// Component (or tool key) that was invoked.
+    pub component: String,
+    /// Action (or export) that was called.
+    pub action: String,
+    /// Arguments as sent, including `_meta.idempotency_key` when it was injected.
+    pub args: Value,
+}
+
+/// Storage for [`RetryState`] keyed by invocation id (the idempotency key).
+///
+/// State is saved before every backoff and cleared once the invocation succeeds or
+/// gives up. Failures are logged by the retry loop and otherwise ignored, so a broken
+/// store degrades to in-memory retries.
+pub trait RetryStore: fmt::Debug + Send + Sync {
+    fn load(&self, invocation_id: &str) -> io::Result<Option<RetryState>>;
+    fn save(&self, invocation_id: &str, state: &RetryState) -> io::Result<()>;
+    fn clear(&self, invocation_id: &str) -> io::Result<()>;
+    /// Every saved invocation with its state, e.g. to resubmit them after a restart.
+    fn pending(&self) -> io::Result<Vec<(String, RetryState)>>;
+}
+
+/// Process-local [`RetryStore`], mainly useful for tests.
+#[derive(Debug, Default)]
+pub struct MemoryRetryStore {
+    states: Mutex<HashMap<String, RetryState>>,
+}
+
+impl MemoryRetryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RetryStore for MemoryRetryStore {
+    fn load(&self, invocation_id: &str) -> io::Result<Option<RetryState>> {
+        let states = self.states.lock().expect("retry store lock poisoned");
+        Ok(states.get(invocation_id).cloned())
+    }
+
+    fn save(&self, invocation_id: &str, state: &RetryState) -> io::Result<()> {
+        let mut states = self.states.lock().expect("retry store lock poisoned");
+        states.insert(invocation_id.to_string(), state.clone());
+        Ok(())
+    }
+
+    fn clear(&self, invocation_id: &str) -> io::Result<()> {
+        let mut states = self.states.lock().expect("retry store lock poisoned");
+        states.remove(invocation_id);
+        Ok(())
+    }
+
+    fn pending(&self) -> io::Result<Vec<(String, RetryState)>> {
+        let states = self.states.lock().expect("retry store lock poisoned");
+        Ok(states
+            .iter()
+            .map(|(id, state)| (id.clone(), state.clone()))
+            .collect())
+    }
+}
+
+/// What [`FileRetryStore`] writes: the state and the id it is saved under, which the
+/// hashed file name does not reveal.
+#[derive(Serialize, Deserialize)]
+struct StoredState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    invocation_id: Option<String>,
+    #[serde(flatten)]
+    state: RetryState,
+}
+
+/// [`RetryStore`] keeping one JSON file per invocation in a directory.
+#[derive(Clone, Debug)]
+pub struct FileRetryStore {
+    dir: PathBuf,
+}
+
+impl FileRetryStore {
+    /// Store state under `dir`, which is created on first save.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, invocation_id: &str) -> PathBuf {
+        // Ids are caller-supplied, so hash them into safe file names.
+        let digest = Sha256::digest(invocation_id.as_bytes());
+        self.dir.join(format!("{}.json", hex::encode(digest)))
+    }
+}
+
+impl RetryStore for FileRetryStore {
+    fn load(&self, invocation_id: &str) -> io::Result<Option<RetryState>> {
+        let content = match fs::read(self.path(invocation_id)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        parse(&content).map(|stored| Some(stored.state))
+    }
+
+    fn save(&self, invocation_id: &str, state: &RetryState) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(invocation_id);
+        let tmp = path.with_extension("json.tmp");
+        let stored = StoredState {
+            invocation_id: Some(invocation_id.to_string()),
+            state: state.clone(),
+        };
+        fs::write(&tmp, serde_json::to_vec(&stored)?)?;
+        fs::rename(tmp, path)
+    }
+
+    fn clear(&self, invocation_id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(invocation_id)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// Files written before ids were stored are skipped, as they cannot be resumed.
+    fn pending(&self) -> io::Result<Vec<(String, RetryState)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut pending = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let stored = parse(&fs::read(&path)?)?;
+            if let Some(id) = stored.invocation_id {
+                pending.push((id, stored.state));
+            }
+        }
+        Ok(pending)
+    }
+}
+
+fn parse(content: &[u8]) -> io::Result<StoredState> {
+    serde_json::from_slice(content).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn file_store_round_trips_state() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = FileRetryStore::new(tmp.path().join("retries"));
+        let state = RetryState {
+            attempts: 2,
+            started_at: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            last_error: Some("transient.upstream".into()),
+            next_retry_at: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_005)),
+            request: Some(RetryRequest {
+                component: "payments".into(),
+                action: "charge".into(),
+                args: serde_json::json!({ "amount": 5 }),
+            }),
+        };
+
+        assert_eq!(store.load("inv/1").unwrap(), None);
+        assert!(store.pending().unwrap().is_empty());
+        store.save("inv/1", &state).unwrap();
+        assert_eq!(store.load("inv/1").unwrap(), Some(state.clone()));
+        assert_eq!(store.load("inv/2").unwrap(), None);
+        // A fresh store over the same directory, as after a restart, lists it.
+        let restarted = FileRetryStore::new(tmp.path().join("retries"));
+        assert_eq!(restarted.pending().unwrap(), [("inv/1".to_string(), state)]);
+
+        store.clear("inv/1").unwrap();
+        store.clear("inv/1").unwrap();
+        assert_eq!(store.load("inv/1").unwrap(), None);
+        assert!(restarted.pending().unwrap().is_empty());
+    }
+}
@@ -1,18 +1,29 @@
 //! Runtime integration with Wasmtime for invoking the MCP component entrypoint.
 
+use std::borrow::Cow;
 use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use greentic_interfaces::runner_host_v1::{self as runner_host, RunnerHost};
 use serde_json::Value;
 use wasmtime::component::{Component, Linker};
-use wasmtime::{Engine, Store};
+use wasmtime::{Engine, EngineWeak, Store, Trap};
 
 use crate::ExecRequest;
-use crate::config::RuntimePolicy;
+use crate::config::{HttpAllowlist, RuntimePolicy};
 use crate::error::RunnerError;
+use crate::host_calls::{HostCall, HostCallTape};
+use crate::kv::{self, KvStore};
 use crate::verify::VerifiedArtifact;
+use crate::{telemetry, tenant};
+/// How often the epoch of every runner engine is advanced.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Engines whose epoch the ticker advances; an entry goes away with its engine.
+static TICKED_ENGINES: Mutex<Vec<EngineWeak>> = Mutex::new(Vec::new());
+
 pub struct ExecutionContext<'a> {
     pub runtime: &'a RuntimePolicy,
     pub http_enabled: bool,
@@ -42,8 +53,29 @@ impl DefaultRunner {
             config.consume_fuel(true);
         }
         let engine = Engine::new(&config)?;
+        tick_epoch(&engine)?;
         Ok(Self { engine })
     }
+
+    /// Run the component on the current thread.
+    ///
+    /// A guest still running after `per_call_timeout` traps at the next epoch tick,
+    /// which frees the thread. Callers that must return on time regardless, e.g. while
+    /// a host call blocks, bound the call themselves with a Tokio timer.
+    pub fn run_inline(
+        &self,
+        request: &ExecRequest,
+        artifact: &VerifiedArtifact,
+        ctx: ExecutionContext<'_>,
+    ) -> Result<Value, RunnerError> {
+        run_sync(
+            self.engine.clone(),
+            request.clone(),
+            artifact.clone(),
+            ctx.runtime.clone(),
+            ctx.http_enabled,
+        )
+    }
 }
 
 impl Runner for DefaultRunner {
@@ -61,7 +93,9 @@ impl Runner for DefaultRunner {
         let timeout_duration = runtime.per_call_timeout;
 
         let (tx, rx) = mpsc::channel();
+        let span = tracing::Span::current();
         thread::spawn(move || {
+            let _span = span.enter();
             let res = run_sync(engine, request, artifact, runtime, http_enabled);
             let _ = tx.send(res);
         });
@@ -78,6 +112,45 @@ impl Runner for DefaultRunner {
     }
 }
 
+/// Advance the epoch of `engine` every [`EPOCH_TICK`] from one shared thread, started
+/// on first use, so guests past their deadline trap instead of running forever.
+fn tick_epoch(engine: &Engine) -> Result<(), RunnerError> {
+    static TICKER: OnceLock<Result<(), String>> = OnceLock::new();
+    TICKER
+        .get_or_init(|| {
+            thread::Builder::new()
+                .name("mcp-exec-epoch".into())
+                .spawn(|| {
+                    loop {
+                        thread::sleep(EPOCH_TICK);
+                        let mut engines = TICKED_ENGINES.lock().expect("epoch engines poisoned");
+                        engines.retain(|engine| match engine.upgrade() {
+                            Some(engine) => {
+                                engine.increment_epoch();
+                                true
+                            }
+                            None => false,
+                        });
+                    }
+                })
+                .map(drop)
+                .map_err(|err| format!("failed to start the epoch ticker: {err}"))
+        })
+        .clone()
+        .map_err(RunnerError::Internal)?;
+    TICKED_ENGINES
+        .lock()
+        .expect("epoch engines poisoned")
+        .push(engine.weak());
+    Ok(())
+}
+
+/// Epoch ticks after which a guest running for `timeout` is interrupted.
+fn epoch_deadline(timeout: Duration) -> u64 {
+    let ticks = timeout.as_nanos() / EPOCH_TICK.as_nanos();
+    u64::try_from(ticks).unwrap_or(u64::MAX).saturating_add(1)
+}
+
 fn run_sync(
     engine: Engine,
     request: ExecRequest,
@@ -85,7 +158,9 @@ fn run_sync(
     runtime: RuntimePolicy,
     http_enabled: bool,
 ) -> Result<Value, RunnerError> {
-    let component = match Component::from_binary(&engine, artifact.resolved.bytes.as_ref()) {
+    let compiled = tracing::info_span!("compile")
+        .in_scope(|| Component::from_binary(&engine, artifact.resolved.bytes.as_ref()));
+    let component = match compiled {
         Ok(component) => component,
         Err(err) => {
             if let Some(result) = try_mock_json(artifact.resolved.bytes.as_ref(), &request.action) {
@@ -95,20 +170,46 @@ fn run_sync(
         }
     };
 
+    let instantiate = tracing::info_span!("instantiate").entered();
     let mut linker = Linker::new(&engine);
     linker.allow_shadowing(true);
     runner_host::add_to_linker(&mut linker, |state: &mut StoreState| state)
         .map_err(RunnerError::from)?;
 
-    let mut store = Store::new(&engine, StoreState::new(http_enabled));
+    let tenant = request
+        .tenant
+        .as_ref()
+        .map(|tenant| tenant.tenant_id.as_str().to_string());
+    let state = StoreState::new(http_enabled)
+        .with_http_allowlist(runtime.http_allowlist.clone())
+        .with_kv(runtime.kv_store.clone(), tenant)
+        .with_host_calls(runtime.host_calls.clone());
+    let mut store = Store::new(&engine, state);
+    let timeout = runtime.per_call_timeout.min(runtime.wallclock_timeout);
+    store.set_epoch_deadline(epoch_deadline(timeout));
 
     let instance = linker.instantiate(&mut store, &component)?;
     let exec = instance.get_typed_func::<(String, String), (String,)>(&mut store, "exec")?;
+    drop(instantiate);
 
-    let args_json = serde_json::to_string(&request.args)?;
+    let mut args = Cow::Borrowed(&request.args);
+    if let Some(id) = &request.correlation_id {
+        telemetry::inject_correlation_id(args.to_mut(), id);
+    }
+    if let (true, Some(tenant)) = (runtime.inject_tenant, &request.tenant) {
+        tenant::inject_tenant(args.to_mut(), tenant, request.identity.as_ref());
+    }
+    let args_json = serde_json::to_string(&args)?;
     let started = Instant::now();
-    let (raw_response,) = match exec.call(&mut store, (request.action.clone(), args_json)) {
+    let called = tracing::info_span!("call", action = %request.action)
+        .in_scope(|| exec.call(&mut store, (request.action.clone(), args_json)));
+    let (raw_response,) = match called {
         Ok(result) => result,
+        Err(trap) if trap.downcast_ref::<Trap>() == Some(&Trap::Interrupt) => {
+            return Err(RunnerError::Timeout {
+                elapsed: started.elapsed(),
+            });
+        }
         Err(trap) => {
             let msg = trap.to_string();
             if msg.contains("transient.") {
@@ -134,6 +235,11 @@ fn run_sync(
 struct StoreState {
     http_enabled: bool,
     http_client: Option<reqwest::blocking::Client>,
+    http_allowlist: Option<HttpAllowlist>,
+    kv: Option<Arc<dyn KvStore>>,
+    /// Tenant whose namespaces the guest's KV calls are confined to.
+    tenant: Option<String>,
+    host_calls: Option<Arc<HostCallTape>>,
 }
 
 impl StoreState {
@@ -141,9 +247,33 @@ impl StoreState {
         Self {
             http_enabled,
             http_client: None,
+            http_allowlist: None,
+            kv: None,
+            tenant: None,
+            host_calls: None,
         }
     }
 
+    fn with_http_allowlist(mut self, allowlist: Option<HttpAllowlist>) -> Self {
+        self.http_allowlist = allowlist;
+        self
+    }
+
+    fn with_kv(mut self, kv: Option<Arc<dyn KvStore>>, tenant: Option<String>) -> Self {
+        self.kv = kv;
+        self.tenant = tenant;
+        self
+    }
+
+    fn with_host_calls(mut self, tape: Option<Arc<HostCallTape>>) -> Self {
+        self.host_calls = tape;
+        self
+    }
+
+    fn kv_namespace(&self, ns: &str) -> wasmtime::Result<String> {
+        kv::scoped_namespace(self.tenant.as_deref(), ns)
+    }
+
     fn http_client(&mut self) -> Result<&reqwest::blocking::Client, String> {
         if !self.http_enabled {
             return Err("http-disabled".into());
@@ -152,9 +282,20 @@ impl StoreState {
         if self.http_client.is_none() {
             // Lazily construct a blocking client so hosts that never expose
             // outbound HTTP do not pay the initialization cost.
-            let client = reqwest::blocking::Client::builder()
+            let mut builder = reqwest::blocking::Client::builder()
                 .use_rustls_tls()
-                .timeout(std::time::Duration::from_secs(30))
+                .timeout(std::time::Duration::from_secs(30));
+            if let Some(allowlist) = self.http_allowlist.clone() {
+                // Redirects must not lead outside the allowlist either.
+                builder =
+                    builder.redirect(reqwest::redirect::Policy::custom(
+                        move |attempt| match attempt.url().host_str() {
+                            Some(host) if allowlist.allows(host) => attempt.follow(),
+                            _ => attempt.error("redirect to a host that is not allowed"),
+                        },
+                    ));
+            }
+            let client = builder
                 .build()
                 .map_err(|err| format!("http-client: {err}"))?;
             self.http_client = Some(client);
@@ -162,36 +303,44 @@ impl StoreState {
 
         Ok(self.http_client.as_ref().expect("client initialized"))
     }
-}
 
-impl RunnerHost for StoreState {
-    fn http_request(
+    fn live_http_request(
         &mut self,
-        method: String,
-        url: String,
-        headers: Vec<String>,
+        method: &str,
+        url: &str,
+        headers: &[String],
         body: Option<Vec<u8>>,
-    ) -> wasmtime::Result<Result<Vec<u8>, String>> {
+    ) -> Result<Vec<u8>, String> {
         if !self.http_enabled {
-            return Ok(Err("http-disabled".into()));
+            return Err("http-disabled".into());
         }
 
         use reqwest::Method;
 
+        if let Some(allowlist) = &self.http_allowlist {
+            let Ok(parsed) = reqwest::Url::parse(url) else {
+                return Err("invalid-url".into());
+            };
+            match parsed.host_str() {
+                Some(host) if allowlist.allows(host) => {}
+                host => return Err(format!("host-not-allowed:{}", host.unwrap_or(""))),
+            }
+        }
+
         let client = match self.http_client() {
             Ok(client) => client,
-            Err(err) => return Ok(Err(err)),
+            Err(err) => return Err(err),
         };
 
         let method = match Method::from_bytes(method.as_bytes()) {
             Ok(method) => method,
-            Err(_) => return Ok(Err("invalid-method".into())),
+            Err(_) => return Err("invalid-method".into()),
         };
 
-        let builder = client.request(method, &url);
-        let mut builder = match apply_headers(builder, &headers) {
+        let builder = client.request(method, url);
+        let mut builder = match apply_headers(builder, headers) {
             Ok(builder) => builder,
-            Err(err) => return Ok(Err(err)),
+            Err(err) => return Err(err),
         };
 
         if let Some(body) = body {
@@ -200,29 +349,120 @@ impl RunnerHost for StoreState {
 
         let response = match builder.send() {
             Ok(resp) => resp,
-            Err(err) => return Ok(Err(format!("request: {err}"))),
+            Err(err) => return Err(format!("request: {err}")),
         };
 
         if !response.status().is_success() {
-            return Ok(Err(format!("status-{}", response.status().as_u16())));
+            return Err(format!("status-{}", response.status().as_u16()));
         }
 
         match response.bytes() {
-            Ok(bytes) => Ok(Ok(bytes.to_vec())),
-            Err(err) => Ok(Err(format!("body: {err}"))),
+            Ok(bytes) => Ok(bytes.to_vec()),
+            Err(err) => Err(format!("body: {err}")),
         }
     }
 
-    fn secret_get(&mut self, _name: String) -> wasmtime::Result<Result<String, String>> {
-        Ok(Err("secrets-disabled".into()))
+    fn live_kv_get(&self, ns: &str, key: &str) -> wasmtime::Result<Option<String>> {
+        let ns = self.kv_namespace(ns)?;
+        match &self.kv {
+            Some(kv) => kv.get(&ns, key),
+            None => Ok(None),
+        }
     }
 
-    fn kv_get(&mut self, _ns: String, _key: String) -> wasmtime::Result<Option<String>> {
-        Ok(None)
+    fn live_kv_put(&self, ns: &str, key: &str, val: String) -> wasmtime::Result<()> {
+        let ns = self.kv_namespace(ns)?;
+        match &self.kv {
+            Some(kv) => kv.put(&ns, key, val),
+            None => Ok(()),
+        }
     }
 
-    fn kv_put(&mut self, _ns: String, _key: String, _val: String) -> wasmtime::Result<()> {
-        Ok(())
+    /// Make a host call with `live`, logging it to a recording tape, or answer it
+    /// from a replaying tape. `call` builds the logged call from a result, and
+    /// `result_of` reads the result back from a logged call of the same kind.
+    fn host_call<T: Clone>(
+        &mut self,
+        call: impl Fn(T) -> HostCall,
+        result_of: impl FnOnce(&HostCall) -> Option<T>,
+        live: impl FnOnce(&mut Self) -> wasmtime::Result<T>,
+    ) -> wasmtime::Result<T> {
+        match self.host_calls.clone() {
+            Some(tape) if tape.is_replaying() => tape.replay(|recorded| {
+                result_of(recorded).filter(|result| call(result.clone()) == *recorded)
+            }),
+            tape => {
+                let result = live(self)?;
+                if let Some(tape) = tape {
+                    tape.record(call(result.clone()));
+                }
+                Ok(result)
+            }
+        }
+    }
+}
+
+impl RunnerHost for StoreState {
+    fn http_request(
+        &mut self,
+        method: String,
+        url: String,
+        headers: Vec<String>,
+        body: Option<Vec<u8>>,
+    ) -> wasmtime::Result<Result<Vec<u8>, String>> {
+        self.host_call(
+            |result| HostCall::Http {
+                method: method.clone(),
+                url: url.clone(),
+                result,
+            },
+            |recorded| match recorded {
+                HostCall::Http { result, .. } => Some(result.clone()),
+                _ => None,
+            },
+            |state| Ok(state.live_http_request(&method, &url, &headers, body)),
+        )
+    }
+
+    fn secret_get(&mut self, name: String) -> wasmtime::Result<Result<String, String>> {
+        self.host_call(
+            |result| HostCall::Secret {
+                name: name.clone(),
+                result,
+            },
+            |recorded| match recorded {
+                HostCall::Secret { result, .. } => Some(result.clone()),
+                _ => None,
+            },
+            |_| Ok(Err("secrets-disabled".into())),
+        )
+    }
+
+    fn kv_get(&mut self, ns: String, key: String) -> wasmtime::Result<Option<String>> {
+        self.host_call(
+            |value| HostCall::KvGet {
+                namespace: ns.clone(),
+                key: key.clone(),
+                value,
+            },
+            |recorded| match recorded {
+                HostCall::KvGet { value, .. } => Some(value.clone()),
+                _ => None,
+            },
+            |state| state.live_kv_get(&ns, &key),
+        )
+    }
+
+    fn kv_put(&mut self, ns: String, key: String, val: String) -> wasmtime::Result<()> {
+        self.host_call(
+            |()| HostCall::KvPut {
+                namespace: ns.clone(),
+                key: key.clone(),
+                value: val.clone(),
+            },
+            |recorded| matches!(recorded, HostCall::KvPut { .. }).then_some(()),
+            |state| state.live_kv_put(&ns, &key, val.clone()),
+        )
     }
 }
 
@@ -288,6 +528,21 @@ mod tests {
         assert!(matches!(result, Err(err) if err == "invalid-method"));
     }
 
+    #[test]
+    fn http_request_rejects_hosts_outside_the_allowlist() {
+        let mut state = StoreState::new(true)
+            .with_http_allowlist(Some(HttpAllowlist::new(["crm.acme.example"])));
+        let result = state
+            .http_request(
+                "GET".into(),
+                "https://other.example/contacts".into(),
+                Vec::new(),
+                None,
+            )
+            .expect("request should run");
+        assert!(matches!(result, Err(err) if err == "host-not-allowed:other.example"));
+    }
+
     #[test]
     fn secret_get_is_disabled() {
         let mut state = StoreState::new(true);
@@ -296,4 +551,108 @@ mod tests {
             .expect("call should succeed");
         assert!(matches!(result, Err(err) if err == "secrets-disabled"));
     }
+
+    #[test]
+    fn kv_calls_reach_the_configured_store() {
+        let kv = Arc::new(crate::kv::MemoryKvStore::new());
+        let mut state = StoreState::new(false).with_kv(Some(kv.clone()), None);
+        state
+            .kv_put("reports".into(), "daily".into(), "ok".into())
+            .unwrap();
+        assert_eq!(
+            state.kv_get("reports".into(), "daily".into()).unwrap(),
+            Some("ok".into())
+        );
+        assert_eq!(kv.keys("reports").unwrap(), ["daily"]);
+
+        let mut detached = StoreState::new(false);
+        assert_eq!(
+            detached.kv_get("reports".into(), "daily".into()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn kv_namespaces_are_scoped_to_the_tenant() {
+        let kv = Arc::new(crate::kv::MemoryKvStore::new());
+        kv.put("reports", "daily", "shared".into()).unwrap();
+        let mut acme = StoreState::new(false).with_kv(Some(kv.clone()), Some("acme".into()));
+        let mut other = StoreState::new(false).with_kv(Some(kv.clone()), Some("other".into()));
+        acme.kv_put("reports".into(), "daily".into(), "acme".into())
+            .unwrap();
+
+        assert_eq!(
+            acme.kv_get("reports".into(), "daily".into()).unwrap(),
+            Some("acme".into())
+        );
+        assert_eq!(
+            other.kv_get("reports".into(), "daily".into()).unwrap(),
+            None
+        );
+        assert_eq!(kv.get("reports", "daily").unwrap(), Some("shared".into()));
+        assert_eq!(kv.keys("acme/reports").unwrap(), ["daily"]);
+
+        // Without a tenant, a namespace must not reach into a tenant's entries.
+        let mut unscoped = StoreState::new(false).with_kv(Some(kv.clone()), None);
+        assert!(
+            unscoped
+                .kv_get("acme/reports".into(), "daily".into())
+                .is_err()
+        );
+        assert!(
+            unscoped
+                .kv_put("acme/reports".into(), "daily".into(), "forged".into())
+                .is_err()
+        );
+        assert!(
+            other
+                .kv_get("../acme/reports".into(), "daily".into())
+                .is_err()
+        );
+        assert_eq!(
+            kv.get("acme/reports", "daily").unwrap(),
+            Some("acme".into())
+        );
+    }
+
+    #[test]
+    fn host_calls_are_recorded_and_replayed() {
+        let kv = Arc::new(crate::kv::MemoryKvStore::new());
+        kv.put("reports", "daily", "ok".into()).unwrap();
+        let tape = Arc::new(HostCallTape::recording());
+        let mut state = StoreState::new(false)
+            .with_kv(Some(kv), None)
+            .with_host_calls(Some(tape.clone()));
+        state.kv_get("reports".into(), "daily".into()).unwrap();
+        state
+            .http_request("GET".into(), "https://example.com".into(), Vec::new(), None)
+            .unwrap();
+        let calls = tape.calls();
+        assert_eq!(
+            calls[1],
+            HostCall::Http {
+                method: "GET".into(),
+                url: "https://example.com".into(),
+                result: Err("http-disabled".into()),
+            }
+        );
+
+        // Replayed without a KV store, and in order.
+        let tape = Arc::new(HostCallTape::replaying(calls));
+        let mut replay = StoreState::new(false).with_host_calls(Some(tape.clone()));
+        assert_eq!(
+            replay.kv_get("reports".into(), "daily".into()).unwrap(),
+            Some("ok".into())
+        );
+        let err = replay
+            .http_request(
+                "GET".into(),
+                "https://other.example".into(),
+                Vec::new(),
+                None,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("recorded call #1"), "{err}");
+        assert_eq!(tape.remaining(), 1);
+    }
 }
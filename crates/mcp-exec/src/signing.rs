@@ -0,0 +1,186 @@
+//! Ed25519 signatures of components, kept next to them as `<component>.sig` files.
+//!
+//! A signature covers the component's SHA-256 digest. Signers are named by the hex
+//! encoding of their public key; verification accepts a component signed by one of
+//! the [`VerifyPolicy::trusted_signers`](crate::VerifyPolicy::trusted_signers) as
+//! verified, and rejects one whose signature does not match.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+pub use ed25519_dalek::SigningKey;
+
+/// Contents of a `.sig` file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentSignature {
+    /// Hex-encoded public key of the signer.
+    pub signer: String,
+    /// Hex-encoded SHA-256 digest of the signed component.
+    pub sha256: String,
+    /// Hex-encoded Ed25519 signature of the digest.
+    pub signature: String,
+}
+
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    #[error("failed to access `{}`: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("malformed signature: {0}")]
+    Malformed(String),
+    #[error("signature covers digest {signed}, but the component has {actual}")]
+    DigestMismatch { signed: String, actual: String },
+    #[error("signature was not made by signer {signer}")]
+    Invalid { signer: String },
+}
+
+/// Where the signature of the component at `component` is kept.
+pub fn signature_path(component: &Path) -> PathBuf {
+    let mut path = component.as_os_str().to_owned();
+    path.push(".sig");
+    path.into()
+}
+
+/// Name of the signer using `key`: its hex-encoded public key.
+pub fn signer_id(key: &SigningKey) -> String {
+    hex::encode(key.verifying_key().as_bytes())
+}
+
+/// Read a signing key stored as 64 hex characters.
+pub fn read_signing_key(path: &Path) -> Result<SigningKey, SignatureError> {
+    let text = fs::read_to_string(path).map_err(|source| SignatureError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let bytes: [u8; 32] = decode(text.trim(), "signing key")?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Sign the component `bytes` with `key`.
+pub fn sign(bytes: &[u8], key: &SigningKey) -> ComponentSignature {
+    let sha256 = hex::encode(Sha256::digest(bytes));
+    let signature = key.sign(&message(&sha256));
+    ComponentSignature {
+        signer: signer_id(key),
+        signature: hex::encode(signature.to_bytes()),
+        sha256,
+    }
+}
+
+/// Signer of the component at `component`, if its `.sig` file is valid for `bytes`
+/// and names one of `trusted`. Unsigned components and untrusted signers yield
+/// `None`; a signature that does not match is an error.
+pub fn trusted_signer(
+    component: &Path,
+    bytes: &[u8],
+    trusted: &[String],
+) -> Result<Option<String>, SignatureError> {
+    let Some(signature) = ComponentSignature::read(component)? else {
+        return Ok(None);
+    };
+    signature.verify(bytes)?;
+    Ok(trusted
+        .iter()
+        .any(|signer| signer.eq_ignore_ascii_case(&signature.signer))
+        .then_some(signature.signer))
+}
+
+impl ComponentSignature {
+    /// The signature of the component at `component`, if it has one.
+    pub fn read(component: &Path) -> Result<Option<Self>, SignatureError> {
+        let path = signature_path(component);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(SignatureError::Io { path, source }),
+        };
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|err| SignatureError::Malformed(format!("{}: {err}", path.display())))
+    }
+
+    /// Store the signature next to the component at `component`.
+    pub fn write(&self, component: &Path) -> Result<PathBuf, SignatureError> {
+        let path = signature_path(component);
+        let json = serde_json::to_string_pretty(self).expect("signature serializes");
+        fs::write(&path, json + "\n").map_err(|source| SignatureError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(path)
+    }
+
+    /// Check that the signature covers `bytes` and was made by its signer.
+    pub fn verify(&self, bytes: &[u8]) -> Result<(), SignatureError> {
+        let actual = hex::encode(Sha256::digest(bytes));
+        if !self.sha256.eq_ignore_ascii_case(&actual) {
+            return Err(SignatureError::DigestMismatch {
+                signed: self.sha256.clone(),
+                actual,
+            });
+        }
+        let key = VerifyingKey::from_bytes(&decode(&self.signer, "signer")?)
+            .map_err(|err| SignatureError::Malformed(format!("signer: {err}")))?;
+        let signature = Signature::from_bytes(&decode(&self.signature, "signature")?);
+        key.verify(&message(&actual), &signature)
+            .map_err(|_| SignatureError::Invalid {
+                signer: self.signer.clone(),
+            })
+    }
+}
+
+/// What is signed: the digest, tagged so the signature cannot be reused elsewhere.
+fn message(sha256: &str) -> Vec<u8> {
+    format!("greentic-component-sha256:{}", sha256.to_ascii_lowercase()).into_bytes()
+}
+
+fn decode<const N: usize>(text: &str, what: &str) -> Result<[u8; N], SignatureError> {
+    hex::decode(text)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| SignatureError::Malformed(format!("{what} is not {N} hex-encoded bytes")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_cover_the_component_digest() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let component = tmp.path().join("tool.wasm");
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let signer = signer_id(&key);
+
+        let written = sign(b"bytes", &key).write(&component).expect("write");
+        assert_eq!(written, tmp.path().join("tool.wasm.sig"));
+        let signature = ComponentSignature::read(&component).unwrap().unwrap();
+        assert!(signature.verify(b"bytes").is_ok());
+        assert!(matches!(
+            signature.verify(b"other"),
+            Err(SignatureError::DigestMismatch { .. })
+        ));
+
+        let forged = ComponentSignature {
+            signer: signer_id(&SigningKey::from_bytes(&[8; 32])),
+            ..signature
+        };
+        assert!(matches!(
+            forged.verify(b"bytes"),
+            Err(SignatureError::Invalid { .. })
+        ));
+
+        let trusted = trusted_signer(&component, b"bytes", &[signer.clone()]).unwrap();
+        assert_eq!(trusted, Some(signer));
+        assert_eq!(trusted_signer(&component, b"bytes", &[]).unwrap(), None);
+    }
+}
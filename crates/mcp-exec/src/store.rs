@@ -1,11 +1,13 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow};
 use sha2::{Digest, Sha256};
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub enum ToolStore {
     /// Local directory populated with `.wasm` tool components.
     LocalDir(PathBuf),
@@ -15,9 +17,37 @@ pub enum ToolStore {
         url: String,
         cache_dir: PathBuf,
     },
+    /// Component bytes by name, e.g. bundled with `include_bytes!`; nothing is read
+    /// from the filesystem. Components have no signatures, so pin their digests or
+    /// allow unverified components.
+    Memory(HashMap<String, Arc<[u8]>>),
     // Additional registries (OCI/Warg) will be supported in future revisions.
 }
 
+impl std::fmt::Debug for ToolStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolStore::LocalDir(root) => f.debug_tuple("LocalDir").field(root).finish(),
+            ToolStore::HttpSingleFile {
+                name,
+                url,
+                cache_dir,
+            } => f
+                .debug_struct("HttpSingleFile")
+                .field("name", name)
+                .field("url", url)
+                .field("cache_dir", cache_dir)
+                .finish(),
+            // Component names only; the bytes would flood logs.
+            ToolStore::Memory(components) => {
+                let mut names: Vec<_> = components.keys().collect();
+                names.sort();
+                f.debug_tuple("Memory").field(&names).finish()
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ToolInfo {
     pub name: String,
@@ -56,6 +86,14 @@ impl ToolStore {
                 let info = self.fetch(name)?;
                 Ok(vec![info])
             }
+            ToolStore::Memory(components) => {
+                let mut items: Vec<_> = components
+                    .iter()
+                    .map(|(name, bytes)| memory_info(name, bytes))
+                    .collect();
+                items.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(items)
+            }
         }
     }
 
@@ -67,8 +105,29 @@ impl ToolStore {
                 url,
                 cache_dir,
             } => fetch_http(expected, url, cache_dir, name),
+            ToolStore::Memory(components) => components
+                .get(name)
+                .map(|bytes| memory_info(name, bytes))
+                .ok_or_else(|| anyhow!(ToolNotFound::new(name))),
         }
     }
+
+    /// Bytes of a [`ToolStore::Memory`] component, which has no file to read.
+    pub(crate) fn memory_bytes(&self, name: &str) -> Option<Arc<[u8]>> {
+        match self {
+            ToolStore::Memory(components) => components.get(name).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// In-memory components are listed under a `memory:<name>` path.
+fn memory_info(name: &str, bytes: &[u8]) -> ToolInfo {
+    ToolInfo {
+        name: name.to_string(),
+        path: PathBuf::from(format!("memory:{name}")),
+        sha256: Some(hex::encode(Sha256::digest(bytes))),
+    }
 }
 
 fn list_local(root: &Path) -> Result<Vec<ToolInfo>> {
@@ -0,0 +1,74 @@
+//! Tracing fields shared by every runner of the workspace.
+//!
+//! [`exec`](crate::exec), [`exec_async`](crate::exec_async), and `greentic-mcp`'s
+//! `WasixExecutor` end each attempt with one `attempt finished` event under [`TARGET`],
+//! carrying the fields of [`AttemptRecord`], so log pipelines need a single parser.
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+/// Target of the per-attempt events.
+pub const TARGET: &str = "greentic_mcp::invocation";
+
+/// Fields of an `attempt finished` event.
+#[derive(Clone, Copy, Debug)]
+pub struct AttemptRecord<'a> {
+    pub tool: &'a str,
+    pub tenant_id: Option<&'a str>,
+    pub correlation_id: Option<&'a str>,
+    /// `sha256` of the component, when known.
+    pub digest: Option<&'a str>,
+    /// One-based attempt number.
+    pub attempt: u32,
+    pub duration: Duration,
+    /// `ok`, `error`, `timeout`, or `cancelled`.
+    pub outcome: &'a str,
+    pub error_code: Option<&'a str>,
+}
+
+impl AttemptRecord<'_> {
+    /// Emit the event: `info` for successful attempts, `warn` otherwise.
+    pub fn emit(&self) {
+        macro_rules! attempt_finished {
+            ($level:ident) => {
+                tracing::$level!(
+                    target: TARGET,
+                    tool = self.tool,
+                    tenant_id = self.tenant_id,
+                    correlation_id = self.correlation_id,
+                    digest = self.digest,
+                    attempt = self.attempt,
+                    duration_ms = self.duration.as_millis() as u64,
+                    outcome = self.outcome,
+                    error_code = self.error_code,
+                    "attempt finished"
+                )
+            };
+        }
+        if self.outcome == "ok" {
+            attempt_finished!(info);
+        } else {
+            attempt_finished!(warn);
+        }
+    }
+}
+
+/// Field under `_meta` carrying the correlation id to guests.
+pub const CORRELATION_ID_FIELD: &str = "correlation_id";
+
+/// Store `id` as `_meta.correlation_id` in an object payload, keeping other `_meta`
+/// fields. Non-object payloads are left unchanged and `false` is returned.
+pub fn inject_correlation_id(args: &mut Value, id: &str) -> bool {
+    let Value::Object(fields) = args else {
+        return false;
+    };
+    let meta = fields
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let Value::Object(meta) = meta else {
+        return false;
+    };
+    meta.insert(CORRELATION_ID_FIELD.into(), Value::String(id.into()));
+    true
+}
@@ -0,0 +1,128 @@
+//! Tenant context passed to guests.
+//!
+//! Tools opting in (see `RuntimePolicy::inject_tenant`) receive the tenant of the
+//! invocation as `_meta.tenant` in their arguments, e.g.
+//! `{ "id": "acme", "env": "prod", "user": "u-7", "locale": "nl-NL" }`, to brand
+//! output, route per tenant, or apply per-user rules.
+//!
+//! The team, user, and trace id come from [`TenantCtx`]. Identity it does not
+//! carry, such as the session or locale, travels in a [`TenantIdentity`].
+
+use std::collections::BTreeMap;
+
+use greentic_types::TenantCtx;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+/// Field under `_meta` carrying the tenant to guests.
+pub const TENANT_FIELD: &str = "tenant";
+
+/// Caller identity beyond what [`TenantCtx`] holds, passed to guests with the tenant.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TenantIdentity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Span of the caller, next to `TenantCtx::trace_id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    /// BCP 47 language tag, e.g. `nl-NL`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Host-defined attributes, such as a plan or region, for tools and policies.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, Value>,
+}
+
+/// Store `tenant`, and `identity` if given, as `_meta.tenant` in an object payload,
+/// replacing any value the caller put there and keeping other `_meta` fields.
+/// Non-object payloads are left unchanged and `false` is returned.
+pub fn inject_tenant(
+    args: &mut Value,
+    tenant: &TenantCtx,
+    identity: Option<&TenantIdentity>,
+) -> bool {
+    let Value::Object(fields) = args else {
+        return false;
+    };
+    let meta = fields
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let Value::Object(meta) = meta else {
+        return false;
+    };
+    meta.insert(
+        TENANT_FIELD.into(),
+        Value::Object(tenant_fields(tenant, identity)),
+    );
+    true
+}
+
+fn tenant_fields(tenant: &TenantCtx, identity: Option<&TenantIdentity>) -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert("id".into(), json!(tenant.tenant_id.as_str()));
+    fields.insert("env".into(), json!(tenant.env.as_str()));
+    let known = [
+        ("team", json!(tenant.team_id)),
+        ("user", json!(tenant.user_id)),
+        ("trace_id", json!(tenant.trace_id)),
+    ];
+    for (name, value) in known {
+        if !value.is_null() {
+            fields.insert(name.into(), value);
+        }
+    }
+    if let Some(Value::Object(identity)) = identity.map(|identity| json!(identity)) {
+        fields.extend(identity);
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_caller_supplied_tenant() {
+        let tenant = TenantCtx::new("prod".try_into().unwrap(), "acme".try_into().unwrap());
+        let mut args = json!({ "q": 1, "_meta": { "tenant": "other", "trace": "t" } });
+        assert!(inject_tenant(&mut args, &tenant, None));
+        assert_eq!(
+            args,
+            json!({
+                "q": 1,
+                "_meta": { "tenant": { "id": "acme", "env": "prod" }, "trace": "t" },
+            })
+        );
+        assert!(!inject_tenant(&mut json!([1]), &tenant, None));
+    }
+
+    #[test]
+    fn passes_caller_identity() {
+        let mut tenant = TenantCtx::new("prod".try_into().unwrap(), "acme".try_into().unwrap());
+        tenant.trace_id = Some("4bf92f35".into());
+        let identity = TenantIdentity {
+            session_id: Some("s-1".into()),
+            locale: Some("nl-NL".into()),
+            attributes: [("plan".to_string(), json!("premium"))].into(),
+            ..TenantIdentity::default()
+        };
+        let mut args = json!({});
+        assert!(inject_tenant(&mut args, &tenant, Some(&identity)));
+        assert_eq!(
+            args["_meta"]["tenant"],
+            json!({
+                "id": "acme",
+                "env": "prod",
+                "trace_id": "4bf92f35",
+                "session_id": "s-1",
+                "locale": "nl-NL",
+                "attributes": { "plan": "premium" },
+            })
+        );
+        let parsed: TenantIdentity =
+            serde_json::from_value(json!({ "locale": "nl-NL", "attributes": {} })).unwrap();
+        assert_eq!(parsed.locale.as_deref(), Some("nl-NL"));
+        assert!(serde_json::from_value::<TenantIdentity>(json!({ "lang": "nl" })).is_err());
+    }
+}
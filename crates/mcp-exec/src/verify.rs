@@ -3,6 +3,7 @@
 use crate::config::VerifyPolicy;
 use crate::error::VerificationError;
 use crate::resolve::ResolvedArtifact;
+use crate::signing;
 
 #[derive(Clone, Debug)]
 pub struct VerifiedArtifact {
@@ -18,22 +19,34 @@ pub fn verify(
     artifact: ResolvedArtifact,
     policy: &VerifyPolicy,
 ) -> Result<VerifiedArtifact, VerificationError> {
-    if let Some(expected_digest) = policy.required_digests.get(component) {
-        if artifact.digest != *expected_digest {
-            return Err(VerificationError::DigestMismatch {
-                expected: expected_digest.clone(),
-                actual: artifact.digest,
-            });
-        }
-    } else if !policy.allow_unverified {
+    let pinned = policy.required_digests.get(component);
+    if let Some(expected_digest) = pinned
+        && artifact.digest != *expected_digest
+    {
+        return Err(VerificationError::DigestMismatch {
+            expected: expected_digest.clone(),
+            actual: artifact.digest,
+        });
+    }
+
+    let verified_signer = if policy.trusted_signers.is_empty() {
+        None
+    } else {
+        signing::trusted_signer(
+            &artifact.info.path,
+            &artifact.bytes,
+            &policy.trusted_signers,
+        )
+        .map_err(VerificationError::BadSignature)?
+    };
+    if pinned.is_none() && verified_signer.is_none() && !policy.allow_unverified {
         return Err(VerificationError::UnsignedRejected);
     }
 
-    // Signature verification will be added once the signing infrastructure is finalized.
     Ok(VerifiedArtifact {
         verified_digest: Some(artifact.digest.clone()),
         resolved: artifact,
-        verified_signer: None,
+        verified_signer,
     })
 }
 
@@ -88,4 +101,36 @@ mod tests {
         );
         assert!(verified.verified_signer.is_none());
     }
+
+    #[test]
+    fn accepts_components_signed_by_trusted_signers() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let wasm_path = tmp.path().join("tool.wasm");
+        std::fs::write(&wasm_path, b"bytes").expect("write wasm");
+        let key = signing::SigningKey::from_bytes(&[7; 32]);
+        let policy = VerifyPolicy {
+            trusted_signers: vec![signing::signer_id(&key)],
+            ..Default::default()
+        };
+        let store = ToolStore::LocalDir(PathBuf::from(tmp.path()));
+
+        let artifact = resolve::resolve("tool", &store).expect("resolve");
+        let err = verify("tool", artifact, &policy).expect_err("unsigned");
+        assert!(matches!(err, VerificationError::UnsignedRejected));
+
+        signing::sign(b"bytes", &key)
+            .write(&wasm_path)
+            .expect("sign");
+        let artifact = resolve::resolve("tool", &store).expect("resolve");
+        let verified = verify("tool", artifact, &policy).expect("verify");
+        assert_eq!(
+            verified.verified_signer,
+            policy.trusted_signers.first().cloned()
+        );
+
+        std::fs::write(&wasm_path, b"tampered").expect("write wasm");
+        let artifact = resolve::resolve("tool", &store).expect("resolve");
+        let err = verify("tool", artifact, &policy).expect_err("tampered");
+        assert!(matches!(err, VerificationError::BadSignature(_)));
+    }
 }
@@ -18,6 +18,7 @@ fn offline_mock_describe_and_list() {
             ..Default::default()
         },
         runtime: Default::default(),
+        tenant_runtime: Default::default(),
         http_enabled: false,
     };
 
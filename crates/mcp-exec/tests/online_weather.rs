@@ -19,6 +19,7 @@ fn online_weather_list_and_describe() {
         },
         security: Default::default(),
         runtime: Default::default(),
+        tenant_runtime: Default::default(),
         http_enabled: true,
     };
 
@@ -47,6 +48,7 @@ fn online_weather_list_and_describe() {
         capabilities,
         secrets,
         config_schema,
+        ..
     } = describe;
 
     if let Some(doc) = describe_v1 {
@@ -0,0 +1,263 @@
+//! Audit trail of tool invocations, written as JSON lines.
+//!
+//! Attach an [`AuditLog`] with
+//! [`WasixExecutor::with_audit_log`](crate::executor::WasixExecutor::with_audit_log)
+//! and every invocation appends one [`AuditRecord`], successful or not. Payloads are
+//! left out unless enabled with [`AuditLog::with_payloads`], which also names the
+//! fields to redact.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::telemetry::{self, PhaseTimings};
+use crate::types::{ErrorReport, McpError, ToolRef};
+
+/// Replacement for redacted payload values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// One invocation, as written to the audit log.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the invocation finished, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Tool key, including its namespace.
+    pub tool: String,
+    /// SHA-256 of the component that ran, or its pin when none was loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// Export (or remote tool) that was called.
+    pub action: String,
+    pub duration_ms: u64,
+    /// Milliseconds spent in each phase of the last attempt, by phase name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub phase_ms: BTreeMap<String, u64>,
+    /// `ok`, `error`, `timeout`, or `cancelled`.
+    pub outcome: String,
+    /// What went wrong, when the invocation failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+}
+
+impl AuditRecord {
+    /// Record for an invocation of `tool` that took `duration`, without payloads.
+    pub(crate) fn new<T>(tool: &ToolRef, duration: Duration, result: &Result<T, McpError>) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            tenant: None,
+            correlation_id: None,
+            tool: tool.key(),
+            digest: tool.sha256.clone(),
+            action: tool.entry.clone(),
+            duration_ms: duration.as_millis() as u64,
+            phase_ms: BTreeMap::new(),
+            outcome: telemetry::outcome(result).to_string(),
+            error: result.as_ref().err().map(McpError::report),
+            input: None,
+            output: None,
+        }
+    }
+
+    pub(crate) fn with_phases(mut self, phases: &PhaseTimings) -> Self {
+        self.phase_ms = phases
+            .iter()
+            .map(|(phase, elapsed)| (phase.as_str().to_string(), elapsed.as_millis() as u64))
+            .collect();
+        self
+    }
+}
+
+/// Sink for [`AuditRecord`]s, one JSON object per line.
+pub struct AuditLog {
+    writer: Mutex<Box<dyn Write + Send>>,
+    redact: Option<Vec<String>>,
+}
+
+impl AuditLog {
+    /// Append to the file at `path`, creating it if needed.
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self, McpError> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| {
+                McpError::Internal(format!(
+                    "failed to open audit log `{}`: {err}",
+                    path.display()
+                ))
+            })?;
+        Ok(Self::to_writer(file))
+    }
+
+    /// Write to `writer`, flushing after every record.
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            redact: None,
+        }
+    }
+
+    /// Include input and output payloads, replacing the values of fields named in
+    /// `redact` (at any depth) with [`REDACTED`].
+    pub fn with_payloads<I>(mut self, redact: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.redact = Some(redact.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Append `record`, attaching redacted copies of `input` and `output` if payloads
+    /// are captured. Write failures are logged rather than failing the invocation.
+    pub fn record(&self, mut record: AuditRecord, input: &Value, output: Option<&Value>) {
+        if let Some(redact) = &self.redact {
+            record.input = Some(redacted(input, redact));
+            record.output = output.map(|output| redacted(output, redact));
+        }
+        let mut line = match serde_json::to_vec(&record) {
+            Ok(line) => line,
+            Err(err) => {
+                tracing::warn!(%err, tool = %record.tool, "failed to serialize audit record");
+                return;
+            }
+        };
+        line.push(b'\n');
+        let mut writer = self.writer.lock().expect("audit log poisoned");
+        if let Err(err) = writer.write_all(&line).and_then(|()| writer.flush()) {
+            tracing::warn!(%err, tool = %record.tool, "failed to write audit record");
+        }
+    }
+}
+
+impl std::fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("redact", &self.redact)
+            .finish_non_exhaustive()
+    }
+}
+
+fn redacted(value: &Value, keys: &[String]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if keys.contains(key) {
+                        Value::String(REDACTED.into())
+                    } else {
+                        redacted(value, keys)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| redacted(item, keys)).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::{InvokeOptions, WasixExecutor};
+    use crate::types::{ErrorCode, ToolInput};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn records(buffer: &Buffer) -> Vec<AuditRecord> {
+        String::from_utf8(buffer.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn records_every_invocation() {
+        let buffer = Buffer::default();
+        let executor = WasixExecutor::new()
+            .unwrap()
+            .with_audit_log(Arc::new(AuditLog::to_writer(buffer.clone())));
+        let mut tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+        tool.namespace = Some("crm".into());
+        let input = ToolInput::new(json!({ "api_key": "s3cret" }));
+        let call = InvokeOptions {
+            tenant: Some(greentic_types::TenantCtx::new(
+                "dev".try_into().unwrap(),
+                "acme".try_into().unwrap(),
+            )),
+            ..InvokeOptions::default()
+        };
+        assert!(executor.invoke_with(&tool, &input, call).await.is_err());
+
+        let records = records(&buffer);
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.tenant.as_deref(), Some("acme"));
+        assert_eq!(record.tool, "crm/missing");
+        assert_eq!(record.action, "tool-invoke");
+        assert_eq!(record.outcome, "error");
+        let error = record.error.as_ref().unwrap();
+        assert_eq!(error.code, ErrorCode::RunnerFailed);
+        assert!(
+            error.message.contains("does-not-exist.wasm"),
+            "{}",
+            error.message
+        );
+        assert_eq!(record.input, None);
+        assert_eq!(record.phase_ms.keys().collect::<Vec<_>>(), ["resolve"]);
+        // The executor's history keeps the same record.
+        let recent = executor.recent_invocations(&crate::history::HistoryFilter::new());
+        assert_eq!(recent, records);
+    }
+
+    #[test]
+    fn redacts_captured_payloads() {
+        let buffer = Buffer::default();
+        let log = AuditLog::to_writer(buffer.clone()).with_payloads(["password"]);
+        let input = json!({ "user": "ada", "auth": [{ "password": "hunter2" }] });
+        log.record(AuditRecord::default(), &input, Some(&json!({ "ok": true })));
+
+        let record = records(&buffer).remove(0);
+        assert_eq!(
+            record.input,
+            Some(json!({ "user": "ada", "auth": [{ "password": REDACTED }] }))
+        );
+        assert_eq!(record.output, Some(json!({ "ok": true })));
+    }
+}
@@ -0,0 +1,116 @@
+//! `greentic-mcp describe`: print what a component says about itself.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Args;
+use greentic_mcp::snapshot::{describe_json, describe_map_tool};
+use mcp_exec::describe::{ToolDescribe, describe_tool};
+use mcp_exec::{ExecConfig, ToolStore, VerifyPolicy};
+use serde_json::Value;
+
+use crate::Cli;
+
+#[derive(Debug, Args)]
+pub struct DescribeArgs {
+    /// Name of the tool, as registered in the map (or in `--store`).
+    tool: String,
+    /// Describe a component of this local store directory instead of a map tool.
+    #[arg(long)]
+    store: Option<PathBuf>,
+    /// Print one JSON object instead of readable sections.
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(cli: &Cli, args: &DescribeArgs) -> anyhow::Result<()> {
+    let describe = match &args.store {
+        Some(dir) => {
+            let cfg = ExecConfig {
+                store: ToolStore::LocalDir(dir.clone()),
+                security: VerifyPolicy {
+                    allow_unverified: true,
+                    ..VerifyPolicy::default()
+                },
+                runtime: Default::default(),
+                tenant_runtime: Default::default(),
+                http_enabled: false,
+            };
+            describe_tool(&args.tool, &cfg)
+                .with_context(|| format!("failed to describe `{}`", args.tool))?
+        }
+        None => describe_map_tool(cli.load_map()?.get(&args.tool)?)?,
+    };
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&describe_json(&describe))?
+        );
+    } else {
+        print!("{}", to_text(&describe)?);
+    }
+    Ok(())
+}
+
+fn to_text(describe: &ToolDescribe) -> anyhow::Result<String> {
+    let mut out = String::new();
+    let json = describe_json(describe);
+    let sections = [
+        ("describe-v1", "describe_v1"),
+        ("capabilities", "capabilities"),
+        ("secrets", "secrets"),
+        ("config schema", "config_schema"),
+        ("input schema", "input_schema"),
+    ];
+    for (title, field) in sections {
+        match &json[field] {
+            Value::Null => out.push_str(&format!("{title}: unsupported\n")),
+            Value::Array(items) if items.iter().all(Value::is_string) => {
+                let items: Vec<_> = items.iter().filter_map(Value::as_str).collect();
+                out.push_str(&format!("{title}: {}\n", items.join(", ")));
+            }
+            value => {
+                out.push_str(&format!("{title}:\n"));
+                for line in serde_json::to_string_pretty(value)?.lines() {
+                    out.push_str(&format!("  {line}\n"));
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use mcp_exec::describe::Maybe;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn prints_sections_and_marks_unsupported_ones() {
+        let describe = ToolDescribe {
+            describe_v1: None,
+            capabilities: Maybe::Data(vec!["http".into(), "kv".into()]),
+            secrets: Maybe::Unsupported,
+            config_schema: Maybe::Data(json!({ "type": "object" })),
+            input_schema: Maybe::Unsupported,
+        };
+        assert_eq!(
+            to_text(&describe).unwrap(),
+            "describe-v1: unsupported\n\
+             capabilities: http, kv\n\
+             secrets: unsupported\n\
+             config schema:\n\
+             \x20 {\n\
+             \x20   \"type\": \"object\"\n\
+             \x20 }\n\
+             input schema: unsupported\n"
+        );
+        assert_eq!(
+            describe_json(&describe)["capabilities"],
+            json!(["http", "kv"])
+        );
+        assert_eq!(describe_json(&describe)["secrets"], Value::Null);
+    }
+}
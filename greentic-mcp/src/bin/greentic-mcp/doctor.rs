@@ -0,0 +1,247 @@
+//! `greentic-mcp doctor`: check the environment tools run in.
+
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::bail;
+use clap::Args;
+use greentic_mcp::{McpEndpoint, ToolSource};
+
+use crate::Cli;
+
+/// Target tool components are built for.
+const GUEST_TARGET: &str = "wasm32-wasip2";
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    /// Skip the network reachability checks.
+    #[arg(long)]
+    offline: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// Outcome of one check, with a fix when it did not pass.
+#[derive(Debug, PartialEq)]
+struct Check {
+    status: Status,
+    name: String,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            status: Status::Ok,
+            name: name.into(),
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn problem(
+        status: Status,
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            status,
+            name: name.into(),
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        write!(f, "[{status:>4}] {}: {}", self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       fix: {fix}")?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn run(cli: &Cli, args: &DoctorArgs) -> anyhow::Result<()> {
+    let mut checks = Vec::new();
+    match cli.executor() {
+        Ok(executor) => {
+            checks.push(engine());
+            checks.push(cache_dir(executor.cache_dir()));
+        }
+        Err(err) => checks.push(Check::problem(
+            Status::Fail,
+            "wasmtime",
+            err.to_string(),
+            "run on a platform Wasmtime supports (x86_64, aarch64, riscv64, s390x)",
+        )),
+    }
+    checks.push(guest_target());
+    match cli.load_map() {
+        Ok(map) => {
+            checks.push(Check::ok(
+                "tool map",
+                format!("{} tools", map.iter().count()),
+            ));
+            if !args.offline {
+                for (key, tool) in map.iter() {
+                    checks.extend(reachable(key, &tool.source()).await);
+                }
+            }
+        }
+        Err(err) => checks.push(Check::problem(
+            Status::Fail,
+            "tool map",
+            err.to_string(),
+            "pass the map with `--map <file>`, or fix the error above",
+        )),
+    }
+
+    for check in &checks {
+        println!("{check}");
+    }
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == Status::Fail)
+        .count();
+    if failed > 0 {
+        bail!("{failed} check(s) failed");
+    }
+    Ok(())
+}
+
+/// Creating the executor already proved the component model, fuel, and epochs work.
+fn engine() -> Check {
+    let features = [
+        ("describe-v1", cfg!(feature = "describe-v1")),
+        ("http", cfg!(feature = "http")),
+        ("websocket", cfg!(feature = "websocket")),
+        ("profiling", cfg!(feature = "profiling")),
+    ];
+    let enabled: Vec<_> = features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    Check::ok(
+        "wasmtime",
+        format!("component model ready; features: {}", enabled.join(", ")),
+    )
+}
+
+fn cache_dir(dir: &Path) -> Check {
+    let probe = dir.join(".greentic-mcp-doctor");
+    let writable = std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&probe, b"ok"))
+        .and_then(|()| std::fs::remove_file(&probe));
+    match writable {
+        Ok(()) => Check::ok("cache directory", format!("{} is writable", dir.display())),
+        Err(err) => Check::problem(
+            Status::Fail,
+            "cache directory",
+            format!("{} is not writable: {err}", dir.display()),
+            "pass a writable directory with `--cache-dir`, or fix its permissions",
+        ),
+    }
+}
+
+fn guest_target() -> Check {
+    let output = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output();
+    let fix = format!("rustup target add {GUEST_TARGET}");
+    match output {
+        Ok(output)
+            if String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.trim() == GUEST_TARGET) =>
+        {
+            Check::ok("guest toolchain", format!("{GUEST_TARGET} is installed"))
+        }
+        Ok(_) => Check::problem(
+            Status::Warn,
+            "guest toolchain",
+            format!("{GUEST_TARGET} is not installed; needed to build tools"),
+            fix,
+        ),
+        Err(_) => Check::problem(
+            Status::Warn,
+            "guest toolchain",
+            "rustup not found; needed to build tools",
+            format!("install rustup from https://rustup.rs, then `{fix}`"),
+        ),
+    }
+}
+
+/// Whether the component or server behind `source` can be reached.
+async fn reachable(key: &str, source: &ToolSource) -> Option<Check> {
+    let name = format!("tool `{key}`");
+    let url = match source {
+        ToolSource::Path(path) if path.is_file() => {
+            return Some(Check::ok(name, format!("{} exists", path.display())));
+        }
+        ToolSource::Path(path) => {
+            return Some(Check::problem(
+                Status::Fail,
+                name,
+                format!("{} does not exist", path.display()),
+                "build the component, or fix its path in the map",
+            ));
+        }
+        ToolSource::Url(url) | ToolSource::Mcp(McpEndpoint::Http { url, .. }) => url,
+        // Not checked yet: local server processes.
+        ToolSource::Mcp(McpEndpoint::Stdio { .. }) => return None,
+    };
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .ok()?;
+    // Any HTTP answer means the host is reachable; a missing artifact shows on `pull`.
+    Some(match client.head(url).send().await {
+        Ok(response) => Check::ok(name, format!("{url} answered {}", response.status())),
+        Err(err) => Check::problem(
+            Status::Fail,
+            name,
+            format!("{url} is unreachable: {err}"),
+            "check the URL, DNS, and proxy settings (HTTPS_PROXY) of this host",
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_problems_with_fixes() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(cache_dir(tmp.path()).status, Status::Ok);
+
+        let missing = ToolSource::Path(tmp.path().join("missing.wasm"));
+        let check = reachable("echo", &missing).await.unwrap();
+        assert_eq!(check.status, Status::Fail);
+        assert_eq!(
+            check.to_string(),
+            format!(
+                "[FAIL] tool `echo`: {} does not exist\n       \
+                 fix: build the component, or fix its path in the map",
+                tmp.path().join("missing.wasm").display()
+            )
+        );
+    }
+}
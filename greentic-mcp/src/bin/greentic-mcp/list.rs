@@ -0,0 +1,140 @@
+//! `greentic-mcp list`: print the tools of a map or store.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::Args;
+use greentic_mcp::ToolMap;
+use mcp_exec::ToolStore;
+use serde::Serialize;
+
+use crate::Cli;
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Print a JSON array instead of a table.
+    #[arg(long)]
+    json: bool,
+    /// List the components in this local store directory instead of the tool map.
+    #[arg(long)]
+    store: Option<PathBuf>,
+}
+
+/// One listed tool.
+#[derive(Debug, PartialEq, Serialize)]
+struct Row {
+    name: String,
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
+}
+
+pub fn run(cli: &Cli, args: &ListArgs) -> anyhow::Result<()> {
+    let rows = match &args.store {
+        Some(dir) => store_rows(&ToolStore::LocalDir(dir.clone()))?,
+        None => map_rows(&cli.load_map()?),
+    };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        print!("{}", table(&rows));
+    }
+    Ok(())
+}
+
+fn map_rows(map: &ToolMap) -> Vec<Row> {
+    map.iter()
+        .map(|(key, tool)| Row {
+            name: key.clone(),
+            source: tool.source().to_string(),
+            digest: tool.sha256.clone(),
+            timeout_ms: tool.timeout().map(|timeout| timeout.as_millis() as u64),
+            labels: tool.labels.clone(),
+        })
+        .collect()
+}
+
+fn store_rows(store: &ToolStore) -> anyhow::Result<Vec<Row>> {
+    Ok(store
+        .list()?
+        .into_iter()
+        .map(|info| Row {
+            name: info.name,
+            source: info.path.display().to_string(),
+            digest: info.sha256,
+            timeout_ms: None,
+            labels: BTreeMap::new(),
+        })
+        .collect())
+}
+
+fn table(rows: &[Row]) -> String {
+    let header = ["NAME", "SOURCE", "DIGEST", "TIMEOUT", "LABELS"].map(String::from);
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| {
+            let labels: Vec<_> = row.labels.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            [
+                row.name.clone(),
+                row.source.clone(),
+                // Enough of the digest to tell components apart.
+                row.digest
+                    .as_deref()
+                    .map_or("-".into(), |digest| digest.chars().take(12).collect()),
+                row.timeout_ms.map_or("-".into(), |ms| format!("{ms}ms")),
+                if labels.is_empty() {
+                    "-".into()
+                } else {
+                    labels.join(",")
+                },
+            ]
+        })
+        .collect();
+    let mut widths = header.clone().map(|cell| cell.len());
+    for line in &cells {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for line in std::iter::once(&header).chain(&cells) {
+        let padded: Vec<_> = line
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        out.push_str(padded.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use greentic_mcp::{ToolMapConfig, ToolRef};
+
+    use super::*;
+
+    #[test]
+    fn lists_map_tools_as_a_table() {
+        let config = ToolMapConfig {
+            tools: vec![ToolRef {
+                timeout_ms: Some(500),
+                labels: BTreeMap::from([("audience".into(), "agent".into())]),
+                ..ToolRef::new("echo", "./echo.wasm", "run")
+            }],
+            ..Default::default()
+        };
+        let rows = map_rows(&ToolMap::from_config(&config).unwrap());
+        assert_eq!(rows[0].timeout_ms, Some(500));
+        assert_eq!(
+            table(&rows),
+            "NAME  SOURCE       DIGEST  TIMEOUT  LABELS\n\
+             echo  ./echo.wasm  -       500ms    audience=agent\n"
+        );
+    }
+}
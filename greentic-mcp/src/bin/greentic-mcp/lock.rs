@@ -0,0 +1,37 @@
+//! `greentic-mcp lock`: record the component digests of the map in a lockfile.
+
+use anyhow::{Context, bail};
+use clap::Args;
+use greentic_mcp::ToolLock;
+
+use crate::Cli;
+
+#[derive(Debug, Args)]
+pub struct LockArgs {
+    /// Fail if the lockfile does not match the map's components instead of
+    /// updating it.
+    #[arg(long)]
+    check: bool,
+}
+
+pub async fn run(cli: &Cli, args: &LockArgs) -> anyhow::Result<()> {
+    let path = cli.lock_path();
+    let current = ToolLock::resolve(&cli.load_map()?, &cli.executor()?).await?;
+    if args.check {
+        let locked = ToolLock::read(&path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let stale = locked.stale(&current);
+        if !stale.is_empty() {
+            bail!(
+                "`{}` is stale for {}; run `greentic-mcp lock` to update it",
+                path.display(),
+                stale.join(", ")
+            );
+        }
+        println!("{} is up to date", path.display());
+    } else {
+        current.write(&path)?;
+        println!("locked {} tools in {}", current.tools.len(), path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! `greentic-mcp` command line, built with the `cli` feature.
+
+mod describe;
+mod doctor;
+mod list;
+mod lock;
+mod pull;
+mod record;
+mod run;
+mod serve;
+mod sign;
+mod verify;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use greentic_mcp::{
+    LOCK_FILE, McpError, ToolLock, ToolMap, WasixExecutor, load_tool_map_config,
+    load_tool_map_config_for_env,
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "greentic-mcp",
+    version,
+    about = "Run and host Greentic MCP tools"
+)]
+struct Cli {
+    /// Tool map to load.
+    #[arg(long, short, global = true, default_value = "toolmap.yaml")]
+    map: PathBuf,
+    /// Environment whose overlay (e.g. `toolmap.prod.yaml`) is applied to the map.
+    #[arg(long, global = true)]
+    env: Option<String>,
+    /// Directory caching components fetched from remote sources.
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+    /// Lockfile; `tools.lock` next to the map by default.
+    #[arg(long, global = true)]
+    lock: Option<PathBuf>,
+    /// Pin every tool to the digest in the lockfile.
+    #[arg(long, global = true)]
+    locked: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Invoke a tool and print its output as JSON.
+    Run(run::RunArgs),
+    /// Print the tools of the map (or a store) with their source, digest,
+    /// timeout, and labels.
+    List(list::ListArgs),
+    /// Print a component's describe-v1 document, capabilities, secrets, and
+    /// config schema.
+    Describe(describe::DescribeArgs),
+    /// Fetch every component of the map into the cache directory.
+    Pull(pull::PullArgs),
+    /// Sign a component, writing `<component>.sig`.
+    Sign(sign::SignArgs),
+    /// Check a component's signature and, optionally, its signer.
+    Verify(verify::VerifyArgs),
+    /// Serve the map's tools to MCP clients over stdio, HTTP, or WebSocket.
+    Serve(serve::ServeArgs),
+    /// Invoke a tool and append the invocation to a recording file.
+    Record(record::RecordArgs),
+    /// Replay recorded invocations against the current map and report changes.
+    Replay(record::ReplayArgs),
+    /// Write the component digest of every tool to the lockfile, or check it.
+    Lock(lock::LockArgs),
+    /// Check the runtime, cache directory, guest toolchain, and tool sources.
+    Doctor(doctor::DoctorArgs),
+}
+
+impl Cli {
+    fn load_map(&self) -> Result<ToolMap, McpError> {
+        let config = match &self.env {
+            Some(env) => load_tool_map_config_for_env(&self.map, env)?,
+            None => load_tool_map_config(&self.map)?,
+        };
+        let map = ToolMap::from_config(&config)?;
+        if self.locked {
+            return ToolLock::read(self.lock_path())?.pin(&map);
+        }
+        Ok(map)
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.lock
+            .clone()
+            .unwrap_or_else(|| self.map.with_file_name(LOCK_FILE))
+    }
+
+    fn executor(&self) -> Result<WasixExecutor, McpError> {
+        let executor = WasixExecutor::new()?;
+        Ok(match &self.cache_dir {
+            Some(dir) => executor.with_cache_dir(dir),
+            None => executor,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Command::Run(args) => run::run(&cli, args).await,
+        Command::List(args) => list::run(&cli, args),
+        Command::Describe(args) => describe::run(&cli, args),
+        Command::Pull(args) => pull::run(&cli, args).await,
+        Command::Sign(args) => sign::run(args),
+        Command::Verify(args) => verify::run(args),
+        Command::Serve(args) => serve::run(&cli, args).await,
+        Command::Record(args) => record::record(&cli, args).await,
+        Command::Replay(args) => record::replay(&cli, args).await,
+        Command::Lock(args) => lock::run(&cli, args).await,
+        Command::Doctor(args) => doctor::run(&cli, args).await,
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            match err.downcast_ref::<McpError>() {
+                Some(mcp) => eprintln!("error [{}]: {err:#}", mcp.code()),
+                None => eprintln!("error: {err:#}"),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
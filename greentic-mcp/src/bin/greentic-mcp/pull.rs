@@ -0,0 +1,73 @@
+//! `greentic-mcp pull`: pre-warm the component cache for a tool map.
+
+use std::sync::Arc;
+
+use anyhow::bail;
+use clap::Args;
+use greentic_mcp::{ToolRef, ToolSource};
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::Cli;
+
+#[derive(Debug, Args)]
+pub struct PullArgs {
+    /// Components fetched at once.
+    #[arg(long, default_value_t = 4)]
+    parallel: usize,
+    /// Do not draw a progress bar.
+    #[arg(long)]
+    quiet: bool,
+}
+
+pub async fn run(cli: &Cli, args: &PullArgs) -> anyhow::Result<()> {
+    let map = cli.load_map()?;
+    let executor = cli.executor()?;
+    // Tools served by remote MCP servers have no component.
+    let tools: Vec<ToolRef> = map
+        .iter()
+        .map(|(_, tool)| tool)
+        .filter(|tool| !matches!(tool.source, Some(ToolSource::Mcp(_))))
+        .cloned()
+        .collect();
+    let progress = if args.quiet {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(tools.len() as u64)
+    };
+    progress.set_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} {wide_msg}").expect("valid template"),
+    );
+
+    let permits = Arc::new(Semaphore::new(args.parallel.max(1)));
+    let mut pulls = JoinSet::new();
+    for tool in tools {
+        let executor = executor.clone();
+        let permits = permits.clone();
+        let progress = progress.clone();
+        pulls.spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed");
+            progress.set_message(tool.source().to_string());
+            let result = executor.prefetch(&tool).await;
+            progress.inc(1);
+            (tool, result)
+        });
+    }
+    let mut failed = 0;
+    while let Some(joined) = pulls.join_next().await {
+        let (tool, result) = joined?;
+        if let Err(err) = result {
+            failed += 1;
+            progress.println(format!("failed to pull `{}`: {err}", tool.key()));
+        }
+    }
+    progress.finish_and_clear();
+    if failed > 0 {
+        bail!("failed to pull {failed} component(s)");
+    }
+    Ok(())
+}
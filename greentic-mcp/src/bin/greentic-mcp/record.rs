@@ -0,0 +1,225 @@
+//! `greentic-mcp record` and `replay`: regression-test tool upgrades against
+//! recorded invocations.
+//!
+//! Recordings are JSON lines holding a tool's input, its output or error code, and
+//! the progress it reported. Replaying runs each input against the tool in the
+//! current map and reports what changed.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, bail};
+use clap::Args;
+use greentic_mcp::{InvokeOptions, McpError, ProgressSink, ToolInput, ToolMap, WasixExecutor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Cli;
+use crate::run::parse_args;
+
+#[derive(Debug, Args)]
+pub struct RecordArgs {
+    /// Name of the tool, as registered in the map.
+    tool: String,
+    /// Input payload as JSON, or `@path` to read it from a file.
+    #[arg(long, default_value = "{}")]
+    args: String,
+    /// Recording file the invocation is appended to.
+    #[arg(long, default_value = "recordings.jsonl")]
+    out: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// Recording file written by `record`.
+    #[arg(default_value = "recordings.jsonl")]
+    recordings: PathBuf,
+}
+
+/// One recorded invocation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Recording {
+    tool: String,
+    input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<Value>,
+    /// Code of the error the invocation failed with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Progress the tool reported, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    progress: Vec<Value>,
+}
+
+pub async fn record(cli: &Cli, args: &RecordArgs) -> anyhow::Result<()> {
+    let input = parse_args(&args.args)?;
+    let map = cli.load_map()?;
+    let (recording, result) = invoke(&map, &cli.executor()?, &args.tool, input).await;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&args.out)
+        .with_context(|| format!("failed to open `{}`", args.out.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&recording)?)?;
+    let output = result?;
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+pub async fn replay(cli: &Cli, args: &ReplayArgs) -> anyhow::Result<()> {
+    let text = fs::read_to_string(&args.recordings)
+        .with_context(|| format!("failed to read `{}`", args.recordings.display()))?;
+    let map = cli.load_map()?;
+    let executor = cli.executor()?;
+    let (mut total, mut changed) = (0, 0);
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let recorded: Recording = serde_json::from_str(line).with_context(|| {
+            format!(
+                "invalid recording on line {} of `{}`",
+                index + 1,
+                args.recordings.display()
+            )
+        })?;
+        let (replayed, _) = invoke(&map, &executor, &recorded.tool, recorded.input.clone()).await;
+        let changes = changes(&recorded, &replayed);
+        total += 1;
+        if changes.is_empty() {
+            println!("ok       {} (line {})", recorded.tool, index + 1);
+        } else {
+            changed += 1;
+            println!("changed  {} (line {})", recorded.tool, index + 1);
+            for change in changes {
+                println!("  {change}");
+            }
+        }
+    }
+    if changed > 0 {
+        bail!("{changed} of {total} recordings changed");
+    }
+    Ok(())
+}
+
+/// Invoke `tool`, recording the outcome and the progress it reports.
+async fn invoke(
+    map: &ToolMap,
+    executor: &WasixExecutor,
+    tool: &str,
+    input: Value,
+) -> (Recording, Result<Value, McpError>) {
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let sink = ProgressSink::new({
+        let progress = progress.clone();
+        move |report| {
+            let report = serde_json::to_value(report).expect("progress serializes");
+            progress.lock().expect("progress poisoned").push(report);
+        }
+    });
+    let options = InvokeOptions {
+        progress: Some(sink),
+        ..InvokeOptions::default()
+    };
+    let result = match map.get(tool) {
+        Ok(tool_ref) => executor
+            .invoke_with(tool_ref, &ToolInput::new(input.clone()), options)
+            .await
+            .map(|output| output.payload),
+        Err(err) => Err(err),
+    };
+    let recording = Recording {
+        tool: tool.to_string(),
+        input,
+        output: result.as_ref().ok().cloned(),
+        error: result.as_ref().err().map(|err| err.code().to_string()),
+        progress: std::mem::take(&mut progress.lock().expect("progress poisoned")),
+    };
+    (recording, result)
+}
+
+/// What differs between a recorded and a replayed invocation.
+fn changes(recorded: &Recording, replayed: &Recording) -> Vec<String> {
+    let mut changes = Vec::new();
+    if recorded.error != replayed.error {
+        changes.push(format!(
+            "error: {} -> {}",
+            recorded.error.as_deref().unwrap_or("none"),
+            replayed.error.as_deref().unwrap_or("none")
+        ));
+    }
+    let null = Value::Null;
+    diff_values(
+        "/output",
+        recorded.output.as_ref().unwrap_or(&null),
+        replayed.output.as_ref().unwrap_or(&null),
+        &mut changes,
+    );
+    diff_values(
+        "/progress",
+        &Value::from(recorded.progress.clone()),
+        &Value::from(replayed.progress.clone()),
+        &mut changes,
+    );
+    changes
+}
+
+/// Push one line per JSON pointer at which `old` and `new` differ.
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let child = |key: &str| format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+            for (key, old_value) in old {
+                let new_value = new.get(key).unwrap_or(&Value::Null);
+                diff_values(&child(key), old_value, new_value, changes);
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                changes.push(format!("{}: (absent) -> {new_value}", child(key)));
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items))
+            if old_items.len() == new_items.len() =>
+        {
+            for (index, (old, new)) in old_items.iter().zip(new_items).enumerate() {
+                diff_values(&format!("{path}/{index}"), old, new, changes);
+            }
+        }
+        (old, new) if old != new => changes.push(format!("{path}: {old} -> {new}")),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reports_changed_outputs_by_pointer() {
+        let recorded = Recording {
+            tool: "weather".into(),
+            input: json!({ "city": "Paris" }),
+            output: Some(json!({ "temp": 21, "units": "C", "tags": ["sun"] })),
+            error: None,
+            progress: vec![json!({ "progress": 1.0 })],
+        };
+        assert!(changes(&recorded, &recorded).is_empty());
+
+        let replayed = Recording {
+            output: Some(json!({ "temp": 21, "units": "F", "tags": ["sun"], "wind": 3 })),
+            progress: Vec::new(),
+            ..recorded.clone()
+        };
+        assert_eq!(
+            changes(&recorded, &replayed),
+            [
+                r#"/output/units: "C" -> "F""#,
+                "/output/wind: (absent) -> 3",
+                r#"/progress: [{"progress":1.0}] -> []"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! `greentic-mcp run`: invoke one tool from the command line.
+
+use std::path::Path;
+
+use anyhow::Context;
+use clap::Args;
+use greentic_mcp::invoke_with_map;
+use serde_json::Value;
+
+use crate::Cli;
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Name of the tool, as registered in the map.
+    tool: String,
+    /// Input payload as JSON, or `@path` to read it from a file.
+    #[arg(long, default_value = "{}")]
+    args: String,
+}
+
+pub async fn run(cli: &Cli, args: &RunArgs) -> anyhow::Result<()> {
+    let input = parse_args(&args.args)?;
+    let map = cli.load_map()?;
+    let executor = cli.executor()?;
+    let output = invoke_with_map(&map, &executor, &args.tool, input).await?;
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+pub fn parse_args(args: &str) -> anyhow::Result<Value> {
+    match args.strip_prefix('@') {
+        Some(path) => {
+            let content = std::fs::read_to_string(Path::new(path))
+                .with_context(|| format!("failed to read `{path}`"))?;
+            serde_json::from_str(&content).with_context(|| format!("invalid JSON in `{path}`"))
+        }
+        None => serde_json::from_str(args).context("`--args` is not valid JSON"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_args_inline_or_from_a_file() {
+        assert_eq!(
+            parse_args(r#"{"q": 1}"#).unwrap(),
+            serde_json::json!({ "q": 1 })
+        );
+        assert!(parse_args("{").is_err());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("args.json");
+        std::fs::write(&path, "[1, 2]").unwrap();
+        let arg = format!("@{}", path.display());
+        assert_eq!(parse_args(&arg).unwrap(), serde_json::json!([1, 2]));
+    }
+}
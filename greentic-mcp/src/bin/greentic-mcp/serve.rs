@@ -0,0 +1,64 @@
+//! `greentic-mcp serve`: host the tool map as an MCP server.
+
+use std::net::SocketAddr;
+
+use anyhow::{Context, bail};
+use clap::{Args, ValueEnum};
+use greentic_mcp::{AccessGrant, McpAuth, McpServer, StaticTokens};
+use tokio::net::TcpListener;
+
+use crate::Cli;
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Transport to serve MCP over.
+    #[arg(long, value_enum, default_value_t = Transport::Stdio)]
+    transport: Transport,
+    /// Address network transports listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    /// Bearer token required on network transports, granting every tool.
+    #[arg(long)]
+    token: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Transport {
+    /// Newline-delimited JSON-RPC on stdin/stdout.
+    Stdio,
+    /// Streamable HTTP: one JSON-RPC message per POST.
+    Http,
+    /// One JSON-RPC message per WebSocket text frame.
+    #[cfg(feature = "websocket")]
+    Websocket,
+}
+
+pub async fn run(cli: &Cli, args: &ServeArgs) -> anyhow::Result<()> {
+    let mut server = McpServer::new(cli.load_map()?, cli.executor()?);
+    if let Some(token) = &args.token {
+        server = server.with_auth(McpAuth::new(
+            StaticTokens::new().token(token, AccessGrant::new("greentic-mcp")),
+        ));
+    }
+    match args.transport {
+        Transport::Stdio => {
+            if args.token.is_some() {
+                bail!("`--token` only applies to network transports");
+            }
+            server.serve_stdio().await?
+        }
+        Transport::Http => server.serve_http(listen(args.listen).await?).await?,
+        #[cfg(feature = "websocket")]
+        Transport::Websocket => server.serve_websocket(listen(args.listen).await?).await?,
+    }
+    Ok(())
+}
+
+async fn listen(addr: SocketAddr) -> anyhow::Result<TcpListener> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to listen on {addr}"))?;
+    // Stdout belongs to the protocol on stdio, so this goes to stderr.
+    eprintln!("serving MCP on {}", listener.local_addr()?);
+    Ok(listener)
+}
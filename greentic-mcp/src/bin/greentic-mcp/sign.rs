@@ -0,0 +1,55 @@
+//! `greentic-mcp sign`: sign a component for `VerifyPolicy::trusted_signers`.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use clap::Args;
+use mcp_exec::signing::{self, SigningKey};
+
+#[derive(Debug, Args)]
+pub struct SignArgs {
+    /// Component to sign; the signature is written to `<component>.sig`.
+    component: PathBuf,
+    /// Signing key file, 64 hex characters.
+    #[arg(long)]
+    key: PathBuf,
+    /// Create a new signing key at `--key` first.
+    #[arg(long)]
+    generate_key: bool,
+}
+
+pub fn run(args: &SignArgs) -> anyhow::Result<()> {
+    let key = if args.generate_key {
+        generate_key(&args.key)?
+    } else {
+        signing::read_signing_key(&args.key)?
+    };
+    let bytes = fs::read(&args.component)
+        .with_context(|| format!("failed to read `{}`", args.component.display()))?;
+    let signature = signing::sign(&bytes, &key);
+    let path = signature.write(&args.component)?;
+    println!("signer: {}", signature.signer);
+    println!("sha256: {}", signature.sha256);
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+/// Write a new random key to `path`, readable only by its owner.
+fn generate_key(path: &Path) -> anyhow::Result<SigningKey> {
+    if path.exists() {
+        bail!("`{}` already exists", path.display());
+    }
+    let key = SigningKey::from_bytes(&rand::random());
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to create `{}`", path.display()))?;
+    writeln!(file, "{}", hex::encode(key.to_bytes()))?;
+    eprintln!("wrote a new signing key to {}", path.display());
+    Ok(key)
+}
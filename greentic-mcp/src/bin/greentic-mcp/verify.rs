@@ -0,0 +1,43 @@
+//! `greentic-mcp verify`: check a component's signature.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, bail};
+use clap::Args;
+use mcp_exec::signing::{self, ComponentSignature};
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    /// Component whose `<component>.sig` is checked.
+    component: PathBuf,
+    /// Public key (hex) of a trusted signer; repeat for several. Without any, a
+    /// valid signature by anyone is accepted.
+    #[arg(long = "signer")]
+    signers: Vec<String>,
+}
+
+pub fn run(args: &VerifyArgs) -> anyhow::Result<()> {
+    let bytes = fs::read(&args.component)
+        .with_context(|| format!("failed to read `{}`", args.component.display()))?;
+    let Some(signature) = ComponentSignature::read(&args.component)? else {
+        bail!(
+            "`{}` is unsigned: {} does not exist",
+            args.component.display(),
+            signing::signature_path(&args.component).display()
+        );
+    };
+    signature.verify(&bytes)?;
+    if !args.signers.is_empty()
+        && signing::trusted_signer(&args.component, &bytes, &args.signers)?.is_none()
+    {
+        bail!(
+            "`{}` is signed by untrusted signer {}",
+            args.component.display(),
+            signature.signer
+        );
+    }
+    println!("signer: {}", signature.signer);
+    println!("sha256: {}", signature.sha256);
+    Ok(())
+}
@@ -0,0 +1,353 @@
+//! Binary data exchanged with tools without encoding it into JSON.
+//!
+//! Guests import [`OUTPUT_INTERFACE`] (see `ABI.md`) and `write` raw bytes, such as
+//! images, PDFs, or archives, with their content type. What the successful attempt
+//! wrote ends up in [`ToolOutput::binary`](crate::types::ToolOutput::binary), up to
+//! [`DEFAULT_MAX_BINARY_OUTPUT`] bytes unless the executor sets another limit with
+//! [`WasixExecutor::with_max_binary_output`](crate::executor::WasixExecutor::with_max_binary_output).
+//!
+//! In the other direction, [`Attachment`]s of a
+//! [`ToolInput`](crate::types::ToolInput) are read by guests through
+//! [`INPUT_INTERFACE`], in chunks of their choosing.
+
+use std::sync::{Arc, Mutex};
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
+use wasmtime::StoreContextMut;
+use wasmtime::component::Linker;
+
+/// Interface guests import to return binary output.
+pub const OUTPUT_INTERFACE: &str = "greentic:mcp/output@0.1.0";
+
+/// Interface guests import to read the attachments of their input.
+pub const INPUT_INTERFACE: &str = "greentic:mcp/input@0.1.0";
+
+/// Bytes a tool may write per invocation unless the executor says otherwise.
+pub const DEFAULT_MAX_BINARY_OUTPUT: usize = 16 * 1024 * 1024;
+
+/// Binary output of a tool. Serializes `bytes` as base64, for JSON consumers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryOutput {
+    pub content_type: String,
+    #[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
+    pub bytes: Vec<u8>,
+}
+
+impl BinaryOutput {
+    /// MCP content item for the output: `image` or `audio` for those content types,
+    /// an embedded `resource` blob at `uri` otherwise.
+    pub fn mcp_content(&self, uri: &str) -> Value {
+        let data = BASE64.encode(&self.bytes);
+        match self.content_type.split('/').next() {
+            Some(kind @ ("image" | "audio")) => {
+                json!({ "type": kind, "data": data, "mimeType": self.content_type })
+            }
+            _ => json!({
+                "type": "resource",
+                "resource": { "uri": uri, "mimeType": self.content_type, "blob": data },
+            }),
+        }
+    }
+}
+
+/// Named binary data passed to a tool next to its JSON payload, see
+/// [`ToolInput::with_attachment`](crate::types::ToolInput::with_attachment).
+/// Serializes `bytes` as base64; clones share the bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    pub content_type: String,
+    #[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
+    pub bytes: Arc<[u8]>,
+}
+
+impl Attachment {
+    pub fn new(
+        name: impl Into<String>,
+        content_type: impl Into<String>,
+        bytes: impl Into<Arc<[u8]>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            content_type: content_type.into(),
+            bytes: bytes.into(),
+        }
+    }
+}
+
+/// Up to `len` bytes of the attachment named `name`, starting at `offset`; empty at
+/// the end of the attachment.
+fn read(attachments: &[Attachment], name: &str, offset: u64, len: u32) -> Result<Vec<u8>, String> {
+    let attachment = attachments
+        .iter()
+        .find(|attachment| attachment.name == name)
+        .ok_or_else(|| format!("not-found: {name}"))?;
+    let size = attachment.bytes.len();
+    let start = usize::try_from(offset).unwrap_or(usize::MAX).min(size);
+    let end = start.saturating_add(len as usize).min(size);
+    Ok(attachment.bytes[start..end].to_vec())
+}
+
+fn to_base64<S: Serializer, B: AsRef<[u8]>>(bytes: &B, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&BASE64.encode(bytes))
+}
+
+fn from_base64<'de, D, B>(deserializer: D) -> Result<B, D::Error>
+where
+    D: Deserializer<'de>,
+    B: From<Vec<u8>>,
+{
+    let text = String::deserialize(deserializer)?;
+    BASE64
+        .decode(text)
+        .map(B::from)
+        .map_err(serde::de::Error::custom)
+}
+
+/// What the guest of the current attempt wrote, shared with the executor.
+#[derive(Clone, Debug)]
+pub(crate) struct BinarySink {
+    max_bytes: usize,
+    output: Arc<Mutex<Option<BinaryOutput>>>,
+}
+
+impl BinarySink {
+    pub(crate) fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            output: Arc::default(),
+        }
+    }
+
+    /// Forget the output of a previous attempt.
+    pub(crate) fn reset(&self) {
+        self.output().take();
+    }
+
+    pub(crate) fn take(&self) -> Option<BinaryOutput> {
+        self.output().take()
+    }
+
+    /// Append `bytes`; every write of an invocation has to use the same content type.
+    fn write(&self, content_type: String, bytes: Vec<u8>) -> Result<(), String> {
+        let mut output = self.output();
+        let written = match output.as_ref() {
+            Some(current) if current.content_type != content_type => {
+                return Err(format!(
+                    "content-type-mismatch: already writing {}",
+                    current.content_type
+                ));
+            }
+            Some(current) => current.bytes.len(),
+            None => 0,
+        };
+        if written + bytes.len() > self.max_bytes {
+            return Err(format!("too-large: limit is {} bytes", self.max_bytes));
+        }
+        output
+            .get_or_insert_with(|| BinaryOutput {
+                content_type,
+                bytes: Vec::new(),
+            })
+            .bytes
+            .extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn output(&self) -> std::sync::MutexGuard<'_, Option<BinaryOutput>> {
+        self.output.lock().expect("binary output poisoned")
+    }
+}
+
+/// Define [`OUTPUT_INTERFACE`] in `linker`, writing to the sink returned by `sink`,
+/// and [`INPUT_INTERFACE`], reading the attachments returned by `attachments`.
+pub(crate) fn add_to_linker<T: 'static>(
+    linker: &mut Linker<T>,
+    sink: fn(&mut T) -> &BinarySink,
+    attachments: fn(&mut T) -> &[Attachment],
+) -> wasmtime::Result<()> {
+    linker.instance(OUTPUT_INTERFACE)?.func_wrap(
+        "write",
+        move |mut store: StoreContextMut<'_, T>, (content_type, bytes): (String, Vec<u8>)| {
+            Ok((sink(store.data_mut()).write(content_type, bytes),))
+        },
+    )?;
+    let mut input = linker.instance(INPUT_INTERFACE)?;
+    input.func_wrap("list", move |mut store: StoreContextMut<'_, T>, (): ()| {
+        let list: Vec<(String, String, u64)> = attachments(store.data_mut())
+            .iter()
+            .map(|attachment| {
+                let size = attachment.bytes.len() as u64;
+                (
+                    attachment.name.clone(),
+                    attachment.content_type.clone(),
+                    size,
+                )
+            })
+            .collect();
+        Ok((list,))
+    })?;
+    input.func_wrap(
+        "read",
+        move |mut store: StoreContextMut<'_, T>, (name, offset, len): (String, u64, u32)| {
+            Ok((read(attachments(store.data_mut()), &name, offset, len),))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::WasixExecutor;
+    use crate::types::{ToolInput, ToolRef};
+
+    /// Component whose `tool-invoke` writes the 4-byte `image/png` `\x89PNG` and
+    /// returns `{"written":true}`, or `{"written":false}` when the host refuses.
+    fn binary_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (import "greentic:mcp/output@0.1.0" (instance $output
+                    (export "write" (func (param "content-type" string)
+                        (param "bytes" (list u8)) (result (result (error string)))))))
+                (core module $Libc
+                    (memory (export "memory") 1)
+                    (global $next (mut i32) (i32.const 1024))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (local $ptr i32)
+                        (local.set $ptr (global.get $next))
+                        (global.set $next (i32.and
+                            (i32.add (i32.add (local.get $ptr) (local.get 3)) (i32.const 7))
+                            (i32.const -8)))
+                        (local.get $ptr)))
+                (core instance $libc (instantiate $Libc))
+                (core func $write (canon lower (func $output "write")
+                    (memory $libc "memory") (realloc (func $libc "realloc"))))
+                (core module $Tool
+                    (import "host" "write" (func $write (param i32 i32 i32 i32 i32)))
+                    (import "host" "memory" (memory 1))
+                    (data (i32.const 16) "image/png")
+                    (data (i32.const 32) "\89PNG")
+                    (data (i32.const 64) "{\"written\":true}")
+                    (data (i32.const 96) "{\"written\":false}")
+                    (data (i32.const 128) "\40\00\00\00\10\00\00\00\60\00\00\00\11\00\00\00")
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (call $write (i32.const 16) (i32.const 9) (i32.const 32) (i32.const 4)
+                            (i32.const 256))
+                        (if (result i32) (i32.load8_u (i32.const 256))
+                            (then (i32.const 136))
+                            (else (i32.const 128)))))
+                (core instance $host
+                    (export "write" (func $write))
+                    (export "memory" (memory $libc "memory")))
+                (core instance $tool (instantiate $Tool (with "host" (instance $host))))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $libc "memory")
+                        (realloc (func $libc "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    /// Component whose `tool-invoke` returns the first 1 KiB of the attachment `doc`
+    /// as its output, or `{"missing":true}` when there is none.
+    fn attachment_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (import "greentic:mcp/input@0.1.0" (instance $input
+                    (export "read" (func (param "name" string) (param "offset" u64)
+                        (param "len" u32) (result (result (list u8) (error string)))))))
+                (core module $Libc
+                    (memory (export "memory") 1)
+                    (global $next (mut i32) (i32.const 1024))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (local $ptr i32)
+                        (local.set $ptr (global.get $next))
+                        (global.set $next (i32.and
+                            (i32.add (i32.add (local.get $ptr) (local.get 3)) (i32.const 7))
+                            (i32.const -8)))
+                        (local.get $ptr)))
+                (core instance $libc (instantiate $Libc))
+                (core func $read (canon lower (func $input "read")
+                    (memory $libc "memory") (realloc (func $libc "realloc"))))
+                (core module $Tool
+                    (import "host" "read" (func $read (param i32 i32 i64 i32 i32)))
+                    (import "host" "memory" (memory 1))
+                    (data (i32.const 16) "doc")
+                    (data (i32.const 64) "{\"missing\":true}")
+                    (data (i32.const 96) "\40\00\00\00\10\00\00\00")
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (call $read (i32.const 16) (i32.const 3) (i64.const 0) (i32.const 1024)
+                            (i32.const 256))
+                        (if (result i32) (i32.load8_u (i32.const 256))
+                            (then (i32.const 96))
+                            (else (i32.const 260)))))
+                (core instance $host
+                    (export "read" (func $read))
+                    (export "memory" (memory $libc "memory")))
+                (core instance $tool (instantiate $Tool (with "host" (instance $host))))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $libc "memory")
+                        (realloc (func $libc "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    #[tokio::test]
+    async fn guests_read_attachments() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("pdf-info.wasm");
+        std::fs::write(&path, attachment_component()).unwrap();
+        let tool = ToolRef::new("pdf-info", path.to_string_lossy(), "tool-invoke");
+        let executor = WasixExecutor::new().unwrap();
+
+        let input = ToolInput::new(json!({})).with_attachment(Attachment::new(
+            "doc",
+            "application/json",
+            br#"{"pages":3}"#.to_vec(),
+        ));
+        let output = executor.invoke(&tool, &input).await.unwrap();
+        assert_eq!(output.payload, json!({ "pages": 3 }));
+
+        let output = executor
+            .invoke(&tool, &ToolInput::new(json!({})))
+            .await
+            .unwrap();
+        assert_eq!(output.payload, json!({ "missing": true }));
+
+        let attachments = &input.attachments[..];
+        assert_eq!(read(attachments, "doc", 9, 10).unwrap(), b"3}");
+        assert_eq!(read(attachments, "doc", 64, 10).unwrap(), b"");
+        let json = serde_json::to_value(&input).unwrap();
+        assert_eq!(json["attachments"][0]["bytes"], "eyJwYWdlcyI6M30=");
+    }
+
+    #[tokio::test]
+    async fn returns_written_bytes_within_the_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("chart.wasm");
+        std::fs::write(&path, binary_component()).unwrap();
+        let tool = ToolRef::new("chart", path.to_string_lossy(), "tool-invoke");
+        let input = ToolInput::new(json!({}));
+
+        let output = WasixExecutor::new()
+            .unwrap()
+            .invoke(&tool, &input)
+            .await
+            .unwrap();
+        assert_eq!(output.payload, json!({ "written": true }));
+        let binary = output.binary.unwrap();
+        assert_eq!(binary.content_type, "image/png");
+        assert_eq!(binary.bytes, b"\x89PNG");
+        assert_eq!(
+            binary.mcp_content("output://chart"),
+            json!({ "type": "image", "data": "iVBORw==", "mimeType": "image/png" })
+        );
+
+        let executor = WasixExecutor::new().unwrap().with_max_binary_output(2);
+        let output = executor.invoke(&tool, &input).await.unwrap();
+        assert_eq!(output.payload, json!({ "written": false }));
+        assert!(output.binary.is_none());
+    }
+}
@@ -0,0 +1,295 @@
+//! Fluent construction of [`ToolMap`]s in code.
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::retry::RetryPolicy;
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolDefaults, ToolExample, ToolMapConfig, ToolRef, ToolSource};
+
+/// Builds a [`ToolMap`] without hand-assembling [`ToolRef`]s.
+///
+/// ```
+/// use std::time::Duration;
+/// use greentic_mcp::ToolMapBuilder;
+///
+/// let map = ToolMapBuilder::new()
+///     .tool("echo")
+///     .component("./tools/echo.wasm")
+///     .timeout(Duration::from_secs(1))
+///     .tool("weather")
+///     .url("https://tools.example/weather.wasm")
+///     .max_retries(2)
+///     .build()?;
+/// assert!(map.get("weather").is_ok());
+/// # Ok::<(), greentic_mcp::McpError>(())
+/// ```
+///
+/// `build()` applies the same checks as loading a config file, so duplicate keys and tools
+/// without a component are rejected.
+#[derive(Clone, Debug, Default)]
+pub struct ToolMapBuilder {
+    config: ToolMapConfig,
+    host_features: Vec<String>,
+}
+
+impl ToolMapBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Namespace applied to tools that do not set their own.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.config.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Execution hints inherited by tools that do not set them.
+    pub fn defaults(mut self, defaults: ToolDefaults) -> Self {
+        self.config.defaults = Some(defaults);
+        self
+    }
+
+    /// Declare a host feature so tools requiring it are registered.
+    pub fn host_feature(mut self, feature: impl Into<String>) -> Self {
+        self.host_features.push(feature.into());
+        self
+    }
+
+    /// Add a fully assembled tool reference.
+    pub fn tool_ref(mut self, tool: ToolRef) -> Self {
+        self.config.tools.push(tool);
+        self
+    }
+
+    /// Start describing a tool invoked through [`ToolRef::DEFAULT_ENTRY`].
+    pub fn tool(self, name: impl Into<String>) -> ToolBuilder {
+        ToolBuilder {
+            parent: self,
+            tool: ToolRef::new(name, "", ToolRef::DEFAULT_ENTRY),
+        }
+    }
+
+    /// Validate the collected tools and build the map.
+    pub fn build(mut self) -> Result<ToolMap, McpError> {
+        if let Some(namespace) = &self.config.namespace {
+            for tool in self.config.tools.iter_mut() {
+                tool.namespace.get_or_insert_with(|| namespace.clone());
+            }
+        }
+        let features: Vec<&str> = self.host_features.iter().map(String::as_str).collect();
+        ToolMap::from_config_with_features(&self.config, &features)
+    }
+}
+
+/// A tool being described within a [`ToolMapBuilder`].
+#[derive(Clone, Debug)]
+pub struct ToolBuilder {
+    parent: ToolMapBuilder,
+    tool: ToolRef,
+}
+
+impl ToolBuilder {
+    /// Filesystem path of the component.
+    pub fn component(mut self, path: impl Into<String>) -> Self {
+        self.tool.component = path.into();
+        self
+    }
+
+    /// Fetch the component from an HTTP(S) URL.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.tool.source = Some(ToolSource::Url(url.into()));
+        self
+    }
+
+    /// Fetch the component from an explicit source.
+    pub fn source(mut self, source: ToolSource) -> Self {
+        self.tool.source = Some(source);
+        self
+    }
+
+    pub fn entry(mut self, entry: impl Into<String>) -> Self {
+        self.tool.entry = entry.into();
+        self
+    }
+
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.tool.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.tool.version = Some(version.into());
+        self
+    }
+
+    /// Resolve the bare tool name to this version.
+    pub fn default_version(mut self) -> Self {
+        self.tool.default_version = true;
+        self
+    }
+
+    pub fn sha256(mut self, digest: impl Into<String>) -> Self {
+        self.tool.sha256 = Some(digest.into());
+        self
+    }
+
+    /// Bound each attempt to `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.tool.attempt_timeout_ms = Some(duration_ms(timeout));
+        self
+    }
+
+    /// Bound the whole invocation, retries included, to `timeout`.
+    pub fn total_timeout(mut self, timeout: Duration) -> Self {
+        self.tool.total_timeout_ms = Some(duration_ms(timeout));
+        self
+    }
+
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.tool.max_retries = Some(retries);
+        self
+    }
+
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.tool.retry_backoff_ms = Some(duration_ms(backoff));
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.tool.retry_policy = Some(policy);
+        self
+    }
+
+    pub fn max_retry_duration(mut self, budget: Duration) -> Self {
+        self.tool.max_retry_duration_ms = Some(duration_ms(budget));
+        self
+    }
+
+    /// Pass a per-invocation idempotency key to the tool as `_meta.idempotency_key`.
+    pub fn inject_idempotency_key(mut self) -> Self {
+        self.tool.inject_idempotency_key = true;
+        self
+    }
+
+    /// Pass the tenant of each invocation to the tool as `_meta.tenant`.
+    pub fn inject_tenant(mut self) -> Self {
+        self.tool.inject_tenant = true;
+        self
+    }
+
+    /// Let the tool request LLM completions from the hosting agent.
+    pub fn allow_sampling(mut self) -> Self {
+        self.tool.allow_sampling = true;
+        self
+    }
+
+    /// Keep the tool in the map's config but do not register it.
+    pub fn disabled(mut self) -> Self {
+        self.tool.enabled = Some(false);
+        self
+    }
+
+    pub fn requires_feature(mut self, feature: impl Into<String>) -> Self {
+        self.tool.requires_features.push(feature.into());
+        self
+    }
+
+    /// Only let `tenant` (and other allowed tenants) invoke the tool.
+    pub fn allow_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tool.allowed_tenants.push(tenant.into());
+        self
+    }
+
+    /// Keep `tenant` from invoking the tool.
+    pub fn deny_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tool.denied_tenants.push(tenant.into());
+        self
+    }
+
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tool.labels.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.tool.description = Some(description.into());
+        self
+    }
+
+    pub fn input_schema(mut self, schema: Value) -> Self {
+        self.tool.input_schema = Some(schema);
+        self
+    }
+
+    pub fn output_schema(mut self, schema: Value) -> Self {
+        self.tool.output_schema = Some(schema);
+        self
+    }
+
+    pub fn example(mut self, example: ToolExample) -> Self {
+        self.tool.examples.push(example);
+        self
+    }
+
+    /// Finish this tool and start the next one.
+    pub fn tool(self, name: impl Into<String>) -> ToolBuilder {
+        self.done().tool(name)
+    }
+
+    /// Finish this tool and return to the map builder.
+    pub fn done(self) -> ToolMapBuilder {
+        self.parent.tool_ref(self.tool)
+    }
+
+    /// Finish this tool and build the map.
+    pub fn build(self) -> Result<ToolMap, McpError> {
+        self.done().build()
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_map_with_namespace_and_hints() {
+        let map = ToolMapBuilder::new()
+            .namespace("crm")
+            .tool("lead")
+            .component("./lead.wasm")
+            .timeout(Duration::from_millis(1500))
+            .label("audience", "agent")
+            .tool("debug")
+            .component("./debug.wasm")
+            .requires_feature("debug")
+            .build()
+            .unwrap();
+
+        let lead = map.get("crm/lead").unwrap();
+        assert_eq!(lead.timeout(), Some(Duration::from_millis(1500)));
+        assert_eq!(lead.entry, ToolRef::DEFAULT_ENTRY);
+        assert!(matches!(
+            map.get("crm/debug"),
+            Err(McpError::ToolDisabled { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicates_and_missing_components() {
+        let duplicate = ToolMapBuilder::new()
+            .tool("echo")
+            .component("./a.wasm")
+            .tool("echo")
+            .component("./b.wasm")
+            .build();
+        assert!(duplicate.is_err());
+
+        assert!(ToolMapBuilder::new().tool("empty").build().is_err());
+    }
+}
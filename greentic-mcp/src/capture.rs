@@ -0,0 +1,199 @@
+//! Opt-in capture of request and response payloads, for debugging tools in production.
+//!
+//! Attach a [`PayloadCapture`] with
+//! [`WasixExecutor::with_payload_capture`](crate::executor::WasixExecutor::with_payload_capture)
+//! and a sampled fraction of invocations is kept, with their payloads, as
+//! [`AuditRecord`]s. Values at the configured JSON pointers are replaced with
+//! [`REDACTED`] before a payload is stored or logged.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+use crate::audit::{AuditRecord, REDACTED};
+
+/// Target of the DEBUG event logged for every captured invocation.
+pub const TARGET: &str = "greentic_mcp::capture";
+
+/// Captured invocations kept unless configured with [`PayloadCapture::with_capacity`].
+pub const DEFAULT_CAPTURE_SIZE: usize = 32;
+
+/// Sampled, redacted payloads of recent invocations.
+#[derive(Debug)]
+pub struct PayloadCapture {
+    sample_rate: f64,
+    redact: Vec<Vec<String>>,
+    capacity: usize,
+    records: Mutex<VecDeque<AuditRecord>>,
+}
+
+impl PayloadCapture {
+    /// Capture roughly `sample_rate` (between 0.0 and 1.0) of all invocations.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            redact: Vec::new(),
+            capacity: DEFAULT_CAPTURE_SIZE,
+            records: Mutex::default(),
+        }
+    }
+
+    /// Redact the value at the JSON pointer `pointer` (RFC 6901, e.g. `/card_number`)
+    /// in inputs and outputs. A `*` segment matches every key of an object or every
+    /// element of an array, as in `/items/*/card_number`.
+    pub fn redact(mut self, pointer: &str) -> Self {
+        self.redact.push(
+            pointer
+                .split('/')
+                .skip(1)
+                .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+        );
+        self
+    }
+
+    /// Keep the last `size` captured invocations instead of [`DEFAULT_CAPTURE_SIZE`].
+    pub fn with_capacity(mut self, size: usize) -> Self {
+        self.capacity = size;
+        self
+    }
+
+    /// Captured invocations, newest first.
+    pub fn captured(&self) -> Vec<AuditRecord> {
+        self.records
+            .lock()
+            .expect("payload capture poisoned")
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the invocation that just finished should be captured.
+    pub(crate) fn sampled(&self) -> bool {
+        self.sample_rate > 0.0 && rand::random::<f64>() < self.sample_rate
+    }
+
+    /// Keep `record` with redacted copies of `input` and `output`, and log it.
+    pub(crate) fn record(&self, mut record: AuditRecord, input: &Value, output: Option<&Value>) {
+        record.input = Some(self.redacted(input));
+        record.output = output.map(|output| self.redacted(output));
+        tracing::debug!(
+            target: TARGET,
+            tool = %record.tool,
+            correlation_id = record.correlation_id.as_deref(),
+            outcome = %record.outcome,
+            input = %record.input.as_ref().unwrap_or(&Value::Null),
+            output = %record.output.as_ref().unwrap_or(&Value::Null),
+            "payload captured"
+        );
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().expect("payload capture poisoned");
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    fn redacted(&self, value: &Value) -> Value {
+        let mut value = value.clone();
+        for pointer in &self.redact {
+            redact_at(&mut value, pointer);
+        }
+        value
+    }
+}
+
+fn redact_at(value: &mut Value, pointer: &[String]) {
+    let Some((segment, rest)) = pointer.split_first() else {
+        *value = Value::String(REDACTED.into());
+        return;
+    };
+    match value {
+        Value::Object(map) if segment == "*" => {
+            map.values_mut().for_each(|value| redact_at(value, rest));
+        }
+        Value::Object(map) => {
+            if let Some(value) = map.get_mut(segment) {
+                redact_at(value, rest);
+            }
+        }
+        Value::Array(items) if segment == "*" => {
+            items.iter_mut().for_each(|item| redact_at(item, rest));
+        }
+        Value::Array(items) => {
+            let index = segment.parse::<usize>().ok();
+            if let Some(item) = index.and_then(|index| items.get_mut(index)) {
+                redact_at(item, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::WasixExecutor;
+    use crate::types::{ToolInput, ToolRef};
+
+    #[test]
+    fn redacts_values_at_pointers() {
+        let capture = PayloadCapture::new(1.0)
+            .redact("/card_number")
+            .redact("/items/*/secret")
+            .redact("/a~1b")
+            .redact("/missing/field");
+        let input = json!({
+            "card_number": "4111",
+            "items": [{ "secret": 1, "name": "x" }, { "name": "y" }],
+            "a/b": true,
+            "nested": { "card_number": "kept" },
+        });
+        capture.record(AuditRecord::default(), &input, Some(&json!([1, 2])));
+
+        let record = capture.captured().remove(0);
+        assert_eq!(
+            record.input,
+            Some(json!({
+                "card_number": REDACTED,
+                "items": [{ "secret": REDACTED, "name": "x" }, { "name": "y" }],
+                "a/b": REDACTED,
+                "nested": { "card_number": "kept" },
+            }))
+        );
+        assert_eq!(record.output, Some(json!([1, 2])));
+    }
+
+    #[tokio::test]
+    async fn captures_sampled_invocations() {
+        let tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+        let input = ToolInput::new(json!({ "token": "t" })).with_correlation_id("req-3");
+
+        let capture = Arc::new(PayloadCapture::new(1.0).redact("/token").with_capacity(1));
+        let executor = WasixExecutor::new()
+            .unwrap()
+            .with_payload_capture(capture.clone());
+        assert!(executor.invoke(&tool, &input).await.is_err());
+        assert!(executor.invoke(&tool, &input).await.is_err());
+        let captured = capture.captured();
+        assert_eq!(captured.len(), 1);
+        assert_eq!(captured[0].correlation_id.as_deref(), Some("req-3"));
+        assert_eq!(captured[0].input, Some(json!({ "token": REDACTED })));
+        assert_eq!(captured[0].output, None);
+
+        let capture = Arc::new(PayloadCapture::new(0.0));
+        let executor = WasixExecutor::new()
+            .unwrap()
+            .with_payload_capture(capture.clone());
+        assert!(executor.invoke(&tool, &input).await.is_err());
+        assert!(capture.captured().is_empty());
+    }
+}
@@ -0,0 +1,314 @@
+//! Recorded tool calls for hermetic tests, with the `test-util` feature.
+//!
+//! A [`Recorder`] wraps an exec function for
+//! [`exec_with_retries_backend`](crate::exec_with_retries_backend) and keeps every
+//! call in a [`Cassette`]: the request, the output or error, and the host calls
+//! (HTTP, secrets, KV) the tool made through the runner host imports. A
+//! [`Replayer`] serves a saved cassette back, either answering calls with the
+//! recorded outputs or running the tools again against their recorded host calls,
+//! so flows built on network-backed tools can be tested without the network.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mcp_exec::{
+    ErrorCode, ExecConfig, ExecError, ExecRequest, HostCall, HostCallTape, RunnerError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::types::McpError;
+
+/// Recorded calls, saved as JSON.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+/// One recorded call.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub component: String,
+    pub action: String,
+    /// Arguments without `_meta`, which carries per-call keys.
+    pub args: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RecordedError>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub host_calls: Vec<HostCall>,
+}
+
+/// Error of a recorded call; replayed with the same code.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Payload of tool errors.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub payload: Value,
+}
+
+impl Cassette {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, McpError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|err| McpError::config_file(path, err))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), McpError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+impl Interaction {
+    fn matches(&self, req: &ExecRequest) -> bool {
+        self.component == req.component && self.action == req.action && self.args == args(req)
+    }
+
+    fn result(&self) -> Result<Value, ExecError> {
+        match &self.error {
+            None => Ok(self.output.clone().unwrap_or(Value::Null)),
+            Some(error) => Err(error.to_exec_error(&self.component, &self.action)),
+        }
+    }
+}
+
+impl RecordedError {
+    fn new(err: &ExecError) -> Self {
+        Self {
+            code: err.code(),
+            message: err.to_string(),
+            payload: match err {
+                ExecError::Tool { payload, .. } => payload.clone(),
+                _ => Value::Null,
+            },
+        }
+    }
+
+    fn to_exec_error(&self, component: &str, action: &str) -> ExecError {
+        let runner = |source| ExecError::runner(component, source);
+        match &self.code {
+            ErrorCode::Tool(code) => {
+                ExecError::tool_error(component, action, code, self.payload.clone())
+            }
+            ErrorCode::RunnerTimeout => runner(RunnerError::Timeout {
+                elapsed: Duration::ZERO,
+            }),
+            ErrorCode::RunnerTotalTimeout => runner(RunnerError::TotalTimeout {
+                elapsed: Duration::ZERO,
+            }),
+            ErrorCode::RunnerTransient => runner(RunnerError::ToolTransient {
+                component: component.to_string(),
+                message: self.message.clone(),
+            }),
+            ErrorCode::RunnerActionNotFound => ExecError::not_found(component, action),
+            _ => runner(RunnerError::Internal(self.message.clone())),
+        }
+    }
+}
+
+/// Arguments as recorded and matched: without `_meta`.
+fn args(req: &ExecRequest) -> Value {
+    let mut args = req.args.clone();
+    if let Some(object) = args.as_object_mut() {
+        object.remove("_meta");
+    }
+    args
+}
+
+/// Every attempt's runtime policy with `tape` for its host calls.
+fn with_tape(cfg: &ExecConfig, tape: &Arc<HostCallTape>) -> ExecConfig {
+    let mut cfg = cfg.clone();
+    cfg.runtime.host_calls = Some(tape.clone());
+    for runtime in cfg.tenant_runtime.values_mut() {
+        runtime.host_calls = Some(tape.clone());
+    }
+    cfg
+}
+
+/// Records calls into a [`Cassette`].
+///
+/// Every attempt is recorded, so a retried call appears once per attempt.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    cassette: Mutex<Cassette>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An exec function that records each call it passes to `inner`, e.g.
+    /// [`mcp_exec::exec`]. Host calls are only seen when `inner` runs tools through
+    /// the `mcp-exec` runner.
+    pub fn backend<F>(
+        self: &Arc<Self>,
+        inner: F,
+    ) -> impl Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static
+    where
+        F: Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static,
+    {
+        let recorder = self.clone();
+        move |req, cfg| {
+            let tape = Arc::new(HostCallTape::recording());
+            let mut interaction = Interaction {
+                component: req.component.clone(),
+                action: req.action.clone(),
+                args: args(&req),
+                output: None,
+                error: None,
+                host_calls: Vec::new(),
+            };
+            let result = inner(req, &with_tape(cfg, &tape));
+            interaction.output = result.as_ref().ok().cloned();
+            interaction.error = result.as_ref().err().map(RecordedError::new);
+            interaction.host_calls = tape.calls();
+            recorder.push(interaction);
+            result
+        }
+    }
+
+    /// The calls recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().expect("recorder poisoned").clone()
+    }
+
+    fn push(&self, interaction: Interaction) {
+        self.cassette
+            .lock()
+            .expect("recorder poisoned")
+            .interactions
+            .push(interaction);
+    }
+}
+
+/// Serves the calls of a [`Cassette`] back.
+///
+/// Each call is answered by the first unused interaction with the same component,
+/// action, and arguments, so repeated calls replay in the order they were recorded.
+/// Calls without one fail with `runner.failed`.
+#[derive(Debug)]
+pub struct Replayer {
+    interactions: Mutex<Vec<Option<Interaction>>>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            interactions: Mutex::new(cassette.interactions.into_iter().map(Some).collect()),
+        }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, McpError> {
+        Cassette::read(path).map(Self::new)
+    }
+
+    /// An exec function answering calls with their recorded outputs and errors,
+    /// without running any tool.
+    pub fn backend(
+        self: &Arc<Self>,
+    ) -> impl Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static {
+        let replayer = self.clone();
+        move |req, _| replayer.take(&req)?.result()
+    }
+
+    /// An exec function running each call through `inner`, e.g. [`mcp_exec::exec`],
+    /// with its host calls answered from the recording. Host calls that differ from
+    /// the recorded ones trap the tool.
+    pub fn host_backend<F>(
+        self: &Arc<Self>,
+        inner: F,
+    ) -> impl Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static
+    where
+        F: Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static,
+    {
+        let replayer = self.clone();
+        move |req, cfg| {
+            let interaction = replayer.take(&req)?;
+            let tape = Arc::new(HostCallTape::replaying(interaction.host_calls));
+            inner(req, &with_tape(cfg, &tape))
+        }
+    }
+
+    /// Interactions not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.interactions().iter().flatten().count()
+    }
+
+    fn take(&self, req: &ExecRequest) -> Result<Interaction, ExecError> {
+        self.interactions()
+            .iter_mut()
+            .find(|slot| {
+                slot.as_ref()
+                    .is_some_and(|interaction| interaction.matches(req))
+            })
+            .and_then(Option::take)
+            .ok_or_else(|| {
+                ExecError::runner(
+                    &req.component,
+                    RunnerError::Internal(format!(
+                        "no recorded call of `{}` with these arguments: {}",
+                        req.action,
+                        args(req)
+                    )),
+                )
+            })
+    }
+
+    fn interactions(&self) -> std::sync::MutexGuard<'_, Vec<Option<Interaction>>> {
+        self.interactions.lock().expect("replayer poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{MockToolStore, exec_with_retries_backend};
+
+    fn request(city: &str) -> ExecRequest {
+        ExecRequest {
+            component: "weather".into(),
+            action: "forecast".into(),
+            args: json!({ "city": city }),
+            tenant: None,
+            correlation_id: None,
+            identity: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_calls_without_the_tool() {
+        let store = MockToolStore::new().with_responses("weather", [("forecast", json!(21))]);
+        let cfg = store.exec_config();
+        let recorder = Arc::new(Recorder::new());
+        let output =
+            exec_with_retries_backend(request("Oslo"), &cfg, recorder.backend(mcp_exec::exec))
+                .await
+                .unwrap();
+        assert_eq!(output, json!(21));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("weather.json");
+        recorder.cassette().write(&path).unwrap();
+        drop(store);
+
+        let replayer = Arc::new(Replayer::read(&path).unwrap());
+        let output = exec_with_retries_backend(request("Oslo"), &cfg, replayer.backend())
+            .await
+            .unwrap();
+        assert_eq!(output, json!(21));
+        assert_eq!(replayer.remaining(), 0);
+        let err = exec_with_retries_backend(request("Oslo"), &cfg, replayer.backend())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no recorded call"), "{err}");
+    }
+}
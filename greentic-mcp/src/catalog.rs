@@ -0,0 +1,203 @@
+//! Several tool maps composed into one catalog, e.g. to serve builtin, tenant-specific,
+//! and remote-proxied tools from a single MCP endpoint.
+
+use std::sync::{Arc, Mutex};
+
+use crate::shared::SharedToolMap;
+use crate::tool_map::{ConflictPolicy, ToolMap};
+use crate::types::McpError;
+
+/// Live composition of mounted [`SharedToolMap`]s.
+///
+/// Each mount may place its tools under a prefix namespace. Keys defined by more than
+/// one mount are resolved with the catalog's [`ConflictPolicy`], earlier mounts being
+/// the "left" side. Changes to a mounted map show up in the next [`load`](Self::load).
+pub struct ToolCatalog {
+    mounts: Vec<Mount>,
+    policy: ConflictPolicy,
+    composed: Mutex<Option<Composed>>,
+}
+
+struct Mount {
+    prefix: Option<String>,
+    tools: Arc<SharedToolMap>,
+}
+
+/// Last composition, reused while no mounted map has changed.
+struct Composed {
+    sources: Vec<Arc<ToolMap>>,
+    map: Arc<ToolMap>,
+}
+
+impl ToolCatalog {
+    pub fn new(policy: ConflictPolicy) -> Self {
+        Self {
+            mounts: Vec::new(),
+            policy,
+            composed: Mutex::new(None),
+        }
+    }
+
+    /// Add `tools`, under the namespace `prefix` if given.
+    ///
+    /// Fails if the current contents conflict with earlier mounts and the policy is
+    /// [`ConflictPolicy::Error`].
+    pub fn mount(
+        mut self,
+        prefix: Option<&str>,
+        tools: Arc<SharedToolMap>,
+    ) -> Result<Self, McpError> {
+        self.mounts.push(Mount {
+            prefix: prefix.map(str::to_owned),
+            tools,
+        });
+        let sources = self.snapshots();
+        compose(&self.mounts, &sources, self.policy)?;
+        Ok(self)
+    }
+
+    /// Snapshot of the composed catalog.
+    ///
+    /// With [`ConflictPolicy::Error`], a conflict introduced after mounting keeps the
+    /// tool from the earlier mount and is logged, so a bad update to one map cannot
+    /// take the whole catalog down.
+    pub fn load(&self) -> Arc<ToolMap> {
+        let sources = self.snapshots();
+        let mut composed = self.composed.lock().expect("tool catalog poisoned");
+        if let Some(current) = composed.as_ref()
+            && current.sources.len() == sources.len()
+            && current
+                .sources
+                .iter()
+                .zip(&sources)
+                .all(|(cached, source)| Arc::ptr_eq(cached, source))
+        {
+            return current.map.clone();
+        }
+
+        let map = compose(&self.mounts, &sources, self.policy).unwrap_or_else(|err| {
+            tracing::warn!(%err, "tool catalog conflict; keeping tools from earlier mounts");
+            compose(&self.mounts, &sources, ConflictPolicy::PreferLeft)
+                .expect("preferring earlier mounts cannot conflict")
+        });
+        let map = Arc::new(map);
+        *composed = Some(Composed {
+            sources,
+            map: map.clone(),
+        });
+        map
+    }
+
+    fn snapshots(&self) -> Vec<Arc<ToolMap>> {
+        self.mounts.iter().map(|mount| mount.tools.load()).collect()
+    }
+}
+
+fn compose(
+    mounts: &[Mount],
+    sources: &[Arc<ToolMap>],
+    policy: ConflictPolicy,
+) -> Result<ToolMap, McpError> {
+    let mut composed: Option<ToolMap> = None;
+    for (mount, source) in mounts.iter().zip(sources) {
+        let map = match &mount.prefix {
+            Some(prefix) => source.prefixed(prefix),
+            None => ToolMap::clone(source),
+        };
+        composed = Some(match composed {
+            Some(composed) => composed.merge(map, policy)?,
+            None => map,
+        });
+    }
+    match composed {
+        Some(map) => Ok(map),
+        None => ToolMap::from_config(&Default::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ToolMapConfig, ToolRef};
+
+    fn shared(names: &[&str]) -> Arc<SharedToolMap> {
+        let config = ToolMapConfig {
+            tools: names
+                .iter()
+                .map(|name| ToolRef::new(*name, format!("./{name}.wasm"), "run"))
+                .collect(),
+            ..Default::default()
+        };
+        Arc::new(SharedToolMap::new(ToolMap::from_config(&config).unwrap()))
+    }
+
+    fn keys(map: &ToolMap) -> Vec<&str> {
+        map.iter().map(|(key, _)| key.as_str()).collect()
+    }
+
+    #[test]
+    fn composes_prefixed_mounts_and_follows_updates() {
+        let builtin = shared(&["echo", "search"]);
+        let tenant = shared(&["search"]);
+        let catalog = ToolCatalog::new(ConflictPolicy::Error)
+            .mount(None, builtin.clone())
+            .unwrap()
+            .mount(Some("acme"), tenant.clone())
+            .unwrap();
+        let first = catalog.load();
+        assert_eq!(keys(&first), ["echo", "search", "acme/search"]);
+        assert!(Arc::ptr_eq(&first, &catalog.load()));
+
+        tenant
+            .register(ToolRef::new("report", "./report.wasm", "run"))
+            .unwrap();
+        let updated = catalog.load();
+        assert_eq!(
+            keys(&updated),
+            ["echo", "search", "acme/search", "acme/report"]
+        );
+        assert_eq!(
+            updated.get("acme/report").unwrap().component,
+            "./report.wasm"
+        );
+    }
+
+    #[test]
+    fn resolves_conflicts_with_the_policy() {
+        let builtin = shared(&["search"]);
+        let overrides = Arc::new(SharedToolMap::new(
+            ToolMap::from_config(&ToolMapConfig {
+                tools: vec![ToolRef::new("search", "./better-search.wasm", "run")],
+                ..Default::default()
+            })
+            .unwrap(),
+        ));
+        let conflict = ToolCatalog::new(ConflictPolicy::Error)
+            .mount(None, builtin.clone())
+            .unwrap()
+            .mount(None, overrides.clone());
+        assert!(conflict.is_err());
+
+        let catalog = ToolCatalog::new(ConflictPolicy::PreferRight)
+            .mount(None, builtin.clone())
+            .unwrap()
+            .mount(None, overrides)
+            .unwrap();
+        let search = catalog.load().get("search").unwrap().clone();
+        assert_eq!(search.component, "./better-search.wasm");
+
+        // Conflicts appearing later keep the earlier mount's tool.
+        let late = shared(&[]);
+        let catalog = ToolCatalog::new(ConflictPolicy::Error)
+            .mount(None, builtin)
+            .unwrap()
+            .mount(None, late.clone())
+            .unwrap();
+        late.register(ToolRef::new("search", "./other.wasm", "run"))
+            .unwrap();
+        assert_eq!(
+            catalog.load().get("search").unwrap().component,
+            "./search.wasm"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Compiled components, partitioned by tenant.
+//!
+//! Attach a [`ComponentCache`] with
+//! [`WasixExecutor::with_component_cache`](crate::executor::WasixExecutor::with_component_cache)
+//! and each component is compiled and linked once rather than on every attempt.
+//! Entries are keyed by tenant and component digest. Every tenant has its own size
+//! quota and evicts only its own entries, so a tenant invoking many custom tools
+//! cannot push shared hot tools out of the cache. Calls without a tenant share one
+//! partition. A component already compiled for another tenant is reused rather than
+//! compiled again, but still counts against each tenant's quota.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use wasmtime::component::InstancePre;
+
+use crate::executor::WasiState;
+use crate::telemetry;
+
+/// Compiled, linked components by tenant and digest, shared by executor clones.
+#[derive(Debug)]
+pub struct ComponentCache {
+    quota: u64,
+    tenants: HashMap<String, u64>,
+    partitions: Mutex<HashMap<Option<String>, Partition>>,
+}
+
+#[derive(Debug, Default)]
+struct Partition {
+    entries: HashMap<String, Entry>,
+    size: u64,
+    /// Bumped on every use, to find the least recently used entry.
+    clock: u64,
+}
+
+struct Entry {
+    pre: InstancePre<WasiState>,
+    size: u64,
+    last_used: u64,
+}
+
+impl std::fmt::Debug for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entry")
+            .field("size", &self.size)
+            .field("last_used", &self.last_used)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ComponentCache {
+    /// Cache up to `quota` bytes of components per tenant, measured by the size of
+    /// their Wasm binaries.
+    pub fn new(quota: u64) -> Self {
+        Self {
+            quota,
+            tenants: HashMap::new(),
+            partitions: Mutex::default(),
+        }
+    }
+
+    /// Give `tenant` a quota of `quota` bytes instead of the default.
+    pub fn with_tenant_quota(mut self, tenant: impl Into<String>, quota: u64) -> Self {
+        self.tenants.insert(tenant.into(), quota);
+        self
+    }
+
+    /// Bytes cached for `tenant`; `None` for calls without a tenant.
+    pub fn size(&self, tenant: Option<&str>) -> u64 {
+        self.partitions()
+            .get(&tenant.map(str::to_owned))
+            .map_or(0, |partition| partition.size)
+    }
+
+    /// The cached component with `digest` for `tenant`, or the one `prepare` returns,
+    /// cached if it fits the tenant's quota.
+    pub(crate) fn get_or_prepare<E>(
+        &self,
+        tenant: Option<&str>,
+        digest: &str,
+        size: u64,
+        prepare: impl FnOnce() -> Result<InstancePre<WasiState>, E>,
+    ) -> Result<InstancePre<WasiState>, E> {
+        let key = tenant.map(str::to_owned);
+        let shared = {
+            let mut partitions = self.partitions();
+            if let Some(partition) = partitions.get_mut(&key) {
+                partition.clock += 1;
+                if let Some(entry) = partition.entries.get_mut(digest) {
+                    entry.last_used = partition.clock;
+                    telemetry::cache_lookup("component", true);
+                    return Ok(entry.pre.clone());
+                }
+            }
+            partitions
+                .values()
+                .find_map(|partition| partition.entries.get(digest))
+                .map(|entry| entry.pre.clone())
+        };
+        telemetry::cache_lookup("component", false);
+        let pre = match shared {
+            Some(pre) => pre,
+            None => prepare()?,
+        };
+        self.insert(key, digest, size, pre.clone());
+        Ok(pre)
+    }
+
+    fn insert(&self, tenant: Option<String>, digest: &str, size: u64, pre: InstancePre<WasiState>) {
+        let quota = tenant
+            .as_ref()
+            .and_then(|tenant| self.tenants.get(tenant))
+            .copied()
+            .unwrap_or(self.quota);
+        if size > quota {
+            return;
+        }
+        let mut partitions = self.partitions();
+        let partition = partitions.entry(tenant).or_default();
+        if partition.entries.contains_key(digest) {
+            // Prepared concurrently by another call.
+            return;
+        }
+        while partition.size + size > quota {
+            let Some(oldest) = partition
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(digest, _)| digest.clone())
+            else {
+                break;
+            };
+            let evicted = partition.entries.remove(&oldest).expect("entry exists");
+            partition.size -= evicted.size;
+        }
+        partition.clock += 1;
+        partition.size += size;
+        partition.entries.insert(
+            digest.to_string(),
+            Entry {
+                pre,
+                size,
+                last_used: partition.clock,
+            },
+        );
+    }
+
+    fn partitions(&self) -> std::sync::MutexGuard<'_, HashMap<Option<String>, Partition>> {
+        self.partitions.lock().expect("component cache poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::{InvokeOptions, WasixExecutor, tests::echo_component};
+    use crate::types::{ToolInput, ToolRef};
+
+    #[tokio::test]
+    async fn caches_components_within_tenant_quotas() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        let bytes = echo_component();
+        std::fs::write(&path, &bytes).unwrap();
+        let size = bytes.len() as u64;
+        let cache = Arc::new(ComponentCache::new(size).with_tenant_quota("tiny", size - 1));
+        let executor = WasixExecutor::new()
+            .unwrap()
+            .with_component_cache(cache.clone());
+        let tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        let input = ToolInput::new(json!({ "text": "hi" }));
+        let call = |tenant: &str| InvokeOptions {
+            tenant: Some(greentic_types::TenantCtx::new(
+                "dev".try_into().unwrap(),
+                tenant.try_into().unwrap(),
+            )),
+            ..InvokeOptions::default()
+        };
+
+        executor.invoke(&tool, &input).await.unwrap();
+        assert_eq!(cache.size(None), size);
+        executor
+            .invoke_with(&tool, &input, call("acme"))
+            .await
+            .unwrap();
+        assert_eq!(cache.size(Some("acme")), size);
+        // Over its quota, the component is run without being cached.
+        executor
+            .invoke_with(&tool, &input, call("tiny"))
+            .await
+            .unwrap();
+        assert_eq!(cache.size(Some("tiny")), 0);
+        assert_eq!(cache.size(None), size);
+    }
+}
@@ -0,0 +1,226 @@
+//! Per-tenant concurrency quotas, with fair queueing once the executor is saturated.
+//!
+//! Attach a [`ConcurrencyLimiter`] with
+//! [`WasixExecutor::with_concurrency_limiter`](crate::executor::WasixExecutor::with_concurrency_limiter)
+//! and every invocation with a tenant holds a [`ConcurrencyPermit`] while it runs. A
+//! call waits when the executor runs its maximum number of calls, or when its tenant
+//! runs its quota. Freed slots go to waiting tenants in turn, so a burst from one
+//! tenant does not starve the others.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+/// Running and queued invocations by tenant, shared by executor clones.
+#[derive(Debug)]
+pub struct ConcurrencyLimiter {
+    total: usize,
+    per_tenant: usize,
+    tenants: HashMap<String, usize>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    running: usize,
+    by_tenant: HashMap<String, usize>,
+    queues: HashMap<String, VecDeque<oneshot::Sender<ConcurrencyPermit>>>,
+    /// Tenants with queued calls, in the order they are served.
+    turns: VecDeque<String>,
+}
+
+impl ConcurrencyLimiter {
+    /// Run at most `total` invocations at once, and at most `per_tenant` per tenant.
+    pub fn new(total: usize, per_tenant: usize) -> Self {
+        Self {
+            total: total.max(1),
+            per_tenant: per_tenant.max(1),
+            tenants: HashMap::new(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Let `tenant` run `limit` invocations at once instead of the default.
+    pub fn with_tenant_limit(mut self, tenant: impl Into<String>, limit: usize) -> Self {
+        self.tenants.insert(tenant.into(), limit.max(1));
+        self
+    }
+
+    /// Wait for a slot for `tenant`, held until the permit is dropped.
+    pub async fn acquire(self: &Arc<Self>, tenant: &str) -> ConcurrencyPermit {
+        let waiting = {
+            let mut state = self.state();
+            if state.running < self.total && self.has_quota(&state, tenant) {
+                return self.grant(&mut state, tenant);
+            }
+            let (sender, receiver) = oneshot::channel();
+            let queue = state.queues.entry(tenant.to_string()).or_default();
+            if queue.is_empty() {
+                state.turns.push_back(tenant.to_string());
+            }
+            state
+                .queues
+                .get_mut(tenant)
+                .expect("queue just created")
+                .push_back(sender);
+            receiver
+        };
+        // The limiter outlives its queues, so the sender is never dropped unsent.
+        waiting.await.expect("concurrency limiter dropped a waiter")
+    }
+
+    /// Invocations of `tenant` running now.
+    pub fn running(&self, tenant: &str) -> usize {
+        self.state().by_tenant.get(tenant).copied().unwrap_or(0)
+    }
+
+    /// Invocations of `tenant` waiting for a slot.
+    pub fn waiting(&self, tenant: &str) -> usize {
+        self.state().queues.get(tenant).map_or(0, VecDeque::len)
+    }
+
+    fn limit(&self, tenant: &str) -> usize {
+        self.tenants.get(tenant).copied().unwrap_or(self.per_tenant)
+    }
+
+    fn has_quota(&self, state: &State, tenant: &str) -> bool {
+        state.by_tenant.get(tenant).copied().unwrap_or(0) < self.limit(tenant)
+    }
+
+    fn grant(self: &Arc<Self>, state: &mut State, tenant: &str) -> ConcurrencyPermit {
+        state.running += 1;
+        *state.by_tenant.entry(tenant.to_string()).or_default() += 1;
+        ConcurrencyPermit {
+            limiter: Some(self.clone()),
+            tenant: tenant.to_string(),
+        }
+    }
+
+    fn ungrant(state: &mut State, tenant: &str) {
+        state.running -= 1;
+        if let Some(running) = state.by_tenant.get_mut(tenant) {
+            *running -= 1;
+            if *running == 0 {
+                state.by_tenant.remove(tenant);
+            }
+        }
+    }
+
+    /// Free the slot of `tenant` and hand free slots to waiting tenants in turn.
+    fn release(self: &Arc<Self>, tenant: &str) {
+        let mut state = self.state();
+        Self::ungrant(&mut state, tenant);
+        // Tenants looked at since a queued call last moved; stop once all were.
+        let mut skipped = 0;
+        while state.running < self.total && skipped < state.turns.len() {
+            let next = state.turns.pop_front().expect("turns is not empty");
+            if self.has_quota(&state, &next) {
+                skipped = 0;
+                let waiter = state
+                    .queues
+                    .get_mut(&next)
+                    .and_then(VecDeque::pop_front)
+                    .expect("queued tenants have waiters");
+                let permit = self.grant(&mut state, &next);
+                if let Err(mut permit) = waiter.send(permit) {
+                    // The caller stopped waiting; take the slot back without re-locking.
+                    permit.limiter = None;
+                    Self::ungrant(&mut state, &next);
+                }
+            } else {
+                skipped += 1;
+            }
+            if state
+                .queues
+                .get(&next)
+                .is_some_and(|queue| !queue.is_empty())
+            {
+                state.turns.push_back(next);
+            } else {
+                state.queues.remove(&next);
+            }
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("concurrency limiter poisoned")
+    }
+}
+
+/// Slot of one running invocation; dropping it frees the slot.
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    limiter: Option<Arc<ConcurrencyLimiter>>,
+    tenant: String,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.limiter.take() {
+            limiter.release(&self.tenant);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start acquiring a slot for `tenant` and wait until the call is queued.
+    async fn queue(
+        limiter: &Arc<ConcurrencyLimiter>,
+        tenant: &'static str,
+    ) -> tokio::task::JoinHandle<ConcurrencyPermit> {
+        let queued = limiter.waiting(tenant) + 1;
+        let task = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire(tenant).await }
+        });
+        while limiter.waiting(tenant) < queued {
+            tokio::task::yield_now().await;
+        }
+        task
+    }
+
+    #[tokio::test]
+    async fn serves_waiting_tenants_in_turn() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(1, 4));
+        let a1 = limiter.acquire("a").await;
+        let a2 = queue(&limiter, "a").await;
+        let a3 = queue(&limiter, "a").await;
+        let b1 = queue(&limiter, "b").await;
+
+        drop(a1);
+        let a2 = a2.await.unwrap();
+        assert_eq!(limiter.running("a"), 1);
+        drop(a2);
+        // `b` gets its turn ahead of the rest of `a`'s burst.
+        let b1 = b1.await.unwrap();
+        assert_eq!(limiter.running("b"), 1);
+        assert_eq!(limiter.waiting("a"), 1);
+        drop(b1);
+        drop(a3.await.unwrap());
+        assert_eq!(limiter.running("a"), 0);
+    }
+
+    #[tokio::test]
+    async fn enforces_tenant_quotas_and_skips_abandoned_waiters() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(4, 1).with_tenant_limit("premium", 2));
+        let p1 = limiter.acquire("premium").await;
+        let _p2 = limiter.acquire("premium").await;
+        let a1 = limiter.acquire("a").await;
+        let abandoned = queue(&limiter, "a").await;
+        let a3 = queue(&limiter, "a").await;
+        assert_eq!(limiter.running("premium"), 2);
+
+        abandoned.abort();
+        let _ = abandoned.await;
+        drop(p1);
+        assert_eq!(limiter.waiting("a"), 2);
+        drop(a1);
+        let _a3 = a3.await.unwrap();
+        assert_eq!(limiter.running("a"), 1);
+        assert_eq!(limiter.waiting("a"), 0);
+    }
+}
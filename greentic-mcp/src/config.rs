@@ -1,22 +1,539 @@
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::types::{McpError, ToolMapConfig};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+use crate::schema;
+use crate::secrets::SecretsProvider;
+use crate::types::{McpError, ToolMapConfig, ToolRef};
+
+const DISCOVER_SUFFIX: &str = ".component.wasm";
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Load a [`ToolMapConfig`] from JSON or YAML.
+///
+/// Entries listed under `include` are loaded recursively (directories contribute every
+/// JSON/YAML file they contain, in name order) and their tools are placed ahead of the
+/// including file's own tools. A `discover` directory registers every `*.component.wasm`
+/// file it contains under its file stem, unless the file lists a tool with that name.
+/// A file-level `namespace` and `defaults` block apply to every composed tool that does not
+/// set those values itself.
 pub fn load_tool_map_config(path: &Path) -> Result<ToolMapConfig, McpError> {
     let content = fs::read_to_string(path)?;
-    parse_tool_map_config(path, &content)
+    let config = parse_tool_map_config(path, &content)?;
+    let mut stack = vec![fs::canonicalize(path)?];
+    resolve_includes(path, config, &mut stack)
+}
+
+/// Load a config and resolve its `secret://name` references through `secrets`.
+pub fn load_tool_map_config_with_secrets(
+    path: &Path,
+    secrets: &dyn SecretsProvider,
+) -> Result<ToolMapConfig, McpError> {
+    let mut config = load_tool_map_config(path)?;
+    config.resolve_secrets(secrets)?;
+    Ok(config)
+}
+
+fn resolve_includes(
+    path: &Path,
+    mut config: ToolMapConfig,
+    stack: &mut Vec<PathBuf>,
+) -> Result<ToolMapConfig, McpError> {
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut tools = Vec::new();
+    config.resolved |= !config.include.is_empty() || config.discover.is_some();
+
+    for include in std::mem::take(&mut config.include) {
+        let target = base.join(&include);
+        for file in include_files(path, &target)? {
+            let canonical =
+                fs::canonicalize(&file).map_err(|err| McpError::config_file(&file, err))?;
+            if stack.contains(&canonical) {
+                let chain = stack
+                    .iter()
+                    .chain(std::iter::once(&canonical))
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(McpError::config_file(
+                    path,
+                    format!("include cycle detected: {chain}"),
+                ));
+            }
+
+            let content =
+                fs::read_to_string(&file).map_err(|err| McpError::config_file(&file, err))?;
+            let nested = parse_tool_map_config(&file, &content)?;
+
+            stack.push(canonical);
+            let nested = resolve_includes(&file, nested, stack)?;
+            stack.pop();
+
+            config.resolved |= nested.resolved;
+            tools.extend(nested.tools);
+        }
+    }
+
+    if let Some(dir) = config.discover.take() {
+        let discovered = discover_tools(path, &base.join(dir))?;
+        tools.extend(
+            discovered
+                .into_iter()
+                .filter(|tool| !config.tools.iter().any(|own| own.name == tool.name)),
+        );
+    }
+
+    tools.append(&mut config.tools);
+    inherit_file_settings(&config, &mut tools);
+    config.tools = tools;
+    Ok(config)
+}
+
+fn inherit_file_settings(config: &ToolMapConfig, tools: &mut [ToolRef]) {
+    if let Some(namespace) = &config.namespace {
+        for tool in tools.iter_mut().filter(|tool| tool.namespace.is_none()) {
+            tool.namespace = Some(namespace.clone());
+        }
+    }
+    if let Some(defaults) = &config.defaults {
+        tools.iter_mut().for_each(|tool| defaults.apply(tool));
+    }
+}
+
+/// Fetch a [`ToolMapConfig`] from an HTTP(S) config service.
+///
+/// `auth_header`, when set, is sent verbatim as the `Authorization` header (for example
+/// `Bearer <token>`). The body is parsed like a local file: JSON or YAML, with `${VAR}`
+/// expansion and schema validation. `include` and `discover` refer to the local
+/// filesystem and are rejected in remote configs.
+pub async fn load_tool_map_config_remote(
+    url: &str,
+    auth_header: Option<&str>,
+) -> Result<ToolMapConfig, McpError> {
+    let client = reqwest::Client::builder()
+        .use_rustls_tls()
+        .timeout(REMOTE_TIMEOUT)
+        .build()
+        .map_err(|err| McpError::remote_config(url, err))?;
+
+    let mut request = client.get(url);
+    if let Some(auth) = auth_header {
+        request = request.header(reqwest::header::AUTHORIZATION, auth);
+    }
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| McpError::remote_config(url, err))?;
+    let content = response
+        .text()
+        .await
+        .map_err(|err| McpError::remote_config(url, err))?;
+
+    parse_remote_config(url, &content)
+}
+
+fn parse_remote_config(url: &str, content: &str) -> Result<ToolMapConfig, McpError> {
+    // The URL path (without query) decides the format the same way a file extension does.
+    let hint = Path::new(url.split(['?', '#']).next().unwrap_or(url));
+    let (document, expanded) =
+        parse_value(hint, content).map_err(|message| McpError::remote_config(url, message))?;
+    let mut config =
+        schema::validate(document).map_err(|message| McpError::remote_config(url, message))?;
+    config.resolved = expanded;
+
+    if !config.include.is_empty() || config.discover.is_some() {
+        return Err(McpError::remote_config(
+            url,
+            "`include` and `discover` are not supported in remote configs",
+        ));
+    }
+    let mut tools = std::mem::take(&mut config.tools);
+    inherit_file_settings(&config, &mut tools);
+    config.tools = tools;
+    Ok(config)
+}
+
+fn discover_tools(from: &Path, dir: &Path) -> Result<Vec<ToolRef>, McpError> {
+    let entries = fs::read_dir(dir).map_err(|err| {
+        McpError::config_file(
+            from,
+            format!("cannot scan discover directory `{}`: {err}", dir.display()),
+        )
+    })?;
+
+    let mut tools = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| McpError::config_file(dir, err))?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(DISCOVER_SUFFIX))
+            .filter(|name| !name.is_empty())
+        else {
+            continue;
+        };
+        if path.is_file() {
+            tools.push(ToolRef::new(
+                name,
+                path.to_string_lossy(),
+                ToolRef::DEFAULT_ENTRY,
+            ));
+        }
+    }
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tools)
+}
+
+fn include_files(from: &Path, target: &Path) -> Result<Vec<PathBuf>, McpError> {
+    if !target.is_dir() {
+        if !target.exists() {
+            return Err(McpError::config_file(
+                from,
+                format!("included path `{}` does not exist", target.display()),
+            ));
+        }
+        return Ok(vec![target.to_path_buf()]);
+    }
+
+    let entries = fs::read_dir(target).map_err(|err| McpError::config_file(target, err))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|err| McpError::config_file(target, err))?
+            .path();
+        let is_config = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("json" | "yaml" | "yml")
+        );
+        if path.is_file() && is_config {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
 }
 
 fn parse_tool_map_config(path: &Path, content: &str) -> Result<ToolMapConfig, McpError> {
-    if is_json(path, content) {
-        Ok(serde_json::from_str(content)?)
+    let (document, expanded) = parse_document(path, content)?;
+    let mut config =
+        schema::validate(document).map_err(|message| McpError::config_file(path, message))?;
+    config.resolved = expanded;
+    Ok(config)
+}
+
+fn parse_document(path: &Path, content: &str) -> Result<(Value, bool), McpError> {
+    parse_value(path, content).map_err(|message| McpError::config_file(path, message))
+}
+
+/// Parse `content` and expand its placeholders, returning whether any string changed.
+fn parse_value(path: &Path, content: &str) -> Result<(Value, bool), String> {
+    let mut document: Value = if is_json(path, content) {
+        serde_json::from_str(content).map_err(|err| err.to_string())?
+    } else {
+        serde_yaml_bw::from_str(content).map_err(|err| err.to_string())?
+    };
+    let schema = schema::tool_map_schema();
+    let expanded = expand_env_in(&mut document, &[&schema], &schema, &|name: &str| {
+        std::env::var(name).ok()
+    })?;
+    Ok((document, expanded))
+}
+
+/// Load a base config and apply overlay files on top of it, in order.
+///
+/// Overlay tools are matched to base tools by namespace, name, and version. Fields set
+/// in the overlay replace the base values, `remove: true` drops the tool, and entries
+/// without a base counterpart are appended as new tools.
+pub fn load_layered_tool_map_config(
+    base: &Path,
+    overlays: &[PathBuf],
+) -> Result<ToolMapConfig, McpError> {
+    let mut config = load_tool_map_config(base)?;
+    for overlay in overlays {
+        config = apply_overlay_file(&config, overlay)?;
+    }
+    Ok(config)
+}
+
+/// Apply the overlay file at `path` to an already loaded `config`.
+pub(crate) fn apply_overlay_file(
+    config: &ToolMapConfig,
+    path: &Path,
+) -> Result<ToolMapConfig, McpError> {
+    let content = fs::read_to_string(path).map_err(|err| McpError::config_file(path, err))?;
+    let (overlay, _) = parse_document(path, &content)?;
+
+    let mut document = serde_json::to_value(config)?;
+    let Value::Array(tools) = &mut document["tools"] else {
+        unreachable!("tool list serializes to an array");
+    };
+    apply_overlay(tools, overlay).map_err(|message| McpError::config_file(path, message))?;
+    let mut config =
+        schema::validate(document).map_err(|message| McpError::config_file(path, message))?;
+    config.resolved = true;
+    Ok(config)
+}
+
+/// Load `base` plus its environment overlay (`toolmap.prod.yaml` for `toolmap.yaml` and
+/// `prod`) when that file exists.
+pub fn load_tool_map_config_for_env(
+    base: &Path,
+    environment: &str,
+) -> Result<ToolMapConfig, McpError> {
+    let overlay = overlay_path(base, environment);
+    let overlays = if overlay.is_file() {
+        vec![overlay]
     } else {
-        Ok(serde_yaml_bw::from_str(content)?)
+        Vec::new()
+    };
+    load_layered_tool_map_config(base, &overlays)
+}
+
+impl ToolMapConfig {
+    /// Write the config to `path`, as JSON for `.json` files and YAML otherwise.
+    ///
+    /// Unset fields are omitted to keep the output close to a hand-written file. The file
+    /// is replaced atomically via a temporary file in the same directory. Configs marked
+    /// [`resolved`](Self::resolved) are refused; edit and save the source model instead,
+    /// e.g. one parsed without placeholders or built in code.
+    pub fn save(&self, path: &Path) -> Result<(), McpError> {
+        if self.resolved {
+            return Err(McpError::config_file(
+                path,
+                "refusing to save a config with resolved placeholders, includes, overlays, \
+                 or secrets",
+            ));
+        }
+        let mut content = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::to_string_pretty(self)?
+        } else {
+            serde_yaml_bw::to_string(self).map_err(|err| McpError::config_file(path, err))?
+        };
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut tmp = NamedTempFile::new_in(dir).map_err(|err| McpError::config_file(path, err))?;
+        tmp.write_all(content.as_bytes())
+            .map_err(|err| McpError::config_file(tmp.path(), err))?;
+        tmp.persist(path)
+            .map_err(|err| McpError::config_file(path, err.error))?;
+        Ok(())
+    }
+}
+
+/// Path of the overlay for `environment` next to `base`.
+pub fn overlay_path(base: &Path, environment: &str) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match base.extension() {
+        Some(ext) => format!("{stem}.{environment}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{environment}"),
+    };
+    base.with_file_name(file_name)
+}
+
+fn apply_overlay(tools: &mut Vec<Value>, overlay: Value) -> Result<(), String> {
+    let Value::Object(mut root) = overlay else {
+        return Err("expected a mapping at the top level".into());
+    };
+    let entries = match root.remove("tools") {
+        Some(Value::Array(entries)) => entries,
+        Some(_) => return Err("`tools` must be a list".into()),
+        None => Vec::new(),
+    };
+    if let Some(key) = root.keys().next() {
+        return Err(format!("overlays may only contain `tools`, found `{key}`"));
+    }
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let Value::Object(mut fields) = entry else {
+            return Err(format!("tools[{index}]: expected a mapping"));
+        };
+        let remove = match fields.remove("remove") {
+            None => false,
+            Some(Value::Bool(remove)) => remove,
+            Some(_) => return Err(format!("tools[{index}].remove: expected a boolean")),
+        };
+        let identity = overlay_identity(&fields)
+            .ok_or_else(|| format!("tools[{index}]: overlay entries need a `name`"))?;
+        let position = tools
+            .iter()
+            .position(|tool| tool.as_object().and_then(overlay_identity) == Some(identity.clone()));
+
+        match (position, remove) {
+            (Some(position), true) => {
+                tools.remove(position);
+            }
+            (None, true) => {}
+            (Some(position), false) => {
+                if let Some(base) = tools[position].as_object_mut() {
+                    base.extend(fields);
+                }
+            }
+            (None, false) => tools.push(Value::Object(fields)),
+        }
+    }
+    Ok(())
+}
+
+fn overlay_identity(
+    fields: &serde_json::Map<String, Value>,
+) -> Option<(Option<String>, String, Option<String>)> {
+    let text = |key: &str| fields.get(key).and_then(Value::as_str).map(str::to_string);
+    Some((text("namespace"), text("name")?, text("version")))
+}
+
+/// Expand placeholders in the strings of a parsed `value` (never in keys or comments),
+/// so expanded text cannot change the document's structure, and return whether any
+/// string changed. `schemas` describe `value`; a string that is a single placeholder
+/// becomes a number or boolean where they expect one, so `timeout_ms: ${TIMEOUT_MS}`
+/// works.
+fn expand_env_in(
+    value: &mut Value,
+    schemas: &[&Value],
+    root: &Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<bool, String> {
+    let variants = schema_variants(schemas, root);
+    let mut changed = false;
+    match value {
+        Value::String(text) => {
+            let expanded = expand_env(text, lookup)?;
+            if expanded != *text {
+                changed = true;
+                *value = if is_placeholder(text) {
+                    typed_scalar(expanded, &variants)
+                } else {
+                    Value::String(expanded)
+                };
+            }
+        }
+        Value::Array(items) => {
+            let children = variants
+                .iter()
+                .filter_map(|variant| variant.get("items"))
+                .collect::<Vec<_>>();
+            for item in items {
+                changed |= expand_env_in(item, &children, root, lookup)?;
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                let children = variants
+                    .iter()
+                    .filter_map(|variant| {
+                        variant
+                            .get("properties")
+                            .and_then(|properties| properties.get(key.as_str()))
+                            .or_else(|| variant.get("additionalProperties"))
+                    })
+                    .collect::<Vec<_>>();
+                changed |= expand_env_in(field, &children, root, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(changed)
+}
+
+/// The concrete schemas behind `schemas`, following `$ref` and `anyOf`/`oneOf`/`allOf`.
+fn schema_variants<'a>(schemas: &[&'a Value], root: &'a Value) -> Vec<&'a Value> {
+    let mut variants = Vec::new();
+    let mut pending = schemas.to_vec();
+    while let Some(schema) = pending.pop() {
+        if let Some(target) = schema.get("$ref").and_then(Value::as_str) {
+            pending.extend(target.strip_prefix('#').and_then(|path| root.pointer(path)));
+        }
+        for combinator in ["anyOf", "oneOf", "allOf"] {
+            if let Some(Value::Array(options)) = schema.get(combinator) {
+                pending.extend(options);
+            }
+        }
+        variants.push(schema);
+    }
+    variants
+}
+
+fn is_placeholder(text: &str) -> bool {
+    text.strip_prefix("${")
+        .and_then(|rest| rest.strip_suffix('}'))
+        .is_some_and(|body| !body.contains('}'))
+}
+
+/// `expanded` as a number or boolean if one of `variants` expects that type.
+fn typed_scalar(expanded: String, variants: &[&Value]) -> Value {
+    let accepts = |kind: &str| {
+        variants.iter().any(|variant| match variant.get("type") {
+            Some(Value::String(ty)) => ty == kind,
+            Some(Value::Array(types)) => types.iter().any(|ty| ty == kind),
+            _ => false,
+        })
+    };
+    match serde_json::from_str::<Value>(expanded.trim()) {
+        Ok(Value::Number(number))
+            if accepts("number") || (accepts("integer") && !number.is_f64()) =>
+        {
+            Value::Number(number)
+        }
+        Ok(Value::Bool(flag)) if accepts("boolean") => Value::Bool(flag),
+        _ => Value::String(expanded),
     }
 }
 
+/// Expand `${VAR}` and `${VAR:-default}` placeholders; `$${` escapes a literal `${`.
+fn expand_env(content: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+
+        if let Some(escaped) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let Some(body) = tail.strip_prefix("${") else {
+            out.push('$');
+            rest = &tail[1..];
+            continue;
+        };
+        let end = body
+            .find('}')
+            .ok_or_else(|| "unterminated `${` placeholder".to_string())?;
+        let placeholder = &body[..end];
+        let (name, default) = match placeholder.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (placeholder, None),
+        };
+        if name.is_empty() {
+            return Err("empty `${}` placeholder".into());
+        }
+
+        match lookup(name).or_else(|| default.map(str::to_string)) {
+            Some(value) => out.push_str(&value),
+            None => return Err(format!("environment variable `{name}` is not set")),
+        }
+        rest = &body[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
 fn is_json(path: &Path, content: &str) -> bool {
     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
         if matches!(ext, "json") {
@@ -36,6 +553,7 @@ fn is_json(path: &Path, content: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::retry::{BackoffStrategy, Jitter, RetryPolicy};
 
     #[test]
     fn parses_json() {
@@ -49,6 +567,77 @@ mod tests {
         assert_eq!(config.tools[0].name, "echo");
     }
 
+    #[test]
+    fn save_round_trips_json_and_yaml() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut tool = ToolRef::new("echo", "./echo.wasm", "tool_invoke");
+        tool.timeout_ms = Some(250);
+        tool.labels.insert("team".into(), "core".into());
+        let config = ToolMapConfig {
+            tools: vec![tool, ToolRef::new("weather", "./weather.wasm", "run")],
+            ..Default::default()
+        };
+
+        for name in ["toolmap.json", "toolmap.yaml"] {
+            let path = tmp.path().join(name);
+            config.save(&path).unwrap();
+            assert_eq!(load_tool_map_config(&path).unwrap(), config, "{name}");
+        }
+
+        let yaml = fs::read_to_string(tmp.path().join("toolmap.yaml")).unwrap();
+        assert!(!yaml.contains("enabled"), "{yaml}");
+        assert!(!yaml.contains("sha256"), "{yaml}");
+
+        let templated = tmp.path().join("templated.yaml");
+        fs::write(
+            &templated,
+            "tools:\n  - name: echo\n    component: ${TOOLS_DIR:-/opt/tools}/echo.wasm\n    entry: run\n",
+        )
+        .unwrap();
+        let loaded = load_tool_map_config(&templated).unwrap();
+        assert!(loaded.resolved);
+        let err = loaded.save(&templated).unwrap_err();
+        assert!(matches!(err, McpError::ConfigFile { .. }), "{err}");
+        assert!(
+            fs::read_to_string(&templated)
+                .unwrap()
+                .contains("${TOOLS_DIR")
+        );
+    }
+
+    #[test]
+    fn parses_tool_metadata() {
+        let config = parse_tool_map_config(
+            Path::new("config.yaml"),
+            r#"
+tools:
+  - name: weather
+    description: Current conditions for a city.
+    component: ./weather.wasm
+    entry: tool_invoke
+    input_schema:
+      type: object
+      required: [city]
+      properties:
+        city: { type: string }
+    examples:
+      - description: Conditions in Paris
+        input: { city: Paris }
+        output: { temp_c: 18 }
+        "#,
+        )
+        .unwrap();
+
+        let tool = &config.tools[0];
+        assert_eq!(
+            tool.description.as_deref(),
+            Some("Current conditions for a city.")
+        );
+        assert_eq!(tool.input_schema.as_ref().unwrap()["required"][0], "city");
+        assert_eq!(tool.output_schema, None);
+        assert_eq!(tool.examples[0].input["city"], "Paris");
+    }
+
     #[test]
     fn parses_yaml() {
         let config = parse_tool_map_config(
@@ -65,4 +654,281 @@ tools:
         assert_eq!(config.tools.len(), 1);
         assert_eq!(config.tools[0].name, "echo");
     }
+
+    #[test]
+    fn composes_included_files_and_directories() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::create_dir(root.join("team")).unwrap();
+        std::fs::write(
+            root.join("team/b.yaml"),
+            "tools:\n  - name: b\n    component: ./b.wasm\n    entry: run\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("team/a.json"),
+            r#"{"tools":[{"name":"a","component":"./a.wasm","entry":"run"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("toolmap.yaml"),
+            "include:\n  - team\ntools:\n  - name: main\n    component: ./main.wasm\n    entry: run\n",
+        )
+        .unwrap();
+
+        let config = load_tool_map_config(&root.join("toolmap.yaml")).unwrap();
+        let names = config
+            .tools
+            .iter()
+            .map(|tool| tool.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "main"]);
+        assert!(config.include.is_empty());
+    }
+
+    #[test]
+    fn rejects_include_cycles() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::write(root.join("a.yaml"), "include:\n  - b.yaml\n").unwrap();
+        std::fs::write(root.join("b.yaml"), "include:\n  - a.yaml\n").unwrap();
+
+        let err = load_tool_map_config(&root.join("a.yaml")).unwrap_err();
+        match err {
+            McpError::ConfigFile { path, message } => {
+                assert!(path.ends_with("b.yaml"));
+                assert!(message.contains("include cycle"));
+            }
+            other => panic!("expected include cycle error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn expands_environment_placeholders() {
+        let lookup = |name: &str| match name {
+            "TOOLS_DIR" => Some("/opt/tools".to_string()),
+            "ECHO_TIMEOUT" => Some("1500".to_string()),
+            _ => None,
+        };
+
+        let expanded = expand_env(
+            "component: ${TOOLS_DIR}/echo.wasm\ntimeout_ms: ${ECHO_TIMEOUT}\nretries: ${RETRIES:-2}\nliteral: $${KEEP}",
+            lookup,
+        )
+        .unwrap();
+        assert_eq!(
+            expanded,
+            "component: /opt/tools/echo.wasm\ntimeout_ms: 1500\nretries: 2\nliteral: ${KEEP}"
+        );
+
+        let err = expand_env("component: ${MISSING}/echo.wasm", lookup).unwrap_err();
+        assert!(err.contains("MISSING"));
+    }
+
+    #[test]
+    fn expands_placeholders_inside_string_values_only() {
+        let lookup = |name: &str| match name {
+            "DESCRIPTION" => Some("say \"hi\"\n# not a comment\nentry: evil".to_string()),
+            "TIMEOUT" => Some("1500".to_string()),
+            "VERSION" => Some("2".to_string()),
+            _ => None,
+        };
+        let mut document: Value = serde_yaml_bw::from_str(
+            r#"
+# ${UNSET} in a comment is left alone
+tools:
+  - name: echo
+    description: ${DESCRIPTION}
+    component: ./echo.wasm
+    entry: run
+    version: ${VERSION}
+    timeout_ms: ${TIMEOUT}
+"#,
+        )
+        .unwrap();
+        let schema = schema::tool_map_schema();
+        expand_env_in(&mut document, &[&schema], &schema, &lookup).unwrap();
+        let config = schema::validate(document).unwrap();
+
+        let tool = &config.tools[0];
+        assert_eq!(
+            tool.description.as_deref(),
+            Some("say \"hi\"\n# not a comment\nentry: evil")
+        );
+        assert_eq!(tool.entry, "run");
+        assert_eq!(tool.version.as_deref(), Some("2"));
+        assert_eq!(tool.timeout_ms, Some(1500));
+    }
+
+    #[test]
+    fn discovers_component_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::create_dir(root.join("tools")).unwrap();
+        std::fs::write(root.join("tools/weather.component.wasm"), b"wasm").unwrap();
+        std::fs::write(root.join("tools/echo.component.wasm"), b"wasm").unwrap();
+        std::fs::write(root.join("tools/notes.txt"), b"ignored").unwrap();
+        std::fs::write(
+            root.join("toolmap.yaml"),
+            "discover: ./tools/\ntools:\n  - name: echo\n    component: ./custom.wasm\n    entry: run\n",
+        )
+        .unwrap();
+
+        let config = load_tool_map_config(&root.join("toolmap.yaml")).unwrap();
+        assert_eq!(config.tools.len(), 2);
+        assert_eq!(config.tools[0].name, "weather");
+        assert_eq!(config.tools[0].entry, ToolRef::DEFAULT_ENTRY);
+        assert!(
+            config.tools[0]
+                .component
+                .ends_with("weather.component.wasm")
+        );
+        assert_eq!(config.tools[1].name, "echo");
+        assert_eq!(config.tools[1].entry, "run");
+    }
+
+    #[test]
+    fn applies_file_namespace_to_included_tools() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        std::fs::write(
+            root.join("crm.yaml"),
+            "namespace: crm\ntools:\n  - name: create_lead\n    component: ./lead.wasm\n    entry: run\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("toolmap.yaml"),
+            "include:\n  - crm.yaml\ntools:\n  - name: echo\n    component: ./echo.wasm\n    entry: run\n",
+        )
+        .unwrap();
+
+        let config = load_tool_map_config(&root.join("toolmap.yaml")).unwrap();
+        assert_eq!(config.tools[0].qualified_name(), "crm/create_lead");
+        assert_eq!(config.tools[1].qualified_name(), "echo");
+    }
+
+    #[test]
+    fn overlays_override_remove_and_add_tools() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let base = root.join("toolmap.yaml");
+        std::fs::write(
+            &base,
+            "tools:\n  - name: echo\n    component: ./echo.wasm\n    entry: run\n    timeout_ms: 100\n  - name: legacy\n    component: ./legacy.wasm\n    entry: run\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("toolmap.prod.yaml"),
+            "tools:\n  - name: echo\n    timeout_ms: 5000\n  - name: legacy\n    remove: true\n  - name: extra\n    component: ./extra.wasm\n    entry: run\n",
+        )
+        .unwrap();
+
+        let config = load_tool_map_config_for_env(&base, "prod").unwrap();
+        let names = config
+            .tools
+            .iter()
+            .map(|tool| tool.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["echo", "extra"]);
+        assert_eq!(config.tools[0].timeout_ms, Some(5000));
+        assert_eq!(config.tools[0].component, "./echo.wasm");
+
+        let staging = load_tool_map_config_for_env(&base, "staging").unwrap();
+        assert_eq!(staging.tools.len(), 2);
+    }
+
+    #[test]
+    fn parses_retry_policy() {
+        let config = parse_tool_map_config(
+            Path::new("config.yaml"),
+            "defaults:\n  retry_policy: { strategy: fibonacci }\ntools:\n  - name: a\n    component: ./a.wasm\n    entry: run\n    retry_policy:\n      strategy: exponential_capped\n      max_delay_ms: 2000\n      jitter: full\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.tools[0].retry_policy,
+            Some(
+                RetryPolicy::new(BackoffStrategy::ExponentialCapped { max_delay_ms: 2000 })
+                    .with_jitter(Jitter::Full)
+            )
+        );
+        assert_eq!(
+            config.defaults.unwrap().retry_policy,
+            Some(BackoffStrategy::Fibonacci.into())
+        );
+    }
+
+    #[test]
+    fn tools_inherit_defaults() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("toolmap.yaml");
+        std::fs::write(
+            &path,
+            "defaults:\n  timeout_ms: 750\n  max_retries: 3\ntools:\n  - name: a\n    component: ./a.wasm\n    entry: run\n  - name: b\n    component: ./b.wasm\n    entry: run\n    timeout_ms: 50\n",
+        )
+        .unwrap();
+
+        let config = load_tool_map_config(&path).unwrap();
+        assert_eq!(config.tools[0].timeout_ms, Some(750));
+        assert_eq!(config.tools[0].max_retries, Some(3));
+        assert_eq!(config.tools[1].timeout_ms, Some(50));
+        assert_eq!(config.tools[1].max_retries, Some(3));
+        assert_eq!(config.tools[1].retry_backoff_ms, None);
+    }
+
+    /// Serve a single HTTP response and hand back the raw request it answered.
+    fn serve_once(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..read]);
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (format!("http://{addr}"), handle)
+    }
+
+    #[tokio::test]
+    async fn loads_remote_config_with_auth_header() {
+        let (base, server) = serve_once(
+            "namespace: fleet\ntools:\n  - name: echo\n    component: ./echo.wasm\n    entry: run\n",
+        );
+
+        let config =
+            load_tool_map_config_remote(&format!("{base}/toolmap.yaml"), Some("Bearer s3cret"))
+                .await
+                .unwrap();
+        assert_eq!(config.tools[0].name, "echo");
+        assert_eq!(config.tools[0].namespace.as_deref(), Some("fleet"));
+
+        let request = server.join().unwrap().to_ascii_lowercase();
+        assert!(
+            request.contains("authorization: bearer s3cret"),
+            "{request}"
+        );
+    }
+
+    #[test]
+    fn remote_configs_reject_includes() {
+        let err = parse_remote_config(
+            "https://config.example/toolmap.json",
+            r#"{"include":["more.yaml"],"tools":[]}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, McpError::RemoteConfig { .. }), "{err}");
+    }
 }
@@ -0,0 +1,248 @@
+//! Contract checks of a tool against its declared schemas.
+//!
+//! [`WasixExecutor::contract_check`] validates sample inputs against the tool's
+//! input schema, invokes the tool with the valid ones, and validates the outputs
+//! against its output schema. The [`ContractReport`] lists every violation, so a
+//! tool publisher can check in CI that the component keeps the promises its
+//! schemas make:
+//!
+//! ```ignore
+//! let inputs = tool.examples.iter().map(|example| example.input.clone());
+//! let report = executor.contract_check(&tool, inputs).await?;
+//! assert!(report.is_success(), "{report}");
+//! ```
+
+use std::fmt;
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+use crate::executor::WasixExecutor;
+use crate::types::{McpError, ToolInput, ToolRef};
+
+impl WasixExecutor {
+    /// Check `tool` against its schemas with each of `inputs`.
+    ///
+    /// The input schema is the one [`input_schema`](Self::input_schema) returns; the
+    /// output schema is the tool's `output_schema`. A missing schema is not checked.
+    /// Fails if a declared schema is not valid JSON Schema; violations of valid
+    /// schemas and failed invocations are in the report.
+    pub async fn contract_check(
+        &self,
+        tool: &ToolRef,
+        inputs: impl IntoIterator<Item = Value>,
+    ) -> Result<ContractReport, McpError> {
+        let input_schema = compile(tool, "input", self.input_schema(tool).await?.as_ref())?;
+        let output_schema = compile(tool, "output", tool.output_schema.as_ref())?;
+        let mut report = ContractReport {
+            input_schema: input_schema.is_some(),
+            output_schema: output_schema.is_some(),
+            cases: Vec::new(),
+        };
+        for input in inputs {
+            let mut case = ContractCase {
+                violations: check(input_schema.as_ref(), &Schema::Input, &input),
+                input,
+                output: None,
+            };
+            if case.violations.is_empty() {
+                match self.invoke(tool, &ToolInput::new(case.input.clone())).await {
+                    Ok(output) => {
+                        case.violations =
+                            check(output_schema.as_ref(), &Schema::Output, &output.payload);
+                        case.output = Some(output.payload);
+                    }
+                    Err(err) => case.violations.push(Violation::Failed(err.to_string())),
+                }
+            }
+            report.cases.push(case);
+        }
+        Ok(report)
+    }
+}
+
+enum Schema {
+    Input,
+    Output,
+}
+
+fn compile(
+    tool: &ToolRef,
+    kind: &str,
+    schema: Option<&Value>,
+) -> Result<Option<Validator>, McpError> {
+    schema
+        .map(|schema| {
+            jsonschema::validator_for(schema).map_err(|err| {
+                McpError::InvalidInput(format!(
+                    "`{}` declares an invalid {kind} schema: {err}",
+                    tool.name
+                ))
+            })
+        })
+        .transpose()
+}
+
+fn check(validator: Option<&Validator>, schema: &Schema, value: &Value) -> Vec<Violation> {
+    let Some(validator) = validator else {
+        return Vec::new();
+    };
+    validator
+        .iter_errors(value)
+        .map(|err| {
+            let path = err.instance_path.to_string();
+            let message = err.to_string();
+            match schema {
+                Schema::Input => Violation::Input { path, message },
+                Schema::Output => Violation::Output { path, message },
+            }
+        })
+        .collect()
+}
+
+/// Outcome of [`WasixExecutor::contract_check`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContractReport {
+    /// Whether the tool declares an input schema; inputs are not checked without one.
+    pub input_schema: bool,
+    /// Whether the tool declares an output schema; outputs are not checked without one.
+    pub output_schema: bool,
+    /// One case per input, in order.
+    pub cases: Vec<ContractCase>,
+}
+
+impl ContractReport {
+    pub fn violations(&self) -> usize {
+        self.cases.iter().map(|case| case.violations.len()).sum()
+    }
+
+    /// Whether there were cases and none of them violated the contract.
+    pub fn is_success(&self) -> bool {
+        !self.cases.is_empty() && self.violations() == 0
+    }
+}
+
+impl fmt::Display for ContractReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, case) in self.cases.iter().enumerate() {
+            let status = if case.passed() { "PASS" } else { "FAIL" };
+            writeln!(f, "[{status}] case {index}: {}", case.input)?;
+            for violation in &case.violations {
+                writeln!(f, "       {violation}")?;
+            }
+        }
+        for (declared, kind) in [(self.input_schema, "input"), (self.output_schema, "output")] {
+            if !declared {
+                writeln!(f, "no {kind} schema declared; {kind}s were not checked")?;
+            }
+        }
+        write!(
+            f,
+            "{} cases, {} violations",
+            self.cases.len(),
+            self.violations()
+        )
+    }
+}
+
+/// One sample input and what became of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContractCase {
+    pub input: Value,
+    /// The tool's output, when the input was valid and the invocation succeeded.
+    pub output: Option<Value>,
+    pub violations: Vec<Violation>,
+}
+
+impl ContractCase {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A way in which a case broke the tool's contract.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// The input does not match the input schema; the tool was not invoked.
+    Input { path: String, message: String },
+    /// The output does not match the output schema.
+    Output { path: String, message: String },
+    /// The invocation of a valid input failed.
+    Failed(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = |path: &str| {
+            if path.is_empty() {
+                "/".to_string()
+            } else {
+                path.to_string()
+            }
+        };
+        match self {
+            Violation::Input { path, message } => write!(f, "input {}: {message}", at(path)),
+            Violation::Output { path, message } => write!(f, "output {}: {message}", at(path)),
+            Violation::Failed(message) => write!(f, "invocation failed: {message}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::tests::echo_component;
+
+    #[tokio::test]
+    async fn reports_input_and_output_violations() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let mut tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        tool.input_schema = Some(json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "required": ["city"],
+        }));
+        tool.output_schema = Some(json!({ "type": "object", "required": ["temp"] }));
+        let executor = WasixExecutor::new().unwrap();
+
+        let report = executor
+            .contract_check(
+                &tool,
+                [
+                    json!({ "city": "Oslo", "temp": 3 }),
+                    json!({ "city": 7 }),
+                    json!({ "city": "Oslo" }),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert!(report.cases[0].passed(), "{report}");
+        assert_eq!(
+            report.cases[0].output,
+            Some(json!({ "city": "Oslo", "temp": 3 }))
+        );
+        assert!(matches!(
+            &report.cases[1].violations[..],
+            [Violation::Input { path, .. }] if path == "/city"
+        ));
+        assert_eq!(report.cases[1].output, None);
+        assert!(matches!(
+            &report.cases[2].violations[..],
+            [Violation::Output { message, .. }] if message.contains("temp")
+        ));
+        assert!(!report.is_success());
+        assert!(
+            report.to_string().ends_with("3 cases, 2 violations"),
+            "{report}"
+        );
+
+        tool.output_schema = Some(json!({ "type": 5 }));
+        let err = executor.contract_check(&tool, []).await.unwrap_err();
+        assert!(err.to_string().contains("invalid output schema"), "{err}");
+    }
+}
@@ -0,0 +1,118 @@
+//! Reproducible clocks and randomness for guests, with the `test-util` feature.
+//!
+//! [`WasixExecutor::with_deterministic_guests`](crate::executor::WasixExecutor::with_deterministic_guests)
+//! gives every guest a [`MockClock`] for its wall and monotonic clocks, and random
+//! number generators seeded from a fixed seed. Tools that read the time or draw
+//! random numbers then produce the same output on every run, e.g. in CI. Time only
+//! moves when the test advances the clock.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cap_rand::SeedableRng;
+use cap_rand::rngs::StdRng;
+use wasmtime_wasi::{HostMonotonicClock, HostWallClock, WasiCtxBuilder};
+
+/// Clock that only moves when told to, shared by its clones.
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    start: Duration,
+    /// Nanoseconds since `start`.
+    elapsed: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// A clock reading `start`.
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            start: start.duration_since(UNIX_EPOCH).unwrap_or_default(),
+            elapsed: Arc::default(),
+        }
+    }
+
+    /// Move the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        let nanos = u64::try_from(by.as_nanos()).unwrap_or(u64::MAX);
+        self.elapsed.fetch_add(nanos, Ordering::SeqCst);
+    }
+
+    pub fn now(&self) -> SystemTime {
+        UNIX_EPOCH + self.start + self.elapsed()
+    }
+
+    fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed.load(Ordering::SeqCst))
+    }
+}
+
+impl Default for MockClock {
+    /// A clock reading 2024-01-01T00:00:00Z.
+    fn default() -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(1_704_067_200))
+    }
+}
+
+impl HostWallClock for MockClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        self.start + self.elapsed()
+    }
+}
+
+impl HostMonotonicClock for MockClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.elapsed.load(Ordering::SeqCst)
+    }
+}
+
+/// Clock and seed given to every guest.
+#[derive(Clone, Debug)]
+pub(crate) struct DeterministicGuests {
+    pub(crate) clock: MockClock,
+    pub(crate) seed: u64,
+}
+
+impl DeterministicGuests {
+    /// Replace the clocks and random sources of `builder`. Every guest starts from
+    /// the same seed, so each call sees the same random sequence.
+    pub(crate) fn configure(&self, mut builder: WasiCtxBuilder) -> WasiCtxBuilder {
+        builder
+            .wall_clock(self.clock.clone())
+            .monotonic_clock(self.clock.clone())
+            .secure_random(StdRng::seed_from_u64(self.seed))
+            .insecure_random(StdRng::seed_from_u64(self.seed.rotate_left(32)))
+            .insecure_random_seed(u128::from(self.seed));
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_moves_only_when_advanced() {
+        let clock = MockClock::default();
+        let shared = clock.clone();
+        assert_eq!(
+            HostWallClock::now(&clock),
+            Duration::from_secs(1_704_067_200)
+        );
+        assert_eq!(HostMonotonicClock::now(&clock), 0);
+
+        shared.advance(Duration::from_millis(1500));
+        assert_eq!(
+            clock.now(),
+            UNIX_EPOCH + Duration::from_secs(1_704_067_200) + Duration::from_millis(1500)
+        );
+        assert_eq!(HostMonotonicClock::now(&clock), 1_500_000_000);
+    }
+}
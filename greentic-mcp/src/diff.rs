@@ -0,0 +1,115 @@
+//! Structural comparison between two [`ToolMap`]s.
+
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::tool_map::ToolMap;
+use crate::types::ToolRef;
+
+/// Differences between two tool maps, keyed by tool key.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ToolMapDiff {
+    pub added: Vec<ToolRef>,
+    pub removed: Vec<ToolRef>,
+    pub changed: Vec<ToolChange>,
+}
+
+/// A tool present in both maps whose settings differ.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolChange {
+    pub key: String,
+    pub previous: ToolRef,
+    pub current: ToolRef,
+    /// Names of the config fields that differ, sorted alphabetically.
+    pub fields: Vec<String>,
+}
+
+impl ToolMapDiff {
+    /// Whether the two maps are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl ToolMap {
+    /// Compare `self` (the previous map) against `other` (the new map).
+    pub fn diff(&self, other: &ToolMap) -> ToolMapDiff {
+        let mut diff = ToolMapDiff::default();
+
+        for (key, current) in other.iter() {
+            match self.entry(key) {
+                Some(previous) if previous == current => {}
+                Some(previous) => diff.changed.push(ToolChange {
+                    key: key.clone(),
+                    previous: previous.clone(),
+                    current: current.clone(),
+                    fields: changed_fields(previous, current),
+                }),
+                None => diff.added.push(current.clone()),
+            }
+        }
+        for (key, previous) in self.iter() {
+            if other.entry(key).is_none() {
+                diff.removed.push(previous.clone());
+            }
+        }
+
+        diff
+    }
+}
+
+fn changed_fields(previous: &ToolRef, current: &ToolRef) -> Vec<String> {
+    let (Ok(Value::Object(previous)), Ok(Value::Object(current))) = (
+        serde_json::to_value(previous),
+        serde_json::to_value(current),
+    ) else {
+        return Vec::new();
+    };
+    // Unset fields are skipped when serializing, so look at keys from both sides.
+    let keys: BTreeSet<&String> = previous.keys().chain(current.keys()).collect();
+    keys.into_iter()
+        .filter(|field| previous.get(*field) != current.get(*field))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ToolMapConfig;
+
+    fn map(tools: Vec<ToolRef>) -> ToolMap {
+        ToolMap::from_config(&ToolMapConfig {
+            tools,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_fields() {
+        let before = map(vec![
+            ToolRef::new("echo", "./echo.wasm", "run"),
+            ToolRef::new("old", "./old.wasm", "run"),
+        ]);
+        let after = map(vec![
+            ToolRef {
+                timeout_ms: Some(500),
+                ..ToolRef::new("echo", "./echo-v2.wasm", "run")
+            },
+            ToolRef::new("new", "./new.wasm", "run"),
+        ]);
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "new");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].name, "old");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].key, "echo");
+        assert_eq!(diff.changed[0].fields, ["component", "timeout_ms"]);
+
+        assert!(after.diff(&after).is_empty());
+    }
+}
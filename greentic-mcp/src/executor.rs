@@ -1,20 +1,120 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::{Duration, Instant};
 
+use async_trait::async_trait;
+use bytes::Bytes;
+use greentic_types::TenantCtx;
+use mcp_exec::telemetry::AttemptRecord;
+use mcp_exec::{ExecError, RunnerError, TenantIdentity, ToolStore};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWrite;
 use tokio::task::JoinError;
-use tokio::time::{sleep, timeout};
-use tracing::instrument;
-use wasmtime::component::{Component, Linker, ResourceTable};
-use wasmtime::{Engine, Store, Trap};
-use wasmtime_wasi::p2;
+use tokio::time::timeout;
+use tracing::{Instrument, info_span};
+use wasmtime::component::{Component, InstancePre, Linker, ResourceTable};
+use wasmtime::{Engine, Store, StoreContextMut, Trap, UpdateDeadline};
+use wasmtime_wasi::cli::{IsTerminal, StdoutStream};
+use wasmtime_wasi::p2::{self, OutputStream, Pollable, StreamResult};
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
 
-use crate::retry;
-use crate::types::{McpError, ToolInput, ToolOutput, ToolRef};
+use crate::audit::{AuditLog, AuditRecord};
+use crate::binary::{self, Attachment, BinarySink, DEFAULT_MAX_BINARY_OUTPUT};
+use crate::cancel::CancellationToken;
+use crate::capture::PayloadCapture;
+use crate::component_cache::ComponentCache;
+use crate::concurrency::ConcurrencyLimiter;
+#[cfg(feature = "test-util")]
+use crate::deterministic::{DeterministicGuests, MockClock};
+#[cfg(feature = "test-util")]
+use crate::fault::{ForcedFailure, ForcedFailures};
+use crate::history::{HistoryFilter, InvocationHistory};
+use crate::mcp_client::McpClient;
+#[cfg(feature = "profiling")]
+use crate::profiling::{HotCallProfiler, HotCallProfiling, ProfileSink};
+use crate::progress::{self, ProgressSink};
+use crate::rate_limit::RateLimiter;
+use crate::retry::{self, GiveUpReason, RetryBudget, RetryClassifier, RetryObserver, RetryStore};
+use crate::sampling::{self, Sampler, SamplingAccess};
+use crate::secrets::SecretScrubber;
+use crate::telemetry::{self, InvocationMetrics, Phase, PhaseClock, PhaseTimings};
+use crate::tool_map::ToolMap;
+use crate::types::{ErrorCode, McpEndpoint, McpError, ToolInput, ToolOutput, ToolRef, ToolSource};
+use crate::usage::{self, UsageCounter, UsageMeter};
+
+/// Live connections to remote MCP servers, shared by executor clones.
+type McpClients = tokio::sync::Mutex<HashMap<McpEndpoint, Arc<McpClient>>>;
+
+/// `describe-v1` documents by component digest; a component's document never changes.
+type DescribeCache = Mutex<HashMap<String, Option<Value>>>;
+
+/// How often running guests check whether their invocation was cancelled.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Per-call extras for [`WasixExecutor::invoke_with`].
+#[derive(Clone, Debug, Default)]
+pub struct InvokeOptions {
+    /// Receives progress the tool reports through
+    /// [`PROGRESS_INTERFACE`](crate::progress::PROGRESS_INTERFACE).
+    pub progress: Option<ProgressSink>,
+    /// Stops the invocation, including pending retries, once cancelled.
+    pub cancellation: Option<CancellationToken>,
+    /// Answers sampling requests from tools that set `allow_sampling`.
+    pub sampler: Option<Sampler>,
+    /// Trace the invocation belongs to. Exported spans join it with the `otel` feature.
+    pub trace_context: Option<TraceContext>,
+    /// Tenant the invocation runs for. It decides which tools may be invoked and
+    /// which quotas apply, and is recorded in the audit log.
+    pub tenant: Option<TenantCtx>,
+    /// Session, locale, and other caller identity passed with the tenant to tools
+    /// that set `inject_tenant`.
+    pub identity: Option<TenantIdentity>,
+}
+
+/// W3C Trace Context of the caller, as carried in `traceparent`/`tracestate` headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceContext {
+    pub traceparent: String,
+    pub tracestate: Option<String>,
+}
 
 /// Executes WASIX/WASI tools compiled to WebAssembly.
 #[derive(Clone)]
 pub struct WasixExecutor {
     engine: Engine,
+    cache_dir: PathBuf,
+    retry_budget: Option<Arc<RetryBudget>>,
+    retry_observer: Option<RetryObserver>,
+    retry_store: Option<Arc<dyn RetryStore>>,
+    retry_classifier: RetryClassifier,
+    mcp_clients: Arc<McpClients>,
+    describes: Arc<DescribeCache>,
+    audit_log: Option<Arc<AuditLog>>,
+    payload_capture: Option<Arc<PayloadCapture>>,
+    secret_scrubber: Option<Arc<SecretScrubber>>,
+    usage_meter: Option<Arc<UsageMeter>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    concurrency_limiter: Option<Arc<ConcurrencyLimiter>>,
+    component_cache: Option<Arc<ComponentCache>>,
+    #[cfg(feature = "profiling")]
+    hot_call_profiling: Option<HotCallProfiling>,
+    history: Arc<InvocationHistory>,
+    slow_call_threshold: Option<Duration>,
+    log_capture: Option<usize>,
+    max_binary_output: usize,
+    #[cfg(feature = "test-util")]
+    deterministic_guests: Option<DeterministicGuests>,
+    #[cfg(feature = "test-util")]
+    forced_failures: Arc<ForcedFailures>,
 }
 
 impl WasixExecutor {
@@ -24,9 +124,175 @@ impl WasixExecutor {
         config.wasm_component_model(true);
         config.async_support(false);
         config.epoch_interruption(true);
+        config.consume_fuel(true);
         let engine = Engine::new(&config)
             .map_err(|err| McpError::Internal(format!("failed to create engine: {err}")))?;
-        Ok(Self { engine })
+        spawn_epoch_ticker(&engine)?;
+        Ok(Self {
+            engine,
+            cache_dir: std::env::temp_dir().join("greentic-mcp"),
+            retry_budget: None,
+            retry_observer: None,
+            retry_store: None,
+            retry_classifier: RetryClassifier::default(),
+            mcp_clients: Arc::default(),
+            describes: Arc::default(),
+            audit_log: None,
+            payload_capture: None,
+            secret_scrubber: None,
+            usage_meter: None,
+            rate_limiter: None,
+            concurrency_limiter: None,
+            component_cache: None,
+            #[cfg(feature = "profiling")]
+            hot_call_profiling: None,
+            history: Arc::default(),
+            slow_call_threshold: None,
+            log_capture: None,
+            max_binary_output: DEFAULT_MAX_BINARY_OUTPUT,
+            #[cfg(feature = "test-util")]
+            deterministic_guests: None,
+            #[cfg(feature = "test-util")]
+            forced_failures: Arc::default(),
+        })
+    }
+
+    /// Directory used to cache components fetched from remote sources.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+
+    /// Limit retries across every tool invoked through this executor (and its clones).
+    pub fn with_retry_budget(mut self, budget: Arc<RetryBudget>) -> Self {
+        self.retry_budget = Some(budget);
+        self
+    }
+
+    /// Report every step of the retry loop to `observer`, in addition to tracing.
+    pub fn with_retry_observer(mut self, observer: RetryObserver) -> Self {
+        self.retry_observer = Some(observer);
+        self
+    }
+
+    /// Persist retry progress of invocations whose payload carries
+    /// `_meta.idempotency_key`, so they resume after a restart.
+    pub fn with_retry_store(mut self, store: Arc<dyn RetryStore>) -> Self {
+        self.retry_store = Some(store);
+        self
+    }
+
+    /// Decide which guest failures are retried with `classifier`, the same one
+    /// `RuntimePolicy::retry_classifier` sets for `exec_with_retries`. Guest errors
+    /// reach it as `ExecError::Runner` with a `RunnerError::Wasmtime` source;
+    /// traps it does not claim are retried.
+    pub fn with_retry_classifier(mut self, classifier: RetryClassifier) -> Self {
+        self.retry_classifier = classifier;
+        self
+    }
+
+    /// Append a record of every invocation to `log`.
+    pub fn with_audit_log(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(log);
+        self
+    }
+
+    /// Keep redacted payloads of a sampled fraction of invocations in `capture`.
+    pub fn with_payload_capture(mut self, capture: Arc<PayloadCapture>) -> Self {
+        self.payload_capture = Some(capture);
+        self
+    }
+
+    /// Replace secrets known to `scrubber` in errors, retry events, audit records,
+    /// and captured payloads.
+    pub fn with_secret_scrubber(mut self, scrubber: Arc<SecretScrubber>) -> Self {
+        self.secret_scrubber = Some(scrubber);
+        self
+    }
+
+    /// Add the resources used by every invocation with a tenant to `meter`.
+    pub fn with_usage_meter(mut self, meter: Arc<UsageMeter>) -> Self {
+        self.usage_meter = Some(meter);
+        self
+    }
+
+    /// Reject invocations of tenants over their limit in `limiter` before they run.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Queue invocations of tenants at their quota in `limiter`, or while the
+    /// executor runs its maximum number of invocations.
+    pub fn with_concurrency_limiter(mut self, limiter: Arc<ConcurrencyLimiter>) -> Self {
+        self.concurrency_limiter = Some(limiter);
+        self
+    }
+
+    /// Compile and link each component once, keeping it in `cache` under the
+    /// tenant of the invocation.
+    pub fn with_component_cache(mut self, cache: Arc<ComponentCache>) -> Self {
+        self.component_cache = Some(cache);
+        self
+    }
+
+    /// Profile every attempt still running after `threshold` and pass the profile to
+    /// `sink` when the attempt ends.
+    #[cfg(feature = "profiling")]
+    pub fn with_hot_call_profiling(mut self, threshold: Duration, sink: ProfileSink) -> Self {
+        self.hot_call_profiling = Some(HotCallProfiling { threshold, sink });
+        self
+    }
+
+    /// Keep the last `size` invocations for [`recent_invocations`](Self::recent_invocations)
+    /// instead of [`DEFAULT_HISTORY_SIZE`](crate::history::DEFAULT_HISTORY_SIZE); 0
+    /// disables the history.
+    pub fn with_history_size(mut self, size: usize) -> Self {
+        self.history = Arc::new(InvocationHistory::new(size));
+        self
+    }
+
+    /// Recent invocations through this executor (and its clones) matching `filter`,
+    /// newest first.
+    pub fn recent_invocations(&self, filter: &HistoryFilter) -> Vec<AuditRecord> {
+        self.history.recent(filter)
+    }
+
+    /// Log invocations taking at least `threshold` at WARN, with their phase timings.
+    pub fn with_slow_call_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_call_threshold = Some(threshold);
+        self
+    }
+
+    /// Capture up to `max_bytes` a guest writes to stderr per attempt into
+    /// [`ToolOutput::logs`] instead of passing it through to the host's stderr.
+    /// Output past the limit is dropped, and the capture ends with a marker line.
+    pub fn with_log_capture(mut self, max_bytes: usize) -> Self {
+        self.log_capture = Some(max_bytes);
+        self
+    }
+
+    /// Accept up to `max_bytes` of binary output per invocation instead of
+    /// [`DEFAULT_MAX_BINARY_OUTPUT`]; guest writes past the limit fail.
+    pub fn with_max_binary_output(mut self, max_bytes: usize) -> Self {
+        self.max_binary_output = max_bytes;
+        self
+    }
+
+    /// Give every guest `clock` as its wall and monotonic clock, and random number
+    /// generators seeded with `seed`, so repeated runs produce the same output.
+    #[cfg(feature = "test-util")]
+    pub fn with_deterministic_guests(mut self, clock: MockClock, seed: u64) -> Self {
+        self.deterministic_guests = Some(DeterministicGuests { clock, seed });
+        self
+    }
+
+    /// Fail the next `count` attempts of the tool named `tool` with `failure`,
+    /// before its component runs. Retries are attempts too, so they use up the
+    /// count. Clones of the executor share the pending failures.
+    #[cfg(feature = "test-util")]
+    pub fn fail_next(&self, tool: &str, count: usize, failure: ForcedFailure) {
+        self.forced_failures.push(tool, count, failure);
     }
 
     /// Access the underlying Wasmtime engine.
@@ -34,53 +300,583 @@ impl WasixExecutor {
         &self.engine
     }
 
+    /// Directory caching components fetched from remote sources.
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
     /// Invoke the specified tool with the provided input payload.
-    #[instrument(skip(self, tool, input), fields(tool = %tool.name))]
     pub async fn invoke(&self, tool: &ToolRef, input: &ToolInput) -> Result<ToolOutput, McpError> {
-        let input_bytes = serde_json::to_vec(&input.payload)
-            .map_err(|err| McpError::InvalidInput(err.to_string()))?;
-        let attempts = tool.max_retries().saturating_add(1);
+        self.invoke_with(tool, input, InvokeOptions::default())
+            .await
+    }
+
+    /// Like [`invoke`](Self::invoke), serializing `input` as the payload and
+    /// deserializing the output payload as `O`.
+    ///
+    /// Fails with [`McpError::InvalidInput`] if `input` does not serialize, and with
+    /// [`McpError::ExecutionFailed`] naming the offending field if the output does not
+    /// match `O`.
+    pub async fn invoke_typed<I, O>(&self, tool: &ToolRef, input: &I) -> Result<O, McpError>
+    where
+        I: Serialize + ?Sized,
+        O: DeserializeOwned,
+    {
+        let payload =
+            serde_json::to_value(input).map_err(|err| McpError::InvalidInput(err.to_string()))?;
+        let output = self.invoke(tool, &ToolInput::new(payload)).await?;
+        serde_path_to_error::deserialize(output.payload).map_err(|err| {
+            McpError::ExecutionFailed(format!(
+                "output of `{}` does not match the expected type at {}: {}",
+                tool.name,
+                err.path(),
+                err.inner()
+            ))
+        })
+    }
+
+    /// Like [`invoke`](Self::invoke), for `tenant`: its tool access and quotas apply,
+    /// and it is recorded with the invocation.
+    pub async fn invoke_as(
+        &self,
+        tool: &ToolRef,
+        input: &ToolInput,
+        tenant: TenantCtx,
+    ) -> Result<ToolOutput, McpError> {
+        let call = InvokeOptions {
+            tenant: Some(tenant),
+            ..InvokeOptions::default()
+        };
+        self.invoke_with(tool, input, call).await
+    }
+
+    /// Like [`invoke`](Self::invoke), forwarding progress the tool reports through
+    /// [`PROGRESS_INTERFACE`](crate::progress::PROGRESS_INTERFACE) to `progress`.
+    pub async fn invoke_with_progress(
+        &self,
+        tool: &ToolRef,
+        input: &ToolInput,
+        progress: Option<ProgressSink>,
+    ) -> Result<ToolOutput, McpError> {
+        let call = InvokeOptions {
+            progress,
+            ..InvokeOptions::default()
+        };
+        self.invoke_with(tool, input, call).await
+    }
+
+    /// Invoke a tool with progress reporting and/or cancellation.
+    ///
+    /// A cancelled invocation fails with [`McpError::Cancelled`]; its running attempt
+    /// is interrupted rather than left to finish in the background.
+    pub async fn invoke_with(
+        &self,
+        tool: &ToolRef,
+        input: &ToolInput,
+        call: InvokeOptions,
+    ) -> Result<ToolOutput, McpError> {
+        let span = info_span!(
+            "invoke",
+            tool = %tool.name,
+            tenant = call.tenant.as_ref().map(|tenant| tenant.tenant_id.as_str()),
+            correlation_id = input.correlation_id.as_deref()
+        );
+        // The parent has to be set before the span is first entered.
+        #[cfg(feature = "otel")]
+        if let Some(context) = &call.trace_context {
+            crate::otel::set_parent(&span, context);
+        }
+        let started = Instant::now();
+        let metrics = InvocationMetrics::start(&tool.name);
+        let tenant = call.tenant.clone();
+        let phases = PhaseClock::new(&tool.name);
+        let usage = UsageCounter::default();
+        let digest = ComponentDigest::default();
+        let result = self
+            .run_invocation(tool, input, call, &phases, &usage, &digest)
+            .instrument(span)
+            .await;
+        let result = match &self.secret_scrubber {
+            Some(scrubber) => result.map_err(|err| scrubber.scrub_error(err)),
+            None => result,
+        };
+        let elapsed = started.elapsed();
+        let result = result.map(|output| ToolOutput {
+            duration_ms: Some(elapsed.as_millis() as u64),
+            logs: match &self.secret_scrubber {
+                Some(scrubber) => output
+                    .logs
+                    .iter()
+                    .map(|line| scrubber.scrub(line).into_owned())
+                    .collect(),
+                None => output.logs,
+            },
+            ..output
+        });
+        metrics.finish(telemetry::outcome(&result));
+        let timings = phases.timings();
+        if let Some(meter) = &self.usage_meter
+            && let Some(tenant) = &tenant
+        {
+            meter.record(tenant.tenant_id.as_str(), &usage.finish(elapsed));
+        }
+        let record = AuditRecord {
+            tenant: tenant.map(|tenant| tenant.tenant_id.as_str().to_string()),
+            correlation_id: input.correlation_id.clone(),
+            digest: digest.get().or_else(|| tool.sha256.clone()),
+            ..AuditRecord::new(tool, elapsed, &result).with_phases(&timings)
+        };
+        if let Some(threshold) = self.slow_call_threshold
+            && elapsed >= threshold
+        {
+            log_slow_call(&record, &timings);
+        }
+        let capture = self
+            .payload_capture
+            .as_ref()
+            .filter(|capture| capture.sampled());
+        if self.audit_log.is_some() || capture.is_some() {
+            let output = result.as_ref().ok().map(|output| &output.payload);
+            let (payload, output) = self.recorded_payloads(&input.payload, output);
+            if let Some(log) = &self.audit_log {
+                log.record(record.clone(), &payload, output.as_deref());
+            }
+            if let Some(capture) = capture {
+                capture.record(record.clone(), &payload, output.as_deref());
+            }
+        }
+        self.history.push(record);
+        result
+    }
+
+    /// Input and output payloads as recorded, with known secrets scrubbed.
+    fn recorded_payloads<'a>(
+        &self,
+        input: &'a Value,
+        output: Option<&'a Value>,
+    ) -> (Cow<'a, Value>, Option<Cow<'a, Value>>) {
+        match &self.secret_scrubber {
+            Some(scrubber) => (
+                Cow::Owned(scrubber.scrub_value(input)),
+                output.map(|output| Cow::Owned(scrubber.scrub_value(output))),
+            ),
+            None => (Cow::Borrowed(input), output.map(Cow::Borrowed)),
+        }
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        match &self.secret_scrubber {
+            Some(scrubber) => scrubber.scrub(text).into_owned(),
+            None => text.to_string(),
+        }
+    }
+
+    async fn run_invocation(
+        &self,
+        tool: &ToolRef,
+        input: &ToolInput,
+        call: InvokeOptions,
+        phases: &PhaseClock,
+        usage: &UsageCounter,
+        digest: &ComponentDigest,
+    ) -> Result<ToolOutput, McpError> {
+        let InvokeOptions {
+            progress,
+            cancellation,
+            sampler,
+            trace_context: _,
+            tenant,
+            identity,
+        } = call;
+        let tenant_id = tenant.as_ref().map(|tenant| tenant.tenant_id.as_str());
+        if matches!(tool.source, Some(ToolSource::Mcp(_))) && !input.attachments.is_empty() {
+            return Err(McpError::InvalidInput(format!(
+                "`{}` is served by a remote MCP server and takes no attachments",
+                tool.name
+            )));
+        }
+        if !tool.allows_tenant(tenant_id) {
+            return Err(McpError::Forbidden {
+                tool: tool.key(),
+                tenant: tenant_id.map(str::to_owned),
+            });
+        }
+        if let (Some(limiter), Some(tenant)) = (&self.rate_limiter, &tenant) {
+            limiter.acquire(tenant.tenant_id.as_str(), &tool.key())?;
+        }
+        if let Some(schema) = &tool.input_schema {
+            check_input(tool, schema, &input.payload)?;
+        }
+        // Tools without a schema of their own are checked against their component's
+        // `describe-v1` schema once an attempt has loaded the component.
+        let described_input = (cfg!(feature = "describe-v1")
+            && tool.input_schema.is_none()
+            && !matches!(tool.source, Some(ToolSource::Mcp(_))))
+        .then(|| Arc::new(input.payload.clone()));
+        let mut payload = Cow::Borrowed(&input.payload);
+        if tool.inject_idempotency_key && retry::idempotency_key(&payload).is_none() {
+            retry::inject_idempotency_key(payload.to_mut(), &retry::new_idempotency_key());
+        }
+        if let Some(id) = &input.correlation_id {
+            mcp_exec::telemetry::inject_correlation_id(payload.to_mut(), id);
+        }
+        if let (true, Some(tenant)) = (tool.inject_tenant, &tenant) {
+            mcp_exec::tenant::inject_tenant(payload.to_mut(), tenant, identity.as_ref());
+        }
+        let input_bytes =
+            serde_json::to_vec(&payload).map_err(|err| McpError::InvalidInput(err.to_string()))?;
         let timeout_duration = tool.timeout();
-        let base_backoff = tool.retry_backoff();
-
-        for attempt in 0..attempts {
-            let exec = self.exec_once(tool.clone(), input_bytes.clone());
-            let result = if let Some(duration) = timeout_duration {
-                match timeout(duration, exec).await {
-                    Ok(res) => res,
-                    Err(_) => return Err(McpError::timeout(&tool.name, duration)),
-                }
-            } else {
-                exec.await
+        // Only keys the caller supplied can be presented again after a restart.
+        let invocation_id = retry::idempotency_key(&input.payload);
+        let request = invocation_id.map(|_| retry::RetryRequest {
+            component: tool.key(),
+            action: tool.entry.clone(),
+            args: input.payload.clone(),
+        });
+        let options = retry::RetryOptions {
+            tool: &tool.name,
+            max_attempts: tool.max_retries().saturating_add(1),
+            policy: tool.retry_policy(),
+            base_backoff: tool.retry_backoff(),
+            max_duration: retry::tighter(tool.max_retry_duration(), tool.total_timeout()),
+            budget: self.retry_budget.as_deref(),
+            observer: self.retry_observer.as_ref(),
+            store: self.retry_store.as_deref(),
+            invocation_id,
+            request: request.as_ref(),
+        };
+
+        let attempts = AtomicU32::new(0);
+        let logs = self.log_capture.map(GuestLogs::new);
+        let binary = BinarySink::new(self.max_binary_output);
+        let attachments: Arc<[Attachment]> = input.attachments.clone().into();
+        let attempt = |attempt: u32| {
+            telemetry::attempt(&tool.name, attempt);
+            attempts.store(attempt, Ordering::Relaxed);
+            phases.reset();
+            binary.reset();
+            let host = GuestHost {
+                progress: progress.clone(),
+                sampler: sampler.clone(),
+                phases: phases.clone(),
+                usage: usage.clone(),
+                logs: logs.clone(),
+                binary: binary.clone(),
+                attachments: attachments.clone(),
+                digest: digest.clone(),
+                described_input: described_input.clone(),
+                #[cfg(feature = "profiling")]
+                profiling: self.hot_call_profiling.clone(),
             };
+            let exec = self.exec_once(tool.clone(), input_bytes.clone(), host, tenant_id);
+            async move {
+                let started = Instant::now();
+                let result = match timeout_duration {
+                    Some(duration) => timeout(duration, exec).await.unwrap_or_else(|_| {
+                        Err(InvocationFailure::fatal(McpError::timeout(
+                            &tool.name, duration,
+                        )))
+                    }),
+                    None => exec.await,
+                };
+                phases.observe();
+                let digest = digest.get().or_else(|| tool.sha256.clone());
+                let error = result.as_ref().err();
+                let error_code = error.map(InvocationFailure::code);
+                let error_code = error_code.as_ref().map(ErrorCode::as_str);
+                AttemptRecord {
+                    tool: &tool.name,
+                    tenant_id,
+                    correlation_id: input.correlation_id.as_deref(),
+                    digest: digest.as_deref(),
+                    attempt,
+                    duration: started.elapsed(),
+                    outcome: error.map_or("ok", InvocationFailure::outcome),
+                    error_code: error_code.as_deref(),
+                }
+                .emit();
+                result
+            }
+            .instrument(info_span!("attempt", attempt))
+        };
+        let describe = |failure: &InvocationFailure| match failure {
+            InvocationFailure::Transient(message) => retry::FailureInfo {
+                retryable: true,
+                class: "transient".into(),
+                message: self.scrub(message),
+            },
+            InvocationFailure::Fatal(err) => retry::FailureInfo {
+                retryable: false,
+                class: match err {
+                    McpError::Timeout { .. } => "timeout",
+                    _ => "fatal",
+                }
+                .into(),
+                message: self.scrub(&err.to_string()),
+            },
+        };
 
-            match result {
-                Ok(bytes) => {
-                    let payload = serde_json::from_slice(&bytes).map_err(|err| {
-                        McpError::ExecutionFailed(format!("invalid tool output JSON: {err}"))
-                    })?;
-                    return Ok(ToolOutput { payload });
+        let retried = retry::retry(&options, attempt, describe);
+        let run = async {
+            // Waiting for a slot counts against cancellation, not the total timeout.
+            let _permit = match (&self.concurrency_limiter, &tenant) {
+                (Some(limiter), Some(tenant)) => {
+                    Some(limiter.acquire(tenant.tenant_id.as_str()).await)
                 }
-                Err(InvocationFailure::Transient(msg)) => {
-                    if attempt + 1 >= attempts {
-                        return Err(McpError::Transient(tool.name.clone(), msg));
-                    }
-                    let backoff = retry::backoff(base_backoff, attempt);
-                    tracing::debug!(attempt, ?backoff, "transient failure, retrying");
-                    sleep(backoff).await;
+                _ => None,
+            };
+            match tool.total_timeout() {
+                Some(total) => timeout(total, retried)
+                    .await
+                    .map_err(|_| McpError::total_timeout(&tool.name, total)),
+                None => Ok(retried.await),
+            }
+        };
+        let result = match &cancellation {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => Err(McpError::Cancelled(tool.name.clone())),
+                result = run => result,
+            },
+            None => run.await,
+        }?;
+        let bytes = result.map_err(|failure| match (failure.error, failure.reason) {
+            (InvocationFailure::Transient(msg), GiveUpReason::Deadline) => {
+                McpError::DeadlineExceeded {
+                    name: tool.name.clone(),
+                    elapsed: failure.elapsed,
+                    last_error: msg,
                 }
-                Err(InvocationFailure::Fatal(err)) => return Err(err),
             }
+            (InvocationFailure::Transient(msg), _) => McpError::Transient(tool.name.clone(), msg),
+            (InvocationFailure::Fatal(err), _) => err,
+        })?;
+
+        Ok(ToolOutput {
+            attempts: Some(attempts.load(Ordering::Relaxed)),
+            digest: digest.get().or_else(|| tool.sha256.clone()),
+            logs: logs.map(GuestLogs::finish).unwrap_or_default(),
+            usage: Some(usage.resources()),
+            binary: binary.take(),
+            ..ToolOutput::new(parse_output(&bytes)?)
+        })
+    }
+
+    /// Connect to the MCP server at `endpoint` and add each tool it advertises to `map`
+    /// under the `prefix` namespace, returning the keys of the mounted tools.
+    ///
+    /// Mounted tools are invoked through the same retry and timeout handling as
+    /// components; the connection is reused across calls and re-established after
+    /// transport failures.
+    pub async fn mount_mcp(
+        &self,
+        map: &mut ToolMap,
+        prefix: &str,
+        endpoint: McpEndpoint,
+    ) -> Result<Vec<String>, McpError> {
+        let client = self.mcp_client(&endpoint).await?;
+        let mut keys = Vec::new();
+        for remote in client.list_tools().await? {
+            let tool = ToolRef {
+                name: remote.name.clone(),
+                description: remote.description,
+                entry: remote.name,
+                source: Some(ToolSource::Mcp(endpoint.clone())),
+                namespace: Some(prefix.to_string()),
+                input_schema: remote.input_schema,
+                output_schema: remote.output_schema,
+                ..ToolRef::default()
+            };
+            keys.push(tool.key());
+            map.insert(tool)?;
         }
+        Ok(keys)
+    }
 
-        Err(McpError::Internal("unreachable retry loop".into()))
+    /// Fetch the tool's component into the cache directory and check its digest, so
+    /// the first invocation does not download it. Tools served by remote MCP servers
+    /// have nothing to fetch.
+    pub async fn prefetch(&self, tool: &ToolRef) -> Result<(), McpError> {
+        if matches!(tool.source, Some(ToolSource::Mcp(_))) {
+            return Ok(());
+        }
+        let bytes = self.fetch_component(tool).await?;
+        verify_digest(tool, &bytes)
     }
 
-    async fn exec_once(&self, tool: ToolRef, input: Vec<u8>) -> Result<Vec<u8>, InvocationFailure> {
-        let engine = self.engine.clone();
-        tokio::task::spawn_blocking(move || invoke_blocking(engine, tool, input))
-            .await
-            .map_err(|err| join_error(err, "spawn_blocking failed"))?
+    /// The tool's component bytes, fetched into the cache directory if remote.
+    pub(crate) async fn fetch_component(&self, tool: &ToolRef) -> Result<Vec<u8>, McpError> {
+        let tool = tool.clone();
+        let cache_dir = self.cache_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            let source = tool.source();
+            load_component(&tool, &source, &cache_dir).map_err(|err| {
+                McpError::ExecutionFailed(format!("failed to fetch `{source}`: {err}"))
+            })
+        })
+        .await
+        .map_err(|err| McpError::Internal(format!("fetch task failed: {err}")))?
+    }
+
+    /// Fetch the tool's component and return its `describe-v1` document, if it exports one.
+    ///
+    /// Documents are cached per component digest, so later calls only read and hash
+    /// the component.
+    #[cfg(feature = "describe-v1")]
+    pub async fn describe(&self, tool: &ToolRef) -> Result<Option<Value>, McpError> {
+        if matches!(tool.source, Some(ToolSource::Mcp(_))) {
+            return Ok(None);
+        }
+        let tool = tool.clone();
+        let cache_dir = self.cache_dir.clone();
+        let describes = self.describes.clone();
+        tokio::task::spawn_blocking(move || {
+            let source = tool.source();
+            let bytes = load_component(&tool, &source, &cache_dir).map_err(|err| {
+                McpError::ExecutionFailed(format!("failed to read `{source}`: {err}"))
+            })?;
+            let digest = hex::encode(Sha256::digest(&bytes));
+            check_digest(&tool, &digest)?;
+            describe_cached(&describes, &digest, &bytes, &source)
+        })
+        .await
+        .map_err(|err| McpError::Internal(format!("describe task failed: {err}")))?
+    }
+
+    /// JSON Schema for the tool's input: the `ToolRef`'s own `input_schema`, or else
+    /// the schema of the matching version in the component's `describe-v1` document.
+    pub async fn input_schema(&self, tool: &ToolRef) -> Result<Option<Value>, McpError> {
+        if let Some(schema) = &tool.input_schema {
+            return Ok(Some(schema.clone()));
+        }
+        #[cfg(feature = "describe-v1")]
+        if let Some(document) = self.describe(tool).await? {
+            let schema =
+                mcp_exec::describe::describe_v1_input_schema(&document, tool.version.as_deref());
+            return Ok(schema.cloned());
+        }
+        Ok(None)
+    }
+
+    async fn exec_once(
+        &self,
+        tool: ToolRef,
+        input: Vec<u8>,
+        host: GuestHost,
+        tenant: Option<&str>,
+    ) -> Result<Vec<u8>, InvocationFailure> {
+        #[cfg(feature = "test-util")]
+        if let Some(failure) = self.forced_failures.take(&tool.name) {
+            return Err(forced(failure, &tool));
+        }
+        if let Some(ToolSource::Mcp(endpoint)) = &tool.source {
+            host.usage.add_egress(input.len() as u64);
+            return self.call_mcp(endpoint, &tool, &input).await;
+        }
+        let loader = Loader {
+            engine: self.engine.clone(),
+            cache_dir: self.cache_dir.clone(),
+            cache: self.component_cache.clone(),
+            tenant: tenant.map(str::to_owned),
+            classifier: self.retry_classifier.clone(),
+            digest: host.digest,
+            #[cfg(feature = "describe-v1")]
+            describes: self.describes.clone(),
+            #[cfg(feature = "describe-v1")]
+            described_input: host.described_input,
+        };
+        // Interrupt the guest if this attempt is abandoned (timeout or cancellation).
+        let interrupt = CancellationToken::new();
+        let _abandon = interrupt.cancel_on_drop();
+        let sampling = SamplingAccess {
+            allowed: tool.allow_sampling,
+            sampler: host.sampler,
+            runtime: tokio::runtime::Handle::current(),
+            interrupt: interrupt.clone(),
+        };
+        let stderr = host
+            .logs
+            .as_ref()
+            .map(|logs| TruncatingPipe::new(logs.max_bytes));
+        let mut ctx = WasiState::ctx_builder();
+        if let Some(pipe) = &stderr {
+            ctx.stderr(pipe.clone());
+        }
+        #[cfg(feature = "test-util")]
+        let ctx = match &self.deterministic_guests {
+            Some(guests) => guests.configure(ctx),
+            None => ctx,
+        };
+        let state = WasiState::new(
+            ctx.build(),
+            host.progress,
+            sampling,
+            host.usage,
+            host.binary,
+            host.attachments,
+        );
+        #[cfg(feature = "profiling")]
+        let state = WasiState {
+            profiler: host
+                .profiling
+                .map(|settings| HotCallProfiler::new(settings, &tool.name, EPOCH_TICK)),
+            ..state
+        };
+        let phases = host.phases;
+        // Keep the phase spans under this attempt, even with a scoped subscriber.
+        let span = tracing::Span::current();
+        let dispatch = tracing::dispatcher::get_default(Clone::clone);
+        let result = tokio::task::spawn_blocking(move || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                span.in_scope(|| invoke_blocking(loader, tool, input, state, interrupt, &phases))
+            })
+        })
+        .await
+        .map_err(|err| join_error(err, "spawn_blocking failed"))?;
+        if let (Some(logs), Some(pipe)) = (&host.logs, stderr) {
+            logs.append(&pipe);
+        }
+        result
+    }
+
+    async fn call_mcp(
+        &self,
+        endpoint: &McpEndpoint,
+        tool: &ToolRef,
+        input: &[u8],
+    ) -> Result<Vec<u8>, InvocationFailure> {
+        let arguments = serde_json::from_slice(input)
+            .map_err(|err| InvocationFailure::fatal(McpError::InvalidInput(err.to_string())))?;
+        let client = self.mcp_client(endpoint).await.map_err(|err| match err {
+            McpError::Transient(_, msg) => InvocationFailure::Transient(msg),
+            other => InvocationFailure::Fatal(other),
+        })?;
+        match client.call_tool(&tool.entry, arguments).await {
+            Ok(output) => serde_json::to_vec(&output)
+                .map_err(|err| InvocationFailure::fatal(McpError::Internal(err.to_string()))),
+            Err(McpError::Transient(_, msg)) => {
+                // Drop the broken connection so the next attempt reconnects.
+                let mut clients = self.mcp_clients.lock().await;
+                if clients
+                    .get(endpoint)
+                    .is_some_and(|current| Arc::ptr_eq(current, &client))
+                {
+                    clients.remove(endpoint);
+                }
+                Err(InvocationFailure::Transient(msg))
+            }
+            Err(err) => Err(InvocationFailure::Fatal(err)),
+        }
+    }
+
+    async fn mcp_client(&self, endpoint: &McpEndpoint) -> Result<Arc<McpClient>, McpError> {
+        let mut clients = self.mcp_clients.lock().await;
+        if let Some(client) = clients.get(endpoint) {
+            return Ok(client.clone());
+        }
+        let client = Arc::new(McpClient::connect(endpoint).await?);
+        clients.insert(endpoint.clone(), client.clone());
+        Ok(client)
     }
 }
 
@@ -90,6 +886,218 @@ impl Default for WasixExecutor {
     }
 }
 
+/// Advance the engine epoch so running guests periodically check for interruption.
+/// The thread exits once the engine is dropped.
+fn spawn_epoch_ticker(engine: &Engine) -> Result<(), McpError> {
+    let engine = engine.weak();
+    std::thread::Builder::new()
+        .name("greentic-mcp-epoch".into())
+        .spawn(move || {
+            while let Some(engine) = engine.upgrade() {
+                engine.increment_epoch();
+                drop(engine);
+                std::thread::sleep(EPOCH_TICK);
+            }
+        })?;
+    Ok(())
+}
+
+/// Reject `payload` if it does not match the tool's input `schema`.
+///
+/// Schemas that are not valid JSON Schema are ignored, so a bad description never
+/// blocks a working tool.
+fn check_input(tool: &ToolRef, schema: &Value, payload: &Value) -> Result<(), McpError> {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(err) => {
+            tracing::warn!(tool = %tool.name, %err, "ignoring invalid input schema");
+            return Ok(());
+        }
+    };
+    validator.validate(payload).map_err(|err| {
+        let path = err.instance_path.to_string();
+        let location = if path.is_empty() { "input" } else { &path };
+        McpError::InvalidInput(format!(
+            "`{}` {location} does not match the input schema: {err}",
+            tool.name
+        ))
+    })
+}
+
+fn log_slow_call(record: &AuditRecord, phases: &PhaseTimings) {
+    let ms = |phase| phases.get(phase).map(|elapsed| elapsed.as_millis() as u64);
+    tracing::warn!(
+        tool = %record.tool,
+        tenant_id = record.tenant.as_deref(),
+        correlation_id = record.correlation_id.as_deref(),
+        digest = record.digest.as_deref(),
+        duration_ms = record.duration_ms,
+        outcome = %record.outcome,
+        error_code = record
+            .error
+            .as_ref()
+            .map(|error| tracing::field::display(&error.code)),
+        resolve_ms = ms(Phase::Resolve),
+        verify_ms = ms(Phase::Verify),
+        compile_ms = ms(Phase::Compile),
+        instantiate_ms = ms(Phase::Instantiate),
+        call_ms = ms(Phase::Call),
+        "slow invocation"
+    );
+}
+
+/// Host capabilities handed to the guest of one attempt.
+struct GuestHost {
+    progress: Option<ProgressSink>,
+    sampler: Option<Sampler>,
+    phases: PhaseClock,
+    usage: UsageCounter,
+    logs: Option<GuestLogs>,
+    binary: BinarySink,
+    attachments: Arc<[Attachment]>,
+    digest: ComponentDigest,
+    /// Payload to check against the component's `describe-v1` input schema.
+    described_input: Option<Arc<Value>>,
+    #[cfg(feature = "profiling")]
+    profiling: Option<HotCallProfiling>,
+}
+
+/// Stderr lines of the attempts of one invocation, for [`ToolOutput::logs`].
+#[derive(Clone)]
+struct GuestLogs {
+    max_bytes: usize,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl GuestLogs {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            lines: Arc::default(),
+        }
+    }
+
+    fn append(&self, pipe: &TruncatingPipe) {
+        let (stderr, dropped) = pipe.contents();
+        let stderr = String::from_utf8_lossy(&stderr);
+        let mut lines = self.lines.lock().expect("guest logs poisoned");
+        lines.extend(stderr.lines().map(str::to_owned));
+        if dropped > 0 {
+            lines.push(format!("[{dropped} bytes of stderr truncated]"));
+        }
+    }
+
+    fn finish(self) -> Vec<String> {
+        std::mem::take(&mut *self.lines.lock().expect("guest logs poisoned"))
+    }
+}
+
+/// Guest stderr that keeps the first `max_bytes` and counts the rest, so a guest
+/// that logs too much never sees a failed write.
+#[derive(Clone)]
+struct TruncatingPipe {
+    max_bytes: usize,
+    buffer: Arc<Mutex<(Vec<u8>, usize)>>,
+}
+
+impl TruncatingPipe {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            buffer: Arc::default(),
+        }
+    }
+
+    fn push(&self, bytes: &[u8]) {
+        let mut buffer = self.buffer.lock().expect("guest stderr poisoned");
+        let (kept, dropped) = &mut *buffer;
+        let take = bytes.len().min(self.max_bytes.saturating_sub(kept.len()));
+        kept.extend_from_slice(&bytes[..take]);
+        *dropped += bytes.len() - take;
+    }
+
+    /// The bytes kept, and how many were dropped.
+    fn contents(&self) -> (Vec<u8>, usize) {
+        self.buffer.lock().expect("guest stderr poisoned").clone()
+    }
+}
+
+impl IsTerminal for TruncatingPipe {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl StdoutStream for TruncatingPipe {
+    fn p2_stream(&self) -> Box<dyn OutputStream> {
+        Box::new(self.clone())
+    }
+
+    fn async_stream(&self) -> Box<dyn AsyncWrite + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+#[async_trait]
+impl Pollable for TruncatingPipe {
+    async fn ready(&mut self) {}
+}
+
+impl OutputStream for TruncatingPipe {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        self.push(&bytes);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        Ok(usize::MAX)
+    }
+}
+
+impl AsyncWrite for TruncatingPipe {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.push(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// SHA-256 of the component the latest attempt loaded and verified, for
+/// [`ToolOutput::digest`] and the audit record.
+#[derive(Clone, Default)]
+struct ComponentDigest(Arc<Mutex<Option<String>>>);
+
+impl ComponentDigest {
+    fn set(&self, digest: &str) {
+        *self.0.lock().expect("component digest poisoned") = Some(digest.to_string());
+    }
+
+    fn get(&self) -> Option<String> {
+        self.0.lock().expect("component digest poisoned").clone()
+    }
+}
+
 fn join_error(err: JoinError, context: &str) -> InvocationFailure {
     InvocationFailure::Fatal(McpError::Internal(format!("{context}: {err}")))
 }
@@ -100,6 +1108,20 @@ enum InvocationFailure {
 }
 
 impl InvocationFailure {
+    fn outcome(&self) -> &'static str {
+        match self {
+            Self::Transient(_) => "error",
+            Self::Fatal(err) => telemetry::error_outcome(err),
+        }
+    }
+
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Transient(_) => ErrorCode::RunnerTransient,
+            Self::Fatal(err) => err.code(),
+        }
+    }
+
     fn transient(msg: impl Into<String>) -> Self {
         Self::Transient(msg.into())
     }
@@ -109,42 +1131,93 @@ impl InvocationFailure {
     }
 }
 
-fn invoke_blocking(
+/// Where an attempt gets its component from.
+struct Loader {
     engine: Engine,
+    cache_dir: PathBuf,
+    cache: Option<Arc<ComponentCache>>,
+    tenant: Option<String>,
+    classifier: RetryClassifier,
+    digest: ComponentDigest,
+    #[cfg(feature = "describe-v1")]
+    describes: Arc<DescribeCache>,
+    #[cfg(feature = "describe-v1")]
+    described_input: Option<Arc<Value>>,
+}
+
+fn invoke_blocking(
+    loader: Loader,
     tool: ToolRef,
     input: Vec<u8>,
+    state: WasiState,
+    interrupt: CancellationToken,
+    phases: &PhaseClock,
 ) -> Result<Vec<u8>, InvocationFailure> {
-    let component_bytes = fs::read(tool.component_path()).map_err(|err| {
-        InvocationFailure::fatal(McpError::ExecutionFailed(format!(
-            "failed to read `{}`: {err}",
-            tool.component
-        )))
-    })?;
-    let component = Component::from_binary(&engine, &component_bytes).map_err(|err| {
-        InvocationFailure::fatal(McpError::ExecutionFailed(format!(
-            "failed to compile `{}`: {err}",
-            tool.component
-        )))
-    })?;
-
-    let mut linker = Linker::new(&engine);
-    p2::add_to_linker_sync(&mut linker).map_err(|err| {
-        InvocationFailure::fatal(McpError::Internal(format!(
-            "failed to link WASI imports: {err}"
-        )))
-    })?;
-
-    let pre = linker.instantiate_pre(&component).map_err(|err| {
-        InvocationFailure::fatal(McpError::ExecutionFailed(format!(
-            "failed to prepare `{}`: {err}",
-            tool.component
-        )))
-    })?;
+    let source = tool.source();
+    let component_bytes = info_span!("resolve", %source)
+        .in_scope(|| {
+            phases.time(Phase::Resolve, || {
+                load_component(&tool, &source, &loader.cache_dir)
+            })
+        })
+        .map_err(|err| {
+            InvocationFailure::fatal(McpError::ExecutionFailed(format!(
+                "failed to read `{source}`: {err}"
+            )))
+        })?;
+    let digest = info_span!("verify")
+        .in_scope(|| {
+            phases.time(Phase::Verify, || {
+                let digest = hex::encode(Sha256::digest(&component_bytes));
+                check_digest(&tool, &digest).map(|()| digest)
+            })
+        })
+        .map_err(InvocationFailure::fatal)?;
+    loader.digest.set(&digest);
+    #[cfg(feature = "describe-v1")]
+    if let Some(payload) = &loader.described_input {
+        check_described_input(&loader.describes, &tool, &digest, &component_bytes, payload)
+            .map_err(InvocationFailure::fatal)?;
+    }
+    let prepare = || {
+        info_span!("compile").in_scope(|| {
+            phases.time(Phase::Compile, || {
+                prepare_component(&loader.engine, &component_bytes, &source)
+            })
+        })
+    };
+    let pre = match &loader.cache {
+        Some(cache) => {
+            let size = component_bytes.len() as u64;
+            cache.get_or_prepare(loader.tenant.as_deref(), &digest, size, prepare)?
+        }
+        None => prepare()?,
+    };
 
-    let mut store = Store::new(&engine, WasiState::new());
+    let instantiate = info_span!("instantiate").entered();
+    let instantiate_started = Instant::now();
+    let mut store = Store::new(&loader.engine, state);
+    store
+        .set_fuel(usage::GUEST_FUEL)
+        .map_err(|err| InvocationFailure::fatal(McpError::Internal(err.to_string())))?;
+    #[cfg(feature = "profiling")]
+    if let Some(profiler) = &mut store.data_mut().profiler {
+        profiler.attach(pre.component().clone());
+    }
+    store.set_epoch_deadline(1);
+    store.epoch_deadline_callback(move |store| {
+        if interrupt.is_cancelled() {
+            Err(wasmtime::Error::msg("invocation cancelled"))
+        } else {
+            profile_tick(store);
+            Ok(UpdateDeadline::Continue(1))
+        }
+    });
     let instance = pre
         .instantiate(&mut store)
-        .map_err(|err| classify(err, &tool))?;
+        .map_err(|err| classify(err, &tool, &loader.classifier))?;
+    phases.record(Phase::Instantiate, instantiate_started.elapsed());
+    drop(instantiate);
 
     let func = instance
         .get_typed_func::<(String,), (String,)>(&mut store, &tool.entry)
@@ -155,44 +1228,280 @@ fn invoke_blocking(
             )))
         })?;
 
-    let input_str = String::from_utf8(input).map_err(|err| {
-        InvocationFailure::fatal(McpError::InvalidInput(format!(
-            "input is not valid UTF-8: {err}"
+    let input_str = input_string(input).map_err(InvocationFailure::fatal)?;
+
+    let called = info_span!("call")
+        .in_scope(|| phases.time(Phase::Call, || func.call(&mut store, (input_str,))));
+    let remaining = store.get_fuel().unwrap_or(usage::GUEST_FUEL);
+    store.data().usage.add_fuel(usage::GUEST_FUEL - remaining);
+    #[cfg(feature = "profiling")]
+    if let Some(profiler) = store.data_mut().profiler.take() {
+        profiler.finish();
+    }
+    let (output,) = called.map_err(|err| classify(err, &tool, &loader.classifier))?;
+
+    Ok(output.into_bytes())
+}
+
+/// The serialized input as the string passed to the guest.
+pub(crate) fn input_string(input: Vec<u8>) -> Result<String, McpError> {
+    String::from_utf8(input)
+        .map_err(|err| McpError::InvalidInput(format!("input is not valid UTF-8: {err}")))
+}
+
+/// The JSON payload of a guest's raw output.
+pub(crate) fn parse_output(bytes: &[u8]) -> Result<Value, McpError> {
+    serde_json::from_slice(bytes)
+        .map_err(|err| McpError::ExecutionFailed(format!("invalid tool output JSON: {err}")))
+}
+
+/// Compile `bytes` and link them against the WASI and Greentic host imports.
+fn prepare_component(
+    engine: &Engine,
+    bytes: &[u8],
+    source: &ToolSource,
+) -> Result<InstancePre<WasiState>, InvocationFailure> {
+    let component = Component::from_binary(engine, bytes).map_err(|err| {
+        InvocationFailure::fatal(McpError::ExecutionFailed(format!(
+            "failed to compile `{source}`: {err}"
+        )))
+    })?;
+    let mut linker = Linker::new(engine);
+    p2::add_to_linker_sync(&mut linker).map_err(|err| {
+        InvocationFailure::fatal(McpError::Internal(format!(
+            "failed to link WASI imports: {err}"
         )))
     })?;
+    add_host_imports(&mut linker).map_err(|err| {
+        InvocationFailure::fatal(McpError::Internal(format!(
+            "failed to link host imports: {err}"
+        )))
+    })?;
+    linker.instantiate_pre(&component).map_err(|err| {
+        InvocationFailure::fatal(McpError::ExecutionFailed(format!(
+            "failed to prepare `{source}`: {err}"
+        )))
+    })
+}
 
-    let (output,) = func
-        .call(&mut store, (input_str,))
-        .map_err(|err| classify(err, &tool))?;
+/// Sample the profiler of a hot attempt, if it has one.
+#[cfg(feature = "profiling")]
+fn profile_tick(mut store: StoreContextMut<'_, WasiState>) {
+    if let Some(mut profiler) = store.data_mut().profiler.take() {
+        profiler.tick(&store);
+        store.data_mut().profiler = Some(profiler);
+    }
+}
 
-    Ok(output.into_bytes())
+#[cfg(not(feature = "profiling"))]
+fn profile_tick(_store: StoreContextMut<'_, WasiState>) {}
+
+/// Greentic host interfaces offered to every guest; unused imports cost nothing.
+fn add_host_imports(linker: &mut Linker<WasiState>) -> wasmtime::Result<()> {
+    progress::add_to_linker(linker, |state: &mut WasiState| state.progress.as_ref())?;
+    sampling::add_to_linker(linker, |state: &mut WasiState| &state.sampling)?;
+    binary::add_to_linker(
+        linker,
+        |state: &mut WasiState| &state.binary,
+        |state: &mut WasiState| &state.attachments[..],
+    )
+}
+
+/// Fetch component bytes, delegating remote sources to the `mcp-exec` tool stores.
+pub(crate) fn load_component(
+    tool: &ToolRef,
+    source: &ToolSource,
+    cache_dir: &Path,
+) -> anyhow::Result<Vec<u8>> {
+    let path = match source {
+        ToolSource::Path(path) => path.clone(),
+        ToolSource::Url(url) => {
+            let name = cache_name(tool, url);
+            let store = ToolStore::HttpSingleFile {
+                name: name.clone(),
+                url: url.clone(),
+                cache_dir: cache_dir.to_path_buf(),
+            };
+            store.fetch(&name)?.path
+        }
+        ToolSource::Mcp(endpoint) => {
+            anyhow::bail!("`{endpoint}` is served by a remote MCP server, not a component")
+        }
+    };
+    Ok(fs::read(path)?)
+}
+
+/// Cache file stem that stays unique per URL and safe for any tool key.
+fn cache_name(tool: &ToolRef, url: &str) -> String {
+    let stem: String = tool
+        .key()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let url_hash = hex::encode(Sha256::digest(url.as_bytes()));
+    format!("{stem}-{}", &url_hash[..16])
+}
+
+pub(crate) fn verify_digest(tool: &ToolRef, bytes: &[u8]) -> Result<(), McpError> {
+    if tool.sha256.is_none() {
+        return Ok(());
+    }
+    check_digest(tool, &hex::encode(Sha256::digest(bytes)))
+}
+
+/// Compare the hex `digest` of the tool's component with the pinned one, if any.
+fn check_digest(tool: &ToolRef, digest: &str) -> Result<(), McpError> {
+    let Some(expected) = tool.sha256.as_deref() else {
+        return Ok(());
+    };
+    let expected = expected.strip_prefix("sha256:").unwrap_or(expected);
+    if digest.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(McpError::DigestMismatch {
+            name: tool.name.clone(),
+            expected: expected.to_string(),
+            actual: digest.to_string(),
+        })
+    }
+}
+
+/// The `describe-v1` document of the component with `digest`, described once.
+#[cfg(feature = "describe-v1")]
+fn describe_cached(
+    describes: &DescribeCache,
+    digest: &str,
+    bytes: &[u8],
+    source: &ToolSource,
+) -> Result<Option<Value>, McpError> {
+    let cached = describes
+        .lock()
+        .expect("describe cache poisoned")
+        .get(digest)
+        .cloned();
+    telemetry::cache_lookup("describe", cached.is_some());
+    if let Some(document) = cached {
+        return Ok(document);
+    }
+    let document = mcp_exec::describe::describe_component(bytes).map_err(|err| {
+        McpError::ExecutionFailed(format!("failed to describe `{source}`: {err}"))
+    })?;
+    describes
+        .lock()
+        .expect("describe cache poisoned")
+        .insert(digest.to_string(), document.clone());
+    Ok(document)
+}
+
+/// Check `payload` against the input schema of the component's `describe-v1`
+/// document. A component that cannot be described is left for the call to report.
+#[cfg(feature = "describe-v1")]
+fn check_described_input(
+    describes: &DescribeCache,
+    tool: &ToolRef,
+    digest: &str,
+    bytes: &[u8],
+    payload: &Value,
+) -> Result<(), McpError> {
+    let document = match describe_cached(describes, digest, bytes, &tool.source()) {
+        Ok(document) => document,
+        Err(err) => {
+            tracing::debug!(tool = %tool.name, %err, "no input schema");
+            return Ok(());
+        }
+    };
+    let schema = document.as_ref().and_then(|document| {
+        mcp_exec::describe::describe_v1_input_schema(document, tool.version.as_deref())
+    });
+    match schema {
+        Some(schema) => check_input(tool, schema, payload),
+        None => Ok(()),
+    }
 }
 
-fn classify(err: wasmtime::Error, tool: &ToolRef) -> InvocationFailure {
-    if err.downcast_ref::<Trap>().is_some() {
-        InvocationFailure::transient(err.to_string())
+/// Whether a guest error is retried: the classifier decides, and traps it does not
+/// claim are retried.
+fn classify(
+    err: wasmtime::Error,
+    tool: &ToolRef,
+    classifier: &RetryClassifier,
+) -> InvocationFailure {
+    let message = err.to_string();
+    let trap = err.downcast_ref::<Trap>().is_some();
+    let err = ExecError::runner(&tool.name, RunnerError::Wasmtime(err));
+    if classifier.classify(&err).unwrap_or(trap) {
+        InvocationFailure::transient(message)
     } else {
         InvocationFailure::fatal(McpError::ExecutionFailed(format!(
-            "tool `{}` failed: {err}",
+            "tool `{}` failed: {message}",
             tool.name
         )))
     }
 }
 
-struct WasiState {
+#[cfg(feature = "test-util")]
+fn forced(failure: ForcedFailure, tool: &ToolRef) -> InvocationFailure {
+    match failure {
+        ForcedFailure::Timeout => InvocationFailure::fatal(McpError::timeout(
+            &tool.name,
+            tool.timeout().unwrap_or_default(),
+        )),
+        ForcedFailure::Trap => {
+            InvocationFailure::transient(format!("wasm trap: forced trap in `{}`", tool.name))
+        }
+        ForcedFailure::Error(make) => match make() {
+            McpError::Transient(_, msg) => InvocationFailure::Transient(msg),
+            err => InvocationFailure::Fatal(err),
+        },
+    }
+}
+
+pub(crate) struct WasiState {
     ctx: WasiCtx,
     table: ResourceTable,
+    progress: Option<ProgressSink>,
+    sampling: SamplingAccess,
+    usage: UsageCounter,
+    binary: BinarySink,
+    attachments: Arc<[Attachment]>,
+    #[cfg(feature = "profiling")]
+    profiler: Option<HotCallProfiler>,
 }
 
 impl WasiState {
-    fn new() -> Self {
+    /// WASI context settings shared by every guest.
+    fn ctx_builder() -> WasiCtxBuilder {
         let mut builder = WasiCtxBuilder::new();
         builder.inherit_stdio();
         builder.inherit_env();
         builder.allow_blocking_current_thread(true);
+        builder
+    }
+
+    fn new(
+        ctx: WasiCtx,
+        progress: Option<ProgressSink>,
+        sampling: SamplingAccess,
+        usage: UsageCounter,
+        binary: BinarySink,
+        attachments: Arc<[Attachment]>,
+    ) -> Self {
         Self {
-            ctx: builder.build(),
+            ctx,
             table: ResourceTable::new(),
+            progress,
+            sampling,
+            usage,
+            binary,
+            attachments,
+            #[cfg(feature = "profiling")]
+            profiler: None,
         }
     }
 }
@@ -205,3 +1514,575 @@ impl WasiView for WasiState {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Component whose `tool-invoke` never returns.
+    pub(crate) fn spin_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (core module $Tool
+                    (memory (export "memory") 1)
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (loop $spin (br $spin))
+                        (unreachable))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (i32.const 1024)))
+                (core instance $tool (instantiate $Tool))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $tool "memory")
+                        (realloc (func $tool "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    /// Component whose `tool-invoke` traps.
+    fn trap_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (core module $Tool
+                    (memory (export "memory") 1)
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (unreachable))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (i32.const 1024)))
+                (core instance $tool (instantiate $Tool))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $tool "memory")
+                        (realloc (func $tool "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    /// Component whose `tool-invoke` echoes its input.
+    pub(crate) fn echo_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (core module $Tool
+                    (memory (export "memory") 1)
+                    (global $next (mut i32) (i32.const 1024))
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (i32.store (i32.const 16) (local.get 0))
+                        (i32.store (i32.const 20) (local.get 1))
+                        (i32.const 16))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (local $ptr i32)
+                        (local.set $ptr (global.get $next))
+                        (global.set $next (i32.add (local.get $ptr) (local.get 3)))
+                        (local.get $ptr)))
+                (core instance $tool (instantiate $Tool))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $tool "memory")
+                        (realloc (func $tool "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    /// Component whose `tool-invoke` echoes its input and whose `describe-v1` document
+    /// requires a `city` string.
+    #[cfg(feature = "describe-v1")]
+    fn described_echo_component() -> Vec<u8> {
+        let document = json!({
+            "name": "weather",
+            "versions": [{
+                "version": "1.0.0",
+                "schema": {
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                    "required": ["city"],
+                },
+            }],
+        })
+        .to_string();
+        wat::parse_str(format!(
+            r#"(component
+                (core module $Tool
+                    (memory (export "memory") 1)
+                    (global $next (mut i32) (i32.const 1024))
+                    (data (i32.const 64) "{data}")
+                    (func (export "describe") (result i32)
+                        (i32.store (i32.const 16) (i32.const 64))
+                        (i32.store (i32.const 20) (i32.const {len}))
+                        (i32.const 16))
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (i32.store (i32.const 16) (local.get 0))
+                        (i32.store (i32.const 20) (local.get 1))
+                        (i32.const 16))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (local $ptr i32)
+                        (local.set $ptr (global.get $next))
+                        (global.set $next (i32.add (local.get $ptr) (local.get 3)))
+                        (local.get $ptr)))
+                (core instance $tool (instantiate $Tool))
+                (func $describe (result string)
+                    (canon lift (core func $tool "describe") (memory $tool "memory")))
+                (instance $describe_v1 (export "describe-json" (func $describe)))
+                (export "greentic:component/describe-v1@1.0.0" (instance $describe_v1))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $tool "memory")
+                        (realloc (func $tool "realloc")))))"#,
+            data = document.replace('"', "\\\""),
+            len = document.len(),
+        ))
+        .expect("valid component")
+    }
+
+    #[cfg(feature = "describe-v1")]
+    #[tokio::test]
+    async fn validates_input_against_the_described_schema() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("weather.wasm");
+        std::fs::write(&path, described_echo_component()).unwrap();
+        let mut tool = ToolRef::new("weather", path.to_string_lossy(), "tool-invoke");
+        let executor = WasixExecutor::new().unwrap();
+
+        let schema = executor.input_schema(&tool).await.unwrap().unwrap();
+        assert_eq!(schema["required"], json!(["city"]));
+
+        let valid = ToolInput::new(json!({ "city": "Lisbon" }));
+        let output = executor.invoke(&tool, &valid).await.unwrap();
+        assert_eq!(output.payload, valid.payload);
+
+        let invalid = ToolInput::new(json!({ "city": 7 }));
+        let err = executor.invoke(&tool, &invalid).await.unwrap_err();
+        assert!(
+            matches!(&err, McpError::InvalidInput(msg) if msg.contains("/city")),
+            "{err}"
+        );
+
+        // A schema declared on the tool takes precedence over the component's.
+        tool.input_schema = Some(json!({ "type": "object" }));
+        let output = executor.invoke(&tool, &invalid).await.unwrap();
+        assert_eq!(output.payload, invalid.payload);
+    }
+
+    #[tokio::test]
+    async fn passes_the_correlation_id_to_the_guest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        let executor = WasixExecutor::new().unwrap();
+
+        let input = ToolInput::new(json!({ "text": "hi" })).with_correlation_id("req-1");
+        let output = executor.invoke(&tool, &input).await.unwrap();
+        assert_eq!(
+            output.payload,
+            json!({ "text": "hi", "_meta": { "correlation_id": "req-1" } })
+        );
+        let recent = executor.recent_invocations(&HistoryFilter::new());
+        assert_eq!(recent[0].correlation_id.as_deref(), Some("req-1"));
+
+        let output = executor
+            .invoke(&tool, &ToolInput::new(json!("hi")))
+            .await
+            .unwrap();
+        assert_eq!(output.payload, json!("hi"));
+    }
+
+    #[tokio::test]
+    async fn invokes_with_typed_input_and_output() {
+        #[derive(Serialize)]
+        struct Query<'a> {
+            city: &'a str,
+        }
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Echoed {
+            city: String,
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        let executor = WasixExecutor::new().unwrap();
+
+        let echoed: Echoed = executor
+            .invoke_typed(&tool, &Query { city: "Oslo" })
+            .await
+            .unwrap();
+        assert_eq!(echoed.city, "Oslo");
+
+        let err = executor
+            .invoke_typed::<_, Echoed>(&tool, &json!({ "city": 7 }))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, McpError::ExecutionFailed(msg) if msg.contains("at city")),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_execution_metadata() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        let executor = WasixExecutor::new().unwrap().with_log_capture(4096);
+
+        let output = executor
+            .invoke(&tool, &ToolInput::new(json!({ "text": "hi" })))
+            .await
+            .unwrap();
+        assert_eq!(output.attempts, Some(1));
+        assert!(output.duration_ms.is_some());
+        assert!(output.usage.unwrap().fuel > 0);
+        assert!(output.logs.is_empty());
+        assert_eq!(
+            output.digest,
+            Some(hex::encode(Sha256::digest(echo_component())))
+        );
+        let json = serde_json::to_value(&output).unwrap();
+        assert!(json.get("logs").is_none());
+    }
+
+    #[test]
+    fn log_capture_truncates_instead_of_failing_writes() {
+        let logs = GuestLogs::new(8);
+        let mut pipe = TruncatingPipe::new(logs.max_bytes);
+        pipe.write(Bytes::from_static(b"first\n")).unwrap();
+        pipe.write(Bytes::from_static(b"second\n")).unwrap();
+        assert_eq!(pipe.check_write().unwrap(), usize::MAX);
+        logs.append(&pipe);
+        assert_eq!(
+            logs.finish(),
+            ["first", "se", "[5 bytes of stderr truncated]"]
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_tools_the_tenant_may_not_use() {
+        let executor = WasixExecutor::new().unwrap();
+        let tool = ToolRef {
+            allowed_tenants: vec!["acme".into(), "globex".into()],
+            denied_tenants: vec!["globex".into()],
+            ..ToolRef::new("admin", "./does-not-exist.wasm", "tool-invoke")
+        };
+        let input = ToolInput::new(json!({}));
+        let call = |tenant: &str| InvokeOptions {
+            tenant: Some(greentic_types::TenantCtx::new(
+                "dev".try_into().unwrap(),
+                tenant.try_into().unwrap(),
+            )),
+            ..InvokeOptions::default()
+        };
+
+        for tenant in ["globex", "initech"] {
+            let err = executor
+                .invoke_with(&tool, &input, call(tenant))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), ErrorCode::Forbidden, "{tenant}");
+        }
+        let err = executor.invoke(&tool, &input).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "tool `admin` is not available to anonymous callers"
+        );
+        // Allowed tenants get as far as loading the component.
+        let err = executor
+            .invoke_with(&tool, &input, call("acme"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RunnerFailed);
+    }
+
+    #[tokio::test]
+    async fn retry_classifier_decides_whether_traps_are_retried() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("trap.wasm");
+        std::fs::write(&path, trap_component()).unwrap();
+        let tool = ToolRef {
+            max_retries: Some(1),
+            retry_backoff_ms: Some(1),
+            ..ToolRef::new("trap", path.to_string_lossy(), "tool-invoke")
+        };
+        let input = ToolInput::new(json!({}));
+
+        let err = WasixExecutor::new()
+            .unwrap()
+            .invoke(&tool, &input)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, McpError::Transient(..)), "{err:?}");
+
+        let classifier = RetryClassifier::new().with_fn(|err| match err {
+            ExecError::Runner {
+                source: RunnerError::Wasmtime(_),
+                ..
+            } => Some(false),
+            _ => None,
+        });
+        let err = WasixExecutor::new()
+            .unwrap()
+            .with_retry_classifier(classifier)
+            .invoke(&tool, &input)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, McpError::ExecutionFailed(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn passes_the_tenant_to_tools_that_ask_for_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let tool = ToolRef {
+            inject_tenant: true,
+            ..ToolRef::new("echo", path.to_string_lossy(), "tool-invoke")
+        };
+        let executor = WasixExecutor::new().unwrap();
+        let call = InvokeOptions {
+            tenant: Some(greentic_types::TenantCtx::new(
+                "prod".try_into().unwrap(),
+                "acme".try_into().unwrap(),
+            )),
+            ..InvokeOptions::default()
+        };
+
+        let input = ToolInput::new(json!({ "text": "hi" }));
+        let output = executor
+            .invoke_with(&tool, &input, call.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            output.payload,
+            json!({ "text": "hi", "_meta": { "tenant": { "id": "acme", "env": "prod" } } })
+        );
+        let call = InvokeOptions {
+            identity: Some(TenantIdentity {
+                locale: Some("nl-NL".into()),
+                ..TenantIdentity::default()
+            }),
+            ..call
+        };
+        let output = executor.invoke_with(&tool, &input, call).await.unwrap();
+        assert_eq!(output.payload["_meta"]["tenant"]["locale"], "nl-NL");
+        // Anonymous invocations have no tenant to pass.
+        let output = executor.invoke(&tool, &input).await.unwrap();
+        assert_eq!(output.payload, json!({ "text": "hi" }));
+    }
+
+    #[test]
+    fn cancellation_interrupts_the_guest() {
+        // A single blocking worker: the second call only runs if the first one's
+        // worker was freed.
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .max_blocking_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let spin = tmp.path().join("spin.wasm");
+        std::fs::write(&spin, spin_component()).unwrap();
+        let spin = ToolRef::new("spin", spin.to_string_lossy(), "tool-invoke");
+        let input = ToolInput::new(json!({}));
+        let executor = WasixExecutor::new().unwrap();
+
+        runtime.block_on(async {
+            let token = CancellationToken::new();
+            let call = InvokeOptions {
+                cancellation: Some(token.clone()),
+                ..InvokeOptions::default()
+            };
+            let cancel = async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                token.cancel();
+            };
+            let (result, ()) = tokio::join!(executor.invoke_with(&spin, &input, call), cancel);
+            assert!(matches!(result, Err(McpError::Cancelled(name)) if name == "spin"));
+
+            let timed = ToolRef {
+                timeout_ms: Some(50),
+                ..spin.clone()
+            };
+            let err = executor.invoke(&timed, &input).await.unwrap_err();
+            assert!(matches!(err, McpError::Timeout { .. }), "{err}");
+
+            let err = tokio::time::timeout(Duration::from_secs(5), executor.invoke(&timed, &input))
+                .await
+                .expect("blocking worker should have been freed")
+                .unwrap_err();
+            assert!(matches!(err, McpError::Timeout { .. }), "{err}");
+        });
+    }
+
+    #[tokio::test]
+    async fn rejects_component_with_mismatched_digest() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let path = tmp.path().join("tool.wasm");
+        std::fs::write(&path, b"not really wasm").expect("write");
+
+        let tool = ToolRef {
+            sha256: Some("00".repeat(32)),
+            ..ToolRef::new("pinned", path.to_string_lossy(), ToolRef::DEFAULT_ENTRY)
+        };
+        let input = ToolInput::new(json!({}));
+
+        let err = WasixExecutor::new()
+            .expect("executor")
+            .invoke(&tool, &input)
+            .await
+            .expect_err("digest should not match");
+        assert!(matches!(err, McpError::DigestMismatch { name, .. } if name == "pinned"));
+    }
+
+    #[tokio::test]
+    async fn prefetch_checks_the_digest() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let path = tmp.path().join("tool.wasm");
+        std::fs::write(&path, b"bytes").expect("write");
+        let executor = WasixExecutor::new().expect("executor");
+
+        let tool = ToolRef::new("tool", path.to_string_lossy(), ToolRef::DEFAULT_ENTRY);
+        executor.prefetch(&tool).await.expect("prefetch");
+        let pinned = ToolRef {
+            sha256: Some("00".repeat(32)),
+            ..tool
+        };
+        let err = executor.prefetch(&pinned).await.unwrap_err();
+        assert!(matches!(err, McpError::DigestMismatch { .. }), "{err}");
+    }
+
+    /// Target and names of the recorded fields of every event.
+    #[derive(Clone, Default)]
+    struct EventFields(Arc<Mutex<Vec<EventNames>>>);
+
+    type EventNames = (String, Vec<&'static str>);
+
+    impl EventFields {
+        fn with_target(&self, target: &str) -> Vec<Vec<&'static str>> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(event_target, _)| event_target == target)
+                .map(|(_, names)| names.clone())
+                .collect()
+        }
+    }
+
+    impl tracing::Subscriber for EventFields {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct Names(Vec<&'static str>);
+            impl tracing::field::Visit for Names {
+                fn record_debug(&mut self, field: &tracing::field::Field, _: &dyn std::fmt::Debug) {
+                    self.0.push(field.name());
+                }
+            }
+            let mut names = Names(Vec::new());
+            event.record(&mut names);
+            let target = event.metadata().target().to_string();
+            self.0.lock().unwrap().push((target, names.0));
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn both_runners_emit_the_same_attempt_fields() {
+        let events = EventFields::default();
+        let _default = tracing::subscriber::set_default(events.clone());
+
+        let tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+        let input = ToolInput::new(json!({}));
+        let executor = WasixExecutor::new().unwrap();
+        assert!(executor.invoke(&tool, &input).await.is_err());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let cfg = mcp_exec::ExecConfig {
+            store: ToolStore::LocalDir(tmp.path().into()),
+            security: Default::default(),
+            runtime: Default::default(),
+            tenant_runtime: Default::default(),
+            http_enabled: false,
+        };
+        let req = mcp_exec::ExecRequest {
+            component: "missing".into(),
+            action: "tool-invoke".into(),
+            args: json!({}),
+            tenant: None,
+            correlation_id: None,
+            identity: None,
+        };
+        assert!(mcp_exec::exec(req, &cfg).is_err());
+
+        let events = events.with_target(mcp_exec::telemetry::TARGET);
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0],
+            [
+                "message",
+                "tool",
+                "attempt",
+                "duration_ms",
+                "outcome",
+                "error_code"
+            ]
+        );
+        assert_eq!(events[0], events[1]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn logs_slow_calls_with_phase_timings() {
+        let events = EventFields::default();
+        let _default = tracing::subscriber::set_default(events.clone());
+
+        let tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+        let input = ToolInput::new(json!({}));
+        let executor = WasixExecutor::new().unwrap();
+        assert!(executor.invoke(&tool, &input).await.is_err());
+        let executor = executor.with_slow_call_threshold(Duration::ZERO);
+        assert!(executor.invoke(&tool, &input).await.is_err());
+
+        let slow = events
+            .with_target("greentic_mcp::executor")
+            .into_iter()
+            .filter(|names| names.contains(&"resolve_ms"))
+            .collect::<Vec<_>>();
+        assert_eq!(slow.len(), 1);
+        // Only the phases the attempt reached are reported.
+        assert!(!slow[0].contains(&"compile_ms"));
+    }
+
+    #[test]
+    fn cache_name_is_filesystem_safe() {
+        let tool = ToolRef {
+            namespace: Some("crm".into()),
+            version: Some("2".into()),
+            ..ToolRef::new("lead", "", ToolRef::DEFAULT_ENTRY)
+        };
+        let name = cache_name(&tool, "https://example.com/lead.wasm");
+        assert!(name.starts_with("crm_lead_2-"));
+        assert!(!name.contains('/'));
+    }
+
+    #[test]
+    fn accepts_matching_digest_with_prefix() {
+        let digest = hex::encode(Sha256::digest(b"bytes"));
+        let tool = ToolRef {
+            sha256: Some(format!("sha256:{}", digest.to_uppercase())),
+            ..ToolRef::new("pinned", "./tool.wasm", ToolRef::DEFAULT_ENTRY)
+        };
+
+        verify_digest(&tool, b"bytes").expect("digest matches");
+    }
+}
@@ -0,0 +1,322 @@
+//! Seeded fault injection, for testing retry and budget settings under failure.
+//!
+//! A [`FaultInjector`] wraps an exec function for
+//! [`exec_with_retries_backend`](crate::exec_with_retries_backend) and, per tool,
+//! delays calls and fails a fraction of them with a chosen error code before the
+//! wrapped function runs. Its random numbers come from a seed, so a sequence of
+//! calls fails the same way on every run.
+//!
+//! [`WasixExecutor::fail_next`](crate::executor::WasixExecutor::fail_next) forces
+//! a [`ForcedFailure`] on the next attempts of a tool instead, so retry exhaustion
+//! and error handling can be tested against any component.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mcp_exec::{ExecConfig, ExecError, ExecRequest, RunnerError};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::{Value, json};
+
+use crate::types::McpError;
+
+/// Error code of injected faults that fail as a timeout of the attempt.
+pub const TIMEOUT_FAULT: &str = "timeout";
+
+/// Faults injected into the calls of one tool.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fault {
+    /// Added to every call. Calls delayed past the per-call timeout time out.
+    pub latency: Duration,
+    /// Fraction of calls that fail, from 0.0 to 1.0.
+    pub error_rate: f64,
+    /// Tool error code of failed calls; [`TIMEOUT_FAULT`] fails them as timeouts.
+    pub error_code: String,
+}
+
+impl Fault {
+    /// Fail `rate` of the calls with the tool error `code`.
+    pub fn errors(rate: f64, code: impl Into<String>) -> Self {
+        Self {
+            latency: Duration::ZERO,
+            error_rate: rate.clamp(0.0, 1.0),
+            error_code: code.into(),
+        }
+    }
+
+    /// Delay every call by `latency`.
+    pub fn latency(latency: Duration) -> Self {
+        Self {
+            latency,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+}
+
+/// Faults by tool, drawn from one seeded generator.
+///
+/// Calls draw in the order they start, so concurrent calls are only reproducible
+/// when they start in the same order.
+#[derive(Debug)]
+pub struct FaultInjector {
+    default: Option<Fault>,
+    tools: HashMap<String, Fault>,
+    rng: Mutex<StdRng>,
+    injected: Mutex<HashMap<String, u32>>,
+}
+
+impl FaultInjector {
+    /// Inject no faults until some are configured, drawing from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            default: None,
+            tools: HashMap::new(),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            injected: Mutex::default(),
+        }
+    }
+
+    /// Inject `fault` into calls of `tool`.
+    pub fn with_tool(mut self, tool: impl Into<String>, fault: Fault) -> Self {
+        self.tools.insert(tool.into(), fault);
+        self
+    }
+
+    /// Inject `fault` into calls of tools without a fault of their own.
+    pub fn with_default(mut self, fault: Fault) -> Self {
+        self.default = Some(fault);
+        self
+    }
+
+    /// Errors injected into calls of `tool` so far.
+    pub fn injected(&self, tool: &str) -> u32 {
+        self.injected
+            .lock()
+            .expect("fault injector poisoned")
+            .get(tool)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Run `req` through the faults of its tool, then through `inner` if it did not fail.
+    pub fn exec<F>(&self, req: ExecRequest, cfg: &ExecConfig, inner: F) -> Result<Value, ExecError>
+    where
+        F: FnOnce(ExecRequest, &ExecConfig) -> Result<Value, ExecError>,
+    {
+        let Some(fault) = self.tools.get(&req.component).or(self.default.as_ref()) else {
+            return inner(req, cfg);
+        };
+        let per_call = cfg.runtime_for(req.tenant.as_ref()).per_call_timeout;
+        if fault.latency >= per_call {
+            std::thread::sleep(per_call);
+            return Err(self.fail(&req, TIMEOUT_FAULT, per_call));
+        }
+        std::thread::sleep(fault.latency);
+        let fails = fault.error_rate > 0.0
+            && self
+                .rng
+                .lock()
+                .expect("fault injector poisoned")
+                .random_bool(fault.error_rate.min(1.0));
+        if fails {
+            return Err(self.fail(&req, &fault.error_code, fault.latency));
+        }
+        inner(req, cfg)
+    }
+
+    /// An exec function for [`exec_with_retries_backend`](crate::exec_with_retries_backend)
+    /// that injects faults before calling `inner`, e.g. [`mcp_exec::exec`].
+    pub fn backend<F>(
+        self: &Arc<Self>,
+        inner: F,
+    ) -> impl Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static
+    where
+        F: Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static,
+    {
+        let injector = self.clone();
+        move |req, cfg| injector.exec(req, cfg, &inner)
+    }
+
+    fn fail(&self, req: &ExecRequest, code: &str, elapsed: Duration) -> ExecError {
+        *self
+            .injected
+            .lock()
+            .expect("fault injector poisoned")
+            .entry(req.component.clone())
+            .or_default() += 1;
+        if code == TIMEOUT_FAULT {
+            return ExecError::runner(&req.component, RunnerError::Timeout { elapsed });
+        }
+        ExecError::tool_error(
+            &req.component,
+            &req.action,
+            code,
+            json!({ "message": "injected fault" }),
+        )
+    }
+}
+
+/// How a forced attempt of a [`WasixExecutor`](crate::executor::WasixExecutor) fails.
+#[derive(Clone)]
+pub enum ForcedFailure {
+    /// Time out, as if the attempt timeout had elapsed. Not retried.
+    Timeout,
+    /// Trap, like a guest that crashed. Retried as a transient failure.
+    Trap,
+    /// Fail with the error `make` returns. Only [`McpError::Transient`] is retried.
+    Error(Arc<dyn Fn() -> McpError + Send + Sync>),
+}
+
+impl ForcedFailure {
+    pub fn error(make: impl Fn() -> McpError + Send + Sync + 'static) -> Self {
+        Self::Error(Arc::new(make))
+    }
+}
+
+impl fmt::Debug for ForcedFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("Timeout"),
+            Self::Trap => f.write_str("Trap"),
+            Self::Error(_) => f.write_str("Error(..)"),
+        }
+    }
+}
+
+/// Failures still to be forced, by tool name.
+#[derive(Debug, Default)]
+pub(crate) struct ForcedFailures {
+    pending: Mutex<HashMap<String, VecDeque<ForcedFailure>>>,
+}
+
+impl ForcedFailures {
+    pub(crate) fn push(&self, tool: &str, count: usize, failure: ForcedFailure) {
+        self.pending()
+            .entry(tool.to_string())
+            .or_default()
+            .extend(std::iter::repeat_n(failure, count));
+    }
+
+    /// The failure of the next attempt of `tool`, if one is pending.
+    pub(crate) fn take(&self, tool: &str) -> Option<ForcedFailure> {
+        self.pending().get_mut(tool)?.pop_front()
+    }
+
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<String, VecDeque<ForcedFailure>>> {
+        self.pending.lock().expect("forced failures poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockToolStore, exec_with_retries_backend};
+
+    fn request() -> ExecRequest {
+        ExecRequest {
+            component: "weather".into(),
+            action: "forecast".into(),
+            args: json!({}),
+            tenant: None,
+            correlation_id: None,
+            identity: None,
+        }
+    }
+
+    /// Whether each of 16 calls succeeded, with faults seeded by `seed`.
+    async fn outcomes(cfg: &ExecConfig, seed: u64) -> Vec<bool> {
+        let faults = Arc::new(
+            FaultInjector::new(seed).with_tool("weather", Fault::errors(0.5, "transient.down")),
+        );
+        let mut outcomes = Vec::new();
+        for _ in 0..16 {
+            let result =
+                exec_with_retries_backend(request(), cfg, faults.backend(mcp_exec::exec)).await;
+            outcomes.push(result.is_ok());
+        }
+        let failed = outcomes.iter().filter(|ok| !**ok).count();
+        assert_eq!(faults.injected("weather") as usize, failed);
+        outcomes
+    }
+
+    #[tokio::test]
+    async fn seeded_faults_repeat_across_runs() {
+        let store = MockToolStore::new().with_responses("weather", [("forecast", json!(21))]);
+        let mut cfg = store.exec_config();
+        cfg.runtime.max_attempts = 1;
+
+        let first = outcomes(&cfg, 42).await;
+        assert!(first.contains(&true) && first.contains(&false), "{first:?}");
+        assert_eq!(first, outcomes(&cfg, 42).await);
+    }
+
+    #[tokio::test]
+    async fn retries_through_injected_failures() {
+        let store = MockToolStore::new().with_responses("weather", [("forecast", json!(21))]);
+        let mut cfg = store.exec_config();
+        cfg.runtime.max_attempts = 3;
+        cfg.runtime.base_backoff = Duration::from_millis(1);
+        cfg.runtime.per_call_timeout = Duration::from_millis(50);
+        let faults =
+            Arc::new(FaultInjector::new(7).with_default(Fault::errors(1.0, "transient.down")));
+
+        let err = exec_with_retries_backend(request(), &cfg, faults.backend(mcp_exec::exec))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExecError::Tool { ref code, .. } if code == "transient.down"));
+        assert_eq!(faults.injected("weather"), 3);
+
+        let slow = Arc::new(
+            FaultInjector::new(7).with_tool("weather", Fault::latency(Duration::from_secs(1))),
+        );
+        let err = exec_with_retries_backend(request(), &cfg, slow.backend(mcp_exec::exec))
+            .await
+            .unwrap_err();
+        assert!(err.code().is_timeout(), "{err}");
+    }
+
+    #[tokio::test]
+    async fn forces_failures_on_the_next_attempts() {
+        use crate::executor::WasixExecutor;
+        use crate::executor::tests::echo_component;
+        use crate::types::{ToolInput, ToolRef};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let mut tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        tool.max_retries = Some(2);
+        tool.retry_backoff_ms = Some(1);
+        let executor = WasixExecutor::new().unwrap();
+        let input = ToolInput::new(json!({ "n": 1 }));
+
+        // Two traps are retried away; three exhaust the retries.
+        executor.fail_next("echo", 2, ForcedFailure::Trap);
+        assert_eq!(
+            executor.invoke(&tool, &input).await.unwrap().payload,
+            input.payload
+        );
+        executor.fail_next("echo", 3, ForcedFailure::Trap);
+        let err = executor.invoke(&tool, &input).await.unwrap_err();
+        assert!(matches!(&err, McpError::Transient(_, msg) if msg.contains("forced trap")));
+
+        executor.fail_next("echo", 1, ForcedFailure::Timeout);
+        let err = executor.invoke(&tool, &input).await.unwrap_err();
+        assert!(matches!(err, McpError::Timeout { .. }), "{err}");
+        executor.fail_next(
+            "echo",
+            1,
+            ForcedFailure::error(|| McpError::Unauthorized("revoked".into())),
+        );
+        let err = executor.invoke(&tool, &input).await.unwrap_err();
+        assert!(matches!(err, McpError::Unauthorized(_)), "{err}");
+        assert!(executor.invoke(&tool, &input).await.is_ok());
+    }
+}
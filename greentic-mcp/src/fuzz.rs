@@ -0,0 +1,208 @@
+//! Fuzzing entry points, with the `fuzz` feature.
+//!
+//! [`exec_fuzz_input`] turns arbitrary bytes into a request, an input, and a
+//! component, and feeds them through both runners: `mcp-exec` and the
+//! [`WasixExecutor`]. Errors are expected; a panic is a bug. Use it from a
+//! cargo-fuzz target:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| greentic_mcp::fuzz::exec_fuzz_input(data));
+//! ```
+//!
+//! The generators ([`arbitrary_exec_request`], [`arbitrary_value`], and the
+//! [`Arbitrary`] impl of [`ToolInput`]) can drive other targets too.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use arbitrary::{Arbitrary, Unstructured};
+use greentic_types::TenantCtx;
+use mcp_exec::{ExecConfig, ExecRequest, RuntimePolicy, ToolStore, VerifyPolicy};
+use serde_json::{Map, Number, Value};
+
+use crate::executor::{self, WasixExecutor};
+use crate::types::{ToolInput, ToolRef};
+
+/// Nesting depth of generated JSON values.
+const MAX_DEPTH: u32 = 4;
+/// Elements of generated arrays and members of generated objects.
+const MAX_LEN: usize = 8;
+/// Bound on every fuzzed call, so looping components do not stall the fuzzer.
+const CALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Run one fuzz case built from `data`.
+pub fn exec_fuzz_input(data: &[u8]) {
+    let mut u = Unstructured::new(data);
+    let (Ok(request), Ok(input)) = (arbitrary_exec_request(&mut u), ToolInput::arbitrary(&mut u))
+    else {
+        return;
+    };
+    let component = u.take_rest();
+
+    // Conversions of raw guest input and output in the WASIX runner.
+    let _ = executor::input_string(component.to_vec());
+    let _ = executor::parse_output(component);
+
+    let harness = Harness::get();
+    let _ = harness.exec(request, component);
+    let _ = harness.invoke(&input, component);
+}
+
+/// Arbitrary JSON value, nested at most a few levels deep.
+pub fn arbitrary_value(u: &mut Unstructured<'_>) -> arbitrary::Result<Value> {
+    value(u, MAX_DEPTH)
+}
+
+fn value(u: &mut Unstructured<'_>, depth: u32) -> arbitrary::Result<Value> {
+    let kinds = if depth == 0 { 4 } else { 6 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => match Number::from_f64(u.arbitrary()?) {
+            Some(number) => Value::Number(number),
+            None => Value::from(u.arbitrary::<i64>()?),
+        },
+        3 => Value::String(u.arbitrary()?),
+        4 => {
+            let len = u.arbitrary_len::<u8>()?.min(MAX_LEN);
+            let items = (0..len).map(|_| value(u, depth - 1));
+            Value::Array(items.collect::<arbitrary::Result<_>>()?)
+        }
+        _ => {
+            let len = u.arbitrary_len::<u8>()?.min(MAX_LEN);
+            let mut map = Map::new();
+            for _ in 0..len {
+                map.insert(u.arbitrary()?, value(u, depth - 1)?);
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+/// Arbitrary [`ExecRequest`]; generated tenants are dropped when their ids are invalid.
+pub fn arbitrary_exec_request(u: &mut Unstructured<'_>) -> arbitrary::Result<ExecRequest> {
+    let tenant = if u.arbitrary()? {
+        let (env, id): (String, String) = u.arbitrary()?;
+        match (env.as_str().try_into(), id.as_str().try_into()) {
+            (Ok(env), Ok(id)) => Some(TenantCtx::new(env, id)),
+            _ => None,
+        }
+    } else {
+        None
+    };
+    Ok(ExecRequest {
+        component: u.arbitrary()?,
+        action: u.arbitrary()?,
+        args: arbitrary_value(u)?,
+        tenant,
+        correlation_id: u.arbitrary()?,
+        identity: None,
+    })
+}
+
+impl<'a> Arbitrary<'a> for ToolInput {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(ToolInput {
+            correlation_id: u.arbitrary()?,
+            ..ToolInput::new(arbitrary_value(u)?)
+        })
+    }
+}
+
+/// Runners and scratch space shared by every fuzz case of the process.
+struct Harness {
+    dir: tempfile::TempDir,
+    runtime: tokio::runtime::Runtime,
+    executor: WasixExecutor,
+    next: AtomicU64,
+}
+
+impl Harness {
+    fn get() -> &'static Harness {
+        static HARNESS: OnceLock<Harness> = OnceLock::new();
+        HARNESS.get_or_init(|| {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("fuzz runtime");
+            Harness {
+                dir: tempfile::tempdir().expect("fuzz directory"),
+                runtime,
+                executor: WasixExecutor::new().expect("fuzz executor"),
+                next: AtomicU64::new(0),
+            }
+        })
+    }
+
+    /// Write `bytes` as a component of its own, removed when `run` returns.
+    fn with_component<T>(&self, bytes: &[u8], run: impl FnOnce(&str) -> T) -> T {
+        let name = format!("fuzz-{}", self.next.fetch_add(1, Ordering::Relaxed));
+        let path = self.dir.path().join(format!("{name}.wasm"));
+        std::fs::write(&path, bytes).expect("write fuzz component");
+        let result = run(&name);
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    /// Run `request` on `bytes` through `mcp-exec`.
+    fn exec(&self, request: ExecRequest, bytes: &[u8]) -> Result<Value, mcp_exec::ExecError> {
+        let cfg = ExecConfig {
+            store: ToolStore::LocalDir(self.dir.path().into()),
+            security: VerifyPolicy {
+                allow_unverified: true,
+                ..VerifyPolicy::default()
+            },
+            runtime: RuntimePolicy {
+                per_call_timeout: CALL_TIMEOUT,
+                wallclock_timeout: CALL_TIMEOUT,
+                ..RuntimePolicy::default()
+            },
+            tenant_runtime: Default::default(),
+            http_enabled: false,
+        };
+        self.with_component(bytes, |name| {
+            let request = ExecRequest {
+                component: name.to_string(),
+                ..request
+            };
+            mcp_exec::exec(request, &cfg)
+        })
+    }
+
+    /// Invoke `bytes` with `input` through the [`WasixExecutor`].
+    fn invoke(&self, input: &ToolInput, bytes: &[u8]) -> Result<Value, crate::McpError> {
+        self.with_component(bytes, |name| {
+            let path = self.dir.path().join(format!("{name}.wasm"));
+            let tool = ToolRef {
+                timeout_ms: Some(CALL_TIMEOUT.as_millis() as u64),
+                ..ToolRef::new(name, path.to_string_lossy(), ToolRef::DEFAULT_ENTRY)
+            };
+            self.runtime
+                .block_on(self.executor.invoke(&tool, input))
+                .map(|output| output.payload)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survives_arbitrary_and_mock_components() {
+        exec_fuzz_input(&[]);
+        exec_fuzz_input(&[0xff; 64]);
+        let mut data = vec![7; 32];
+        data.extend_from_slice(br#"{"_mock_mcp_exec":true,"responses":{"a":1}}"#);
+        exec_fuzz_input(&data);
+        data.extend_from_slice(b"\xc3\x28");
+        exec_fuzz_input(&data);
+
+        // Generated components are cleaned up after each case.
+        let leftovers = std::fs::read_dir(Harness::get().dir.path())
+            .unwrap()
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
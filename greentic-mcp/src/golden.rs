@@ -0,0 +1,342 @@
+//! Golden tests of tool components, with the `test-util` feature.
+//!
+//! A [`GoldenSuite`] reads a directory of fixture pairs, `<case>.input.json` and
+//! `<case>.expected.json`, runs the tool on every input, and compares its output
+//! with the expected one after applying the suite's [`Normalize`] rules to both.
+//! The [`GoldenReport`] lists every case, so a tool repository gets conformance
+//! tests from a fixtures directory and one test function:
+//!
+//! ```ignore
+//! let report = GoldenSuite::new("tests/golden").run(&executor, &tool).await?;
+//! assert!(report.is_success(), "{report}");
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::executor::WasixExecutor;
+use crate::types::{McpError, ToolInput, ToolRef};
+
+const INPUT_SUFFIX: &str = ".input.json";
+const EXPECTED_SUFFIX: &str = ".expected.json";
+
+/// Rule applied to both the expected and the actual output before comparing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Normalize {
+    /// Remove the value at a JSON pointer, e.g. a timestamp. A `*` segment matches
+    /// every element of an array or member of an object.
+    Ignore(String),
+    /// Sort every array, for tools whose output order is unspecified.
+    SortArrays,
+    /// Round every number to this many decimal places.
+    RoundFloats(u32),
+}
+
+impl Normalize {
+    fn apply(&self, value: &mut Value) {
+        match self {
+            Normalize::Ignore(pointer) => {
+                let segments: Vec<String> = pointer
+                    .split('/')
+                    .skip(1)
+                    .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+                    .collect();
+                remove(value, &segments);
+            }
+            Normalize::SortArrays => sort_arrays(value),
+            Normalize::RoundFloats(decimals) => round_floats(value, *decimals),
+        }
+    }
+}
+
+fn remove(value: &mut Value, segments: &[String]) {
+    let Some((segment, rest)) = segments.split_first() else {
+        return;
+    };
+    let children: Vec<&mut Value> = match (value, segment.as_str()) {
+        (Value::Object(map), "*") if rest.is_empty() => {
+            map.clear();
+            return;
+        }
+        (Value::Array(items), "*") if rest.is_empty() => {
+            items.clear();
+            return;
+        }
+        (Value::Object(map), "*") => map.values_mut().collect(),
+        (Value::Array(items), "*") => items.iter_mut().collect(),
+        (Value::Object(map), key) if rest.is_empty() => {
+            map.remove(key);
+            return;
+        }
+        (Value::Object(map), key) => map.get_mut(key).into_iter().collect(),
+        (Value::Array(items), index) => match index.parse::<usize>() {
+            Ok(index) if rest.is_empty() && index < items.len() => {
+                items.remove(index);
+                return;
+            }
+            Ok(index) => items.get_mut(index).into_iter().collect(),
+            Err(_) => return,
+        },
+        _ => return,
+    };
+    for child in children {
+        remove(child, rest);
+    }
+}
+
+fn sort_arrays(value: &mut Value) {
+    match value {
+        Value::Array(items) => {
+            items.iter_mut().for_each(sort_arrays);
+            items.sort_by_key(Value::to_string);
+        }
+        Value::Object(map) => map.values_mut().for_each(sort_arrays),
+        _ => {}
+    }
+}
+
+fn round_floats(value: &mut Value, decimals: u32) {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            let scale = 10f64.powi(decimals as i32);
+            let rounded = (number.as_f64().unwrap_or_default() * scale).round() / scale;
+            if let Some(rounded) = serde_json::Number::from_f64(rounded) {
+                *number = rounded;
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| round_floats(item, decimals)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| round_floats(item, decimals)),
+        _ => {}
+    }
+}
+
+/// Fixture directory and how its cases are run and compared.
+#[derive(Clone, Debug)]
+pub struct GoldenSuite {
+    dir: PathBuf,
+    timeout: Duration,
+    rules: Vec<Normalize>,
+}
+
+impl GoldenSuite {
+    /// Run the cases in `dir`, each with a 10 second timeout.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            timeout: Duration::from_secs(10),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Fail cases still running after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Apply `rule` to outputs before comparing; rules apply in the order added.
+    pub fn with_rule(mut self, rule: Normalize) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Run every case against `tool`, in name order.
+    ///
+    /// Fails only if the fixtures cannot be read; failing cases are in the report.
+    pub async fn run(
+        &self,
+        executor: &WasixExecutor,
+        tool: &ToolRef,
+    ) -> Result<GoldenReport, McpError> {
+        let timeout_ms = u64::try_from(self.timeout.as_millis()).unwrap_or(u64::MAX);
+        let tool = ToolRef {
+            attempt_timeout_ms: Some(timeout_ms),
+            total_timeout_ms: Some(timeout_ms),
+            ..tool.clone()
+        };
+        let mut cases = Vec::new();
+        for name in self.case_names()? {
+            let input = read_json(&self.dir.join(format!("{name}{INPUT_SUFFIX}")))?;
+            let mut expected = read_json(&self.dir.join(format!("{name}{EXPECTED_SUFFIX}")))?;
+            let started = Instant::now();
+            let result = executor.invoke(&tool, &ToolInput::new(input)).await;
+            let duration = started.elapsed();
+            let outcome = match result {
+                Ok(output) => {
+                    let mut actual = output.payload;
+                    for rule in &self.rules {
+                        rule.apply(&mut expected);
+                        rule.apply(&mut actual);
+                    }
+                    if actual == expected {
+                        CaseOutcome::Passed
+                    } else {
+                        CaseOutcome::Mismatch { expected, actual }
+                    }
+                }
+                Err(err) => CaseOutcome::Failed(err.to_string()),
+            };
+            cases.push(CaseResult {
+                name,
+                outcome,
+                duration,
+            });
+        }
+        Ok(GoldenReport { cases })
+    }
+
+    /// Names of the cases with an input fixture, sorted.
+    fn case_names(&self) -> Result<Vec<String>, McpError> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let file_name = entry?.file_name();
+            if let Some(name) = file_name.to_string_lossy().strip_suffix(INPUT_SUFFIX) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value, McpError> {
+    let text = std::fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|err| McpError::config_file(path, err))
+}
+
+/// Outcome of every case of a suite.
+#[derive(Clone, Debug)]
+pub struct GoldenReport {
+    pub cases: Vec<CaseResult>,
+}
+
+impl GoldenReport {
+    pub fn passed(&self) -> usize {
+        self.cases.iter().filter(|case| case.passed()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.cases.len() - self.passed()
+    }
+
+    /// Whether there were cases and all of them passed.
+    pub fn is_success(&self) -> bool {
+        !self.cases.is_empty() && self.failed() == 0
+    }
+}
+
+impl fmt::Display for GoldenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for case in &self.cases {
+            writeln!(f, "{case}")?;
+        }
+        write!(f, "{} passed, {} failed", self.passed(), self.failed())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CaseResult {
+    pub name: String,
+    pub outcome: CaseOutcome,
+    pub duration: Duration,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, CaseOutcome::Passed)
+    }
+}
+
+impl fmt::Display for CaseResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.duration.as_millis();
+        match &self.outcome {
+            CaseOutcome::Passed => write!(f, "[PASS] {} ({millis} ms)", self.name),
+            CaseOutcome::Mismatch { expected, actual } => write!(
+                f,
+                "[FAIL] {} ({millis} ms)\n       expected: {expected}\n       actual:   {actual}",
+                self.name
+            ),
+            CaseOutcome::Failed(message) => {
+                write!(f, "[FAIL] {} ({millis} ms): {message}", self.name)
+            }
+        }
+    }
+}
+
+/// How a case ended; outputs are shown normalized.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaseOutcome {
+    Passed,
+    Mismatch {
+        expected: Value,
+        actual: Value,
+    },
+    /// The invocation failed or timed out.
+    Failed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::tests::echo_component;
+
+    #[tokio::test]
+    async fn reports_every_case_after_normalizing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let fixtures = tmp.path().join("golden");
+        std::fs::create_dir(&fixtures).unwrap();
+        let case = |name: &str, input: Value, expected: Value| {
+            std::fs::write(
+                fixtures.join(format!("{name}{INPUT_SUFFIX}")),
+                input.to_string(),
+            )
+            .unwrap();
+            std::fs::write(
+                fixtures.join(format!("{name}{EXPECTED_SUFFIX}")),
+                expected.to_string(),
+            )
+            .unwrap();
+        };
+        case(
+            "ordered",
+            json!({ "at": 1, "items": [{ "id": 2, "v": 0.123 }, { "id": 1 }] }),
+            json!({ "at": 9, "items": [{ "id": 1 }, { "id": 2, "v": 0.12 }] }),
+        );
+        case("changed", json!({ "text": "hi" }), json!({ "text": "bye" }));
+
+        let executor = WasixExecutor::new().unwrap();
+        let tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        let report = GoldenSuite::new(&fixtures)
+            .with_rule(Normalize::Ignore("/at".into()))
+            .with_rule(Normalize::SortArrays)
+            .with_rule(Normalize::RoundFloats(2))
+            .run(&executor, &tool)
+            .await
+            .unwrap();
+
+        let names: Vec<_> = report.cases.iter().map(|case| case.name.as_str()).collect();
+        assert_eq!(names, ["changed", "ordered"]);
+        assert!(report.cases[1].passed(), "{report}");
+        assert_eq!(
+            report.cases[0].outcome,
+            CaseOutcome::Mismatch {
+                expected: json!({ "text": "bye" }),
+                actual: json!({ "text": "hi" }),
+            }
+        );
+        assert!(!report.is_success());
+        assert!(report.to_string().ends_with("1 passed, 1 failed"));
+    }
+}
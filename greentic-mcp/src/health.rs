@@ -0,0 +1,244 @@
+//! Liveness and readiness probes for hosts embedding the executor, e.g. behind
+//! Kubernetes `livenessProbe` and `readinessProbe` endpoints.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use wasmtime::component::Component;
+
+use crate::executor::{WasixExecutor, load_component, verify_digest};
+use crate::shared::SharedToolMap;
+use crate::types::{ToolRef, ToolSource};
+
+/// How long the blocking pool may take to run a no-op before it counts as stuck.
+const WORKER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Smallest valid component binary, compiled to prove the engine works.
+const EMPTY_COMPONENT: &[u8] = b"\0asm\x0d\0\x01\0";
+
+/// Probes the state of a [`WasixExecutor`] and the tools it serves.
+pub struct Health {
+    executor: WasixExecutor,
+    tools: Option<Arc<SharedToolMap>>,
+    required_compiled: Option<usize>,
+    /// Digests of components that compiled before; they are not compiled again.
+    compiled: Mutex<HashSet<String>>,
+}
+
+/// Outcome of [`Health::check`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    /// The process works; restart it if not.
+    pub live: bool,
+    /// The process can serve tool calls; route traffic to it only if set.
+    pub ready: bool,
+    /// Tools in the map.
+    pub tools: usize,
+    /// Tools whose components were fetched, verified, and compiled.
+    pub compiled: usize,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// One named check of a [`HealthReport`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HealthCheck {
+    pub name: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl HealthCheck {
+    fn new(name: &str, result: Result<(), String>) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: result.is_ok(),
+            detail: result.err(),
+        }
+    }
+}
+
+impl Health {
+    pub fn new(executor: WasixExecutor) -> Self {
+        Self {
+            executor,
+            tools: None,
+            required_compiled: None,
+            compiled: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Require `tools` to be loaded, with every component reachable and compiling.
+    pub fn with_tools(mut self, tools: Arc<SharedToolMap>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Report ready once `count` components compile instead of requiring all of them.
+    pub fn require_compiled(mut self, count: usize) -> Self {
+        self.required_compiled = Some(count);
+        self
+    }
+
+    /// Run the liveness checks and, if they pass, the readiness checks.
+    ///
+    /// The first check of a tool fetches and compiles its component, so it takes as
+    /// long as a cold invocation; later checks only re-read and hash it.
+    pub async fn check(&self) -> HealthReport {
+        let mut checks = vec![
+            HealthCheck::new("engine", self.check_engine()),
+            HealthCheck::new("workers", check_workers().await),
+        ];
+        let live = checks.iter().all(|check| check.ok);
+
+        let mut report = HealthReport {
+            live,
+            ready: false,
+            tools: 0,
+            compiled: 0,
+            checks: Vec::new(),
+        };
+        if live {
+            self.check_tools(&mut report, &mut checks).await;
+        }
+        report.ready = live && checks.iter().all(|check| check.ok);
+        report.checks = checks;
+        report
+    }
+
+    fn check_engine(&self) -> Result<(), String> {
+        Component::from_binary(self.executor.engine(), EMPTY_COMPONENT)
+            .map(drop)
+            .map_err(|err| format!("engine cannot compile components: {err}"))
+    }
+
+    async fn check_tools(&self, report: &mut HealthReport, checks: &mut Vec<HealthCheck>) {
+        let Some(tools) = &self.tools else {
+            return;
+        };
+        let map = tools.load();
+        let components = map
+            .iter()
+            .map(|(_, tool)| tool)
+            .filter(|tool| !matches!(tool.source, Some(ToolSource::Mcp(_))))
+            .cloned()
+            .collect::<Vec<_>>();
+        report.tools = map.iter().count();
+        checks.push(HealthCheck::new(
+            "tool_map",
+            if report.tools == 0 {
+                Err("no tools are loaded".into())
+            } else {
+                Ok(())
+            },
+        ));
+
+        let mut failures = Vec::new();
+        for tool in &components {
+            match self.compile(tool).await {
+                Ok(()) => report.compiled += 1,
+                Err(err) => failures.push(format!("{}: {err}", tool.key())),
+            }
+        }
+        let required = self.required_compiled.unwrap_or(components.len());
+        checks.push(HealthCheck::new(
+            "components",
+            if report.compiled >= required {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} of {required} required components compiled; {}",
+                    report.compiled,
+                    failures.join("; ")
+                ))
+            },
+        ));
+    }
+
+    /// Fetch, verify, and compile the component of `tool` unless it compiled before.
+    async fn compile(&self, tool: &ToolRef) -> Result<(), String> {
+        let engine = self.executor.engine().clone();
+        let cache_dir = self.executor.cache_dir().to_path_buf();
+        let tool = tool.clone();
+        let loaded = tokio::task::spawn_blocking(move || {
+            let source = tool.source();
+            let bytes = load_component(&tool, &source, &cache_dir)
+                .map_err(|err| format!("failed to read `{source}`: {err}"))?;
+            verify_digest(&tool, &bytes).map_err(|err| err.to_string())?;
+            Ok::<_, String>((hex::encode(Sha256::digest(&bytes)), bytes, engine))
+        })
+        .await
+        .map_err(|err| format!("health check task failed: {err}"))??;
+
+        let (digest, bytes, engine) = loaded;
+        if self
+            .compiled
+            .lock()
+            .expect("health cache poisoned")
+            .contains(&digest)
+        {
+            return Ok(());
+        }
+        tokio::task::spawn_blocking(move || Component::from_binary(&engine, &bytes))
+            .await
+            .map_err(|err| format!("health check task failed: {err}"))?
+            .map_err(|err| format!("failed to compile: {err}"))?;
+        self.compiled
+            .lock()
+            .expect("health cache poisoned")
+            .insert(digest);
+        Ok(())
+    }
+}
+
+/// Whether the blocking pool, which runs every guest, still picks up work.
+async fn check_workers() -> Result<(), String> {
+    match tokio::time::timeout(WORKER_TIMEOUT, tokio::task::spawn_blocking(|| ())).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(format!("blocking task failed: {err}")),
+        Err(_) => Err(format!(
+            "blocking pool did not run a task within {WORKER_TIMEOUT:?}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_map::ToolMap;
+    use crate::types::ToolMapConfig;
+
+    #[tokio::test]
+    async fn reports_liveness_and_readiness() {
+        let executor = WasixExecutor::new().unwrap();
+        let report = Health::new(executor.clone()).check().await;
+        assert!(report.live && report.ready, "{report:?}");
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("spin.wasm");
+        std::fs::write(&path, crate::executor::tests::spin_component()).unwrap();
+        let config = ToolMapConfig {
+            tools: vec![
+                ToolRef::new("spin", path.to_string_lossy(), "tool-invoke"),
+                ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke"),
+            ],
+            ..Default::default()
+        };
+        let tools = Arc::new(SharedToolMap::new(ToolMap::from_config(&config).unwrap()));
+
+        let health = Health::new(executor.clone()).with_tools(tools.clone());
+        let report = health.check().await;
+        assert!(report.live);
+        assert!(!report.ready);
+        assert_eq!((report.tools, report.compiled), (2, 1));
+        let failed = report.checks.iter().find(|check| !check.ok).unwrap();
+        assert_eq!(failed.name, "components");
+        assert!(failed.detail.as_ref().unwrap().contains("missing"));
+
+        let report = health.require_compiled(1).check().await;
+        assert!(report.ready, "{report:?}");
+    }
+}
@@ -0,0 +1,139 @@
+//! Bounded in-memory history of recent invocations.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::audit::AuditRecord;
+
+/// Entries kept by a [`WasixExecutor`](crate::executor::WasixExecutor) unless configured
+/// with [`with_history_size`](crate::executor::WasixExecutor::with_history_size).
+pub const DEFAULT_HISTORY_SIZE: usize = 128;
+
+/// Selects entries of [`WasixExecutor::recent_invocations`](crate::executor::WasixExecutor::recent_invocations).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HistoryFilter {
+    tool: Option<String>,
+    outcome: Option<String>,
+}
+
+impl HistoryFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only invocations of the tool with this key.
+    pub fn tool(mut self, key: impl Into<String>) -> Self {
+        self.tool = Some(key.into());
+        self
+    }
+
+    /// Only invocations with this outcome (`ok`, `error`, `timeout`, or `cancelled`).
+    pub fn outcome(mut self, outcome: impl Into<String>) -> Self {
+        self.outcome = Some(outcome.into());
+        self
+    }
+
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.tool.as_ref().is_none_or(|tool| *tool == record.tool)
+            && self
+                .outcome
+                .as_ref()
+                .is_none_or(|outcome| *outcome == record.outcome)
+    }
+}
+
+/// Ring buffer of the last `capacity` invocations, without payloads.
+#[derive(Debug)]
+pub(crate) struct InvocationHistory {
+    capacity: usize,
+    records: Mutex<VecDeque<AuditRecord>>,
+}
+
+impl InvocationHistory {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub(crate) fn push(&self, record: AuditRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().expect("invocation history poisoned");
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Matching entries, newest first.
+    pub(crate) fn recent(&self, filter: &HistoryFilter) -> Vec<AuditRecord> {
+        self.records
+            .lock()
+            .expect("invocation history poisoned")
+            .iter()
+            .rev()
+            .filter(|record| filter.matches(record))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for InvocationHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(tool: &str, outcome: &str) -> AuditRecord {
+        AuditRecord {
+            tool: tool.into(),
+            outcome: outcome.into(),
+            ..AuditRecord::default()
+        }
+    }
+
+    #[test]
+    fn keeps_the_latest_entries_and_filters_them() {
+        let history = InvocationHistory::new(3);
+        for (tool, outcome) in [
+            ("echo", "ok"),
+            ("search", "error"),
+            ("echo", "error"),
+            ("echo", "ok"),
+        ] {
+            history.push(record(tool, outcome));
+        }
+
+        let tools = |filter: &HistoryFilter| {
+            history
+                .recent(filter)
+                .into_iter()
+                .map(|record| format!("{}:{}", record.tool, record.outcome))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            tools(&HistoryFilter::new()),
+            ["echo:ok", "echo:error", "search:error"]
+        );
+        assert_eq!(
+            tools(&HistoryFilter::new().tool("echo")),
+            ["echo:ok", "echo:error"]
+        );
+        assert_eq!(
+            tools(&HistoryFilter::new().outcome("error")),
+            ["echo:error", "search:error"]
+        );
+        assert!(
+            InvocationHistory::new(0)
+                .recent(&HistoryFilter::new())
+                .is_empty()
+        );
+    }
+}
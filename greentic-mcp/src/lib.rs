@@ -1,20 +1,125 @@
 //! Host-side ToolMap management and WASIX/WASI execution bridge for Greentic MCP tools.
 
+pub mod audit;
+pub mod binary;
+pub mod builder;
+pub mod cancel;
+pub mod capture;
+#[cfg(feature = "test-util")]
+pub mod cassette;
+pub mod catalog;
+pub mod component_cache;
+pub mod concurrency;
 pub mod config;
+pub mod contract;
+#[cfg(feature = "test-util")]
+pub mod deterministic;
+pub mod diff;
 pub mod executor;
+#[cfg(feature = "test-util")]
+pub mod fault;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(feature = "test-util")]
+pub mod golden;
+pub mod health;
+pub mod history;
+pub mod lock;
+pub mod mcp_client;
+pub mod mcp_server;
+#[cfg(feature = "test-util")]
+pub mod mock_store;
+#[cfg(feature = "otel")]
+pub mod otel;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+pub mod progress;
+pub mod rate_limit;
+pub mod resources;
 pub mod retry;
+pub mod sampling;
+pub mod schema;
+pub mod secrets;
+pub mod shared;
+pub mod snapshot;
+pub mod telemetry;
+pub mod tenant;
+#[cfg(feature = "test-util")]
+pub mod test_tools;
 pub mod tool_map;
 pub mod types;
+pub mod usage;
+pub mod validate;
+pub mod watcher;
 
-pub use config::load_tool_map_config;
-pub use executor::WasixExecutor;
-pub use tool_map::ToolMap;
-pub use types::{McpError, ToolInput, ToolMapConfig, ToolOutput, ToolRef};
+pub use audit::{AuditLog, AuditRecord};
+pub use binary::{Attachment, BinaryOutput};
+pub use builder::{ToolBuilder, ToolMapBuilder};
+pub use cancel::CancellationToken;
+pub use capture::PayloadCapture;
+#[cfg(feature = "test-util")]
+pub use cassette::{Cassette, Interaction, RecordedError, Recorder, Replayer};
+pub use catalog::ToolCatalog;
+pub use component_cache::ComponentCache;
+pub use concurrency::{ConcurrencyLimiter, ConcurrencyPermit};
+pub use config::{
+    load_layered_tool_map_config, load_tool_map_config, load_tool_map_config_for_env,
+    load_tool_map_config_remote, load_tool_map_config_with_secrets,
+};
+pub use contract::{ContractCase, ContractReport, Violation};
+#[cfg(feature = "test-util")]
+pub use deterministic::MockClock;
+pub use diff::{ToolChange, ToolMapDiff};
+pub use executor::{InvokeOptions, TraceContext, WasixExecutor};
+#[cfg(feature = "test-util")]
+pub use fault::{Fault, FaultInjector, ForcedFailure};
+#[cfg(feature = "test-util")]
+pub use golden::{CaseOutcome, CaseResult, GoldenReport, GoldenSuite, Normalize};
+pub use health::{Health, HealthCheck, HealthReport};
+pub use history::HistoryFilter;
+pub use lock::{LOCK_FILE, LockedTool, ToolLock};
+pub use mcp_client::{McpClient, RemoteTool};
+#[cfg(feature = "http")]
+pub use mcp_server::MCP_SESSION_HEADER;
+pub use mcp_server::{
+    AccessGrant, InFlightRequests, McpAuth, McpServer, McpSession, StaticTokens, TokenVerifier,
+};
+#[cfg(feature = "test-util")]
+pub use mock_store::MockToolStore;
+#[cfg(feature = "profiling")]
+pub use profiling::{GuestProfile, ProfileSink};
+pub use progress::{Progress, ProgressSink};
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use resources::{
+    DirectoryResources, KvResources, Resource, ResourceBody, ResourceContents, ResourceProvider,
+};
+pub use retry::{
+    BackoffStrategy, FileRetryStore, GiveUpReason, Jitter, MemoryRetryStore, RetryBudget,
+    RetryClassifier, RetryEvent, RetryObserver, RetryPolicy, RetryRequest, RetryState, RetryStore,
+};
+pub use sampling::Sampler;
+pub use schema::tool_map_schema;
+pub use secrets::{EnvSecretsProvider, ScrubbedSecrets, SecretScrubber, SecretsProvider};
+pub use shared::SharedToolMap;
+pub use snapshot::{DescribeDrift, DescribeSnapshot, SnapshotDiff, ToolSnapshot};
+pub use tenant::TenantToolMaps;
+#[cfg(feature = "test-util")]
+pub use test_tools::{FlakyEcho, NativeFn, NativeTools, TestBackend, exec_test_backend};
+pub use tool_map::{ConflictPolicy, LabelSelector, ToolMap};
+pub use types::{
+    ErrorCode, ErrorKind, ErrorReport, McpEndpoint, McpError, ToolDefaults, ToolExample, ToolInput,
+    ToolMapConfig, ToolOutput, ToolRef, ToolSource,
+};
+pub use usage::{ResourceUsage, TenantUsage, UsageMeter, UsageRecord, UsageSink};
+pub use validate::{ValidationIssue, ValidationProblem, ValidationReport};
+pub use watcher::{ToolMapEvent, ToolMapWatcher};
 
 use mcp_exec::{ExecConfig, ExecError, ExecRequest, RunnerError};
-use serde_json::{Value, json};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use std::sync::Arc;
-use tokio::time::sleep;
+use tracing::Instrument;
 /// Invoke a tool by name using a [`ToolMap`] and [`WasixExecutor`].
 pub async fn invoke_with_map(
     map: &ToolMap,
@@ -23,27 +128,55 @@ pub async fn invoke_with_map(
     input_json: Value,
 ) -> Result<Value, McpError> {
     let tool = map.get(name)?;
-    let input = ToolInput {
-        payload: input_json,
-    };
+    let input = ToolInput::new(input_json);
     let output = executor.invoke(tool, &input).await?;
     Ok(output.payload)
 }
 
+/// Like [`invoke_with_map`], with typed input and output; see
+/// [`WasixExecutor::invoke_typed`].
+pub async fn invoke_with_map_typed<I, O>(
+    map: &ToolMap,
+    executor: &WasixExecutor,
+    name: &str,
+    input: &I,
+) -> Result<O, McpError>
+where
+    I: Serialize + ?Sized,
+    O: DeserializeOwned,
+{
+    let tool = map.get(name)?;
+    executor.invoke_typed(tool, input).await
+}
+
+/// Like [`invoke_with_map`], for `tenant`; see [`WasixExecutor::invoke_as`]. Pass
+/// [`TenantToolMaps::for_tenant`] as `map` to also apply the tenant's overlay.
+pub async fn invoke_with_map_as(
+    map: &ToolMap,
+    executor: &WasixExecutor,
+    name: &str,
+    input_json: Value,
+    tenant: greentic_types::TenantCtx,
+) -> Result<Value, McpError> {
+    let tool = map.get(name)?;
+    let input = ToolInput::new(input_json);
+    let output = executor.invoke_as(tool, &input, tenant).await?;
+    Ok(output.payload)
+}
+
 /// Convenience helper for loading a tool map from disk and building a [`ToolMap`].
 pub fn load_tool_map(path: &std::path::Path) -> Result<ToolMap, McpError> {
     let config = load_tool_map_config(path)?;
     ToolMap::from_config(&config)
 }
 
-pub mod test_tools;
-
-use std::time::Duration;
-
+/// Blocking executor used in place of [`mcp_exec::exec_async`].
 type ExecFn = dyn Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync;
 
+/// Run `req` through [`mcp_exec::exec_async`], retrying according to the runtime policy
+/// of its tenant (see [`ExecConfig::runtime_for`]).
 pub async fn exec_with_retries(req: ExecRequest, cfg: &ExecConfig) -> Result<Value, ExecError> {
-    exec_with_retries_with(req, cfg, Arc::new(mcp_exec::exec)).await
+    exec_with_retries_with(req, cfg, None).await
 }
 
 pub async fn exec_with_retries_backend<F>(
@@ -54,55 +187,105 @@ pub async fn exec_with_retries_backend<F>(
 where
     F: Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static,
 {
-    exec_with_retries_with(req, cfg, Arc::new(exec_fn)).await
+    exec_with_retries_with(req, cfg, Some(Arc::new(exec_fn))).await
 }
 
 async fn exec_with_retries_with(
     mut req: ExecRequest,
     cfg: &ExecConfig,
-    executor: Arc<ExecFn>,
+    executor: Option<Arc<ExecFn>>,
 ) -> Result<Value, ExecError> {
-    let max_attempts = cfg.runtime.max_attempts.max(1);
+    // One key per logical invocation, reused by every attempt. Keys supplied by the caller
+    // (in the args or the tenant context) take precedence.
+    let caller_key = retry::idempotency_key(&req.args)
+        .map(str::to_owned)
+        .or_else(|| req.tenant.as_ref()?.idempotency_key.clone());
+    // Only caller-supplied keys can be presented again after a restart.
+    let resumable = caller_key.is_some();
+    let idempotency_key = caller_key.unwrap_or_else(retry::new_idempotency_key);
+    if let Some(tenant) = req.tenant.as_mut() {
+        tenant
+            .idempotency_key
+            .get_or_insert_with(|| idempotency_key.clone());
+    }
+    let runtime = cfg.runtime_for(req.tenant.as_ref());
+    if runtime.inject_idempotency_key {
+        retry::inject_idempotency_key(&mut req.args, &idempotency_key);
+    }
+
+    let request = resumable.then(|| retry::RetryRequest {
+        component: req.component.clone(),
+        action: req.action.clone(),
+        args: req.args.clone(),
+    });
+    let options = retry::RetryOptions {
+        tool: &req.component,
+        max_attempts: runtime.max_attempts,
+        policy: runtime.retry_policy,
+        base_backoff: runtime.base_backoff,
+        max_duration: retry::tighter(runtime.max_retry_duration, runtime.total_timeout),
+        budget: runtime.retry_budget.as_deref(),
+        observer: runtime.retry_observer.as_ref(),
+        store: runtime.retry_store.as_deref(),
+        invocation_id: resumable.then_some(idempotency_key.as_str()),
+        request: request.as_ref(),
+    };
 
-    for attempt in 1..=max_attempts {
+    let attempt = |attempt: u32| {
+        telemetry::attempt(&req.component, attempt);
+        let mut req = req.clone();
         if let Some(tenant) = req.tenant.as_mut() {
             tenant.attempt = attempt - 1;
         }
-
-        let req_clone = req.clone();
-        let cfg_clone = cfg.clone();
+        let cfg = cfg.clone();
         let executor = executor.clone();
-        let attempt_result =
-            tokio::task::spawn_blocking(move || executor(req_clone, &cfg_clone)).await;
-
-        let exec_result = match attempt_result {
-            Ok(result) => result,
-            Err(err) => {
-                return Err(ExecError::runner(
-                    req.component.clone(),
-                    RunnerError::Internal(format!("blocking exec failed: {err:?}")),
-                ));
-            }
-        };
-
-        match exec_result {
-            Ok(value) => return Ok(value),
-            Err(err) => {
-                let should_retry = attempt < max_attempts && is_transient_error(&err);
-                if !should_retry {
-                    return Err(err);
-                }
-                let backoff = cfg
-                    .runtime
-                    .base_backoff
-                    .checked_mul(attempt)
-                    .unwrap_or(cfg.runtime.base_backoff);
-                sleep(backoff).await;
-            }
+        async move {
+            let Some(executor) = executor else {
+                return mcp_exec::exec_async(req, &cfg).await;
+            };
+            let component = req.component.clone();
+            tokio::task::spawn_blocking(move || executor(req, &cfg))
+                .await
+                .unwrap_or_else(|err| {
+                    Err(ExecError::runner(
+                        component,
+                        RunnerError::Internal(format!("blocking exec failed: {err:?}")),
+                    ))
+                })
         }
-    }
+        .instrument(tracing::info_span!("attempt", attempt))
+    };
+    let describe = |err: &ExecError| retry::FailureInfo {
+        retryable: runtime
+            .retry_classifier
+            .classify(err)
+            .unwrap_or_else(|| is_transient_error(err)),
+        class: err.class().to_string(),
+        message: err.to_string(),
+    };
 
-    unreachable!("retry loop should never exit without returning")
+    let metrics = telemetry::InvocationMetrics::start(&req.component);
+    let retried = retry::retry(&options, attempt, describe);
+    let result = match runtime.total_timeout {
+        Some(total) => tokio::time::timeout(total, retried).await.map_err(|_| {
+            ExecError::runner(&req.component, RunnerError::TotalTimeout { elapsed: total })
+        }),
+        None => Ok(retried.await),
+    }
+    .and_then(|result| {
+        result.map_err(|failure| match failure.reason {
+            GiveUpReason::Deadline => {
+                ExecError::deadline_exceeded(&req.component, failure.elapsed, failure.error)
+            }
+            _ => failure.error,
+        })
+    });
+    metrics.finish(match &result {
+        Ok(_) => "ok",
+        Err(err) if err.code().is_timeout() => "timeout",
+        Err(_) => "error",
+    });
+    result
 }
 
 fn is_transient_error(err: &ExecError) -> bool {
@@ -112,44 +295,3 @@ fn is_transient_error(err: &ExecError) -> bool {
         _ => false,
     }
 }
-
-/// Test-only helpers that run native “tools” without Wasm.
-pub enum TestBackend {
-    NativeEcho,
-    NativeFlaky,
-    NativeTimeout(Duration),
-}
-
-pub fn exec_test_backend(
-    backend: TestBackend,
-    input: Value,
-    cfg: &ExecConfig,
-) -> Result<Value, ExecError> {
-    use crate::test_tools::*;
-
-    match backend {
-        TestBackend::NativeEcho => {
-            echo(&input).map_err(|message| tool_error("echo", "tool-invoke", "echo", message))
-        }
-        TestBackend::NativeFlaky => flaky_echo(&input)
-            .map_err(|message| tool_error("echo-flaky", "tool-invoke", "transient.echo", message)),
-        TestBackend::NativeTimeout(sleep) => {
-            if sleep > cfg.runtime.per_call_timeout {
-                Err(ExecError::runner(
-                    "echo-timeout",
-                    RunnerError::Timeout {
-                        elapsed: cfg.runtime.per_call_timeout,
-                    },
-                ))
-            } else {
-                timeout_echo(&input, sleep).map_err(|message| {
-                    tool_error("echo-timeout", "tool-invoke", "timeout", message)
-                })
-            }
-        }
-    }
-}
-
-fn tool_error(component: &str, action: &str, code: &str, message: String) -> ExecError {
-    ExecError::tool_error(component, action, code, json!({ "message": message }))
-}
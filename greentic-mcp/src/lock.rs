@@ -0,0 +1,152 @@
+//! Lockfile pinning the component digest of every tool in a map.
+//!
+//! [`ToolLock::resolve`] fetches each component and records its SHA-256 digest.
+//! [`ToolLock::pin`] applies a lock to a map, so the executor rejects components
+//! that changed since, and [`ToolLock::verify_policy`] gives the same digests to
+//! `mcp-exec`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use mcp_exec::VerifyPolicy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::executor::WasixExecutor;
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolRef, ToolSource};
+
+/// Conventional file name of a lock, next to the tool map.
+pub const LOCK_FILE: &str = "tools.lock";
+
+/// Locked tools by key.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolLock {
+    #[serde(default)]
+    pub tools: BTreeMap<String, LockedTool>,
+}
+
+/// The component a tool resolved to when it was locked.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedTool {
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Hex-encoded SHA-256 digest of the component.
+    pub sha256: String,
+}
+
+impl ToolLock {
+    /// Fetch the component of every tool in `map` and record its digest. Tools
+    /// served by remote MCP servers have no component and are left out.
+    pub async fn resolve(map: &ToolMap, executor: &WasixExecutor) -> Result<Self, McpError> {
+        let mut tools = BTreeMap::new();
+        for (key, tool) in map.iter() {
+            if matches!(tool.source, Some(ToolSource::Mcp(_))) {
+                continue;
+            }
+            let bytes = executor.fetch_component(tool).await?;
+            tools.insert(
+                key.clone(),
+                LockedTool {
+                    source: tool.source().to_string(),
+                    version: tool.version.clone(),
+                    sha256: hex::encode(Sha256::digest(&bytes)),
+                },
+            );
+        }
+        Ok(Self { tools })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, McpError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|err| McpError::config_file(path, err))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), McpError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Keys of the tools that are added, removed, or changed in `current`, sorted.
+    pub fn stale(&self, current: &ToolLock) -> Vec<String> {
+        let keys: BTreeSet<&String> = self
+            .tools
+            .keys()
+            .chain(current.tools.keys())
+            .filter(|key| self.tools.get(*key) != current.tools.get(*key))
+            .collect();
+        keys.into_iter().cloned().collect()
+    }
+
+    /// `map` with every locked tool pinned to its locked digest. Tools missing from
+    /// the lock are kept as they are.
+    pub fn pin(&self, map: &ToolMap) -> Result<ToolMap, McpError> {
+        let mut pinned = map.clone();
+        for (key, tool) in map.iter() {
+            if let Some(locked) = self.tools.get(key) {
+                pinned.insert(ToolRef {
+                    sha256: Some(locked.sha256.clone()),
+                    ..tool.clone()
+                })?;
+            }
+        }
+        Ok(pinned)
+    }
+
+    /// Policy requiring every locked component to match its digest, keyed by tool
+    /// key, and rejecting components that are not locked.
+    pub fn verify_policy(&self) -> VerifyPolicy {
+        VerifyPolicy {
+            allow_unverified: false,
+            required_digests: self
+                .tools
+                .iter()
+                .map(|(key, locked)| (key.clone(), locked.sha256.clone()))
+                .collect(),
+            trusted_signers: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::{ToolInput, ToolMapConfig};
+
+    #[tokio::test]
+    async fn locks_and_pins_component_digests() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("tool.wasm");
+        std::fs::write(&path, b"v1").unwrap();
+        let config = ToolMapConfig {
+            tools: vec![ToolRef::new("tool", path.to_string_lossy(), "run")],
+            ..Default::default()
+        };
+        let map = ToolMap::from_config(&config).unwrap();
+        let executor = WasixExecutor::new().unwrap();
+
+        let lock = ToolLock::resolve(&map, &executor).await.unwrap();
+        lock.write(tmp.path().join(LOCK_FILE)).unwrap();
+        let lock = ToolLock::read(tmp.path().join(LOCK_FILE)).unwrap();
+        assert_eq!(
+            lock.tools["tool"].sha256,
+            hex::encode(Sha256::digest(b"v1"))
+        );
+        assert!(lock.stale(&lock).is_empty());
+
+        std::fs::write(&path, b"v2").unwrap();
+        let current = ToolLock::resolve(&map, &executor).await.unwrap();
+        assert_eq!(lock.stale(&current), ["tool"]);
+        let pinned = lock.pin(&map).unwrap();
+        let err = executor
+            .invoke(pinned.get("tool").unwrap(), &ToolInput::new(json!({})))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, McpError::DigestMismatch { .. }), "{err}");
+    }
+}
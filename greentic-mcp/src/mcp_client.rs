@@ -0,0 +1,551 @@
+//! Client side of MCP, used to mount the tools of remote MCP servers into a
+//! [`ToolMap`](crate::ToolMap) (see [`WasixExecutor::mount_mcp`](crate::WasixExecutor::mount_mcp)).
+
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::sync::Mutex;
+
+use crate::mcp_server::SUPPORTED_PROTOCOL_VERSIONS;
+use crate::types::{McpEndpoint, McpError};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_HEADER: &str = "mcp-protocol-version";
+
+/// Tool advertised by a remote MCP server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteTool {
+    pub name: String,
+    pub description: Option<String>,
+    pub input_schema: Option<Value>,
+    pub output_schema: Option<Value>,
+}
+
+/// Initialized connection to one MCP server.
+///
+/// Transport failures are reported as [`McpError::Transient`] so callers can reconnect
+/// and retry; errors reported by the server are [`McpError::ExecutionFailed`].
+pub struct McpClient {
+    name: String,
+    transport: Transport,
+    next_id: AtomicU64,
+}
+
+enum Transport {
+    Stream(Mutex<StreamConnection>),
+    Http(HttpConnection),
+}
+
+type Reader = Box<dyn AsyncBufRead + Send + Unpin>;
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Newline-delimited JSON-RPC over a byte stream. Requests are sent one at a time.
+struct StreamConnection {
+    lines: Lines<Reader>,
+    writer: Writer,
+    /// Stdio server process, killed when the connection is dropped.
+    _child: Option<tokio::process::Child>,
+}
+
+struct HttpConnection {
+    client: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+    session_id: std::sync::Mutex<Option<String>>,
+    protocol_version: std::sync::Mutex<Option<String>>,
+}
+
+impl McpClient {
+    /// Start or contact the server behind `endpoint` and complete the MCP handshake.
+    pub async fn connect(endpoint: &McpEndpoint) -> Result<Self, McpError> {
+        let name = endpoint.to_string();
+        let transport = match endpoint {
+            McpEndpoint::Stdio { command, args, env } => {
+                let mut child = tokio::process::Command::new(command)
+                    .args(args)
+                    .envs(env)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|err| transient(&name, format!("failed to start: {err}")))?;
+                let stdin = child.stdin.take().expect("stdin is piped");
+                let stdout = child.stdout.take().expect("stdout is piped");
+                Transport::Stream(Mutex::new(StreamConnection::new(
+                    BufReader::new(stdout),
+                    stdin,
+                    Some(child),
+                )))
+            }
+            McpEndpoint::Http { url, headers } => {
+                let mut header_map = HeaderMap::new();
+                for (key, value) in headers {
+                    let key = HeaderName::from_bytes(key.as_bytes()).map_err(|err| {
+                        McpError::InvalidInput(format!("invalid header `{key}`: {err}"))
+                    })?;
+                    let value = HeaderValue::from_str(value).map_err(|err| {
+                        McpError::InvalidInput(format!("invalid value for header `{key}`: {err}"))
+                    })?;
+                    header_map.insert(key, value);
+                }
+                Transport::Http(HttpConnection {
+                    client: reqwest::Client::new(),
+                    url: url.clone(),
+                    headers: header_map,
+                    session_id: Default::default(),
+                    protocol_version: Default::default(),
+                })
+            }
+        };
+        Self::initialize(name, transport).await
+    }
+
+    /// Speak MCP over an existing newline-delimited JSON stream, e.g. a socket.
+    pub async fn from_streams<R, W>(
+        name: impl Into<String>,
+        reader: R,
+        writer: W,
+    ) -> Result<Self, McpError>
+    where
+        R: AsyncBufRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let connection = StreamConnection::new(reader, writer, None);
+        Self::initialize(name.into(), Transport::Stream(Mutex::new(connection))).await
+    }
+
+    async fn initialize(name: String, transport: Transport) -> Result<Self, McpError> {
+        let client = Self {
+            name,
+            transport,
+            next_id: AtomicU64::new(1),
+        };
+        let params = json!({
+            "protocolVersion": SUPPORTED_PROTOCOL_VERSIONS[0],
+            "capabilities": {},
+            "clientInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        });
+        let result = client.request("initialize", params).await?;
+        if let Transport::Http(http) = &client.transport {
+            *http.protocol_version.lock().expect("lock poisoned") = result
+                .get("protocolVersion")
+                .and_then(Value::as_str)
+                .map(str::to_owned);
+        }
+        client.notify("notifications/initialized").await?;
+        Ok(client)
+    }
+
+    /// Every tool the server advertises, following pagination cursors.
+    pub async fn list_tools(&self) -> Result<Vec<RemoteTool>, McpError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+            let page = result.get("tools").and_then(Value::as_array);
+            for tool in page.into_iter().flatten() {
+                let Some(name) = tool.get("name").and_then(Value::as_str) else {
+                    continue;
+                };
+                tools.push(RemoteTool {
+                    name: name.to_string(),
+                    description: tool
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .map(str::to_owned),
+                    input_schema: tool.get("inputSchema").cloned(),
+                    output_schema: tool.get("outputSchema").cloned(),
+                });
+            }
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_owned);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Call a remote tool, returning its structured content, or its text content
+    /// (parsed as JSON when possible).
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, McpError> {
+        let params = json!({ "name": name, "arguments": arguments });
+        let result = self.request("tools/call", params).await?;
+        let content = result.get("content").and_then(Value::as_array);
+        let text = content
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if result.get("isError").and_then(Value::as_bool) == Some(true) {
+            return Err(McpError::ExecutionFailed(format!(
+                "remote tool `{name}` on {} failed: {text}",
+                self.name
+            )));
+        }
+        if let Some(structured) = result.get("structuredContent") {
+            return Ok(structured.clone());
+        }
+        let all_text = content
+            .into_iter()
+            .flatten()
+            .all(|item| item.get("type").and_then(Value::as_str) == Some("text"));
+        if !all_text {
+            return Ok(result);
+        }
+        Ok(serde_json::from_str(&text).unwrap_or(Value::String(text)))
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = match &self.transport {
+            Transport::Stream(connection) => {
+                let mut connection = connection.lock().await;
+                connection.exchange(&self.name, &message, id).await?
+            }
+            Transport::Http(http) => http
+                .post(&self.name, &message, Some(id))
+                .await?
+                .ok_or_else(|| transient(&self.name, "response did not answer the request"))?,
+        };
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error");
+            return Err(McpError::ExecutionFailed(format!(
+                "{} rejected `{method}`: {message}",
+                self.name
+            )));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), McpError> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+        match &self.transport {
+            Transport::Stream(connection) => {
+                connection.lock().await.send(&self.name, &message).await
+            }
+            Transport::Http(http) => http.post(&self.name, &message, None).await.map(drop),
+        }
+    }
+}
+
+impl StreamConnection {
+    fn new(
+        reader: impl AsyncBufRead + Send + Unpin + 'static,
+        writer: impl AsyncWrite + Send + Unpin + 'static,
+        child: Option<tokio::process::Child>,
+    ) -> Self {
+        let reader: Reader = Box::new(reader);
+        Self {
+            lines: reader.lines(),
+            writer: Box::new(writer),
+            _child: child,
+        }
+    }
+
+    async fn send(&mut self, name: &str, message: &Value) -> Result<(), McpError> {
+        let mut line = message.to_string();
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|err| transient(name, err))?;
+        self.writer
+            .flush()
+            .await
+            .map_err(|err| transient(name, err))
+    }
+
+    /// Send a request and wait for its response, answering server pings meanwhile.
+    async fn exchange(&mut self, name: &str, message: &Value, id: u64) -> Result<Value, McpError> {
+        self.send(name, message).await?;
+        loop {
+            let line = self
+                .lines
+                .next_line()
+                .await
+                .map_err(|err| transient(name, err))?
+                .ok_or_else(|| transient(name, "server closed the connection"))?;
+            let Ok(incoming) = serde_json::from_str::<Value>(&line) else {
+                tracing::debug!(server = name, %line, "ignoring non-JSON line from MCP server");
+                continue;
+            };
+            let method = incoming.get("method").and_then(Value::as_str);
+            match (incoming.get("id"), method) {
+                (Some(incoming_id), None) if *incoming_id == json!(id) => return Ok(incoming),
+                (Some(request_id), Some(method)) => {
+                    let reply = match method {
+                        "ping" => json!({ "jsonrpc": "2.0", "id": request_id, "result": {} }),
+                        other => json!({
+                            "jsonrpc": "2.0",
+                            "id": request_id,
+                            "error": { "code": -32601, "message": format!("method `{other}` not supported") },
+                        }),
+                    };
+                    self.send(name, &reply).await?;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl HttpConnection {
+    /// POST one message; for requests, return the response with id `expect`.
+    async fn post(
+        &self,
+        name: &str,
+        message: &Value,
+        expect: Option<u64>,
+    ) -> Result<Option<Value>, McpError> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(session_id) = self.session_id.lock().expect("lock poisoned").as_deref() {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+        if let Some(version) = self
+            .protocol_version
+            .lock()
+            .expect("lock poisoned")
+            .as_deref()
+        {
+            request = request.header(PROTOCOL_HEADER, version);
+        }
+
+        let response = request.send().await.map_err(|err| transient(name, err))?;
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            *self.session_id.lock().expect("lock poisoned") = Some(session_id.to_string());
+        }
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(transient(name, format!("HTTP {status}")));
+        }
+        if !status.is_success() {
+            return Err(McpError::ExecutionFailed(format!(
+                "{name} answered HTTP {status}"
+            )));
+        }
+        let Some(id) = expect else {
+            return Ok(None);
+        };
+
+        let event_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        let body = response.text().await.map_err(|err| transient(name, err))?;
+        if !event_stream {
+            let response = serde_json::from_str(&body).map_err(|err| {
+                McpError::ExecutionFailed(format!("{name} sent invalid JSON: {err}"))
+            })?;
+            return Ok(Some(response));
+        }
+        Ok(sse_messages(&body).find(|message| message.get("id") == Some(&json!(id))))
+    }
+}
+
+/// JSON messages carried in the `data:` fields of a server-sent event stream.
+fn sse_messages(body: &str) -> impl Iterator<Item = Value> + '_ {
+    body.split("\n\n").filter_map(|event| {
+        let data = event
+            .lines()
+            .filter_map(|line| line.trim_end_matches('\r').strip_prefix("data:"))
+            .map(|data| data.strip_prefix(' ').unwrap_or(data))
+            .collect::<Vec<_>>()
+            .join("\n");
+        serde_json::from_str(&data).ok()
+    })
+}
+
+fn transient(name: &str, err: impl std::fmt::Display) -> McpError {
+    McpError::Transient(name.to_string(), err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal MCP server answering the handful of requests the client sends.
+    fn fake_reply(request: &Value) -> Option<Value> {
+        let id = request.get("id")?.clone();
+        let result = match request["method"].as_str()? {
+            "initialize" => json!({ "protocolVersion": "2025-06-18", "capabilities": {} }),
+            "tools/list" if request["params"].get("cursor").is_none() => json!({
+                "tools": [{ "name": "search", "description": "Search issues",
+                            "inputSchema": { "type": "object" } }],
+                "nextCursor": "page-2",
+            }),
+            "tools/list" => json!({ "tools": [{ "name": "fetch" }] }),
+            "tools/call" => match request["params"]["name"].as_str()? {
+                "search" => json!({
+                    "content": [{ "type": "text", "text": "{\"hits\":3}" }],
+                    "isError": false,
+                }),
+                _ => json!({ "content": [{ "type": "text", "text": "boom" }], "isError": true }),
+            },
+            _ => {
+                return Some(
+                    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32601, "message": "nope" } }),
+                );
+            }
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    #[tokio::test]
+    async fn talks_to_a_stream_server() {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (server_read, mut server_write) = tokio::io::split(server_side);
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(server_read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let request: Value = serde_json::from_str(&line).unwrap();
+                if let Some(reply) = fake_reply(&request) {
+                    let reply = format!("{reply}\n");
+                    server_write.write_all(reply.as_bytes()).await.unwrap();
+                }
+            }
+        });
+
+        let (client_read, client_write) = tokio::io::split(client_side);
+        let client = McpClient::from_streams("fake", BufReader::new(client_read), client_write)
+            .await
+            .unwrap();
+
+        let tools = client.list_tools().await.unwrap();
+        let names = tools
+            .iter()
+            .map(|tool| tool.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["search", "fetch"]);
+        assert_eq!(tools[0].description.as_deref(), Some("Search issues"));
+
+        let result = client.call_tool("search", json!({ "q": "bug" })).await;
+        assert_eq!(result.unwrap(), json!({ "hits": 3 }));
+        let err = client.call_tool("fetch", json!({})).await.unwrap_err();
+        assert!(
+            matches!(err, McpError::ExecutionFailed(ref msg) if msg.contains("boom")),
+            "{err}"
+        );
+    }
+
+    /// Serve the fake MCP server over HTTP, answering `tools/call` as an event stream
+    /// and rejecting requests that lose the session id.
+    async fn serve_http() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut stream = BufReader::new(stream);
+                let mut head = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+                    let lower = line.to_ascii_lowercase();
+                    if let Some(value) = lower.strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    head.push_str(&lower);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; length];
+                tokio::io::AsyncReadExt::read_exact(&mut stream, &mut body)
+                    .await
+                    .unwrap();
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let initialize = request["method"] == "initialize";
+                let (status, headers, body) = match fake_reply(&request) {
+                    _ if !initialize && !head.contains("mcp-session-id: s-1") => {
+                        ("400 Bad Request", "", String::new())
+                    }
+                    Some(reply) if request["method"] == "tools/call" => (
+                        "200 OK",
+                        "content-type: text/event-stream\r\n",
+                        format!("event: message\ndata: {reply}\n\n"),
+                    ),
+                    Some(reply) => (
+                        "200 OK",
+                        "content-type: application/json\r\nmcp-session-id: s-1\r\n",
+                        reply.to_string(),
+                    ),
+                    None => ("202 Accepted", "", String::new()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\n{headers}content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream
+                    .get_mut()
+                    .write_all(response.as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+        format!("http://{addr}/mcp")
+    }
+
+    #[tokio::test]
+    async fn mounted_http_tools_are_invoked_through_the_tool_map() {
+        let endpoint = McpEndpoint::Http {
+            url: serve_http().await,
+            headers: Default::default(),
+        };
+        let executor = crate::WasixExecutor::new().unwrap();
+        let mut map = crate::ToolMap::from_config(&Default::default()).unwrap();
+
+        let keys = executor
+            .mount_mcp(&mut map, "issues", endpoint.clone())
+            .await
+            .unwrap();
+        assert_eq!(keys, ["issues/search", "issues/fetch"]);
+        let search = map.get("issues/search").unwrap();
+        assert_eq!(search.source, Some(crate::ToolSource::Mcp(endpoint)));
+        assert_eq!(search.input_schema, Some(json!({ "type": "object" })));
+
+        let output = crate::invoke_with_map(&map, &executor, "issues/search", json!({ "q": "x" }))
+            .await
+            .unwrap();
+        assert_eq!(output, json!({ "hits": 3 }));
+        let err = crate::invoke_with_map(&map, &executor, "issues/fetch", json!({}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, McpError::ExecutionFailed(_)), "{err}");
+    }
+
+    #[test]
+    fn parses_event_stream_bodies() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\nevent: message\r\ndata: {\"jsonrpc\":\"2.0\",\"id\":4,\r\ndata: \"result\":{}}\r\n\r\n";
+        let messages = sse_messages(&body.replace("\r\n", "\n")).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1]["id"], 4);
+    }
+}
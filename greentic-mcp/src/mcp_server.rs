@@ -0,0 +1,1153 @@
+//! Serve a [`ToolMap`] to MCP clients (editors, desktop agents) over JSON-RPC.
+//!
+//! [`McpServer`] implements the protocol independently of the transport: each
+//! connection owns an [`McpSession`] and feeds raw messages to
+//! [`McpServer::handle_message`]. [`McpServer::serve_stdio`] wires that up to
+//! newline-delimited JSON on stdin/stdout, `serve_http` (behind the `http`
+//! feature) to Streamable HTTP, and `serve_websocket` (behind the `websocket`
+//! feature) to WebSocket text frames.
+
+mod auth;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "websocket")]
+mod websocket;
+
+pub use auth::{AccessGrant, McpAuth, StaticTokens, TokenVerifier};
+#[cfg(feature = "http")]
+pub use http::MCP_SESSION_HEADER;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use greentic_types::TenantCtx;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+use crate::cancel::CancellationToken;
+use crate::catalog::ToolCatalog;
+use crate::executor::{InvokeOptions, TraceContext, WasixExecutor};
+use crate::progress::ProgressSink;
+use crate::resources::ResourceProvider;
+use crate::sampling::Sampler;
+use crate::shared::SharedToolMap;
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolInput, ToolRef};
+
+/// Protocol revisions this server speaks, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const RESOURCE_NOT_FOUND: i64 = -32002;
+
+/// MCP server exposing every registered [`ToolRef`] as an MCP tool.
+#[derive(Clone)]
+pub struct McpServer {
+    tools: Tools,
+    executor: WasixExecutor,
+    /// `describe-v1` documents by tool key, kept while the tool definition is unchanged.
+    describes: Arc<DescribeCache>,
+    resources: Vec<Arc<dyn ResourceProvider>>,
+    auth: Option<McpAuth>,
+}
+
+type DescribeCache = Mutex<HashMap<String, (ToolRef, Option<Value>)>>;
+
+/// Where the served tools come from.
+#[derive(Clone)]
+enum Tools {
+    Shared(Arc<SharedToolMap>),
+    Catalog(Arc<ToolCatalog>),
+}
+
+impl Tools {
+    fn load(&self) -> Arc<ToolMap> {
+        match self {
+            Tools::Shared(tools) => tools.load(),
+            Tools::Catalog(catalog) => catalog.load(),
+        }
+    }
+}
+
+/// Per-connection protocol state.
+#[derive(Clone, Debug, Default)]
+pub struct McpSession {
+    /// Protocol revision agreed during `initialize`.
+    pub protocol_version: Option<String>,
+    /// `clientInfo` sent by the client during `initialize`.
+    pub client_info: Option<Value>,
+    /// `capabilities` the client declared during `initialize`.
+    pub client_capabilities: Option<Value>,
+    /// Whether the client has sent `notifications/initialized`.
+    pub initialized: bool,
+    /// Tools the authenticated caller may use; `None` allows every tool.
+    pub grant: Option<AccessGrant>,
+    /// Tenant the session acts for, passed to every tool call. Tools it may not use
+    /// are left out of `tools/list`.
+    pub tenant: Option<TenantCtx>,
+    /// Channel for messages the server sends on its own, e.g. progress notifications.
+    outgoing: Option<UnboundedSender<Value>>,
+    in_flight: InFlightRequests,
+}
+
+/// Requests in flight on a session: `tools/call` requests from the client, which it
+/// may cancel, and requests the server sent to the client (e.g. sampling), awaiting
+/// their response.
+///
+/// Transports that keep reading while a request is being handled pass each message
+/// to [`intercept`](Self::intercept), so cancellations and responses take effect
+/// without waiting for the request to finish.
+#[derive(Clone, Debug, Default)]
+pub struct InFlightRequests(Arc<InFlight>);
+
+#[derive(Debug, Default)]
+struct InFlight {
+    calls: Mutex<HashMap<String, CancellationToken>>,
+    outbound: Mutex<HashMap<String, oneshot::Sender<Result<Value, String>>>>,
+    next_outbound_id: AtomicU64,
+}
+
+impl InFlightRequests {
+    /// Apply `message` if it is a `notifications/cancelled` notification or a response
+    /// to a server-initiated request, returning whether it was consumed.
+    pub fn intercept(&self, message: &str) -> bool {
+        let Ok(message) = serde_json::from_str::<Value>(message) else {
+            return false;
+        };
+        match (
+            message.get("id"),
+            message.get("method").and_then(Value::as_str),
+        ) {
+            (None, Some("notifications/cancelled")) => {
+                self.cancel(message.pointer("/params/requestId"));
+                true
+            }
+            (Some(_), None) => self.respond(&message),
+            _ => false,
+        }
+    }
+
+    /// Cancel the request with JSON-RPC id `request_id`, if it is still running.
+    pub fn cancel(&self, request_id: Option<&Value>) -> bool {
+        let Some(request_id) = request_id else {
+            return false;
+        };
+        let calls = self.0.calls.lock().expect("in-flight requests poisoned");
+        match calls.get(&request_id.to_string()) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn register(&self, request_id: &Value) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.0
+            .calls
+            .lock()
+            .expect("in-flight requests poisoned")
+            .insert(request_id.to_string(), token.clone());
+        token
+    }
+
+    fn finish(&self, request_id: &Value) {
+        self.0
+            .calls
+            .lock()
+            .expect("in-flight requests poisoned")
+            .remove(&request_id.to_string());
+    }
+
+    /// Send `method` to the client through `outgoing` and wait for its response.
+    async fn request(
+        &self,
+        outgoing: &UnboundedSender<Value>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, String> {
+        let id = json!(format!(
+            "greentic-{}",
+            self.0.next_outbound_id.fetch_add(1, Ordering::Relaxed)
+        ));
+        let (tx, rx) = oneshot::channel();
+        self.0
+            .outbound
+            .lock()
+            .expect("in-flight requests poisoned")
+            .insert(id.to_string(), tx);
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if outgoing.send(request).is_err() {
+            self.0
+                .outbound
+                .lock()
+                .expect("in-flight requests poisoned")
+                .remove(&id.to_string());
+            return Err("client disconnected".into());
+        }
+        rx.await.map_err(|_| "client disconnected".to_string())?
+    }
+
+    /// Complete the server-initiated request `response` answers, if any.
+    fn respond(&self, response: &Value) -> bool {
+        let Some(id) = response.get("id") else {
+            return false;
+        };
+        let waiter = self
+            .0
+            .outbound
+            .lock()
+            .expect("in-flight requests poisoned")
+            .remove(&id.to_string());
+        let Some(waiter) = waiter else {
+            return false;
+        };
+        let result = match response.get("error") {
+            Some(error) => Err(error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("request failed")
+                .to_string()),
+            None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+        };
+        let _ = waiter.send(result);
+        true
+    }
+}
+
+impl McpSession {
+    /// Session that can push notifications to the client while requests are in
+    /// flight. The transport must write every message received on the returned
+    /// channel; sessions created with `default()` drop them.
+    pub fn with_notifications() -> (Self, UnboundedReceiver<Value>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let session = Self {
+            outgoing: Some(tx),
+            ..Self::default()
+        };
+        (session, rx)
+    }
+
+    fn may_use(&self, tool: &ToolRef) -> bool {
+        self.grant.as_ref().is_none_or(|grant| grant.allows(tool))
+    }
+
+    /// Handle for routing cancellations and responses to in-flight requests.
+    pub fn in_flight(&self) -> InFlightRequests {
+        self.in_flight.clone()
+    }
+
+    /// Forward tool sampling requests to the client, if it supports sampling.
+    fn sampler(&self) -> Option<Sampler> {
+        self.client_capabilities.as_ref()?.get("sampling")?;
+        let outgoing = self.outgoing.clone()?;
+        let in_flight = self.in_flight.clone();
+        Some(Sampler::new(move |params| {
+            let outgoing = outgoing.clone();
+            let in_flight = in_flight.clone();
+            async move {
+                in_flight
+                    .request(&outgoing, "sampling/createMessage", params)
+                    .await
+            }
+        }))
+    }
+
+    fn progress_sink(&self, token: Value) -> Option<ProgressSink> {
+        let outgoing = self.outgoing.clone()?;
+        Some(ProgressSink::new(move |progress| {
+            let mut params = json!(progress);
+            params["progressToken"] = token.clone();
+            // The client may be gone; the call's response will fail to send as well.
+            let _ = outgoing.send(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/progress",
+                "params": params,
+            }));
+        }))
+    }
+}
+
+/// JSON-RPC error object returned in place of a result.
+#[derive(Clone, Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl McpServer {
+    pub fn new(map: ToolMap, executor: WasixExecutor) -> Self {
+        Self::with_shared(Arc::new(SharedToolMap::new(map)), executor)
+    }
+
+    /// Serve a map that may change at runtime; every request sees the latest snapshot.
+    pub fn with_shared(tools: Arc<SharedToolMap>, executor: WasixExecutor) -> Self {
+        Self::with_tools(Tools::Shared(tools), executor)
+    }
+
+    /// Serve several tool maps composed by `catalog` as one tool list.
+    pub fn with_catalog(catalog: Arc<ToolCatalog>, executor: WasixExecutor) -> Self {
+        Self::with_tools(Tools::Catalog(catalog), executor)
+    }
+
+    fn with_tools(tools: Tools, executor: WasixExecutor) -> Self {
+        Self {
+            tools,
+            executor,
+            describes: Arc::default(),
+            resources: Vec::new(),
+            auth: None,
+        }
+    }
+
+    /// Require a bearer token on network transports (`serve_http`, `serve_websocket`).
+    ///
+    /// Each connection only sees the tools its token's [`AccessGrant`] allows. Stdio
+    /// sessions belong to the local user and are not authenticated.
+    pub fn with_auth(mut self, auth: McpAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Grant for a connection presenting `authorization` (its `Authorization` header).
+    ///
+    /// Always succeeds with `None`, i.e. unrestricted access, when no auth is
+    /// configured. Transports upgraded elsewhere use this to set
+    /// [`McpSession::grant`].
+    pub fn authorize(&self, authorization: Option<&str>) -> Result<Option<AccessGrant>, McpError> {
+        self.auth
+            .as_ref()
+            .map(|auth| auth.authorize(authorization))
+            .transpose()
+    }
+
+    /// Also serve `resources/list` and `resources/read` from `provider`.
+    ///
+    /// Providers are consulted in the order they were added.
+    pub fn with_resources(mut self, provider: impl ResourceProvider + 'static) -> Self {
+        self.resources.push(Arc::new(provider));
+        self
+    }
+
+    /// Serve MCP over stdin/stdout until stdin is closed.
+    pub async fn serve_stdio(&self) -> Result<(), McpError> {
+        self.serve(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+            .await
+    }
+
+    /// Serve MCP over a newline-delimited JSON stream until `reader` reaches EOF.
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> Result<(), McpError>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let (mut session, mut notifications) = McpSession::with_notifications();
+        let in_flight = session.in_flight();
+        let mut lines = reader.lines();
+        // Messages read while a request was being handled, processed in order after it.
+        let mut pending = VecDeque::new();
+        let mut eof = false;
+        loop {
+            let line = match pending.pop_front() {
+                Some(line) => line,
+                None if eof => break,
+                None => match lines.next_line().await? {
+                    Some(line) => line,
+                    None => break,
+                },
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let handled = self.handle_message(&mut session, &line);
+            tokio::pin!(handled);
+            let response = loop {
+                tokio::select! {
+                    // Poll the request first so it is registered before a cancellation is read.
+                    biased;
+                    response = &mut handled => break response,
+                    Some(notification) = notifications.recv() => {
+                        write_line(&mut writer, &notification.to_string()).await?;
+                    }
+                    // Keep reading so cancellations and responses reach the request being handled.
+                    line = lines.next_line(), if !eof => match line? {
+                        Some(line) if !in_flight.intercept(&line) => {
+                            pending.push_back(line);
+                        }
+                        Some(_) => {}
+                        None => eof = true,
+                    },
+                }
+            };
+            while let Ok(notification) = notifications.try_recv() {
+                write_line(&mut writer, &notification.to_string()).await?;
+            }
+            if let Some(response) = response {
+                write_line(&mut writer, &response).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle one raw JSON-RPC message, returning the serialized response (if any).
+    pub async fn handle_message(&self, session: &mut McpSession, message: &str) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(message) {
+            Ok(request) => self.handle(session, request).await?,
+            Err(err) => error_response(Value::Null, RpcError::new(PARSE_ERROR, err.to_string())),
+        };
+        Some(response.to_string())
+    }
+
+    /// Handle one parsed JSON-RPC message. Notifications produce no response.
+    pub async fn handle(&self, session: &mut McpSession, request: Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            // Responses to our own requests, for transports that do not intercept them.
+            if id.is_some() && session.in_flight.respond(&request) {
+                return None;
+            }
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "missing `method`"),
+            ));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let Some(id) = id else {
+            self.notify(session, method, &params);
+            return None;
+        };
+        let result = match method {
+            "initialize" => Ok(self.initialize(session, &params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools(session).await),
+            "tools/call" => {
+                let cancellation = session.in_flight.register(&id);
+                let result = self.call_tool(session, &params, cancellation.clone()).await;
+                session.in_flight.finish(&id);
+                // The client has given up on cancelled requests; they get no response.
+                if cancellation.is_cancelled() {
+                    return None;
+                }
+                result
+            }
+            "resources/list" => self.list_resources().await,
+            "resources/read" => self.read_resource(&params).await,
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method `{other}` not found"),
+            )),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => error_response(id, err),
+        })
+    }
+
+    fn notify(&self, session: &mut McpSession, method: &str, params: &Value) {
+        match method {
+            "notifications/initialized" => session.initialized = true,
+            "notifications/cancelled" => {
+                session.in_flight.cancel(params.get("requestId"));
+            }
+            other => tracing::debug!(method = other, "ignoring MCP notification"),
+        }
+    }
+
+    fn initialize(&self, session: &mut McpSession, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str);
+        let version = requested
+            .and_then(|requested| {
+                SUPPORTED_PROTOCOL_VERSIONS
+                    .iter()
+                    .find(|supported| **supported == requested)
+            })
+            .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0]);
+        session.protocol_version = Some(version.to_string());
+        session.client_info = params.get("clientInfo").cloned();
+        session.client_capabilities = params.get("capabilities").cloned();
+
+        let mut capabilities = json!({ "tools": { "listChanged": false } });
+        if !self.resources.is_empty() {
+            capabilities["resources"] = json!({});
+        }
+        json!({
+            "protocolVersion": version,
+            "capabilities": capabilities,
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })
+    }
+
+    async fn list_resources(&self) -> Result<Value, RpcError> {
+        let providers = self.resources.clone();
+        let resources = blocking(move || {
+            let mut resources = Vec::new();
+            for provider in &providers {
+                resources.extend(provider.list()?);
+            }
+            Ok(resources)
+        })
+        .await?;
+        Ok(json!({ "resources": resources }))
+    }
+
+    async fn read_resource(&self, params: &Value) -> Result<Value, RpcError> {
+        let uri = params
+            .get("uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing resource `uri`"))?
+            .to_string();
+        let providers = self.resources.clone();
+        let lookup = uri.clone();
+        let contents = blocking(move || {
+            for provider in &providers {
+                if let Some(contents) = provider.read(&lookup)? {
+                    return Ok(Some(contents));
+                }
+            }
+            Ok(None)
+        })
+        .await?
+        .ok_or_else(|| RpcError::new(RESOURCE_NOT_FOUND, format!("resource `{uri}` not found")))?;
+        Ok(json!({ "contents": [contents] }))
+    }
+
+    async fn list_tools(&self, session: &McpSession) -> Value {
+        let map = self.tools.load();
+        let mut tools = Vec::new();
+        let tenant = session
+            .tenant
+            .as_ref()
+            .map(|tenant| tenant.tenant_id.as_str());
+        let listed = map
+            .iter()
+            .filter(|(_, tool)| session.may_use(tool) && tool.allows_tenant(tenant));
+        for (key, tool) in listed {
+            let document = if tool.input_schema.is_some() && tool.description.is_some() {
+                None
+            } else {
+                self.describe(key, tool).await
+            };
+            tools.push(describe_tool(key, tool, document.as_ref()));
+        }
+        json!({ "tools": tools })
+    }
+
+    /// The component's `describe-v1` document, fetched once per tool definition.
+    async fn describe(&self, key: &str, tool: &ToolRef) -> Option<Value> {
+        let cached = {
+            let cache = self.describes.lock().expect("describe cache poisoned");
+            cache
+                .get(key)
+                .filter(|(cached_tool, _)| cached_tool == tool)
+                .map(|(_, document)| document.clone())
+        };
+        if let Some(document) = cached {
+            return document;
+        }
+
+        #[cfg(feature = "describe-v1")]
+        let document = match self.executor.describe(tool).await {
+            Ok(document) => document,
+            Err(err) => {
+                // Not cached, so a tool that is temporarily unreachable is retried.
+                tracing::debug!(tool = key, %err, "describe failed; using a permissive schema");
+                return None;
+            }
+        };
+        #[cfg(not(feature = "describe-v1"))]
+        let document = None;
+
+        self.describes
+            .lock()
+            .expect("describe cache poisoned")
+            .insert(key.to_string(), (tool.clone(), document.clone()));
+        document
+    }
+
+    async fn call_tool(
+        &self,
+        session: &McpSession,
+        params: &Value,
+        cancellation: CancellationToken,
+    ) -> Result<Value, RpcError> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing tool `name`"))?;
+        let arguments = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        let map = self.tools.load();
+        let tool = map
+            .get(name)
+            .and_then(|tool| {
+                if session.may_use(tool) {
+                    Ok(tool)
+                } else {
+                    // Hidden tools are indistinguishable from missing ones.
+                    Err(McpError::tool_not_found(name))
+                }
+            })
+            .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+        let input = ToolInput {
+            correlation_id: params
+                .get("_meta")
+                .and_then(|meta| meta.get("greentic/correlationId"))
+                .and_then(Value::as_str)
+                .map(str::to_owned),
+            ..ToolInput::new(arguments)
+        };
+        let call = InvokeOptions {
+            progress: params
+                .pointer("/_meta/progressToken")
+                .and_then(|token| session.progress_sink(token.clone())),
+            cancellation: Some(cancellation),
+            sampler: session.sampler(),
+            trace_context: trace_context(params),
+            tenant: session.tenant.clone(),
+        };
+        // Tool failures are results the model should see, not protocol errors.
+        Ok(match self.executor.invoke_with(tool, &input, call).await {
+            Ok(output) => {
+                let mut result = json!({
+                    "content": [{ "type": "text", "text": output.payload.to_string() }],
+                    "isError": false,
+                });
+                if let Some(binary) = &output.binary {
+                    let uri = format!("greentic://output/{}", tool.key());
+                    result["content"]
+                        .as_array_mut()
+                        .expect("content is an array")
+                        .push(binary.mcp_content(&uri));
+                }
+                if output.payload.is_object() {
+                    result["structuredContent"] = output.payload;
+                }
+                result
+            }
+            Err(err) => {
+                let mut result = json!({
+                    "content": [{ "type": "text", "text": err.to_string() }],
+                    "isError": true,
+                    "_meta": {
+                        "greentic/errorCode": err.code(),
+                        "greentic/error": err.report(),
+                    },
+                });
+                if let McpError::RateLimited { retry_after, .. } = &err {
+                    result["_meta"]["greentic/retryAfterMs"] =
+                        json!(retry_after.as_millis().min(u64::MAX as u128) as u64);
+                }
+                result
+            }
+        })
+    }
+}
+
+/// MCP `Tool` entry for a registered tool.
+///
+/// `ToolRef` metadata wins; otherwise the schema and description come from the
+/// component's `describe-v1` document, and the input schema falls back to any object.
+fn describe_tool(key: &str, tool: &ToolRef, document: Option<&Value>) -> Value {
+    let described = document.and_then(|document| {
+        mcp_exec::describe::describe_v1_input_schema(document, tool.version.as_deref())
+    });
+    let input_schema = tool
+        .input_schema
+        .clone()
+        .or_else(|| described.cloned())
+        .unwrap_or_else(|| json!({ "type": "object" }));
+    let description = tool.description.clone().or_else(|| {
+        [document, described]
+            .into_iter()
+            .flatten()
+            .find_map(|value| value.get("description")?.as_str())
+            .map(str::to_owned)
+    });
+
+    let mut entry = json!({ "name": key, "inputSchema": input_schema });
+    if let Some(description) = description {
+        entry["description"] = json!(description);
+    }
+    if let Some(schema) = &tool.output_schema {
+        entry["outputSchema"] = schema.clone();
+    }
+    entry
+}
+
+/// Caller trace context carried in `_meta.traceparent`/`_meta.tracestate`.
+fn trace_context(params: &Value) -> Option<TraceContext> {
+    let meta = params.get("_meta")?;
+    Some(TraceContext {
+        traceparent: meta.get("traceparent")?.as_str()?.to_string(),
+        tracestate: meta
+            .get("tracestate")
+            .and_then(Value::as_str)
+            .map(str::to_owned),
+    })
+}
+
+/// Run blocking provider calls off the async runtime.
+async fn blocking<T, F>(call: F) -> Result<T, RpcError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, McpError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(call)
+        .await
+        .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?
+        .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))
+}
+
+async fn write_line<W: AsyncWrite + Unpin>(writer: &mut W, line: &str) -> std::io::Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_map::ConflictPolicy;
+    use crate::types::ToolMapConfig;
+
+    fn server() -> McpServer {
+        let mut echo = ToolRef::new("echo", "./echo.wasm", "tool_invoke");
+        echo.description = Some("Echo the input".into());
+        echo.input_schema = Some(json!({
+            "type": "object",
+            "properties": { "message": { "type": "string" } },
+        }));
+        let config = ToolMapConfig {
+            tools: vec![echo, ToolRef::new("missing", "./missing.wasm", "run")],
+            ..Default::default()
+        };
+        McpServer::new(
+            ToolMap::from_config(&config).unwrap(),
+            WasixExecutor::new().unwrap(),
+        )
+    }
+
+    async fn request(server: &McpServer, session: &mut McpSession, request: Value) -> Value {
+        server.handle(session, request).await.expect("response")
+    }
+
+    #[tokio::test]
+    async fn negotiates_protocol_and_lists_tools() {
+        let server = server();
+        let mut session = McpSession::default();
+
+        let init = request(
+            &server,
+            &mut session,
+            json!({
+                "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": { "protocolVersion": "2025-03-26", "clientInfo": { "name": "test" } },
+            }),
+        )
+        .await;
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(init["result"]["serverInfo"]["name"], "greentic-mcp");
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert_eq!(server.handle(&mut session, notification).await, None);
+        assert!(session.initialized);
+
+        let list = request(
+            &server,
+            &mut session,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+        )
+        .await;
+        let tools = list["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["name"], "echo");
+        assert_eq!(tools[0]["description"], "Echo the input");
+        assert_eq!(
+            tools[0]["inputSchema"]["properties"]["message"]["type"],
+            "string"
+        );
+        assert_eq!(tools[1]["inputSchema"], json!({ "type": "object" }));
+    }
+
+    #[test]
+    fn prefers_tool_metadata_over_describe_output() {
+        let document = json!({
+            "name": "weather",
+            "description": "Weather lookups",
+            "versions": [
+                { "version": "1.0.0", "schema": { "type": "object", "description": "v1" } },
+                { "version": "2.0.0", "schema": { "type": "object", "required": ["city"] } },
+            ],
+        });
+        let mut tool = ToolRef::new("weather", "./weather.wasm", "run");
+
+        let entry = describe_tool("weather", &tool, Some(&document));
+        assert_eq!(entry["inputSchema"]["required"], json!(["city"]));
+        assert_eq!(entry["description"], "Weather lookups");
+
+        tool.version = Some("1.0.0".into());
+        let entry = describe_tool("weather@1.0.0", &tool, Some(&document));
+        assert_eq!(entry["inputSchema"]["description"], "v1");
+
+        tool.description = Some("Forecasts".into());
+        tool.input_schema = Some(json!({ "type": "object", "properties": {} }));
+        let entry = describe_tool("weather@1.0.0", &tool, Some(&document));
+        assert_eq!(entry["description"], "Forecasts");
+        assert_eq!(entry["inputSchema"]["properties"], json!({}));
+
+        let bare = ToolRef::new("bare", "./bare.wasm", "run");
+        let entry = describe_tool("bare", &bare, Some(&json!({ "versions": [] })));
+        assert_eq!(
+            entry,
+            json!({ "name": "bare", "inputSchema": { "type": "object" } })
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_a_catalog_of_mounted_maps() {
+        let map = |tools| {
+            let config = ToolMapConfig {
+                tools,
+                ..Default::default()
+            };
+            Arc::new(SharedToolMap::new(ToolMap::from_config(&config).unwrap()))
+        };
+        let builtin = map(vec![ToolRef::new("echo", "./echo.wasm", "run")]);
+        let tenant = map(vec![ToolRef::new("report", "./report.wasm", "run")]);
+        let catalog = ToolCatalog::new(ConflictPolicy::Error)
+            .mount(None, builtin)
+            .unwrap()
+            .mount(Some("acme"), tenant)
+            .unwrap();
+        let server = McpServer::with_catalog(Arc::new(catalog), WasixExecutor::new().unwrap());
+
+        let mut session = McpSession::default();
+        let listed = request(
+            &server,
+            &mut session,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+        )
+        .await;
+        let names = listed["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["echo", "acme/report"]);
+    }
+
+    #[tokio::test]
+    async fn serves_resources_from_providers() {
+        let kv = Arc::new(mcp_exec::MemoryKvStore::new());
+        mcp_exec::KvStore::put(kv.as_ref(), "reports", "daily.txt", "all good".into()).unwrap();
+        let server = server().with_resources(crate::resources::KvResources::new(kv, "reports"));
+        let mut session = McpSession::default();
+
+        let init = request(
+            &server,
+            &mut session,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        )
+        .await;
+        assert_eq!(init["result"]["capabilities"]["resources"], json!({}));
+
+        let list = request(
+            &server,
+            &mut session,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "resources/list" }),
+        )
+        .await;
+        assert_eq!(
+            list["result"]["resources"],
+            json!([{ "uri": "kv://reports/daily.txt", "name": "daily.txt", "mimeType": "text/plain" }])
+        );
+
+        let read = request(
+            &server,
+            &mut session,
+            json!({
+                "jsonrpc": "2.0", "id": 3, "method": "resources/read",
+                "params": { "uri": "kv://reports/daily.txt" },
+            }),
+        )
+        .await;
+        assert_eq!(read["result"]["contents"][0]["text"], "all good");
+
+        let missing = request(
+            &server,
+            &mut session,
+            json!({
+                "jsonrpc": "2.0", "id": 4, "method": "resources/read",
+                "params": { "uri": "kv://reports/nope" },
+            }),
+        )
+        .await;
+        assert_eq!(missing["error"]["code"], RESOURCE_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn reports_protocol_and_tool_errors() {
+        let server = server();
+        let mut session = McpSession::default();
+
+        let unknown_method = request(
+            &server,
+            &mut session,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "bogus" }),
+        )
+        .await;
+        assert_eq!(unknown_method["error"]["code"], METHOD_NOT_FOUND);
+
+        let unknown_tool = request(
+            &server,
+            &mut session,
+            json!({
+                "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": { "name": "nope" },
+            }),
+        )
+        .await;
+        assert_eq!(unknown_tool["error"]["code"], INVALID_PARAMS);
+
+        let failing = request(
+            &server,
+            &mut session,
+            json!({
+                "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": {
+                    "name": "missing",
+                    "arguments": {},
+                    "_meta": { "greentic/correlationId": "req-7" },
+                },
+            }),
+        )
+        .await;
+        assert_eq!(failing["id"], 3);
+        assert_eq!(failing["result"]["isError"], true);
+        assert_eq!(
+            failing["result"]["_meta"]["greentic/errorCode"],
+            "runner.failed"
+        );
+        let report = &failing["result"]["_meta"]["greentic/error"];
+        assert_eq!(report["code"], "runner.failed");
+        assert_eq!(report["retryable"], false);
+        let recent = server
+            .executor
+            .recent_invocations(&crate::history::HistoryFilter::new());
+        assert_eq!(recent[0].correlation_id.as_deref(), Some("req-7"));
+
+        let garbage = server.handle_message(&mut session, "{not json").await;
+        let garbage: Value = serde_json::from_str(&garbage.unwrap()).unwrap();
+        assert_eq!(garbage["error"]["code"], PARSE_ERROR);
+    }
+
+    #[tokio::test]
+    async fn serves_newline_delimited_stream() {
+        let server = server();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#,
+            "\n\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":"b","method":"tools/list"}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+
+        let responses = String::from_utf8(output).unwrap();
+        let responses = responses
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(
+            responses[0],
+            json!({ "jsonrpc": "2.0", "id": 1, "result": {} })
+        );
+        assert_eq!(responses[1]["id"], "b");
+    }
+
+    #[tokio::test]
+    async fn streams_progress_notifications_before_the_result() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("progress.wasm");
+        std::fs::write(&path, crate::progress::tests::progress_component()).unwrap();
+        let config = ToolMapConfig {
+            tools: vec![ToolRef::new("slow", path.to_string_lossy(), "tool-invoke")],
+            ..Default::default()
+        };
+        let server = McpServer::new(
+            ToolMap::from_config(&config).unwrap(),
+            WasixExecutor::new().unwrap(),
+        );
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"slow","_meta":{"progressToken":"p-1"}}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"slow"}}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+
+        let messages = String::from_utf8(output).unwrap();
+        let messages = messages
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(messages.len(), 3, "{messages:?}");
+        assert_eq!(
+            messages[0],
+            json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/progress",
+                "params": { "progressToken": "p-1", "progress": 50.0, "total": 100.0, "message": "halfway" },
+            })
+        );
+        assert_eq!(messages[1]["id"], 1);
+        assert_eq!(
+            messages[1]["result"]["structuredContent"],
+            json!({ "done": true })
+        );
+        // Calls without a progress token get no notifications.
+        assert_eq!(messages[2]["id"], 2);
+    }
+
+    #[tokio::test]
+    async fn cancelled_calls_get_no_response() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("spin.wasm");
+        std::fs::write(&path, crate::executor::tests::spin_component()).unwrap();
+        let config = ToolMapConfig {
+            tools: vec![ToolRef::new("spin", path.to_string_lossy(), "tool-invoke")],
+            ..Default::default()
+        };
+        let server = McpServer::new(
+            ToolMap::from_config(&config).unwrap(),
+            WasixExecutor::new().unwrap(),
+        );
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"spin"}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":7}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":8,"method":"ping"}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            server.serve(input.as_bytes(), &mut output),
+        )
+        .await
+        .expect("the spinning call should be cancelled")
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let responses = output
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            responses,
+            [json!({ "jsonrpc": "2.0", "id": 8, "result": {} })]
+        );
+    }
+
+    #[tokio::test]
+    async fn forwards_sampling_requests_to_the_client() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("sampling.wasm");
+        std::fs::write(&path, crate::sampling::tests::sampling_component()).unwrap();
+        let mut tool = ToolRef::new("summarize", path.to_string_lossy(), "tool-invoke");
+        tool.allow_sampling = true;
+        let config = ToolMapConfig {
+            tools: vec![tool],
+            ..Default::default()
+        };
+        let server = McpServer::new(
+            ToolMap::from_config(&config).unwrap(),
+            WasixExecutor::new().unwrap(),
+        );
+        let (client, server_io) = tokio::io::duplex(4096);
+        let (server_read, server_write) = tokio::io::split(server_io);
+        let serving = server.serve(BufReader::new(server_read), server_write);
+        let client = async {
+            let (read, mut write) = tokio::io::split(client);
+            let mut lines = BufReader::new(read).lines();
+            let mut next = async || -> Value {
+                serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap()
+            };
+            write_line(
+                &mut write,
+                r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18","capabilities":{"sampling":{}}}}"#,
+            )
+            .await
+            .unwrap();
+            assert_eq!(next().await["id"], 1);
+            write_line(
+                &mut write,
+                r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"summarize"}}"#,
+            )
+            .await
+            .unwrap();
+
+            let request = next().await;
+            assert_eq!(request["method"], "sampling/createMessage");
+            assert_eq!(request["params"], json!({ "maxTokens": 5 }));
+            let reply = json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "role": "assistant", "content": { "type": "text", "text": "short" } },
+            });
+            write_line(&mut write, &reply.to_string()).await.unwrap();
+
+            let response = next().await;
+            assert_eq!(response["id"], 2);
+            assert_eq!(
+                response["result"]["structuredContent"]["content"]["text"],
+                "short"
+            );
+            write.shutdown().await.unwrap();
+        };
+        let (served, ()) = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            tokio::join!(serving, client)
+        })
+        .await
+        .expect("the sampling round trip should complete");
+        served.unwrap();
+    }
+}
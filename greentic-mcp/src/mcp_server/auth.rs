@@ -0,0 +1,204 @@
+//! Bearer-token authentication for network transports of [`McpServer`](super::McpServer).
+//!
+//! An [`McpAuth`] turns the `Authorization` header of an incoming connection into an
+//! [`AccessGrant`], which limits the tools the session can list and call. Tokens are
+//! checked by a [`TokenVerifier`]: [`StaticTokens`] for pre-shared secrets, or a
+//! closure validating OAuth access tokens (e.g. JWTs issued by an authorization
+//! server).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use crate::tool_map::LabelSelector;
+use crate::types::{McpError, ToolRef};
+
+/// Tools a caller may see and invoke.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessGrant {
+    /// Who the token was issued to, for logs.
+    pub subject: String,
+    /// Namespaces the caller may use; empty allows every namespace and
+    /// un-namespaced tools.
+    pub namespaces: Vec<String>,
+    /// Labels the tools must carry.
+    pub selector: LabelSelector,
+}
+
+impl AccessGrant {
+    /// Grant access to every tool.
+    pub fn new(subject: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Restrict the grant to `namespace` (in addition to namespaces added before).
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespaces.push(namespace.into());
+        self
+    }
+
+    /// Restrict the grant to tools matching `selector`.
+    pub fn selector(mut self, selector: LabelSelector) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Whether the grant covers `tool`.
+    pub fn allows(&self, tool: &ToolRef) -> bool {
+        let namespace_allowed = self.namespaces.is_empty()
+            || tool
+                .namespace
+                .as_ref()
+                .is_some_and(|namespace| self.namespaces.contains(namespace));
+        namespace_allowed && self.selector.matches(tool)
+    }
+}
+
+/// Checks bearer tokens presented by clients.
+///
+/// Called while the connection is being upgraded, so implementations should not block
+/// for long; cache introspection results rather than calling out on every connection.
+pub trait TokenVerifier: Send + Sync {
+    /// The grant for `token`, or `None` if it is unknown, expired, or revoked.
+    fn verify(&self, token: &str) -> Option<AccessGrant>;
+}
+
+impl<F> TokenVerifier for F
+where
+    F: Fn(&str) -> Option<AccessGrant> + Send + Sync,
+{
+    fn verify(&self, token: &str) -> Option<AccessGrant> {
+        self(token)
+    }
+}
+
+/// Fixed set of pre-shared bearer tokens.
+///
+/// Only SHA-256 digests of the tokens are kept, so lookups do not compare secrets
+/// byte by byte.
+#[derive(Clone, Debug, Default)]
+pub struct StaticTokens {
+    grants: HashMap<[u8; 32], AccessGrant>,
+}
+
+impl StaticTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept `token`, granting `grant`.
+    pub fn token(mut self, token: impl AsRef<str>, grant: AccessGrant) -> Self {
+        self.grants.insert(digest(token.as_ref()), grant);
+        self
+    }
+}
+
+impl TokenVerifier for StaticTokens {
+    fn verify(&self, token: &str) -> Option<AccessGrant> {
+        self.grants.get(&digest(token)).cloned()
+    }
+}
+
+fn digest(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// Authentication required by [`McpServer::with_auth`](super::McpServer::with_auth).
+#[derive(Clone)]
+pub struct McpAuth {
+    verifier: Arc<dyn TokenVerifier>,
+    resource_metadata: Option<String>,
+}
+
+impl McpAuth {
+    pub fn new(verifier: impl TokenVerifier + 'static) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+            resource_metadata: None,
+        }
+    }
+
+    /// Advertise the OAuth protected resource metadata document at `url` in
+    /// challenges, so clients can discover the authorization server.
+    pub fn resource_metadata(mut self, url: impl Into<String>) -> Self {
+        self.resource_metadata = Some(url.into());
+        self
+    }
+
+    /// Grant for a request carrying `authorization` (the `Authorization` header value).
+    pub fn authorize(&self, authorization: Option<&str>) -> Result<AccessGrant, McpError> {
+        let authorization =
+            authorization.ok_or_else(|| McpError::Unauthorized("missing bearer token".into()))?;
+        let token = authorization
+            .split_once(' ')
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token.trim())
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| McpError::Unauthorized("expected a bearer token".into()))?;
+        self.verifier
+            .verify(token)
+            .ok_or_else(|| McpError::Unauthorized("invalid or expired token".into()))
+    }
+
+    /// `WWW-Authenticate` header value for rejected requests.
+    pub fn challenge(&self) -> String {
+        match &self.resource_metadata {
+            Some(url) => format!(r#"Bearer realm="mcp", resource_metadata="{url}""#),
+            None => r#"Bearer realm="mcp""#.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Debug for McpAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpAuth")
+            .field("resource_metadata", &self.resource_metadata)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorizes_known_bearer_tokens() {
+        let auth = McpAuth::new(
+            StaticTokens::new().token("s3cret", AccessGrant::new("ci").namespace("crm")),
+        );
+        let grant = auth.authorize(Some("Bearer s3cret")).unwrap();
+        assert_eq!(grant.subject, "ci");
+        assert!(auth.authorize(Some("bearer  s3cret ")).is_ok());
+
+        for header in [
+            None,
+            Some("Basic czNjcmV0"),
+            Some("Bearer "),
+            Some("Bearer nope"),
+        ] {
+            assert!(matches!(
+                auth.authorize(header),
+                Err(McpError::Unauthorized(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn grants_limit_namespaces_and_labels() {
+        let mut tool = ToolRef::new("create_lead", "./crm.wasm", "run");
+        let grant = AccessGrant::new("agent").namespace("crm");
+        assert!(!grant.allows(&tool));
+        tool.namespace = Some("crm".into());
+        assert!(grant.allows(&tool));
+
+        let grant = grant.selector("audience=agent".parse().unwrap());
+        assert!(!grant.allows(&tool));
+        tool.labels.insert("audience".into(), "agent".into());
+        assert!(grant.allows(&tool));
+        assert!(AccessGrant::new("admin").allows(&tool));
+    }
+}
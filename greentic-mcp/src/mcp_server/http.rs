@@ -0,0 +1,390 @@
+//! Streamable HTTP transport for [`McpServer`]: one JSON-RPC message per `POST`.
+//!
+//! `initialize` starts a session whose id is returned in the `Mcp-Session-Id`
+//! header; later requests must carry it, and `DELETE` ends the session. Requests
+//! are answered with a JSON body and notifications with `202 Accepted`. The server
+//! opens no event streams, so `GET` is refused and messages the server would send
+//! on its own, such as progress notifications, are dropped.
+//!
+//! A session belongs to the grant that started it: other callers get `404 Not
+//! Found` for its id. Sessions idle for 30 minutes are dropped, at most 10,000 are
+//! kept, and request bodies over 4 MiB are refused.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use tokio::net::TcpListener;
+
+use super::{AccessGrant, InFlightRequests, McpServer, McpSession};
+use crate::types::McpError;
+
+/// Header carrying the session id.
+pub const MCP_SESSION_HEADER: &str = "mcp-session-id";
+
+/// Largest request body accepted.
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Sessions without a request for this long are dropped.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Most sessions kept at once; `initialize` is answered with `503 Service
+/// Unavailable` beyond that.
+const MAX_SESSIONS: usize = 10_000;
+
+/// Sessions of one HTTP endpoint, by id.
+#[derive(Default)]
+struct HttpSessions(Mutex<HashMap<String, HttpSession>>);
+
+#[derive(Clone)]
+struct HttpSession {
+    state: Arc<tokio::sync::Mutex<McpSession>>,
+    in_flight: InFlightRequests,
+    /// Grant of the caller that started the session; `None` without auth.
+    owner: Option<AccessGrant>,
+    last_used: Instant,
+}
+
+impl HttpSession {
+    fn usable_by(&self, grant: &Option<AccessGrant>) -> bool {
+        self.owner == *grant && self.last_used.elapsed() < SESSION_IDLE_TIMEOUT
+    }
+}
+
+impl HttpSessions {
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, HttpSession>> {
+        self.0.lock().expect("HTTP sessions poisoned")
+    }
+
+    /// Session `id` if `grant` owns it, marking it used.
+    fn get(&self, id: &str, grant: &Option<AccessGrant>) -> Option<HttpSession> {
+        let mut sessions = self.sessions();
+        let session = sessions
+            .get_mut(id)
+            .filter(|session| session.usable_by(grant))?;
+        session.last_used = Instant::now();
+        Some(session.clone())
+    }
+
+    /// End session `id` if `grant` owns it.
+    fn remove(&self, id: &str, grant: &Option<AccessGrant>) -> bool {
+        let mut sessions = self.sessions();
+        if !sessions
+            .get(id)
+            .is_some_and(|session| session.usable_by(grant))
+        {
+            return false;
+        }
+        sessions.remove(id).is_some()
+    }
+
+    /// Start a session for `grant`, returning its id, or `None` when full.
+    fn start(&self, grant: Option<AccessGrant>) -> Option<(String, HttpSession)> {
+        let mut sessions = self.sessions();
+        sessions.retain(|_, session| session.last_used.elapsed() < SESSION_IDLE_TIMEOUT);
+        if sessions.len() >= MAX_SESSIONS {
+            return None;
+        }
+        let state = McpSession {
+            grant: grant.clone(),
+            ..McpSession::default()
+        };
+        let session = HttpSession {
+            in_flight: state.in_flight(),
+            state: Arc::new(tokio::sync::Mutex::new(state)),
+            owner: grant,
+            last_used: Instant::now(),
+        };
+        let id = hex::encode(rand::random::<[u8; 16]>());
+        sessions.insert(id.clone(), session.clone());
+        Some((id, session))
+    }
+}
+
+impl McpServer {
+    /// Accept HTTP connections on `listener` until accepting fails, serving MCP
+    /// requests posted to any path.
+    ///
+    /// With [`with_auth`](Self::with_auth), requests without a valid bearer token
+    /// are refused with `401 Unauthorized`.
+    pub async fn serve_http(&self, listener: TcpListener) -> Result<(), McpError> {
+        let sessions = Arc::new(HttpSessions::default());
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = self.clone();
+            let sessions = sessions.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let server = server.clone();
+                    let sessions = sessions.clone();
+                    async move { Ok::<_, Infallible>(server.handle_http(&sessions, request).await) }
+                });
+                let connection =
+                    http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+                if let Err(err) = connection.await {
+                    tracing::debug!(%peer, %err, "MCP HTTP connection failed");
+                }
+            });
+        }
+    }
+
+    async fn handle_http(
+        &self,
+        sessions: &HttpSessions,
+        request: Request<Incoming>,
+    ) -> Response<Full<Bytes>> {
+        let authorization = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        let grant = match self.authorize(authorization) {
+            Ok(grant) => grant,
+            Err(err) => return self.http_unauthorized(&err),
+        };
+        let session_id = request
+            .headers()
+            .get(MCP_SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        match *request.method() {
+            Method::POST => {}
+            Method::DELETE => {
+                let removed = session_id.is_some_and(|id| sessions.remove(&id, &grant));
+                return reply(if removed {
+                    StatusCode::OK
+                } else {
+                    StatusCode::NOT_FOUND
+                });
+            }
+            _ => {
+                let mut response = reply(StatusCode::METHOD_NOT_ALLOWED);
+                response
+                    .headers_mut()
+                    .insert(header::ALLOW, HeaderValue::from_static("POST, DELETE"));
+                return response;
+            }
+        }
+        let body = match Limited::new(request.into_body(), MAX_BODY_BYTES)
+            .collect()
+            .await
+        {
+            Ok(body) => body.to_bytes(),
+            Err(err) if err.is::<LengthLimitError>() => {
+                return reply(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            Err(_) => return reply(StatusCode::BAD_REQUEST),
+        };
+        let message = String::from_utf8_lossy(&body).into_owned();
+        let parsed = serde_json::from_str::<Value>(&message).ok();
+        let method = parsed
+            .as_ref()
+            .and_then(|request| request.get("method"))
+            .and_then(Value::as_str);
+
+        let (id, session) = match session_id {
+            Some(id) => match sessions.get(&id, &grant) {
+                Some(session) => (id, session),
+                None => return reply(StatusCode::NOT_FOUND),
+            },
+            None if method == Some("initialize") => match sessions.start(grant) {
+                Some(started) => started,
+                None => return reply(StatusCode::SERVICE_UNAVAILABLE),
+            },
+            None => return reply(StatusCode::BAD_REQUEST),
+        };
+        // Cancellations and responses reach requests still running on the session.
+        if session.in_flight.intercept(&message) {
+            return reply(StatusCode::ACCEPTED);
+        }
+        // Only `initialize` and notifications change the session. Other requests run
+        // on a copy, so a slow `tools/call` does not hold up the rest of the session.
+        let is_request = parsed
+            .as_ref()
+            .is_some_and(|request| request.get("id").is_some());
+        let response = if method == Some("initialize") || !is_request {
+            let mut state = session.state.lock().await;
+            self.handle_message(&mut state, &message).await
+        } else {
+            let mut state = session.state.lock().await.clone();
+            self.handle_message(&mut state, &message).await
+        };
+        let mut response = match response {
+            Some(body) => {
+                let mut response = Response::new(Full::new(Bytes::from(body)));
+                response.headers_mut().insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+                response
+            }
+            None => reply(StatusCode::ACCEPTED),
+        };
+        if let Ok(id) = HeaderValue::from_str(&id) {
+            response.headers_mut().insert(MCP_SESSION_HEADER, id);
+        }
+        response
+    }
+
+    /// `401` answer to a request without a valid bearer token.
+    fn http_unauthorized(&self, err: &McpError) -> Response<Full<Bytes>> {
+        let mut response = Response::new(Full::new(Bytes::from(err.to_string())));
+        *response.status_mut() = StatusCode::UNAUTHORIZED;
+        if let Some(challenge) = self
+            .auth
+            .as_ref()
+            .and_then(|auth| HeaderValue::from_str(&auth.challenge()).ok())
+        {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        response
+    }
+}
+
+fn reply(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::default());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::WasixExecutor;
+    use crate::mcp_server::{McpAuth, StaticTokens};
+    use crate::tool_map::ToolMap;
+    use crate::types::ToolMapConfig;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn answers_requests_within_a_session() {
+        let map = ToolMap::from_config(&ToolMapConfig::default()).unwrap();
+        let server = McpServer::new(map, WasixExecutor::new().unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { server.serve_http(listener).await });
+        let client = reqwest::Client::new();
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 7, "method": "ping" });
+        let response = client.post(&url).json(&ping).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let initialize = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2025-06-18", "capabilities": {} },
+        });
+        let response = client.post(&url).json(&initialize).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let session = response.headers()[MCP_SESSION_HEADER].clone();
+
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = client
+            .post(&url)
+            .header(MCP_SESSION_HEADER, session.clone())
+            .json(&initialized)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let response = client
+            .post(&url)
+            .header(MCP_SESSION_HEADER, session.clone())
+            .json(&ping)
+            .send()
+            .await
+            .unwrap();
+        let reply: Value = response.json().await.unwrap();
+        assert_eq!(reply, json!({ "jsonrpc": "2.0", "id": 7, "result": {} }));
+
+        let response = client
+            .delete(&url)
+            .header(MCP_SESSION_HEADER, session.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .post(&url)
+            .header(MCP_SESSION_HEADER, session)
+            .json(&ping)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn sessions_belong_to_the_caller_that_started_them() {
+        let map = ToolMap::from_config(&ToolMapConfig::default()).unwrap();
+        let tokens = StaticTokens::new()
+            .token("alpha", AccessGrant::new("alpha"))
+            .token("beta", AccessGrant::new("beta"));
+        let server =
+            McpServer::new(map, WasixExecutor::new().unwrap()).with_auth(McpAuth::new(tokens));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { server.serve_http(listener).await });
+        let client = reqwest::Client::new();
+
+        let initialize = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2025-06-18", "capabilities": {} },
+        });
+        let response = client
+            .post(&url)
+            .bearer_auth("alpha")
+            .json(&initialize)
+            .send()
+            .await
+            .unwrap();
+        let session = response.headers()[MCP_SESSION_HEADER].clone();
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 7, "method": "ping" });
+        let response = client
+            .post(&url)
+            .bearer_auth("beta")
+            .header(MCP_SESSION_HEADER, session.clone())
+            .json(&ping)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = client
+            .delete(&url)
+            .bearer_auth("beta")
+            .header(MCP_SESSION_HEADER, session.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = client
+            .post(&url)
+            .bearer_auth("alpha")
+            .header(MCP_SESSION_HEADER, session.clone())
+            .json(&ping)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = client
+            .post(&url)
+            .bearer_auth("alpha")
+            .header(MCP_SESSION_HEADER, session)
+            .body(vec![b' '; MAX_BODY_BYTES + 1])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
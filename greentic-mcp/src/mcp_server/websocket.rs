@@ -0,0 +1,249 @@
+//! WebSocket transport for [`McpServer`]: one JSON-RPC message per text frame.
+
+use std::collections::VecDeque;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{StatusCode, header};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use super::{AccessGrant, McpServer, McpSession};
+use crate::types::McpError;
+
+impl McpServer {
+    /// Accept WebSocket connections on `listener` until accepting fails, serving each
+    /// connection as its own session.
+    ///
+    /// With [`with_auth`](Self::with_auth), upgrade requests without a valid bearer
+    /// token are refused with `401 Unauthorized`.
+    pub async fn serve_websocket(&self, listener: TcpListener) -> Result<(), McpError> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                let mut grant = None;
+                let authorize = |request: &Request, response: Response| {
+                    let authorization = request
+                        .headers()
+                        .get(header::AUTHORIZATION)
+                        .and_then(|value| value.to_str().ok());
+                    grant = server
+                        .authorize(authorization)
+                        .map_err(|err| server.unauthorized(&err))?;
+                    Ok(response)
+                };
+                let result = match tokio_tungstenite::accept_hdr_async(stream, authorize).await {
+                    Ok(ws) => server.serve_websocket_stream_as(ws, grant).await,
+                    Err(err) => Err(ws_error(err)),
+                };
+                if let Err(err) = result {
+                    tracing::debug!(%peer, %err, "MCP WebSocket connection failed");
+                }
+            });
+        }
+    }
+
+    /// Serve one upgraded WebSocket connection (e.g. handed over by a gateway) until
+    /// the client closes it. The connection may use every tool.
+    pub async fn serve_websocket_stream<S>(&self, ws: WebSocketStream<S>) -> Result<(), McpError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.serve_websocket_stream_as(ws, None).await
+    }
+
+    /// Like [`serve_websocket_stream`](Self::serve_websocket_stream), limited to the
+    /// tools in `grant` (see [`authorize`](Self::authorize)).
+    pub async fn serve_websocket_stream_as<S>(
+        &self,
+        mut ws: WebSocketStream<S>,
+        grant: Option<AccessGrant>,
+    ) -> Result<(), McpError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (mut session, mut notifications) = McpSession::with_notifications();
+        session.grant = grant;
+        let in_flight = session.in_flight();
+        // Messages received while a request was being handled, processed after it.
+        let mut pending = VecDeque::new();
+        let mut closed = false;
+        loop {
+            let text = match pending.pop_front() {
+                Some(text) => text,
+                None if closed => break,
+                None => match ws.next().await {
+                    Some(message) => match frame_text(message.map_err(ws_error)?) {
+                        Some(text) => text,
+                        None => continue,
+                    },
+                    None => break,
+                },
+            };
+            let handled = self.handle_message(&mut session, &text);
+            tokio::pin!(handled);
+            let response = loop {
+                tokio::select! {
+                    // Poll the request first so it is registered before a cancellation is read.
+                    biased;
+                    response = &mut handled => break response,
+                    Some(notification) = notifications.recv() => {
+                        let text = notification.to_string();
+                        ws.send(Message::text(text)).await.map_err(ws_error)?;
+                    }
+                    // Keep reading so cancellations and responses reach the request being handled.
+                    message = ws.next(), if !closed => match message {
+                        Some(message) => {
+                            if let Some(text) = frame_text(message.map_err(ws_error)?)
+                                && !in_flight.intercept(&text)
+                            {
+                                pending.push_back(text);
+                            }
+                        }
+                        None => closed = true,
+                    },
+                }
+            };
+            while let Ok(notification) = notifications.try_recv() {
+                let text = notification.to_string();
+                ws.send(Message::text(text)).await.map_err(ws_error)?;
+            }
+            if let Some(response) = response {
+                ws.send(Message::text(response)).await.map_err(ws_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `401` answer to an upgrade request without a valid bearer token.
+    fn unauthorized(&self, err: &McpError) -> ErrorResponse {
+        let mut response = ErrorResponse::new(Some(err.to_string()));
+        *response.status_mut() = StatusCode::UNAUTHORIZED;
+        if let Some(challenge) = self
+            .auth
+            .as_ref()
+            .and_then(|auth| auth.challenge().parse().ok())
+        {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        response
+    }
+}
+
+/// JSON-RPC payload of a data frame. Pings and the closing handshake are answered by
+/// tungstenite itself.
+fn frame_text(message: Message) -> Option<String> {
+    match message {
+        Message::Text(text) => Some(text.to_string()),
+        Message::Binary(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+        _ => None,
+    }
+}
+
+fn ws_error(err: tungstenite::Error) -> McpError {
+    McpError::Io(std::io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::WasixExecutor;
+    use crate::mcp_server::{McpAuth, StaticTokens};
+    use crate::tool_map::ToolMap;
+    use crate::types::{ToolMapConfig, ToolRef};
+    use serde_json::{Value, json};
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    #[tokio::test]
+    async fn answers_requests_over_websocket() {
+        let map = ToolMap::from_config(&ToolMapConfig::default()).unwrap();
+        let server = McpServer::new(map, WasixExecutor::new().unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { server.serve_websocket(listener).await });
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+            .await
+            .unwrap();
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        ws.send(Message::text(notification.to_string()))
+            .await
+            .unwrap();
+        let ping = json!({ "jsonrpc": "2.0", "id": 7, "method": "ping" });
+        ws.send(Message::text(ping.to_string())).await.unwrap();
+
+        let reply = ws.next().await.unwrap().unwrap();
+        let reply: Value = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert_eq!(reply, json!({ "jsonrpc": "2.0", "id": 7, "result": {} }));
+        ws.close(None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn requires_bearer_tokens_and_limits_tools() {
+        let crm = ToolRef {
+            namespace: Some("crm".into()),
+            ..ToolRef::new("create_lead", "./crm.wasm", "run")
+        };
+        let config = ToolMapConfig {
+            tools: vec![crm, ToolRef::new("deploy", "./deploy.wasm", "run")],
+            ..Default::default()
+        };
+        let auth = McpAuth::new(
+            StaticTokens::new().token("crm-token", AccessGrant::new("crm-bot").namespace("crm")),
+        )
+        .resource_metadata("https://tools.example/.well-known/oauth-protected-resource");
+        let server = McpServer::new(
+            ToolMap::from_config(&config).unwrap(),
+            WasixExecutor::new().unwrap(),
+        )
+        .with_auth(auth);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { server.serve_websocket(listener).await });
+
+        let err = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+            .await
+            .unwrap_err();
+        let tungstenite::Error::Http(response) = err else {
+            panic!("expected an HTTP rejection, got {err:?}");
+        };
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(
+            response.headers()[header::WWW_AUTHENTICATE]
+                .to_str()
+                .unwrap()
+                .contains("resource_metadata=")
+        );
+
+        let mut request = format!("ws://{addr}").into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert(header::AUTHORIZATION, "Bearer crm-token".parse().unwrap());
+        let (mut ws, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+        let list = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" });
+        ws.send(Message::text(list.to_string())).await.unwrap();
+        let reply = ws.next().await.unwrap().unwrap();
+        let reply: Value = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        let names = reply["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["crm/create_lead"]);
+
+        let call = json!({
+            "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": { "name": "deploy" },
+        });
+        ws.send(Message::text(call.to_string())).await.unwrap();
+        let reply = ws.next().await.unwrap().unwrap();
+        let reply: Value = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert_eq!(reply["error"]["message"], "tool `deploy` not found");
+        ws.close(None).await.unwrap();
+    }
+}
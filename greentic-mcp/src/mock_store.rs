@@ -0,0 +1,118 @@
+//! In-memory tool store for tests, with the `test-util` feature.
+//!
+//! [`MockToolStore`] registers components by name, either as Wasm bytes or as
+//! canned responses per action, and hands out an [`ExecConfig`] that resolves
+//! them. Crates embedding `greentic-mcp` can test their flows through
+//! [`exec_with_retries`](crate::exec_with_retries) without fixture files or Wasm
+//! builds.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mcp_exec::{ExecConfig, RuntimePolicy, ToolStore, VerifyPolicy};
+use serde_json::{Map, Value, json};
+
+/// Components registered by name, held in memory as a [`ToolStore::Memory`].
+#[derive(Clone, Default)]
+pub struct MockToolStore {
+    components: HashMap<String, Arc<[u8]>>,
+}
+
+impl std::fmt::Debug for MockToolStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("MockToolStore").field(&self.store()).finish()
+    }
+}
+
+impl MockToolStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the component `name` with the Wasm `bytes`.
+    pub fn with_component(mut self, name: &str, bytes: impl AsRef<[u8]>) -> Self {
+        assert!(!name.is_empty(), "invalid mock component name `{name}`");
+        self.components
+            .insert(name.to_string(), Arc::from(bytes.as_ref()));
+        self
+    }
+
+    /// Register the component `name` answering each action with a fixed value.
+    /// Other actions fail with `runner.action_not_found`.
+    pub fn with_responses<A>(
+        self,
+        name: &str,
+        responses: impl IntoIterator<Item = (A, Value)>,
+    ) -> Self
+    where
+        A: Into<String>,
+    {
+        let responses: Map<String, Value> = responses
+            .into_iter()
+            .map(|(action, value)| (action.into(), value))
+            .collect();
+        let mock = json!({ "_mock_mcp_exec": true, "responses": responses });
+        self.with_component(name, mock.to_string())
+    }
+
+    /// Store resolving the registered components.
+    pub fn store(&self) -> ToolStore {
+        ToolStore::Memory(self.components.clone())
+    }
+
+    /// Config resolving the registered components, with the default runtime policy,
+    /// no digest requirements, and HTTP disabled.
+    pub fn exec_config(&self) -> ExecConfig {
+        ExecConfig {
+            store: self.store(),
+            security: VerifyPolicy {
+                allow_unverified: true,
+                ..VerifyPolicy::default()
+            },
+            runtime: RuntimePolicy::default(),
+            tenant_runtime: Default::default(),
+            http_enabled: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mcp_exec::{ErrorCode, ErrorKind, ExecRequest};
+
+    use super::*;
+    use crate::types::McpError;
+
+    #[tokio::test]
+    async fn serves_canned_responses() {
+        let store =
+            MockToolStore::new().with_responses("weather", [("forecast", json!({ "temp": 21 }))]);
+        let request = |action: &str| ExecRequest {
+            component: "weather".into(),
+            action: action.into(),
+            args: json!({}),
+            tenant: None,
+            correlation_id: None,
+            identity: None,
+        };
+
+        let output = crate::exec_with_retries(request("forecast"), &store.exec_config())
+            .await
+            .unwrap();
+        assert_eq!(output, json!({ "temp": 21 }));
+        let err = crate::exec_with_retries(request("alerts"), &store.exec_config())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RunnerActionNotFound);
+
+        let err = McpError::from_exec(err, Some(2));
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(matches!(
+            &err,
+            McpError::Exec { component, action: Some(action), attempt: Some(2), .. }
+                if component == "weather" && action == "alerts"
+        ));
+        assert_eq!(err.code(), ErrorCode::RunnerActionNotFound);
+        assert!(err.to_string().ends_with("(attempt 2)"), "{err}");
+    }
+}
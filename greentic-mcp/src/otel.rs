@@ -0,0 +1,162 @@
+//! OpenTelemetry export of invocation spans (`otel` feature).
+//!
+//! Invocations are traced with `tracing` spans: `invoke` per call, `attempt` per retry,
+//! and `resolve`, `verify`, `compile`, `instantiate`, and `call` for the phases of an
+//! attempt. [`otlp_layer`] turns them into OpenTelemetry spans exported over OTLP/HTTP;
+//! add it to the host's `tracing_subscriber` registry. Callers join an existing trace by
+//! passing a W3C [`TraceContext`](crate::executor::TraceContext) in
+//! [`InvokeOptions`](crate::executor::InvokeOptions).
+
+use std::collections::HashMap;
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::executor::TraceContext;
+use crate::types::McpError;
+
+/// Flushes and shuts down the exporter when dropped; keep it alive as long as spans
+/// should be exported.
+#[derive(Debug)]
+pub struct OtelGuard {
+    provider: SdkTracerProvider,
+}
+
+impl OtelGuard {
+    /// The provider backing the layer, e.g. to force a flush.
+    pub fn provider(&self) -> &SdkTracerProvider {
+        &self.provider
+    }
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.provider.shutdown() {
+            tracing::debug!(%err, "OpenTelemetry shutdown failed");
+        }
+    }
+}
+
+/// Layer exporting spans over OTLP/HTTP as `service_name`.
+///
+/// `endpoint` defaults to `OTEL_EXPORTER_OTLP_ENDPOINT`, or `http://localhost:4318`.
+pub fn otlp_layer<S>(
+    service_name: &str,
+    endpoint: Option<&str>,
+) -> Result<(OpenTelemetryLayer<S, SdkTracer>, OtelGuard), McpError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let mut exporter = SpanExporter::builder().with_http();
+    if let Some(endpoint) = endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    let exporter = exporter
+        .build()
+        .map_err(|err| McpError::Internal(format!("failed to build OTLP exporter: {err}")))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build();
+    Ok(layer(provider))
+}
+
+/// Layer exporting spans through an already configured `provider`.
+pub fn layer<S>(provider: SdkTracerProvider) -> (OpenTelemetryLayer<S, SdkTracer>, OtelGuard)
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    (
+        tracing_opentelemetry::layer().with_tracer(tracer),
+        OtelGuard { provider },
+    )
+}
+
+/// Make the caller's trace the parent of `span`. Malformed contexts are ignored.
+pub(crate) fn set_parent(span: &tracing::Span, context: &TraceContext) {
+    let mut carrier = HashMap::from([("traceparent".to_string(), context.traceparent.clone())]);
+    if let Some(tracestate) = &context.tracestate {
+        carrier.insert("tracestate".to_string(), tracestate.clone());
+    }
+    let parent = TraceContextPropagator::new().extract(&carrier);
+    if let Err(err) = span.set_parent(parent) {
+        tracing::debug!(%err, "could not attach the caller's trace context");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use opentelemetry::trace::TraceId;
+    use opentelemetry_sdk::error::OTelSdkResult;
+    use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+    use serde_json::json;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::executor::{InvokeOptions, WasixExecutor};
+    use crate::types::{ToolInput, ToolRef};
+
+    #[derive(Clone, Debug, Default)]
+    struct Collect(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanExporter for Collect {
+        async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+            self.0.lock().unwrap().extend(batch);
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn exports_invocation_spans_under_the_callers_trace() {
+        let spans = Collect::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(spans.clone())
+            .build();
+        let (layer, _guard) = layer(provider);
+        let _default = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+        let tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+        let call = InvokeOptions {
+            trace_context: Some(TraceContext {
+                traceparent: "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".into(),
+                tracestate: None,
+            }),
+            ..InvokeOptions::default()
+        };
+        let input = ToolInput::new(json!({}));
+        let result = WasixExecutor::new()
+            .unwrap()
+            .invoke_with(&tool, &input, call)
+            .await;
+        assert!(result.is_err());
+
+        let spans = spans.0.lock().unwrap();
+        let names = spans
+            .iter()
+            .map(|span| span.name.as_ref())
+            .collect::<Vec<_>>();
+        for expected in ["invoke", "attempt", "resolve"] {
+            assert!(names.contains(&expected), "{names:?}");
+        }
+        let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+        assert!(
+            spans
+                .iter()
+                .all(|span| span.span_context.trace_id() == trace_id)
+        );
+    }
+}
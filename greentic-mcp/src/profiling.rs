@@ -0,0 +1,204 @@
+//! Guest profiles of hot invocations, with the `profiling` feature.
+//!
+//! [`WasixExecutor::with_hot_call_profiling`](crate::executor::WasixExecutor::with_hot_call_profiling)
+//! starts Wasmtime's guest profiler for every attempt still running after a
+//! threshold, and hands the profile to a [`ProfileSink`] when the attempt ends. Fast
+//! attempts are never profiled. Profiles use the Firefox processed profile format;
+//! open them at <https://profiler.firefox.com/>.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use wasmtime::component::Component;
+use wasmtime::{AsContext, GuestProfiler};
+
+/// Profile of one hot attempt.
+#[derive(Clone, Debug)]
+pub struct GuestProfile {
+    pub tool: String,
+    /// How long the attempt ran; samples only cover the time after the threshold.
+    pub duration: Duration,
+    /// The profile, as Firefox processed profile JSON.
+    pub data: Vec<u8>,
+}
+
+/// Destination of [`GuestProfile`]s.
+///
+/// Called on the blocking thread that ran the tool.
+#[derive(Clone)]
+pub struct ProfileSink(Arc<dyn Fn(GuestProfile) + Send + Sync>);
+
+impl ProfileSink {
+    pub fn new<F>(write: F) -> Self
+    where
+        F: Fn(GuestProfile) + Send + Sync + 'static,
+    {
+        Self(Arc::new(write))
+    }
+
+    /// Write each profile to `dir` as `<tool>-<unix millis>.json`. Failures are logged.
+    pub fn directory(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self::new(move |profile| {
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis());
+            let name: String = profile
+                .tool
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let path = dir.join(format!("{name}-{millis}.json"));
+            if let Err(err) = std::fs::write(&path, &profile.data) {
+                tracing::warn!(%err, path = %path.display(), "failed to write guest profile");
+            }
+        })
+    }
+
+    pub fn write(&self, profile: GuestProfile) {
+        (self.0)(profile)
+    }
+}
+
+impl std::fmt::Debug for ProfileSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProfileSink(..)")
+    }
+}
+
+/// When to profile, and where profiles go.
+#[derive(Clone, Debug)]
+pub(crate) struct HotCallProfiling {
+    pub(crate) threshold: Duration,
+    pub(crate) sink: ProfileSink,
+}
+
+/// Profiler of one attempt, sampled on every epoch tick once the threshold passed.
+pub(crate) struct HotCallProfiler {
+    settings: HotCallProfiling,
+    tool: String,
+    interval: Duration,
+    started: Instant,
+    last_sample: Instant,
+    component: Option<Component>,
+    profiler: Option<GuestProfiler>,
+}
+
+impl HotCallProfiler {
+    pub(crate) fn new(settings: HotCallProfiling, tool: &str, interval: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            settings,
+            tool: tool.to_string(),
+            interval,
+            started: now,
+            last_sample: now,
+            component: None,
+            profiler: None,
+        }
+    }
+
+    /// Set the component once it is compiled; nothing is sampled before.
+    pub(crate) fn attach(&mut self, component: Component) {
+        self.component = Some(component);
+    }
+
+    /// Take a sample, starting the profiler if the attempt just became hot.
+    pub(crate) fn tick(&mut self, store: impl AsContext) {
+        let now = Instant::now();
+        if self.profiler.is_none() {
+            let Some(component) = &self.component else {
+                return;
+            };
+            if now.duration_since(self.started) < self.settings.threshold {
+                return;
+            }
+            let engine = store.as_context().engine().clone();
+            match GuestProfiler::new_component(
+                &engine,
+                &self.tool,
+                self.interval,
+                component.clone(),
+                [],
+            ) {
+                Ok(profiler) => self.profiler = Some(profiler),
+                Err(err) => {
+                    tracing::warn!(%err, tool = %self.tool, "failed to start guest profiler");
+                    // Do not retry on every tick.
+                    self.component = None;
+                    return;
+                }
+            }
+            self.last_sample = now;
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.sample(store, now.duration_since(self.last_sample));
+            self.last_sample = now;
+        }
+    }
+
+    /// Hand the profile to the sink, if the attempt was hot.
+    pub(crate) fn finish(self) {
+        let Some(profiler) = self.profiler else {
+            return;
+        };
+        let mut data = Vec::new();
+        match profiler.finish(&mut data) {
+            Ok(()) => self.settings.sink.write(GuestProfile {
+                tool: self.tool,
+                duration: self.started.elapsed(),
+                data,
+            }),
+            Err(err) => {
+                tracing::warn!(%err, tool = %self.tool, "failed to finish guest profile")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::{WasixExecutor, tests::spin_component};
+    use crate::types::{McpError, ToolInput, ToolRef};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn profiles_attempts_past_the_threshold() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("spin.wasm");
+        std::fs::write(&path, spin_component()).unwrap();
+        let tool = ToolRef {
+            timeout_ms: Some(200),
+            ..ToolRef::new("spin", path.to_string_lossy(), "tool-invoke")
+        };
+        let (sender, mut profiles) = tokio::sync::mpsc::unbounded_channel();
+        let sink = ProfileSink::new(move |profile| {
+            let _ = sender.send(profile);
+        });
+        let executor = WasixExecutor::new()
+            .unwrap()
+            .with_hot_call_profiling(Duration::from_millis(20), sink);
+
+        let input = ToolInput::new(json!({}));
+        let err = executor.invoke(&tool, &input).await.unwrap_err();
+        assert!(matches!(err, McpError::Timeout { .. }), "{err}");
+
+        let profile = tokio::time::timeout(Duration::from_secs(5), profiles.recv())
+            .await
+            .expect("profile written")
+            .unwrap();
+        assert_eq!(profile.tool, "spin");
+        assert!(profile.duration >= Duration::from_millis(20));
+        let document: serde_json::Value = serde_json::from_slice(&profile.data).unwrap();
+        assert!(document.get("threads").is_some(), "{document}");
+    }
+}
@@ -0,0 +1,156 @@
+//! Progress reported by long-running tools while they execute.
+//!
+//! Guests import [`PROGRESS_INTERFACE`] (see `ABI.md`) and call `report`; the host
+//! forwards each report to the [`ProgressSink`] of the invocation, e.g. to send MCP
+//! `notifications/progress` to the client that made the call.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use wasmtime::StoreContextMut;
+use wasmtime::component::Linker;
+
+/// Interface guests import to report progress.
+pub const PROGRESS_INTERFACE: &str = "greentic:mcp/progress@0.1.0";
+
+/// One progress report. `progress` increases with every report; `total` is set when
+/// the amount of work is known.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Progress {
+    pub progress: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Callback receiving the [`Progress`] of a single invocation.
+///
+/// Called on the blocking thread running the tool, so it should hand reports off
+/// (e.g. to a channel) rather than block.
+#[derive(Clone)]
+pub struct ProgressSink(Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl ProgressSink {
+    pub fn new<F>(report: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        Self(Arc::new(report))
+    }
+
+    pub fn report(&self, progress: &Progress) {
+        (self.0)(progress)
+    }
+}
+
+impl std::fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressSink(..)")
+    }
+}
+
+/// Define [`PROGRESS_INTERFACE`] in `linker`, reporting to the sink returned by `sink`.
+///
+/// Reports are dropped when the invocation has no sink, so guests can report
+/// unconditionally.
+pub(crate) fn add_to_linker<T: 'static>(
+    linker: &mut Linker<T>,
+    sink: fn(&mut T) -> Option<&ProgressSink>,
+) -> wasmtime::Result<()> {
+    linker.instance(PROGRESS_INTERFACE)?.func_wrap(
+        "report",
+        move |mut store: StoreContextMut<'_, T>,
+              (progress, total, message): (f64, Option<f64>, Option<String>)| {
+            if let Some(sink) = sink(store.data_mut()) {
+                sink.report(&Progress {
+                    progress,
+                    total,
+                    message,
+                });
+            }
+            Ok(())
+        },
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Mutex;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::WasixExecutor;
+    use crate::types::{ToolInput, ToolRef};
+
+    /// Component exporting `tool-invoke`, which reports progress 50 of 100 with the
+    /// message `halfway`, then returns `{"done":true}`.
+    pub(crate) fn progress_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (import "greentic:mcp/progress@0.1.0" (instance $progress
+                    (export "report" (func
+                        (param "progress" f64) (param "total" (option f64))
+                        (param "message" (option string))))))
+                (core module $Memory (memory (export "memory") 1))
+                (core instance $memory (instantiate $Memory))
+                (core func $report (canon lower (func $progress "report")
+                    (memory $memory "memory")))
+                (core module $Tool
+                    (import "host" "report" (func $report (param f64 i32 f64 i32 i32 i32)))
+                    (import "host" "memory" (memory 1))
+                    (data (i32.const 16) "halfway")
+                    (data (i32.const 64) "{\"done\":true}")
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (call $report (f64.const 50) (i32.const 1) (f64.const 100)
+                            (i32.const 1) (i32.const 16) (i32.const 7))
+                        (i32.store (i32.const 128) (i32.const 64))
+                        (i32.store (i32.const 132) (i32.const 13))
+                        (i32.const 128))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (i32.const 1024)))
+                (core instance $host
+                    (export "report" (func $report))
+                    (export "memory" (memory $memory "memory")))
+                (core instance $tool (instantiate $Tool (with "host" (instance $host))))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $memory "memory")
+                        (realloc (func $tool "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    #[tokio::test]
+    async fn forwards_guest_reports_to_the_sink() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("progress.wasm");
+        std::fs::write(&path, progress_component()).unwrap();
+        let tool = ToolRef::new("slow", path.to_string_lossy(), "tool-invoke");
+        let input = ToolInput::new(json!({}));
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let reports = reports.clone();
+            ProgressSink::new(move |progress| reports.lock().unwrap().push(progress.clone()))
+        };
+
+        let executor = WasixExecutor::new().unwrap();
+        let output = executor
+            .invoke_with_progress(&tool, &input, Some(sink))
+            .await
+            .unwrap();
+        assert_eq!(output.payload, json!({ "done": true }));
+        assert_eq!(
+            *reports.lock().unwrap(),
+            [Progress {
+                progress: 50.0,
+                total: Some(100.0),
+                message: Some("halfway".into()),
+            }]
+        );
+
+        // Without a sink the reports are dropped.
+        let output = executor.invoke(&tool, &input).await.unwrap();
+        assert_eq!(output.payload, json!({ "done": true }));
+    }
+}
@@ -0,0 +1,169 @@
+//! Per-tenant rate limiting, enforced before a tool runs.
+//!
+//! Attach a [`RateLimiter`] with
+//! [`WasixExecutor::with_rate_limiter`](crate::executor::WasixExecutor::with_rate_limiter)
+//! and every invocation with a tenant takes a token from that tenant's bucket (or,
+//! with [`RateLimiter::per_tool`], the bucket of the tenant and tool). Calls finding
+//! the bucket empty fail with [`McpError::RateLimited`] without running the tool.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::McpError;
+
+/// Sustained rate and burst of a token bucket.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    /// Tokens added per second.
+    pub per_second: f64,
+    /// Tokens the bucket holds when full, i.e. calls allowed at once.
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        Self {
+            per_second: per_second.max(0.0),
+            burst: burst.max(1),
+        }
+    }
+}
+
+/// Token buckets by tenant, shared by executor clones.
+#[derive(Debug)]
+pub struct RateLimiter {
+    default: RateLimit,
+    tenants: HashMap<String, RateLimit>,
+    per_tool: bool,
+    buckets: Mutex<HashMap<(String, Option<String>), Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Limit every tenant to `limit`.
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            default: limit,
+            tenants: HashMap::new(),
+            per_tool: false,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Use `limit` for `tenant` instead of the default.
+    pub fn with_tenant_limit(mut self, tenant: impl Into<String>, limit: RateLimit) -> Self {
+        self.tenants.insert(tenant.into(), limit);
+        self
+    }
+
+    /// Give each tenant a separate bucket per tool.
+    pub fn per_tool(mut self) -> Self {
+        self.per_tool = true;
+        self
+    }
+
+    /// Take a token for a call by `tenant` to the tool with key `tool`.
+    pub fn acquire(&self, tenant: &str, tool: &str) -> Result<(), McpError> {
+        let limit = self.tenants.get(tenant).unwrap_or(&self.default);
+        let key = (tenant.to_string(), self.per_tool.then(|| tool.to_string()));
+        let burst = f64::from(limit.burst);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("rate limiter poisoned");
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * limit.per_second;
+        bucket.tokens = (bucket.tokens + refill).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        // Never, for a bucket that does not refill.
+        let retry_after = Duration::try_from_secs_f64((1.0 - bucket.tokens) / limit.per_second)
+            .unwrap_or(Duration::MAX);
+        Err(McpError::RateLimited {
+            tenant: tenant.to_string(),
+            retry_after,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::{InvokeOptions, WasixExecutor};
+    use crate::types::{ErrorCode, ToolInput, ToolRef};
+
+    #[test]
+    fn buckets_are_kept_per_tenant() {
+        let limiter = RateLimiter::new(RateLimit::new(1.0, 2))
+            .with_tenant_limit("premium", RateLimit::new(1.0, 3));
+        assert!(limiter.acquire("acme", "search").is_ok());
+        assert!(limiter.acquire("acme", "fetch").is_ok());
+        let err = limiter.acquire("acme", "search").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RateLimited);
+        let McpError::RateLimited { retry_after, .. } = err else {
+            unreachable!();
+        };
+        assert!(retry_after > Duration::from_millis(900));
+        assert!(retry_after <= Duration::from_secs(1));
+
+        for _ in 0..3 {
+            assert!(limiter.acquire("premium", "search").is_ok());
+        }
+        assert!(limiter.acquire("premium", "search").is_err());
+    }
+
+    #[test]
+    fn per_tool_buckets_are_separate() {
+        let limiter = RateLimiter::new(RateLimit::new(0.0, 1)).per_tool();
+        assert!(limiter.acquire("acme", "search").is_ok());
+        assert!(limiter.acquire("acme", "fetch").is_ok());
+        let err = limiter.acquire("acme", "search").unwrap_err();
+        assert!(
+            matches!(err, McpError::RateLimited { retry_after, .. } if retry_after == Duration::MAX)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_calls_before_running_the_tool() {
+        let executor = WasixExecutor::new()
+            .unwrap()
+            .with_rate_limiter(Arc::new(RateLimiter::new(RateLimit::new(0.0, 1))));
+        let tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+        let input = ToolInput::new(json!({}));
+        let call = || InvokeOptions {
+            tenant: Some(greentic_types::TenantCtx::new(
+                "dev".try_into().unwrap(),
+                "acme".try_into().unwrap(),
+            )),
+            ..InvokeOptions::default()
+        };
+
+        let err = executor
+            .invoke_with(&tool, &input, call())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RunnerFailed);
+        let err = executor
+            .invoke_with(&tool, &input, call())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RateLimited);
+        // Anonymous calls are not limited.
+        let err = executor.invoke(&tool, &input).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::RunnerFailed);
+    }
+}
@@ -0,0 +1,305 @@
+//! Artifacts (reports, files) published to MCP clients as resources.
+//!
+//! An [`McpServer`](crate::McpServer) serves `resources/list` and `resources/read` from
+//! any number of [`ResourceProvider`]s, e.g. a KV namespace tools write into or a
+//! directory on disk.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use mcp_exec::KvStore;
+use serde::Serialize;
+
+use crate::types::McpError;
+
+/// Entry returned by `resources/list`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Size in bytes, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// Contents returned by `resources/read`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(flatten)]
+    pub body: ResourceBody,
+}
+
+/// Resource payload: UTF-8 text, or base64-encoded binary data.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceBody {
+    Text(String),
+    Blob(String),
+}
+
+impl ResourceBody {
+    /// Text when `bytes` are valid UTF-8, base64 otherwise.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => ResourceBody::Text(text),
+            Err(err) => ResourceBody::Blob(BASE64.encode(err.into_bytes())),
+        }
+    }
+}
+
+/// Source of MCP resources.
+pub trait ResourceProvider: Send + Sync {
+    fn list(&self) -> Result<Vec<Resource>, McpError>;
+
+    /// Read `uri`, returning `Ok(None)` when it does not belong to this provider.
+    fn read(&self, uri: &str) -> Result<Option<ResourceContents>, McpError>;
+}
+
+/// Entries of one [`KvStore`] namespace, addressed as `kv://namespace/key`.
+///
+/// Like tool calls, namespaces may not contain `/`; serve a tenant's entries with
+/// [`for_tenant`](Self::for_tenant).
+#[derive(Clone, Debug)]
+pub struct KvResources {
+    store: Arc<dyn KvStore>,
+    namespace: String,
+    /// Namespace in the store, scoped to the tenant if there is one.
+    scoped: String,
+}
+
+impl KvResources {
+    /// Serve the unscoped `namespace`, which tools invoked without a tenant use.
+    ///
+    /// # Panics
+    ///
+    /// If `namespace` contains `/`.
+    pub fn new(store: Arc<dyn KvStore>, namespace: impl Into<String>) -> Self {
+        Self::scoped(store, None, namespace.into())
+    }
+
+    /// Serve `namespace` as tools invoked for `tenant` see it, under the same
+    /// `kv://namespace/key` URIs.
+    ///
+    /// # Panics
+    ///
+    /// If `namespace` contains `/`.
+    pub fn for_tenant(store: Arc<dyn KvStore>, tenant: &str, namespace: impl Into<String>) -> Self {
+        Self::scoped(store, Some(tenant), namespace.into())
+    }
+
+    fn scoped(store: Arc<dyn KvStore>, tenant: Option<&str>, namespace: String) -> Self {
+        let scoped = mcp_exec::scoped_namespace(tenant, &namespace)
+            .unwrap_or_else(|err| panic!("invalid KvResources namespace: {err}"));
+        Self {
+            store,
+            namespace,
+            scoped,
+        }
+    }
+
+    fn uri(&self, key: &str) -> String {
+        format!("kv://{}/{key}", self.namespace)
+    }
+}
+
+impl ResourceProvider for KvResources {
+    fn list(&self) -> Result<Vec<Resource>, McpError> {
+        let keys = self.store.keys(&self.scoped).map_err(kv_error)?;
+        Ok(keys
+            .into_iter()
+            .map(|key| Resource {
+                uri: self.uri(&key),
+                mime_type: mime_type(Path::new(&key)),
+                name: key,
+                description: None,
+                size: None,
+            })
+            .collect())
+    }
+
+    fn read(&self, uri: &str) -> Result<Option<ResourceContents>, McpError> {
+        let Some(key) = uri
+            .strip_prefix("kv://")
+            .and_then(|rest| rest.strip_prefix(self.namespace.as_str()))
+            .and_then(|rest| rest.strip_prefix('/'))
+        else {
+            return Ok(None);
+        };
+        let value = self.store.get(&self.scoped, key).map_err(kv_error)?;
+        Ok(value.map(|text| ResourceContents {
+            uri: uri.to_string(),
+            mime_type: mime_type(Path::new(key)),
+            body: ResourceBody::Text(text),
+        }))
+    }
+}
+
+fn kv_error(err: anyhow::Error) -> McpError {
+    McpError::Internal(format!("kv store: {err}"))
+}
+
+/// Files below a directory, addressed by their absolute `file://` URI.
+///
+/// Reads are confined to the directory: URIs resolving outside of it (through `..` or
+/// symlinks) are treated as unknown.
+#[derive(Clone, Debug)]
+pub struct DirectoryResources {
+    root: PathBuf,
+}
+
+impl DirectoryResources {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl ResourceProvider for DirectoryResources {
+    fn list(&self) -> Result<Vec<Resource>, McpError> {
+        let root = self.root.canonicalize()?;
+        let mut resources = Vec::new();
+        collect_files(&root, &root, &mut resources)?;
+        Ok(resources)
+    }
+
+    fn read(&self, uri: &str) -> Result<Option<ResourceContents>, McpError> {
+        let Some(path) = uri.strip_prefix("file://") else {
+            return Ok(None);
+        };
+        let Ok(path) = Path::new(path).canonicalize() else {
+            return Ok(None);
+        };
+        if !path.starts_with(self.root.canonicalize()?) || !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(ResourceContents {
+            uri: uri.to_string(),
+            mime_type: mime_type(&path),
+            body: ResourceBody::from_bytes(fs::read(&path)?),
+        }))
+    }
+}
+
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<Resource>) -> Result<(), McpError> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let metadata = fs::metadata(&path)?;
+        if metadata.is_dir() {
+            collect_files(root, &path, out)?;
+            continue;
+        }
+        let name = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        out.push(Resource {
+            uri: file_uri(&path),
+            name,
+            description: None,
+            mime_type: mime_type(&path),
+            size: Some(metadata.len()),
+        });
+    }
+    Ok(())
+}
+
+fn file_uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+/// MIME type guessed from the file extension.
+fn mime_type(path: &Path) -> Option<String> {
+    let mime = match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_exec::MemoryKvStore;
+
+    #[test]
+    fn kv_namespace_lists_and_reads_entries() {
+        let store = Arc::new(MemoryKvStore::new());
+        store
+            .put("reports", "daily.json", "{\"ok\":true}".into())
+            .unwrap();
+        store.put("private", "token", "secret".into()).unwrap();
+        let resources = KvResources::new(store.clone(), "reports");
+
+        let listed = resources.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].uri, "kv://reports/daily.json");
+        assert_eq!(listed[0].mime_type.as_deref(), Some("application/json"));
+
+        let contents = resources.read("kv://reports/daily.json").unwrap().unwrap();
+        assert_eq!(contents.body, ResourceBody::Text("{\"ok\":true}".into()));
+        assert_eq!(resources.read("kv://private/token").unwrap(), None);
+        assert_eq!(resources.read("kv://reports/missing").unwrap(), None);
+
+        store.put("acme/reports", "q1.md", "# Q1".into()).unwrap();
+        let acme = KvResources::for_tenant(store, "acme", "reports");
+        assert_eq!(acme.list().unwrap()[0].uri, "kv://reports/q1.md");
+        let contents = acme.read("kv://reports/q1.md").unwrap().unwrap();
+        assert_eq!(contents.body, ResourceBody::Text("# Q1".into()));
+        assert_eq!(acme.read("kv://reports/daily.json").unwrap(), None);
+    }
+
+    #[test]
+    fn directory_reads_stay_inside_the_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("artifacts");
+        fs::create_dir_all(root.join("charts")).unwrap();
+        fs::write(root.join("summary.md"), "# Summary").unwrap();
+        fs::write(root.join("charts/q1.png"), [0x89, 0x50, 0xff]).unwrap();
+        fs::write(tmp.path().join("outside.txt"), "nope").unwrap();
+        let resources = DirectoryResources::new(&root);
+
+        let listed = resources.list().unwrap();
+        let names = listed.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["charts/q1.png", "summary.md"]);
+        assert_eq!(listed[1].size, Some(9));
+
+        let summary = resources.read(&listed[1].uri).unwrap().unwrap();
+        assert_eq!(summary.body, ResourceBody::Text("# Summary".into()));
+        assert_eq!(summary.mime_type.as_deref(), Some("text/markdown"));
+        let chart = resources.read(&listed[0].uri).unwrap().unwrap();
+        assert_eq!(
+            chart.body,
+            ResourceBody::Blob(BASE64.encode([0x89, 0x50, 0xff]))
+        );
+
+        let escape = format!("file://{}/../outside.txt", root.display());
+        assert_eq!(resources.read(&escape).unwrap(), None);
+        assert_eq!(resources.read("kv://reports/x").unwrap(), None);
+    }
+}
@@ -1,18 +1,633 @@
-use std::time::Duration;
+use std::future::Future;
+use std::time::{Duration, Instant, SystemTime};
 
+use rand::RngCore;
 use rand::distr::{Distribution, Uniform};
+use serde_json::Value;
+
+pub use mcp_exec::{
+    BackoffStrategy, FileRetryStore, GiveUpReason, Jitter, MemoryRetryStore, RetryBudget,
+    RetryClassifier, RetryEvent, RetryObserver, RetryPolicy, RetryRequest, RetryState, RetryStore,
+};
 
 /// Compute an exponential backoff delay with jitter.
 ///
 /// `attempt` is zero-based. Jitter is applied in the range [0.5, 1.5] of the computed base delay.
 pub fn backoff(base: Duration, attempt: u32) -> Duration {
-    let multiplier = 1u128.checked_shl(attempt.min(16)).unwrap_or(1u128 << 16);
-    let millis = base.as_millis().max(1);
-    let scaled = millis.saturating_mul(multiplier);
-    let max = scaled.min(u64::MAX as u128) as u64;
-    let uniform = Uniform::new_inclusive(0.5f64, 1.5f64).expect("valid jitter bounds");
+    policy_backoff(RetryPolicy::default(), base, attempt)
+}
+
+/// Compute the delay `policy` prescribes for `attempt` (zero-based), applying its jitter.
+/// Capped policies never exceed their cap after jitter.
+///
+/// [`Jitter::Decorrelated`] depends on the previous delay; use [`next_backoff`] to chain it.
+pub fn policy_backoff(policy: RetryPolicy, base: Duration, attempt: u32) -> Duration {
+    next_backoff(policy, base, attempt, None)
+}
+
+/// Like [`policy_backoff`], given the delay used before the previous retry (if any).
+pub fn next_backoff(
+    policy: RetryPolicy,
+    base: Duration,
+    attempt: u32,
+    previous: Option<Duration>,
+) -> Duration {
+    let base = base.max(Duration::from_millis(1));
+    let millis = |delay: Duration| delay.as_millis().min(u64::MAX as u128) as f64;
+    let delay = millis(policy.delay(base, attempt));
     let mut rng = rand::rng();
-    let jitter = uniform.sample(&mut rng);
-    let jittered = (max as f64 * jitter).round().clamp(1.0, u64::MAX as f64);
-    Duration::from_millis(jittered as u64)
+    let mut between = |low: f64, high: f64| {
+        Uniform::new_inclusive(low, high.max(low))
+            .expect("valid jitter bounds")
+            .sample(&mut rng)
+    };
+    let jittered = match policy.jitter {
+        Jitter::None => delay,
+        Jitter::Proportional => delay * between(0.5, 1.5),
+        Jitter::Full => between(0.0, delay),
+        Jitter::Equal => delay / 2.0 + between(0.0, delay / 2.0),
+        Jitter::Decorrelated => {
+            let low = millis(base);
+            between(low, millis(previous.unwrap_or(base)) * 3.0)
+        }
+    };
+    let jittered = Duration::from_millis(jittered.round().clamp(1.0, u64::MAX as f64) as u64);
+    match policy.max_delay() {
+        Some(max) => jittered.min(max),
+        None => jittered,
+    }
+}
+
+/// Settings for one logical invocation run through [`retry`].
+#[derive(Clone, Copy, Debug)]
+pub struct RetryOptions<'a> {
+    /// Name reported in [`RetryEvent`]s.
+    pub tool: &'a str,
+    /// Total attempts including the first one; at least one attempt is always made.
+    pub max_attempts: u32,
+    pub policy: RetryPolicy,
+    pub base_backoff: Duration,
+    /// Overall deadline measured from the start of the first attempt.
+    pub max_duration: Option<Duration>,
+    pub budget: Option<&'a RetryBudget>,
+    pub observer: Option<&'a RetryObserver>,
+    /// Where progress is persisted between attempts; requires `invocation_id`.
+    pub store: Option<&'a dyn RetryStore>,
+    /// Stable id of the logical invocation (its idempotency key), keying `store`.
+    pub invocation_id: Option<&'a str>,
+    /// Saved with the state, so the invocation can be resubmitted after a restart.
+    pub request: Option<&'a RetryRequest>,
+}
+
+/// How a failed attempt is reported and whether it may be retried.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailureInfo {
+    pub retryable: bool,
+    /// Error class, e.g. a tool error code, `timeout`, or `transient`.
+    pub class: String,
+    pub message: String,
+}
+
+/// Error returned by [`retry`] once it stops without a successful attempt.
+#[derive(Debug)]
+pub struct RetryFailure<E> {
+    /// Error of the last attempt.
+    pub error: E,
+    pub reason: GiveUpReason,
+    pub attempts: u32,
+    pub elapsed: Duration,
+}
+
+/// Run `attempt` until it succeeds or the retry settings say to stop.
+///
+/// `attempt` receives the 1-based attempt number. `describe` decides whether an error
+/// may be retried. Delays follow `options.policy` and its jitter, and every step is
+/// reported through [`emit`]. With a `store`, progress is saved before every backoff and
+/// a later call with the same `invocation_id` resumes from it.
+pub async fn retry<T, E, F, Fut, D>(
+    options: &RetryOptions<'_>,
+    mut attempt: F,
+    describe: D,
+) -> Result<T, RetryFailure<E>>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    D: Fn(&E) -> FailureInfo,
+{
+    let max_attempts = options.max_attempts.max(1);
+    let mut deadline = RetryDeadline::new(options.max_duration);
+    let tool = || options.tool.to_string();
+    let durable = options.store.zip(options.invocation_id);
+    let clear_state = || {
+        if let Some((store, id)) = durable {
+            persist(options.tool, "clear", store.clear(id));
+        }
+    };
+    let give_up = |error, reason, attempts, deadline: &RetryDeadline| {
+        clear_state();
+        emit(
+            options.observer,
+            RetryEvent::GaveUp {
+                tool: tool(),
+                attempts,
+                reason,
+            },
+        );
+        Err(RetryFailure {
+            error,
+            reason,
+            attempts,
+            elapsed: deadline.elapsed(),
+        })
+    };
+
+    let resumed = durable.and_then(|(store, id)| persist(options.tool, "load", store.load(id)));
+    let mut first = 1;
+    let mut started_at = SystemTime::now();
+    match resumed.flatten() {
+        Some(state) => {
+            // Pick up where a previous process left off: keep counting attempts, keep
+            // the original deadline, and finish the backoff it was in.
+            first = state.attempts.saturating_add(1).min(max_attempts);
+            started_at = state.started_at;
+            let since_start = started_at.elapsed().unwrap_or_default();
+            deadline = deadline.with_elapsed(since_start);
+            let wait = state
+                .next_retry_at
+                .and_then(|at| at.duration_since(SystemTime::now()).ok());
+            if let Some(delay) = wait {
+                emit(
+                    options.observer,
+                    RetryEvent::BackingOff {
+                        tool: tool(),
+                        attempt: state.attempts,
+                        delay,
+                    },
+                );
+                tokio::time::sleep(delay).await;
+            }
+        }
+        None => {
+            if let Some(budget) = options.budget {
+                budget.record_call();
+            }
+        }
+    }
+
+    let mut previous = None;
+    for number in first..=max_attempts {
+        emit(
+            options.observer,
+            RetryEvent::AttemptStarted {
+                tool: tool(),
+                attempt: number,
+            },
+        );
+        let started = Instant::now();
+        let result = attempt(number).await;
+        deadline.record_attempt(started.elapsed());
+
+        let error = match result {
+            Ok(value) => {
+                clear_state();
+                emit(
+                    options.observer,
+                    RetryEvent::Succeeded {
+                        tool: tool(),
+                        attempts: number,
+                    },
+                );
+                return Ok(value);
+            }
+            Err(error) => error,
+        };
+
+        let info = describe(&error);
+        let retryable = info.retryable;
+        let last_error = durable.is_some().then(|| info.message.clone());
+        emit(
+            options.observer,
+            RetryEvent::AttemptFailed {
+                tool: tool(),
+                attempt: number,
+                class: info.class,
+                retryable,
+                message: info.message,
+            },
+        );
+        if !retryable {
+            return give_up(error, GiveUpReason::NotRetryable, number, &deadline);
+        }
+        if number >= max_attempts {
+            return give_up(error, GiveUpReason::AttemptsExhausted, number, &deadline);
+        }
+        let backoff = next_backoff(options.policy, options.base_backoff, number - 1, previous);
+        previous = Some(backoff);
+        if !deadline.allows_retry(backoff) {
+            return give_up(error, GiveUpReason::Deadline, number, &deadline);
+        }
+        if options.budget.is_some_and(|budget| !budget.try_acquire()) {
+            return give_up(error, GiveUpReason::Budget, number, &deadline);
+        }
+        if let Some((store, id)) = durable {
+            let state = RetryState {
+                attempts: number,
+                started_at,
+                last_error,
+                next_retry_at: Some(SystemTime::now() + backoff),
+                request: options.request.cloned(),
+            };
+            persist(options.tool, "save", store.save(id, &state));
+        }
+        emit(
+            options.observer,
+            RetryEvent::BackingOff {
+                tool: tool(),
+                attempt: number,
+                delay: backoff,
+            },
+        );
+        tokio::time::sleep(backoff).await;
+    }
+
+    unreachable!("retry loop returns from its last attempt")
+}
+
+/// Run a retry store operation, logging (and otherwise ignoring) failures.
+fn persist<T>(tool: &str, action: &str, result: std::io::Result<T>) -> Option<T> {
+    result
+        .map_err(|err| {
+            tracing::warn!(target: "greentic_mcp::retry", %tool, %err, "failed to {action} retry state");
+        })
+        .ok()
+}
+
+/// The tighter of two optional limits.
+pub(crate) fn tighter(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Field under `_meta` carrying the idempotency key of a logical invocation.
+pub const IDEMPOTENCY_KEY_FIELD: &str = "idempotency_key";
+
+/// Generate a fresh idempotency key (128 random bits, hex encoded).
+pub fn new_idempotency_key() -> String {
+    let mut bytes = [0u8; 16];
+    rand::rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Idempotency key already present in `args` under `_meta.idempotency_key`.
+pub fn idempotency_key(args: &Value) -> Option<&str> {
+    args.get("_meta")?.get(IDEMPOTENCY_KEY_FIELD)?.as_str()
+}
+
+/// Store `key` as `_meta.idempotency_key` in an object payload, keeping other `_meta`
+/// fields. Non-object payloads are left unchanged and `false` is returned.
+pub fn inject_idempotency_key(args: &mut Value, key: &str) -> bool {
+    let Value::Object(fields) = args else {
+        return false;
+    };
+    let meta = fields
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let Value::Object(meta) = meta else {
+        return false;
+    };
+    meta.insert(IDEMPOTENCY_KEY_FIELD.into(), Value::String(key.into()));
+    true
+}
+
+/// Report `event` through tracing and, when set, to `observer`.
+pub fn emit(observer: Option<&RetryObserver>, event: RetryEvent) {
+    match &event {
+        RetryEvent::AttemptStarted { tool, attempt } => {
+            tracing::trace!(target: "greentic_mcp::retry", %tool, attempt, "attempt started");
+        }
+        RetryEvent::AttemptFailed {
+            tool,
+            attempt,
+            class,
+            retryable,
+            message,
+        } => {
+            tracing::debug!(target: "greentic_mcp::retry", %tool, attempt, %class, retryable, %message, "attempt failed");
+        }
+        RetryEvent::BackingOff {
+            tool,
+            attempt,
+            delay,
+        } => {
+            tracing::debug!(target: "greentic_mcp::retry", %tool, attempt, delay_ms = delay.as_millis() as u64, "backing off");
+        }
+        RetryEvent::Succeeded { tool, attempts } => {
+            tracing::trace!(target: "greentic_mcp::retry", %tool, attempts, "attempt succeeded");
+        }
+        RetryEvent::GaveUp {
+            tool,
+            attempts,
+            reason,
+        } => {
+            tracing::info!(target: "greentic_mcp::retry", %tool, attempts, reason = reason.as_str(), "gave up");
+        }
+    }
+    if let Some(observer) = observer {
+        observer.notify(&event);
+    }
+}
+
+/// Tracks elapsed time against an overall retry deadline.
+///
+/// A retry is only worth starting when the backoff plus a typical attempt (the mean of
+/// the attempts seen so far) still ends before the deadline.
+#[derive(Clone, Debug)]
+pub struct RetryDeadline {
+    started: Instant,
+    budget: Option<Duration>,
+    attempt_time: Duration,
+    attempts: u32,
+}
+
+impl RetryDeadline {
+    /// Start the clock for a logical invocation limited to `budget`, if any.
+    pub fn new(budget: Option<Duration>) -> Self {
+        Self {
+            started: Instant::now(),
+            budget,
+            attempt_time: Duration::ZERO,
+            attempts: 0,
+        }
+    }
+
+    /// Treat the clock as having started `elapsed` ago, e.g. when resuming persisted state.
+    pub fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.started = Instant::now().checked_sub(elapsed).unwrap_or(self.started);
+        self
+    }
+
+    /// Record how long a finished attempt took.
+    pub fn record_attempt(&mut self, took: Duration) {
+        self.attempt_time = self.attempt_time.saturating_add(took);
+        self.attempts += 1;
+    }
+
+    /// Mean duration of the recorded attempts.
+    pub fn typical_attempt(&self) -> Duration {
+        self.attempt_time
+            .checked_div(self.attempts)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Time since the first attempt started.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Whether backing off for `backoff` and running another attempt fits the deadline.
+    pub fn allows_retry(&self, backoff: Duration) -> bool {
+        self.budget.is_none_or(|budget| {
+            self.elapsed()
+                .saturating_add(backoff)
+                .saturating_add(self.typical_attempt())
+                < budget
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capped_policy_stays_below_cap_after_jitter() {
+        for jitter in [
+            Jitter::None,
+            Jitter::Proportional,
+            Jitter::Full,
+            Jitter::Equal,
+            Jitter::Decorrelated,
+        ] {
+            let policy = RetryPolicy::new(BackoffStrategy::ExponentialCapped { max_delay_ms: 300 })
+                .with_jitter(jitter);
+            let mut previous = None;
+            for attempt in 0..10 {
+                let delay = next_backoff(policy, Duration::from_millis(100), attempt, previous);
+                assert!(delay <= Duration::from_millis(300), "{jitter:?}: {delay:?}");
+                previous = Some(delay);
+            }
+        }
+    }
+
+    #[test]
+    fn jitter_strategies_stay_in_range() {
+        let base = Duration::from_millis(100);
+        let exponential = |jitter| RetryPolicy::default().with_jitter(jitter);
+        for _ in 0..100 {
+            // Attempt 2 of an exponential policy has an unjittered delay of 400ms.
+            let none = policy_backoff(exponential(Jitter::None), base, 2);
+            assert_eq!(none, Duration::from_millis(400));
+            let full = policy_backoff(exponential(Jitter::Full), base, 2).as_millis();
+            assert!((1..=400).contains(&full), "{full}");
+            let equal = policy_backoff(exponential(Jitter::Equal), base, 2).as_millis();
+            assert!((200..=400).contains(&equal), "{equal}");
+            let decorrelated = next_backoff(
+                exponential(Jitter::Decorrelated),
+                base,
+                2,
+                Some(Duration::from_millis(250)),
+            )
+            .as_millis();
+            assert!((100..=750).contains(&decorrelated), "{decorrelated}");
+        }
+    }
+
+    fn options(max_attempts: u32) -> RetryOptions<'static> {
+        RetryOptions {
+            tool: "echo",
+            max_attempts,
+            policy: BackoffStrategy::Fixed.into(),
+            base_backoff: Duration::from_millis(1),
+            max_duration: None,
+            budget: None,
+            observer: None,
+            store: None,
+            invocation_id: None,
+            request: None,
+        }
+    }
+
+    fn describe(retryable: &bool) -> FailureInfo {
+        FailureInfo {
+            retryable: *retryable,
+            class: "test".into(),
+            message: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let result = retry(
+            &options(3),
+            |attempt| async move { if attempt < 3 { Err(true) } else { Ok(attempt) } },
+            describe,
+        )
+        .await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn reports_why_it_gave_up() {
+        let failure = retry(&options(3), |_| async { Err::<(), _>(false) }, describe)
+            .await
+            .unwrap_err();
+        assert_eq!(failure.reason, GiveUpReason::NotRetryable);
+        assert_eq!(failure.attempts, 1);
+
+        let failure = retry(&options(2), |_| async { Err::<(), _>(true) }, describe)
+            .await
+            .unwrap_err();
+        assert_eq!(failure.reason, GiveUpReason::AttemptsExhausted);
+        assert_eq!(failure.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn persists_and_resumes_retry_state() {
+        let store = MemoryRetryStore::new();
+        let options = RetryOptions {
+            store: Some(&store),
+            invocation_id: Some("inv-1"),
+            ..options(4)
+        };
+
+        // Progress is saved before each backoff and cleared once the invocation ends.
+        let seen = std::sync::Mutex::new(Vec::new());
+        let result = retry(
+            &options,
+            |attempt| {
+                let saved = store.load("inv-1").unwrap();
+                seen.lock().unwrap().push(saved.map(|state| state.attempts));
+                async move { if attempt < 3 { Err(true) } else { Ok(attempt) } }
+            },
+            describe,
+        )
+        .await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(*seen.lock().unwrap(), [None, Some(1), Some(2)]);
+        assert_eq!(store.load("inv-1").unwrap(), None);
+
+        // A new process picks up the attempt count and finishes the pending backoff.
+        let next_retry_at = SystemTime::now() + Duration::from_millis(30);
+        store
+            .save(
+                "inv-1",
+                &RetryState {
+                    attempts: 2,
+                    started_at: SystemTime::now(),
+                    last_error: Some("boom".into()),
+                    next_retry_at: Some(next_retry_at),
+                    request: None,
+                },
+            )
+            .unwrap();
+        let result = retry(
+            &options,
+            |attempt| async move {
+                assert!(attempt >= 3, "resumed at attempt {attempt}");
+                assert!(SystemTime::now() >= next_retry_at);
+                Err::<(), _>(true)
+            },
+            describe,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(result.attempts, 4);
+        assert_eq!(result.reason, GiveUpReason::AttemptsExhausted);
+        assert_eq!(store.load("inv-1").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn resumes_from_a_file_store_after_a_restart() {
+        let tmp = tempfile::tempdir().unwrap();
+        let request = RetryRequest {
+            component: "payments".into(),
+            action: "charge".into(),
+            args: serde_json::json!({ "amount": 5, "_meta": { "idempotency_key": "inv-1" } }),
+        };
+        let store = FileRetryStore::new(tmp.path());
+        let options = RetryOptions {
+            store: Some(&store),
+            invocation_id: Some("inv-1"),
+            request: Some(&request),
+            base_backoff: Duration::from_secs(60),
+            ..options(3)
+        };
+        // The host goes down during the backoff after the first attempt.
+        let interrupted = retry(&options, |_| async { Err::<u32, _>(true) }, describe);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), interrupted)
+                .await
+                .is_err()
+        );
+
+        // The restarted host finds the invocation, with what to submit again.
+        let store = FileRetryStore::new(tmp.path());
+        let pending = store.pending().unwrap();
+        let [(id, state)] = pending.as_slice() else {
+            panic!("expected one pending invocation, got {pending:?}");
+        };
+        assert_eq!((id.as_str(), state.attempts), ("inv-1", 1));
+        assert_eq!(state.request.as_ref(), Some(&request));
+
+        // Resubmitting it continues with the second attempt.
+        let state = RetryState {
+            next_retry_at: None,
+            ..state.clone()
+        };
+        store.save(id, &state).unwrap();
+        let options = RetryOptions {
+            store: Some(&store),
+            invocation_id: Some(id),
+            request: state.request.as_ref(),
+            ..options(3)
+        };
+        let result = retry(
+            &options,
+            |attempt| async move { Ok::<_, bool>(attempt) },
+            describe,
+        );
+        assert_eq!(result.await.unwrap(), 2);
+        assert!(store.pending().unwrap().is_empty());
+    }
+
+    #[test]
+    fn injects_idempotency_key_into_meta() {
+        let mut args = serde_json::json!({"amount": 5, "_meta": {"trace": "t"}});
+        let key = new_idempotency_key();
+        assert_eq!(key.len(), 32);
+        assert!(inject_idempotency_key(&mut args, &key));
+        assert_eq!(idempotency_key(&args), Some(key.as_str()));
+        assert_eq!(args["_meta"]["trace"], "t");
+
+        assert!(!inject_idempotency_key(
+            &mut serde_json::json!("text"),
+            &key
+        ));
+    }
+
+    #[test]
+    fn deadline_accounts_for_typical_attempt() {
+        let mut deadline = RetryDeadline::new(Some(Duration::from_secs(60)));
+        assert!(deadline.allows_retry(Duration::from_secs(10)));
+
+        deadline.record_attempt(Duration::from_secs(20));
+        deadline.record_attempt(Duration::from_secs(40));
+        assert_eq!(deadline.typical_attempt(), Duration::from_secs(30));
+        assert!(deadline.allows_retry(Duration::from_secs(10)));
+        assert!(!deadline.allows_retry(Duration::from_secs(30)));
+
+        assert!(RetryDeadline::new(None).allows_retry(Duration::MAX));
+    }
 }
@@ -0,0 +1,175 @@
+//! LLM sampling requested by tools from the hosting agent.
+//!
+//! Guests import [`SAMPLING_INTERFACE`] (see `ABI.md`) and pass MCP
+//! `sampling/createMessage` parameters as JSON. The host forwards them to the
+//! invocation's [`Sampler`], e.g. the MCP client that made the call, so tools can
+//! delegate reasoning steps without holding model API keys. Only tools with
+//! `allow_sampling` set may sample.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::runtime::Handle;
+use wasmtime::StoreContextMut;
+use wasmtime::component::Linker;
+
+use crate::cancel::CancellationToken;
+
+/// Interface guests import to request completions.
+pub const SAMPLING_INTERFACE: &str = "greentic:mcp/sampling@0.1.0";
+
+type SampleFuture = Pin<Box<dyn Future<Output = Result<Value, String>> + Send>>;
+
+/// Completes `sampling/createMessage` requests on behalf of tools.
+///
+/// Receives the request parameters and resolves with the `CreateMessageResult`, or a
+/// message describing why the agent declined.
+#[derive(Clone)]
+pub struct Sampler(Arc<dyn Fn(Value) -> SampleFuture + Send + Sync>);
+
+impl Sampler {
+    pub fn new<F, Fut>(sample: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        Self(Arc::new(move |params| Box::pin(sample(params))))
+    }
+
+    pub async fn create_message(&self, params: Value) -> Result<Value, String> {
+        (self.0)(params).await
+    }
+}
+
+impl std::fmt::Debug for Sampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Sampler(..)")
+    }
+}
+
+/// What a running guest may use for sampling.
+pub(crate) struct SamplingAccess {
+    /// Set only when the tool allows sampling and the caller provided a sampler.
+    pub sampler: Option<Sampler>,
+    pub allowed: bool,
+    /// Runtime the blocking guest thread waits on for the agent's answer.
+    pub runtime: Handle,
+    /// Stops waiting once the attempt is abandoned.
+    pub interrupt: CancellationToken,
+}
+
+impl SamplingAccess {
+    fn create_message(&self, request: &str) -> Result<String, String> {
+        if !self.allowed {
+            return Err("sampling-disabled".into());
+        }
+        let Some(sampler) = &self.sampler else {
+            return Err("sampling-unavailable".into());
+        };
+        let params =
+            serde_json::from_str(request).map_err(|err| format!("invalid-request: {err}"))?;
+        let result = self.runtime.block_on(async {
+            tokio::select! {
+                result = sampler.create_message(params) => result,
+                _ = self.interrupt.cancelled() => Err("cancelled".into()),
+            }
+        })?;
+        Ok(result.to_string())
+    }
+}
+
+/// Define [`SAMPLING_INTERFACE`] in `linker`, answering with the access returned by
+/// `access`.
+pub(crate) fn add_to_linker<T: 'static>(
+    linker: &mut Linker<T>,
+    access: fn(&mut T) -> &SamplingAccess,
+) -> wasmtime::Result<()> {
+    linker.instance(SAMPLING_INTERFACE)?.func_wrap(
+        "create-message",
+        move |mut store: StoreContextMut<'_, T>, (request,): (String,)| {
+            Ok((access(store.data_mut()).create_message(&request),))
+        },
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::{InvokeOptions, WasixExecutor};
+    use crate::types::{ToolInput, ToolRef};
+
+    /// Component whose `tool-invoke` asks the host for a completion and returns the
+    /// result, or `{"denied":true}` when the request is refused.
+    pub(crate) fn sampling_component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (import "greentic:mcp/sampling@0.1.0" (instance $sampling
+                    (export "create-message" (func (param "request" string)
+                        (result (result string (error string)))))))
+                (core module $Libc
+                    (memory (export "memory") 1)
+                    (global $next (mut i32) (i32.const 1024))
+                    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                        (local $ptr i32)
+                        (local.set $ptr (global.get $next))
+                        (global.set $next (i32.and
+                            (i32.add (i32.add (local.get $ptr) (local.get 3)) (i32.const 7))
+                            (i32.const -8)))
+                        (local.get $ptr)))
+                (core instance $libc (instantiate $Libc))
+                (core func $create (canon lower (func $sampling "create-message")
+                    (memory $libc "memory") (realloc (func $libc "realloc"))))
+                (core module $Tool
+                    (import "host" "create-message" (func $create (param i32 i32 i32)))
+                    (import "host" "memory" (memory 1))
+                    (data (i32.const 16) "{\"maxTokens\":5}")
+                    (data (i32.const 64) "{\"denied\":true}")
+                    (data (i32.const 96) "\40\00\00\00\0f\00\00\00")
+                    (func (export "invoke") (param i32 i32) (result i32)
+                        (call $create (i32.const 16) (i32.const 15) (i32.const 256))
+                        (if (result i32) (i32.load8_u (i32.const 256))
+                            (then (i32.const 96))
+                            (else (i32.const 260)))))
+                (core instance $host
+                    (export "create-message" (func $create))
+                    (export "memory" (memory $libc "memory")))
+                (core instance $tool (instantiate $Tool (with "host" (instance $host))))
+                (func (export "tool-invoke") (param "input" string) (result string)
+                    (canon lift (core func $tool "invoke") (memory $libc "memory")
+                        (realloc (func $libc "realloc")))))"#,
+        )
+        .expect("valid component")
+    }
+
+    #[tokio::test]
+    async fn only_allowed_tools_reach_the_sampler() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("sampling.wasm");
+        std::fs::write(&path, sampling_component()).unwrap();
+        let mut tool = ToolRef::new("summarize", path.to_string_lossy(), "tool-invoke");
+        let input = ToolInput::new(json!({}));
+        let call = InvokeOptions {
+            sampler: Some(Sampler::new(|params: Value| async move {
+                assert_eq!(params, json!({ "maxTokens": 5 }));
+                Ok(json!({ "role": "assistant", "content": { "type": "text", "text": "ok" } }))
+            })),
+            ..InvokeOptions::default()
+        };
+        let executor = WasixExecutor::new().unwrap();
+
+        let output = executor.invoke_with(&tool, &input, call.clone()).await;
+        assert_eq!(output.unwrap().payload, json!({ "denied": true }));
+
+        tool.allow_sampling = true;
+        let output = executor.invoke_with(&tool, &input, call).await.unwrap();
+        assert_eq!(output.payload["content"]["text"], "ok");
+
+        // Allowed, but the caller has no agent to sample from.
+        let output = executor.invoke(&tool, &input).await.unwrap();
+        assert_eq!(output.payload, json!({ "denied": true }));
+    }
+}
@@ -0,0 +1,161 @@
+//! JSON Schema for tool map configs and validation with field-level error messages.
+
+use serde_json::Value;
+
+use crate::types::ToolMapConfig;
+
+/// JSON Schema describing the tool map configuration format.
+pub fn tool_map_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(ToolMapConfig)).expect("schema serializes")
+}
+
+/// Validate a raw config document and deserialize it into a [`ToolMapConfig`].
+///
+/// Errors name the offending location (e.g. `tools[2].timeout_ms`) and suggest
+/// valid keys when an unknown one is used.
+pub(crate) fn validate(document: Value) -> Result<ToolMapConfig, String> {
+    let schema = tool_map_schema();
+    let root_keys = property_names(&schema);
+    let tool_keys = schema
+        .pointer("/$defs/ToolRef")
+        .map(property_names)
+        .unwrap_or_default();
+
+    let Value::Object(root) = &document else {
+        return Err("expected a mapping at the top level".into());
+    };
+    check_keys(root, &root_keys, "")?;
+
+    if let Some(Value::Array(tools)) = root.get("tools") {
+        for (index, tool) in tools.iter().enumerate() {
+            let Value::Object(fields) = tool else {
+                return Err(format!("tools[{index}]: expected a mapping"));
+            };
+            check_keys(fields, &tool_keys, &tool_label(index, fields))?;
+        }
+    }
+
+    serde_path_to_error::deserialize(&document).map_err(|err| {
+        let path = err.path().to_string();
+        let inner = err.into_inner();
+        if path == "." {
+            inner.to_string()
+        } else {
+            format!("{path}: {inner}")
+        }
+    })
+}
+
+fn tool_label(index: usize, fields: &serde_json::Map<String, Value>) -> String {
+    match fields.get("name").and_then(Value::as_str) {
+        Some(name) => format!("tools[{index}] (`{name}`)"),
+        None => format!("tools[{index}]"),
+    }
+}
+
+fn property_names(schema: &Value) -> Vec<String> {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|props| props.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn check_keys(
+    fields: &serde_json::Map<String, Value>,
+    allowed: &[String],
+    location: &str,
+) -> Result<(), String> {
+    for key in fields.keys() {
+        if allowed.iter().any(|candidate| candidate == key) {
+            continue;
+        }
+        let prefix = if location.is_empty() {
+            String::new()
+        } else {
+            format!("{location}: ")
+        };
+        let hint = match suggest(key, allowed) {
+            Some(candidate) => format!("did you mean `{candidate}`?"),
+            None => format!("expected one of: {}", allowed.join(", ")),
+        };
+        return Err(format!("{prefix}unknown field `{key}`, {hint}"));
+    }
+    Ok(())
+}
+
+fn suggest<'a>(key: &str, allowed: &'a [String]) -> Option<&'a str> {
+    allowed
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2 || candidate.starts_with(key))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(current)
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn schema_lists_tool_fields() {
+        let schema = tool_map_schema();
+        let tool = schema.pointer("/$defs/ToolRef/properties").unwrap();
+        assert!(tool.get("timeout_ms").is_some());
+        assert!(tool.get("entry").is_some());
+    }
+
+    #[test]
+    fn suggests_close_key() {
+        let err = validate(json!({
+            "tools": [{"name": "echo", "component": "./echo.wasm", "entry": "run", "timeout": 5}]
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "tools[0] (`echo`): unknown field `timeout`, did you mean `timeout_ms`?"
+        );
+    }
+
+    #[test]
+    fn names_path_of_type_errors() {
+        let err = validate(json!({
+            "tools": [
+                {"name": "ok", "component": "./ok.wasm", "entry": "run"},
+                {"name": "bad", "component": "./bad.wasm", "entry": "run", "max_retries": "many"}
+            ]
+        }))
+        .unwrap_err();
+        assert!(err.starts_with("tools[1].max_retries: "), "{err}");
+    }
+
+    #[test]
+    fn rejects_oci_sources() {
+        let err = validate(json!({
+            "tools": [{"name": "echo", "entry": "run", "source": {"oci": "ghcr.io/greentic/echo:1"}}]
+        }))
+        .unwrap_err();
+        assert!(err.starts_with("tools[0].source: "), "{err}");
+        assert!(err.contains("unknown variant `oci`"), "{err}");
+    }
+}
@@ -0,0 +1,379 @@
+//! Resolution of `secret://name` references in tool map configs, and scrubbing of
+//! resolved secret values from errors, logs, and recorded payloads.
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::schema;
+use crate::types::{McpError, ToolMapConfig};
+
+/// Prefix marking a config string as a reference to a named secret.
+pub const SECRET_SCHEME: &str = "secret://";
+
+/// Secrets shorter than this are not scrubbed, since they would match ordinary text.
+pub const MIN_SCRUBBED_LEN: usize = 4;
+
+/// Documentation fields whose strings are never treated as secret references.
+const DOC_FIELDS: &[&str] = &["description", "input_schema", "output_schema", "examples"];
+
+/// Source of secret values referenced from configuration.
+pub trait SecretsProvider: Send + Sync {
+    /// Return the value of secret `name`, or a reason it is unavailable.
+    fn get_secret(&self, name: &str) -> Result<String, String>;
+}
+
+impl SecretsProvider for HashMap<String, String> {
+    fn get_secret(&self, name: &str) -> Result<String, String> {
+        self.get(name).cloned().ok_or_else(|| "not found".into())
+    }
+}
+
+/// Reads secrets from environment variables, e.g. `oci-token` from
+/// `GREENTIC_SECRET_OCI_TOKEN` with the default prefix.
+#[derive(Clone, Debug)]
+pub struct EnvSecretsProvider {
+    prefix: String,
+}
+
+impl EnvSecretsProvider {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    fn var_name(&self, name: &str) -> String {
+        let suffix: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}{suffix}", self.prefix)
+    }
+}
+
+impl Default for EnvSecretsProvider {
+    fn default() -> Self {
+        Self::new("GREENTIC_SECRET_")
+    }
+}
+
+impl SecretsProvider for EnvSecretsProvider {
+    fn get_secret(&self, name: &str) -> Result<String, String> {
+        let var = self.var_name(name);
+        std::env::var(&var).map_err(|_| format!("environment variable `{var}` is not set"))
+    }
+}
+
+/// A [`SecretsProvider`] that adds every value it returns to a [`SecretScrubber`].
+pub struct ScrubbedSecrets<P> {
+    inner: P,
+    scrubber: Arc<SecretScrubber>,
+}
+
+impl<P: SecretsProvider> ScrubbedSecrets<P> {
+    pub fn new(inner: P, scrubber: Arc<SecretScrubber>) -> Self {
+        Self { inner, scrubber }
+    }
+}
+
+impl<P: SecretsProvider> SecretsProvider for ScrubbedSecrets<P> {
+    fn get_secret(&self, name: &str) -> Result<String, String> {
+        let value = self.inner.get_secret(name)?;
+        self.scrubber.add(&value);
+        Ok(value)
+    }
+}
+
+/// Known secret values, replaced wherever they appear in text with
+/// `[REDACTED:<hash>]`, where `<hash>` is a prefix of the value's `sha256`. The hash
+/// tells which secret leaked without revealing it.
+#[derive(Default)]
+pub struct SecretScrubber {
+    secrets: RwLock<BTreeSet<String>>,
+}
+
+impl SecretScrubber {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scrub `secret` from now on. Values shorter than [`MIN_SCRUBBED_LEN`] are ignored.
+    pub fn add(&self, secret: impl Into<String>) {
+        let secret = secret.into();
+        if secret.len() >= MIN_SCRUBBED_LEN {
+            self.secrets
+                .write()
+                .expect("secret scrubber poisoned")
+                .insert(secret);
+        }
+    }
+
+    /// `text` with every known secret replaced.
+    pub fn scrub<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let secrets = self.secrets.read().expect("secret scrubber poisoned");
+        let mut text = Cow::Borrowed(text);
+        // Longest first, so a secret containing another one is replaced whole.
+        let mut found: Vec<_> = secrets
+            .iter()
+            .filter(|secret| text.contains(*secret))
+            .collect();
+        found.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        for secret in found {
+            text = Cow::Owned(text.replace(secret.as_str(), &redaction(secret)));
+        }
+        text
+    }
+
+    /// A copy of `value` with known secrets replaced in every string.
+    pub fn scrub_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.scrub(text).into_owned()),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|item| self.scrub_value(item)).collect())
+            }
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, field)| (key.clone(), self.scrub_value(field)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    /// `err` with known secrets replaced in its messages, keeping its error code.
+    pub fn scrub_error(&self, err: McpError) -> McpError {
+        let scrub = |text: String| match self.scrub(&text) {
+            Cow::Borrowed(_) => text,
+            Cow::Owned(scrubbed) => scrubbed,
+        };
+        match err {
+            McpError::ToolNotFound(name) => McpError::ToolNotFound(scrub(name)),
+            McpError::InvalidInput(message) => McpError::InvalidInput(scrub(message)),
+            McpError::ExecutionFailed(message) => McpError::ExecutionFailed(scrub(message)),
+            McpError::Unauthorized(message) => McpError::Unauthorized(scrub(message)),
+            McpError::DeadlineExceeded {
+                name,
+                elapsed,
+                last_error,
+            } => McpError::DeadlineExceeded {
+                name,
+                elapsed,
+                last_error: scrub(last_error),
+            },
+            McpError::Transient(name, message) => McpError::Transient(name, scrub(message)),
+            McpError::Internal(message) => McpError::Internal(scrub(message)),
+            McpError::ToolDisabled { name, reason } => McpError::ToolDisabled {
+                name,
+                reason: scrub(reason),
+            },
+            McpError::RemoteConfig { url, message } => McpError::RemoteConfig {
+                url: scrub(url),
+                message: scrub(message),
+            },
+            McpError::ConfigFile { path, message } => McpError::ConfigFile {
+                path,
+                message: scrub(message),
+            },
+            McpError::Secret {
+                name,
+                location,
+                message,
+            } => McpError::Secret {
+                name,
+                location,
+                message: scrub(message),
+            },
+            other @ (McpError::Io(_) | McpError::Config(_) | McpError::Json(_)) => {
+                match self.scrub(&other.to_string()) {
+                    Cow::Borrowed(_) => other,
+                    Cow::Owned(message) => McpError::Internal(message),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+impl std::fmt::Debug for SecretScrubber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secrets = self.secrets.read().expect("secret scrubber poisoned");
+        f.debug_struct("SecretScrubber")
+            .field("secrets", &secrets.len())
+            .finish()
+    }
+}
+
+fn redaction(secret: &str) -> String {
+    let digest = hex::encode(Sha256::digest(secret.as_bytes()));
+    format!("[REDACTED:{}]", &digest[..8])
+}
+
+impl ToolMapConfig {
+    /// Replace every string of the form `secret://name` with the value from `provider`.
+    ///
+    /// Only whole values are replaced; tool documentation fields are left untouched.
+    pub fn resolve_secrets(&mut self, provider: &dyn SecretsProvider) -> Result<(), McpError> {
+        let mut document = serde_json::to_value(&*self)?;
+        resolve_value(&mut document, provider, &mut String::new())?;
+        *self = schema::validate(document).map_err(McpError::InvalidInput)?;
+        self.resolved = true;
+        Ok(())
+    }
+}
+
+fn resolve_value(
+    value: &mut Value,
+    provider: &dyn SecretsProvider,
+    location: &mut String,
+) -> Result<(), McpError> {
+    match value {
+        Value::String(text) => {
+            if let Some(name) = text.strip_prefix(SECRET_SCHEME) {
+                *text = provider
+                    .get_secret(name)
+                    .map_err(|message| McpError::Secret {
+                        name: name.to_string(),
+                        location: location.clone(),
+                        message,
+                    })?;
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                let len = location.len();
+                location.push_str(&format!("[{index}]"));
+                resolve_value(item, provider, location)?;
+                location.truncate(len);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if DOC_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                let len = location.len();
+                if !location.is_empty() {
+                    location.push('.');
+                }
+                location.push_str(key);
+                resolve_value(field, provider, location)?;
+                location.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ToolRef, ToolSource};
+
+    #[test]
+    fn replaces_secret_references() {
+        let mut config = ToolMapConfig {
+            tools: vec![ToolRef {
+                source: Some(ToolSource::Url("secret://echo-url".into())),
+                description: Some("secret://left-alone".into()),
+                ..ToolRef::new("echo", "", "run")
+            }],
+            ..Default::default()
+        };
+        let secrets = HashMap::from([(
+            "echo-url".to_string(),
+            "https://token@registry.example/echo.wasm".to_string(),
+        )]);
+
+        config.resolve_secrets(&secrets).unwrap();
+        assert_eq!(
+            config.tools[0].source,
+            Some(ToolSource::Url(
+                "https://token@registry.example/echo.wasm".into()
+            ))
+        );
+        assert_eq!(
+            config.tools[0].description.as_deref(),
+            Some("secret://left-alone")
+        );
+    }
+
+    #[test]
+    fn names_location_of_missing_secret() {
+        let mut config = ToolMapConfig {
+            tools: vec![ToolRef::new("echo", "secret://missing", "run")],
+            ..Default::default()
+        };
+
+        let err = config.resolve_secrets(&HashMap::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "secret `missing` referenced at tools[0].component is unavailable: not found"
+        );
+    }
+
+    #[test]
+    fn scrubs_resolved_secrets() {
+        let scrubber = Arc::new(SecretScrubber::new());
+        let secrets = HashMap::from([
+            ("api-key".to_string(), "sk-live-123".to_string()),
+            ("pin".to_string(), "42".to_string()),
+        ]);
+        let provider = ScrubbedSecrets::new(secrets, scrubber.clone());
+        assert_eq!(provider.get_secret("api-key").unwrap(), "sk-live-123");
+        assert_eq!(provider.get_secret("pin").unwrap(), "42");
+
+        let hidden = redaction("sk-live-123");
+        assert!(hidden.starts_with("[REDACTED:") && !hidden.contains("sk-live"));
+        assert_eq!(
+            scrubber.scrub("bad key sk-live-123 (pin 42)"),
+            format!("bad key {hidden} (pin 42)")
+        );
+        assert!(matches!(scrubber.scrub("clean"), Cow::Borrowed("clean")));
+        assert_eq!(
+            scrubber.scrub_value(&serde_json::json!({ "echo": ["sk-live-123"] })),
+            serde_json::json!({ "echo": [hidden.clone()] })
+        );
+
+        let err = scrubber.scrub_error(McpError::Transient("crm".into(), "sk-live-123".into()));
+        assert_eq!(err.code(), crate::types::ErrorCode::RunnerTransient);
+        assert_eq!(
+            err.to_string(),
+            format!("transient failure invoking `crm`: {hidden}")
+        );
+    }
+
+    #[tokio::test]
+    async fn scrubs_invocation_errors() {
+        let scrubber = Arc::new(SecretScrubber::new());
+        scrubber.add("does-not-exist");
+        let executor = crate::executor::WasixExecutor::new()
+            .unwrap()
+            .with_secret_scrubber(scrubber);
+        let tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+        let input = crate::types::ToolInput::new(serde_json::json!({}));
+
+        let err = executor.invoke(&tool, &input).await.unwrap_err();
+        assert!(!err.to_string().contains("does-not-exist"), "{err}");
+        let recent = executor.recent_invocations(&crate::history::HistoryFilter::new());
+        let recorded = &recent[0].error.as_ref().unwrap().message;
+        assert!(recorded.contains("[REDACTED:"), "{recorded}");
+    }
+
+    #[test]
+    fn env_provider_maps_names_to_variables() {
+        let provider = EnvSecretsProvider::default();
+        assert_eq!(provider.var_name("oci-token"), "GREENTIC_SECRET_OCI_TOKEN");
+    }
+}
@@ -0,0 +1,166 @@
+//! A [`ToolMap`] that can be changed at runtime while other threads keep reading it.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolRef};
+use crate::watcher::ToolMapEvent;
+
+/// Tool map supporting dynamic registration from a control plane.
+///
+/// Readers take cheap snapshots via [`SharedToolMap::load`] and never block. Writers
+/// copy the current map, apply their change, and swap the result in; subscribers are
+/// notified with the same events the file watcher emits.
+pub struct SharedToolMap {
+    current: ArcSwap<ToolMap>,
+    write: Mutex<()>,
+    subscribers: Mutex<Vec<Sender<ToolMapEvent>>>,
+}
+
+impl SharedToolMap {
+    pub fn new(map: ToolMap) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(map),
+            write: Mutex::new(()),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Snapshot of the current map.
+    pub fn load(&self) -> Arc<ToolMap> {
+        self.current.load_full()
+    }
+
+    /// Receive an event for every subsequent change.
+    pub fn subscribe(&self) -> Receiver<ToolMapEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers
+            .lock()
+            .expect("subscriber lock poisoned")
+            .push(tx);
+        rx
+    }
+
+    /// Add `tool`, or replace the registered tool with the same key.
+    ///
+    /// Events carry the tool as stored, with the map's defaults applied. Registering a
+    /// disabled tool (see [`ToolMap::insert`]) removes the tool it replaces.
+    pub fn register(&self, tool: ToolRef) -> Result<(), McpError> {
+        let _guard = self.write.lock().expect("tool map lock poisoned");
+        let mut next = ToolMap::clone(&self.current.load());
+        let key = tool.key();
+        let previous = next.insert(tool)?;
+        let stored = next.get(&key).ok().cloned();
+        self.current.store(Arc::new(next));
+
+        let event = match (previous, stored) {
+            (Some(previous), Some(stored)) if previous == stored => return Ok(()),
+            (Some(previous), Some(stored)) => ToolMapEvent::Updated {
+                previous: Box::new(previous),
+                current: Box::new(stored),
+            },
+            (None, Some(stored)) => ToolMapEvent::Added(stored),
+            (Some(previous), None) => ToolMapEvent::Removed(previous),
+            (None, None) => return Ok(()),
+        };
+        self.notify(event);
+        Ok(())
+    }
+
+    /// Remove the tool addressed by `name`, returning it if it was registered.
+    pub fn unregister(&self, name: &str) -> Option<ToolRef> {
+        let _guard = self.write.lock().expect("tool map lock poisoned");
+        let mut next = ToolMap::clone(&self.current.load());
+        let removed = next.remove(name)?;
+        self.current.store(Arc::new(next));
+
+        self.notify(ToolMapEvent::Removed(removed.clone()));
+        Some(removed)
+    }
+
+    fn notify(&self, event: ToolMapEvent) {
+        let mut subscribers = self.subscribers.lock().expect("subscriber lock poisoned");
+        subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+impl From<ToolMap> for SharedToolMap {
+    fn from(map: ToolMap) -> Self {
+        Self::new(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ToolDefaults, ToolMapConfig};
+
+    #[test]
+    fn register_and_unregister_notify_subscribers() {
+        let shared = SharedToolMap::new(ToolMap::from_config(&ToolMapConfig::default()).unwrap());
+        let events = shared.subscribe();
+        let before = shared.load();
+
+        shared
+            .register(ToolRef::new("echo", "./echo.wasm", "run"))
+            .unwrap();
+        shared
+            .register(ToolRef {
+                timeout_ms: Some(100),
+                ..ToolRef::new("echo", "./echo.wasm", "run")
+            })
+            .unwrap();
+        assert_eq!(shared.unregister("echo").unwrap().timeout_ms, Some(100));
+        assert!(shared.unregister("echo").is_none());
+
+        assert!(matches!(events.try_recv(), Ok(ToolMapEvent::Added(_))));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ToolMapEvent::Updated { .. })
+        ));
+        assert!(matches!(events.try_recv(), Ok(ToolMapEvent::Removed(_))));
+        assert!(events.try_recv().is_err());
+        assert!(before.get("echo").is_err(), "snapshots are immutable");
+    }
+
+    #[test]
+    fn registering_a_disabled_tool_removes_the_active_one() {
+        let config = ToolMapConfig {
+            defaults: Some(ToolDefaults {
+                timeout_ms: Some(250),
+                ..ToolDefaults::default()
+            }),
+            ..ToolMapConfig::default()
+        };
+        let shared = SharedToolMap::new(ToolMap::from_config(&config).unwrap());
+        let events = shared.subscribe();
+        let disabled = || ToolRef {
+            enabled: Some(false),
+            ..ToolRef::new("echo", "./echo.wasm", "run")
+        };
+
+        shared.register(disabled()).unwrap();
+        shared
+            .register(ToolRef::new("echo", "./echo.wasm", "run"))
+            .unwrap();
+        // Only inherited defaults differ from the stored copy.
+        shared
+            .register(ToolRef::new("echo", "./echo.wasm", "run"))
+            .unwrap();
+        shared.register(disabled()).unwrap();
+
+        match events.try_recv() {
+            Ok(ToolMapEvent::Added(tool)) => assert_eq!(tool.timeout_ms, Some(250)),
+            other => panic!("expected the stored tool to be added, got {other:?}"),
+        }
+        assert!(matches!(events.try_recv(), Ok(ToolMapEvent::Removed(_))));
+        assert!(events.try_recv().is_err());
+        assert!(matches!(
+            shared.load().get("echo"),
+            Err(McpError::ToolDisabled { .. })
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+//! Snapshots of what the tools of a map say about themselves, for drift detection.
+//!
+//! [`ToolMap::describe_snapshot`] runs [`describe_tool`] for every tool and keeps
+//! the normalized results in a [`DescribeSnapshot`], saved as JSON next to the map.
+//! [`DescribeSnapshot::compare`] checks a new snapshot against the saved one and
+//! flags tools whose capabilities, secrets, or schemas changed, including new
+//! versions of a tool that replace the snapshotted one.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use mcp_exec::describe::{Maybe, ToolDescribe, describe_tool};
+use mcp_exec::{ExecConfig, ToolStore, VerifyPolicy};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolRef, ToolSource};
+
+/// Describe results of the tools of a map, keyed by tool key.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DescribeSnapshot {
+    pub tools: BTreeMap<String, ToolSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolSnapshot {
+    /// Name without version (`namespace/name` for namespaced tools).
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Normalized describe results, see [`describe_json`].
+    pub describe: Value,
+}
+
+impl ToolMap {
+    /// Describe every tool with a local component.
+    ///
+    /// Tools fetched from remote sources or served by MCP servers are left out.
+    /// Pinned digests are checked first. This blocks on I/O and on running the
+    /// components, so async callers should run it on a blocking thread.
+    pub fn describe_snapshot(&self) -> Result<DescribeSnapshot, McpError> {
+        let mut snapshot = DescribeSnapshot::default();
+        for (key, tool) in self.iter() {
+            if !matches!(tool.source(), ToolSource::Path(_)) {
+                continue;
+            }
+            let describe = describe_map_tool(tool)?;
+            snapshot.tools.insert(
+                key.clone(),
+                ToolSnapshot {
+                    name: tool.qualified_name(),
+                    version: tool.version.clone(),
+                    describe: describe_json(&describe),
+                },
+            );
+        }
+        Ok(snapshot)
+    }
+}
+
+impl DescribeSnapshot {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, McpError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|err| McpError::config_file(path, err))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), McpError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Compare `self` (the new snapshot) against `previous`.
+    ///
+    /// A tool is compared with its own key in `previous` or, for a new version, with
+    /// the last version of the same tool that is no longer in `self`.
+    pub fn compare(&self, previous: &DescribeSnapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+        let mut replaced = BTreeSet::new();
+        for (key, current) in &self.tools {
+            let predecessor =
+                previous.tools.get_key_value(key).or_else(|| {
+                    previous.tools.iter().rev().find(|(key, tool)| {
+                        tool.name == current.name && !self.tools.contains_key(*key)
+                    })
+                });
+            let Some((previous_key, before)) = predecessor else {
+                diff.added.push(key.clone());
+                continue;
+            };
+            replaced.insert(previous_key);
+            let fields = changed_fields(&before.describe, &current.describe);
+            if !fields.is_empty() {
+                diff.changed.push(DescribeDrift {
+                    key: key.clone(),
+                    previous: previous_key.clone(),
+                    fields,
+                });
+            }
+        }
+        diff.removed = previous
+            .tools
+            .keys()
+            .filter(|key| !self.tools.contains_key(*key) && !replaced.contains(key))
+            .cloned()
+            .collect();
+        diff
+    }
+}
+
+fn changed_fields(previous: &Value, current: &Value) -> Vec<String> {
+    let empty = serde_json::Map::new();
+    let previous = previous.as_object().unwrap_or(&empty);
+    let current = current.as_object().unwrap_or(&empty);
+    let keys: BTreeSet<&String> = previous.keys().chain(current.keys()).collect();
+    keys.into_iter()
+        .filter(|field| previous.get(*field) != current.get(*field))
+        .cloned()
+        .collect()
+}
+
+/// Differences between two describe snapshots.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SnapshotDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<DescribeDrift>,
+}
+
+impl SnapshotDiff {
+    /// Whether every tool describes itself as before.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for key in &self.added {
+            writeln!(f, "added:   {key}")?;
+        }
+        for key in &self.removed {
+            writeln!(f, "removed: {key}")?;
+        }
+        for drift in &self.changed {
+            writeln!(f, "{drift}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A tool that describes itself differently than in the previous snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DescribeDrift {
+    pub key: String,
+    /// Key of the snapshot compared against; another version for new versions.
+    pub previous: String,
+    /// Describe fields that differ, sorted alphabetically.
+    pub fields: Vec<String>,
+}
+
+impl fmt::Display for DescribeDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "changed: {}", self.key)?;
+        if self.previous != self.key {
+            write!(f, " (from {})", self.previous)?;
+        }
+        write!(f, ": {}", self.fields.join(", "))
+    }
+}
+
+/// Describe a tool whose component is a local file.
+pub fn describe_map_tool(tool: &ToolRef) -> Result<ToolDescribe, McpError> {
+    let key = tool.key();
+    let ToolSource::Path(path) = tool.source() else {
+        return Err(McpError::InvalidInput(format!(
+            "tool `{key}` is not a local component ({})",
+            tool.source()
+        )));
+    };
+    let (Some(dir), Some(name)) = (path.parent(), path.file_stem().and_then(|s| s.to_str())) else {
+        return Err(McpError::InvalidInput(format!(
+            "tool `{key}` has no component file name"
+        )));
+    };
+    // Pinned tools are checked against their digest, as when they run.
+    let security = match &tool.sha256 {
+        Some(digest) => VerifyPolicy {
+            required_digests: [(name.to_string(), digest.clone())].into(),
+            ..VerifyPolicy::default()
+        },
+        None => VerifyPolicy {
+            allow_unverified: true,
+            ..VerifyPolicy::default()
+        },
+    };
+    let cfg = ExecConfig {
+        store: ToolStore::LocalDir(dir.to_path_buf()),
+        security,
+        runtime: Default::default(),
+        tenant_runtime: Default::default(),
+        http_enabled: false,
+    };
+    describe_tool(name, &cfg)
+        .map_err(|err| McpError::ExecutionFailed(format!("failed to describe `{key}`: {err:#}")))
+}
+
+/// The describe results as one JSON object; `null` marks what the component does not
+/// support. Capability lists are sorted, so their order does not count as a change.
+pub fn describe_json(describe: &ToolDescribe) -> Value {
+    let capabilities = match &describe.capabilities {
+        Maybe::Data(capabilities) => {
+            let sorted: BTreeSet<&String> = capabilities.iter().collect();
+            json!(sorted)
+        }
+        Maybe::Unsupported => Value::Null,
+    };
+    json!({
+        "describe_v1": describe.describe_v1,
+        "capabilities": capabilities,
+        "secrets": data(&describe.secrets),
+        "config_schema": data(&describe.config_schema),
+        "input_schema": data(&describe.input_schema),
+    })
+}
+
+fn data(maybe: &Maybe<Value>) -> Value {
+    match maybe {
+        Maybe::Data(value) => value.clone(),
+        Maybe::Unsupported => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ToolMapBuilder;
+
+    #[test]
+    fn flags_changed_schemas_of_new_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mock = |file: &str, capabilities: Value, city: &str| {
+            let responses = json!({
+                "capabilities": capabilities,
+                "input_schema": { "properties": { "city": { "type": city } } },
+            });
+            let path = tmp.path().join(file);
+            let mock = json!({ "_mock_mcp_exec": true, "responses": responses });
+            std::fs::write(&path, mock.to_string()).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let map = |version: &str, component: String| {
+            ToolMapBuilder::new()
+                .tool("weather")
+                .version(version)
+                .component(component)
+                .build()
+                .unwrap()
+        };
+
+        let v1 = map(
+            "1.0.0",
+            mock("weather-1.wasm", json!(["kv", "http"]), "string"),
+        );
+        let saved = tmp.path().join("describe.json");
+        v1.describe_snapshot().unwrap().write(&saved).unwrap();
+        let previous = DescribeSnapshot::read(&saved).unwrap();
+        let tool = &previous.tools["weather@1.0.0"];
+        assert_eq!(tool.describe["capabilities"], json!(["http", "kv"]));
+        assert_eq!(tool.describe["secrets"], Value::Null);
+
+        let v2 = map(
+            "1.1.0",
+            mock("weather-2.wasm", json!(["http", "kv"]), "string"),
+        );
+        assert!(
+            v2.describe_snapshot()
+                .unwrap()
+                .compare(&previous)
+                .is_empty()
+        );
+
+        let v3 = map("2.0.0", mock("weather-3.wasm", json!(["http"]), "number"));
+        let diff = v3.describe_snapshot().unwrap().compare(&previous);
+        assert_eq!(
+            diff.changed,
+            [DescribeDrift {
+                key: "weather@2.0.0".into(),
+                previous: "weather@1.0.0".into(),
+                fields: vec!["capabilities".into(), "input_schema".into()],
+            }]
+        );
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(
+            diff.to_string(),
+            "changed: weather@2.0.0 (from weather@1.0.0): capabilities, input_schema\n"
+        );
+    }
+}
@@ -0,0 +1,355 @@
+//! Invocation metrics, recorded through the [`metrics`] facade.
+//!
+//! Nothing is collected until the host installs a recorder. With the `prometheus`
+//! feature, [`install_prometheus`] installs one that serves the Prometheus text format
+//! over HTTP; any other `metrics` exporter works as well.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use metrics::{
+    Unit, counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram,
+};
+
+use crate::types::{ErrorCode, McpError};
+
+/// Finished invocations, by `tool` and `outcome` (`ok`, `error`, `timeout`,
+/// `cancelled`).
+pub const INVOCATIONS: &str = "greentic_mcp_invocations_total";
+/// Wall-clock time of finished invocations including retries, by `tool` and `outcome`.
+pub const INVOCATION_DURATION: &str = "greentic_mcp_invocation_duration_seconds";
+/// Attempts after the first, by `tool`.
+pub const RETRIES: &str = "greentic_mcp_retries_total";
+/// Invocations currently running, by `tool`.
+pub const IN_FLIGHT: &str = "greentic_mcp_invocations_in_flight";
+/// Time spent in each phase of an attempt, by `tool` and `phase`.
+pub const PHASE_DURATION: &str = "greentic_mcp_phase_duration_seconds";
+/// Cache lookups, by `cache` and `result` (`hit` or `miss`).
+pub const CACHE_LOOKUPS: &str = "greentic_mcp_cache_lookups_total";
+
+/// Register units and help texts with the installed recorder.
+pub fn describe_metrics() {
+    describe_counter!(INVOCATIONS, "Finished tool invocations");
+    describe_histogram!(
+        INVOCATION_DURATION,
+        Unit::Seconds,
+        "Tool invocation latency, including retries"
+    );
+    describe_histogram!(
+        PHASE_DURATION,
+        Unit::Seconds,
+        "Time spent resolving, verifying, compiling, instantiating, and calling components"
+    );
+    describe_counter!(RETRIES, "Retried tool invocation attempts");
+    describe_gauge!(IN_FLIGHT, "Tool invocations currently running");
+    describe_counter!(CACHE_LOOKUPS, "Cache lookups by result");
+}
+
+/// Install a Prometheus recorder serving `/metrics` on `addr`.
+///
+/// Call from within a Tokio runtime, which then runs the HTTP listener.
+#[cfg(feature = "prometheus")]
+pub fn install_prometheus(addr: std::net::SocketAddr) -> Result<(), McpError> {
+    use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .set_buckets_for_metric(
+            Matcher::Full(INVOCATION_DURATION.to_string()),
+            &DURATION_BUCKETS,
+        )
+        .and_then(|builder| {
+            builder.set_buckets_for_metric(
+                Matcher::Full(PHASE_DURATION.to_string()),
+                &DURATION_BUCKETS,
+            )
+        })
+        .and_then(PrometheusBuilder::install)
+        .map_err(|err| {
+            McpError::Internal(format!("failed to install Prometheus exporter: {err}"))
+        })?;
+    describe_metrics();
+    Ok(())
+}
+
+/// Buckets for [`INVOCATION_DURATION`] and [`PHASE_DURATION`], from component cache
+/// hits to long tool runs.
+#[cfg(feature = "prometheus")]
+const DURATION_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Phases of an attempt to run a component, named like their tracing spans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Resolve,
+    Verify,
+    Compile,
+    Instantiate,
+    Call,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Resolve,
+        Phase::Verify,
+        Phase::Compile,
+        Phase::Instantiate,
+        Phase::Call,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Resolve => "resolve",
+            Phase::Verify => "verify",
+            Phase::Compile => "compile",
+            Phase::Instantiate => "instantiate",
+            Phase::Call => "call",
+        }
+    }
+}
+
+/// Time spent in each phase of an invocation's last attempt. Phases the attempt did
+/// not reach, and every phase of remote MCP tools, are `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    pub resolve: Option<Duration>,
+    pub verify: Option<Duration>,
+    pub compile: Option<Duration>,
+    pub instantiate: Option<Duration>,
+    pub call: Option<Duration>,
+}
+
+impl PhaseTimings {
+    pub fn get(&self, phase: Phase) -> Option<Duration> {
+        *self.slot(phase)
+    }
+
+    /// The phases that were reached, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (Phase, Duration)> + '_ {
+        Phase::ALL
+            .into_iter()
+            .filter_map(|phase| Some((phase, self.get(phase)?)))
+    }
+
+    fn slot(&self, phase: Phase) -> &Option<Duration> {
+        match phase {
+            Phase::Resolve => &self.resolve,
+            Phase::Verify => &self.verify,
+            Phase::Compile => &self.compile,
+            Phase::Instantiate => &self.instantiate,
+            Phase::Call => &self.call,
+        }
+    }
+
+    fn slot_mut(&mut self, phase: Phase) -> &mut Option<Duration> {
+        match phase {
+            Phase::Resolve => &mut self.resolve,
+            Phase::Verify => &mut self.verify,
+            Phase::Compile => &mut self.compile,
+            Phase::Instantiate => &mut self.instantiate,
+            Phase::Call => &mut self.call,
+        }
+    }
+}
+
+/// Collects [`PhaseTimings`] from the thread running an attempt.
+#[derive(Clone, Debug)]
+pub(crate) struct PhaseClock {
+    tool: Arc<str>,
+    timings: Arc<Mutex<PhaseTimings>>,
+}
+
+impl PhaseClock {
+    pub(crate) fn new(tool: &str) -> Self {
+        Self {
+            tool: tool.into(),
+            timings: Arc::default(),
+        }
+    }
+
+    /// Forget the phases of a previous attempt.
+    pub(crate) fn reset(&self) {
+        *self.timings.lock().expect("phase clock poisoned") = PhaseTimings::default();
+    }
+
+    /// Run `f` as `phase`, recording how long it took.
+    pub(crate) fn time<T>(&self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let output = f();
+        self.record(phase, started.elapsed());
+        output
+    }
+
+    pub(crate) fn record(&self, phase: Phase, elapsed: Duration) {
+        *self
+            .timings
+            .lock()
+            .expect("phase clock poisoned")
+            .slot_mut(phase) = Some(elapsed);
+    }
+
+    pub(crate) fn timings(&self) -> PhaseTimings {
+        *self.timings.lock().expect("phase clock poisoned")
+    }
+
+    /// Add the phases of the finished attempt to [`PHASE_DURATION`]. Called from the
+    /// invoking task rather than the guest thread, so task-local recorders see them.
+    pub(crate) fn observe(&self) {
+        for (phase, elapsed) in self.timings().iter() {
+            histogram!(PHASE_DURATION, "tool" => self.tool.to_string(), "phase" => phase.as_str())
+                .record(elapsed.as_secs_f64());
+        }
+    }
+}
+
+/// Tracks one invocation from start to finish.
+///
+/// Counts as in flight until dropped; an invocation dropped before
+/// [`finish`](Self::finish) (its future was abandoned) is recorded as `cancelled`.
+pub(crate) struct InvocationMetrics {
+    tool: String,
+    started: Instant,
+    finished: bool,
+}
+
+impl InvocationMetrics {
+    pub(crate) fn start(tool: &str) -> Self {
+        gauge!(IN_FLIGHT, "tool" => tool.to_string()).increment(1.0);
+        Self {
+            tool: tool.to_string(),
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    pub(crate) fn finish(mut self, outcome: &'static str) {
+        self.record(outcome);
+    }
+
+    fn record(&mut self, outcome: &'static str) {
+        self.finished = true;
+        let labels = [
+            ("tool", self.tool.clone()),
+            ("outcome", outcome.to_string()),
+        ];
+        counter!(INVOCATIONS, &labels).increment(1);
+        histogram!(INVOCATION_DURATION, &labels).record(self.started.elapsed().as_secs_f64());
+    }
+}
+
+impl Drop for InvocationMetrics {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.record("cancelled");
+        }
+        gauge!(IN_FLIGHT, "tool" => self.tool.clone()).decrement(1.0);
+    }
+}
+
+/// Outcome label for an executor result.
+pub(crate) fn outcome<T>(result: &Result<T, McpError>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(err) => error_outcome(err),
+    }
+}
+
+pub(crate) fn error_outcome(err: &McpError) -> &'static str {
+    match err.code() {
+        code if code.is_timeout() => "timeout",
+        ErrorCode::RunnerCancelled => "cancelled",
+        _ => "error",
+    }
+}
+
+/// Count an attempt; every attempt after the first is a retry.
+pub(crate) fn attempt(tool: &str, attempt: u32) {
+    if attempt > 1 {
+        counter!(RETRIES, "tool" => tool.to_string()).increment(1);
+    }
+}
+
+pub(crate) fn cache_lookup(cache: &'static str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    counter!(CACHE_LOOKUPS, "cache" => cache, "result" => result).increment(1);
+}
+
+#[cfg(all(test, feature = "prometheus"))]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use mcp_exec::{ExecConfig, ExecError, ExecRequest, RuntimePolicy, ToolStore};
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use serde_json::json;
+
+    use crate::executor::WasixExecutor;
+    use crate::types::{ToolInput, ToolRef};
+
+    #[test]
+    fn records_invocations_and_retries() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        metrics::with_local_recorder(&recorder, || {
+            runtime.block_on(async {
+                let tool = ToolRef::new("missing", "./does-not-exist.wasm", "tool-invoke");
+                let input = ToolInput::new(json!({}));
+                let result = WasixExecutor::new().unwrap().invoke(&tool, &input).await;
+                assert!(result.is_err());
+
+                let tmp = tempfile::tempdir().unwrap();
+                let cfg = ExecConfig {
+                    store: ToolStore::LocalDir(tmp.path().into()),
+                    security: Default::default(),
+                    runtime: RuntimePolicy {
+                        max_attempts: 3,
+                        base_backoff: Duration::from_millis(1),
+                        ..RuntimePolicy::default()
+                    },
+                    tenant_runtime: Default::default(),
+                    http_enabled: false,
+                };
+                let req = ExecRequest {
+                    component: "flaky".into(),
+                    action: "tool-invoke".into(),
+                    args: json!({}),
+                    tenant: None,
+                    correlation_id: None,
+                    identity: None,
+                };
+                let calls = Arc::new(AtomicU32::new(0));
+                let result = crate::exec_with_retries_backend(req, &cfg, move |req, _| {
+                    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        Err(ExecError::tool_error(
+                            req.component,
+                            req.action,
+                            "transient.flaky",
+                            json!({}),
+                        ))
+                    } else {
+                        Ok(req.args)
+                    }
+                })
+                .await;
+                assert!(result.is_ok());
+            })
+        });
+
+        let rendered = handle.render();
+        for expected in [
+            r#"greentic_mcp_invocations_total{tool="missing",outcome="error"} 1"#,
+            r#"greentic_mcp_invocations_total{tool="flaky",outcome="ok"} 1"#,
+            r#"greentic_mcp_retries_total{tool="flaky"} 1"#,
+            r#"greentic_mcp_invocations_in_flight{tool="missing"} 0"#,
+            r#"greentic_mcp_phase_duration_seconds_count{tool="missing",phase="resolve"} 1"#,
+        ] {
+            assert!(rendered.contains(expected), "{expected} in\n{rendered}");
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Per-tenant views of a shared tool map.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use greentic_types::TenantCtx;
+
+use crate::config::apply_overlay_file;
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolMapConfig, ToolRef};
+
+/// A base [`ToolMap`] plus overlays for individual tenants.
+///
+/// Each overlay uses the same format as environment overlays: entries override fields of
+/// the matching base tool, `remove: true` hides a tool, and unmatched entries add new
+/// tools. Lookups pick the overlay registered for `TenantCtx::tenant_id` and fall back to
+/// the base map for tenants without one.
+#[derive(Clone, Debug)]
+pub struct TenantToolMaps {
+    config: ToolMapConfig,
+    base: ToolMap,
+    tenants: HashMap<String, ToolMap>,
+}
+
+impl TenantToolMaps {
+    /// Start from a loaded base configuration.
+    pub fn new(config: ToolMapConfig) -> Result<Self, McpError> {
+        let base = ToolMap::from_config(&config)?;
+        Ok(Self {
+            config,
+            base,
+            tenants: HashMap::new(),
+        })
+    }
+
+    /// Register the overlay at `path` for `tenant_id`, replacing any previous overlay.
+    pub fn with_overlay(
+        mut self,
+        tenant_id: impl Into<String>,
+        path: &Path,
+    ) -> Result<Self, McpError> {
+        let config = apply_overlay_file(&self.config, path)?;
+        self.tenants
+            .insert(tenant_id.into(), ToolMap::from_config(&config)?);
+        Ok(self)
+    }
+
+    /// The map shared by tenants without an overlay.
+    pub fn base(&self) -> &ToolMap {
+        &self.base
+    }
+
+    /// The effective map for `tenant`.
+    pub fn for_tenant(&self, tenant: &TenantCtx) -> &ToolMap {
+        self.tenants
+            .get(tenant.tenant_id.as_str())
+            .unwrap_or(&self.base)
+    }
+
+    /// Resolve `name` in the effective map for `tenant`.
+    pub fn get(&self, tenant: &TenantCtx, name: &str) -> Result<&ToolRef, McpError> {
+        self.for_tenant(tenant).get(name)
+    }
+
+    /// Tenant identifiers that have an overlay registered.
+    pub fn tenants(&self) -> impl Iterator<Item = &str> {
+        self.tenants.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WasixExecutor, invoke_with_map_as};
+    use greentic_types::{EnvId, TenantId};
+
+    fn ctx(tenant: &str) -> TenantCtx {
+        TenantCtx::new(
+            EnvId::try_from("dev").unwrap(),
+            TenantId::try_from(tenant).unwrap(),
+        )
+    }
+
+    #[test]
+    fn lookups_use_the_tenant_overlay() {
+        let tmp = tempfile::tempdir().unwrap();
+        let overlay = tmp.path().join("acme.yaml");
+        std::fs::write(
+            &overlay,
+            "tools:\n  - name: echo\n    timeout_ms: 9000\n  - name: admin\n    remove: true\n  - name: crm\n    component: ./crm.wasm\n    entry: run\n",
+        )
+        .unwrap();
+
+        let config = ToolMapConfig {
+            tools: vec![
+                ToolRef::new("echo", "./echo.wasm", "run"),
+                ToolRef::new("admin", "./admin.wasm", "run"),
+            ],
+            ..Default::default()
+        };
+        let maps = TenantToolMaps::new(config)
+            .unwrap()
+            .with_overlay("acme", &overlay)
+            .unwrap();
+
+        let acme = ctx("acme");
+        assert_eq!(maps.get(&acme, "echo").unwrap().timeout_ms, Some(9000));
+        assert!(maps.get(&acme, "admin").is_err());
+        assert!(maps.get(&acme, "crm").is_ok());
+
+        let other = ctx("globex");
+        assert_eq!(maps.get(&other, "echo").unwrap().timeout_ms, None);
+        assert!(maps.get(&other, "admin").is_ok());
+        assert!(maps.get(&other, "crm").is_err());
+    }
+
+    #[tokio::test]
+    async fn invocations_carry_the_tenant() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, crate::executor::tests::echo_component()).unwrap();
+        let config = ToolMapConfig {
+            tools: vec![ToolRef {
+                allowed_tenants: vec!["acme".into()],
+                ..ToolRef::new("echo", path.to_string_lossy(), "tool-invoke")
+            }],
+            ..Default::default()
+        };
+        let maps = TenantToolMaps::new(config).unwrap();
+        let executor = WasixExecutor::new().unwrap();
+        let input = serde_json::json!({ "text": "hi" });
+
+        let acme = ctx("acme");
+        let output = invoke_with_map_as(
+            maps.for_tenant(&acme),
+            &executor,
+            "echo",
+            input.clone(),
+            acme.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, input);
+        let other = ctx("globex");
+        let err = invoke_with_map_as(maps.for_tenant(&other), &executor, "echo", input, other)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), crate::ErrorCode::Forbidden);
+
+        let recent = executor.recent_invocations(&crate::HistoryFilter::new());
+        let tenants: Vec<_> = recent
+            .iter()
+            .map(|record| record.tenant.as_deref())
+            .collect();
+        assert_eq!(tenants, [Some("globex"), Some("acme")]);
+    }
+}
@@ -1,23 +1,209 @@
-use serde_json::Value;
-use std::sync::atomic::{AtomicUsize, Ordering};
+//! Native stand-ins for tools, for tests that run without Wasm, with the
+//! `test-util` feature.
+//!
+//! [`exec_test_backend`] runs a [`TestBackend`]: simulated tools that echo, fail
+//! transiently a set number of times, respond slowly, return oversized output, or
+//! return invalid JSON, failing the way the `mcp-exec` runner does.
+//! [`NativeTools`] registers closures as tools by name.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Duration;
 
-static FLAKY_ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+use mcp_exec::{ExecConfig, ExecError, ExecRequest, RunnerError};
+use serde_json::{Value, json};
+
+use crate::builder::ToolMapBuilder;
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolRef};
+
+/// Output of [`TestBackend::NativeInvalidJson`]: a truncated JSON object.
+pub const INVALID_JSON: &str = r#"{"result": "trunc"#;
 
 pub fn echo(req: &Value) -> Result<Value, String> {
     Ok(req.clone())
 }
 
-pub fn flaky_echo(req: &Value) -> Result<Value, String> {
-    let attempt = FLAKY_ATTEMPTS.fetch_add(1, Ordering::SeqCst);
-    if attempt < 2 {
-        Err("transient.echo".to_string())
-    } else {
-        Ok(req.clone())
+/// Echo that fails with `transient.echo` until it has failed `failures` times.
+#[derive(Debug)]
+pub struct FlakyEcho {
+    failures: u32,
+    calls: AtomicU32,
+}
+
+impl FlakyEcho {
+    pub fn new(failures: u32) -> Self {
+        Self {
+            failures,
+            calls: AtomicU32::new(0),
+        }
+    }
+
+    pub fn call(&self, req: &Value) -> Result<Value, String> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+            Err("transient.echo".to_string())
+        } else {
+            Ok(req.clone())
+        }
+    }
+
+    /// Calls so far, failed or not.
+    pub fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
     }
 }
 
-pub fn timeout_echo(req: &Value, sleep: Duration) -> Result<Value, String> {
-    std::thread::sleep(sleep);
+pub fn slow_echo(req: &Value, delay: Duration) -> Result<Value, String> {
+    std::thread::sleep(delay);
     Ok(req.clone())
 }
+
+/// An object whose `data` string is `len` bytes long.
+pub fn oversized(len: usize) -> Value {
+    json!({ "data": "x".repeat(len) })
+}
+
+/// Simulated tools run by [`exec_test_backend`].
+#[derive(Clone)]
+pub enum TestBackend {
+    NativeEcho,
+    /// A [`FlakyEcho`], shared by the attempts of a call.
+    NativeFlaky(Arc<FlakyEcho>),
+    /// Echo after a delay. Delays longer than the per-call timeout time out.
+    NativeSlow(Duration),
+    /// Return [`oversized`] output of this many bytes.
+    NativeOversized(usize),
+    /// Return [`INVALID_JSON`], which fails like unparsable guest output.
+    NativeInvalidJson,
+    /// A closure, e.g. one registered in [`NativeTools`].
+    Native(NativeFn),
+}
+
+pub fn exec_test_backend(
+    backend: TestBackend,
+    input: Value,
+    cfg: &ExecConfig,
+) -> Result<Value, ExecError> {
+    match backend {
+        TestBackend::NativeEcho => {
+            echo(&input).map_err(|message| tool_error("echo", "tool-invoke", "echo", message))
+        }
+        TestBackend::NativeFlaky(flaky) => flaky
+            .call(&input)
+            .map_err(|code| tool_error("echo-flaky", "tool-invoke", &code, code.clone())),
+        TestBackend::NativeSlow(delay) => {
+            if delay > cfg.runtime.per_call_timeout {
+                std::thread::sleep(cfg.runtime.per_call_timeout);
+                Err(ExecError::runner(
+                    "echo-slow",
+                    RunnerError::Timeout {
+                        elapsed: cfg.runtime.per_call_timeout,
+                    },
+                ))
+            } else {
+                slow_echo(&input, delay)
+                    .map_err(|message| tool_error("echo-slow", "tool-invoke", "slow", message))
+            }
+        }
+        TestBackend::NativeOversized(len) => Ok(oversized(len)),
+        TestBackend::NativeInvalidJson => serde_json::from_str(INVALID_JSON)
+            .map_err(|err| ExecError::runner("invalid-json", RunnerError::Serde(err))),
+        TestBackend::Native(tool) => {
+            tool(input).map_err(|code| tool_error("native", "tool-invoke", &code, code.clone()))
+        }
+    }
+}
+
+fn tool_error(component: &str, action: &str, code: &str, message: String) -> ExecError {
+    ExecError::tool_error(component, action, code, json!({ "message": message }))
+}
+
+/// Closure standing in for a tool. An `Err` fails the call with that string as
+/// the tool error code, e.g. `transient.unavailable` for a retryable failure.
+pub type NativeFn = Arc<dyn Fn(Value) -> Result<Value, String> + Send + Sync>;
+
+/// Native tools by name, for flows tested without Wasm components.
+///
+/// [`tool_map`](Self::tool_map) lists them as a [`ToolMap`] whose tools use their
+/// name as component, and [`backend`](Self::backend) runs them for
+/// [`exec_with_retries_backend`](crate::exec_with_retries_backend).
+#[derive(Clone, Default)]
+pub struct NativeTools {
+    tools: BTreeMap<String, NativeFn>,
+}
+
+impl NativeTools {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `tool` as `name`.
+    pub fn with_tool<F>(mut self, name: impl Into<String>, tool: F) -> Self
+    where
+        F: Fn(Value) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        self.register(name, tool);
+        self
+    }
+
+    /// Register `tool` as `name`, returning the tool it replaces.
+    pub fn register<F>(&mut self, name: impl Into<String>, tool: F) -> Option<NativeFn>
+    where
+        F: Fn(Value) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        self.tools.insert(name.into(), Arc::new(tool))
+    }
+
+    pub fn get(&self, name: &str) -> Result<&NativeFn, McpError> {
+        self.tools
+            .get(name)
+            .ok_or_else(|| McpError::tool_not_found(name.to_string()))
+    }
+
+    /// Registered names, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools.keys().map(String::as_str)
+    }
+
+    /// The registered tools as a [`ToolMap`], invoked through [`ToolRef::DEFAULT_ENTRY`].
+    pub fn tool_map(&self) -> Result<ToolMap, McpError> {
+        self.names()
+            .fold(ToolMapBuilder::new(), |map, name| {
+                map.tool_ref(ToolRef::new(name, name, ToolRef::DEFAULT_ENTRY))
+            })
+            .build()
+    }
+
+    /// Run the tool named by `req.component` on `req.args`.
+    pub fn exec(&self, req: ExecRequest, _cfg: &ExecConfig) -> Result<Value, ExecError> {
+        let Some(tool) = self.tools.get(&req.component) else {
+            return Err(ExecError::not_found(req.component, req.action));
+        };
+        tool(req.args).map_err(|code| {
+            ExecError::tool_error(
+                req.component,
+                req.action,
+                code.clone(),
+                json!({ "message": code }),
+            )
+        })
+    }
+
+    /// An exec function running the registered tools.
+    pub fn backend(
+        &self,
+    ) -> impl Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Send + Sync + 'static {
+        let tools = self.clone();
+        move |req, cfg| tools.exec(req, cfg)
+    }
+}
+
+impl fmt::Debug for NativeTools {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeTools")
+            .field("tools", &self.tools.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -1,39 +1,702 @@
+use std::str::FromStr;
+
 use indexmap::IndexMap;
 
-use crate::types::{McpError, ToolMapConfig, ToolRef};
+use crate::types::{McpError, ToolDefaults, ToolMapConfig, ToolRef};
 
 /// Name to [`ToolRef`] lookup.
+///
+/// Namespaced tools are keyed as `namespace/name`. Versioned tools are keyed as
+/// `name@version`; the bare name resolves to the version flagged with `default_version`,
+/// or to the last declared version otherwise. Disabled tools are not registered, but
+/// looking them up reports why they are unavailable.
 #[derive(Clone, Debug)]
 pub struct ToolMap {
     tools: IndexMap<String, ToolRef>,
+    defaults: IndexMap<String, String>,
+    disabled: IndexMap<String, String>,
+    /// Config defaults and host features the map was built with, applied to
+    /// [`insert`](Self::insert)ed tools as well.
+    tool_defaults: Option<ToolDefaults>,
+    host_features: Vec<String>,
 }
 
 impl ToolMap {
-    /// Build a [`ToolMap`] from a configuration file.
+    /// Build a [`ToolMap`] from a configuration file, assuming no optional host features.
     pub fn from_config(config: &ToolMapConfig) -> Result<Self, McpError> {
+        Self::from_config_with_features(config, &[])
+    }
+
+    /// Build a [`ToolMap`], registering only tools whose `requires_features` are all
+    /// listed in `host_features`.
+    pub fn from_config_with_features(
+        config: &ToolMapConfig,
+        host_features: &[&str],
+    ) -> Result<Self, McpError> {
         let mut tools = IndexMap::with_capacity(config.tools.len());
+        let mut defaults: IndexMap<String, String> = IndexMap::new();
+        let mut pinned: IndexMap<String, String> = IndexMap::new();
+        let mut disabled: IndexMap<String, String> = IndexMap::new();
+
+        let host_features: Vec<String> = host_features.iter().map(|&f| f.to_owned()).collect();
         for tool in &config.tools {
-            if tools.contains_key(&tool.name) {
+            let key = tool.key();
+            let admitted = admit(tool, config.defaults.as_ref(), &host_features)?;
+            if tools.contains_key(&key) || disabled.contains_key(&key) {
                 return Err(McpError::InvalidInput(format!(
-                    "duplicate tool name `{}`",
-                    tool.name
+                    "duplicate tool name `{key}`"
                 )));
             }
-            tools.insert(tool.name.clone(), tool.clone());
+            let tool = match admitted {
+                Ok(tool) => tool,
+                Err(reason) => {
+                    disabled.insert(key, reason);
+                    continue;
+                }
+            };
+
+            if tool.version.is_some() {
+                let name = tool.qualified_name();
+                if tool.default_version
+                    && let Some(existing) = pinned.insert(name.clone(), key.clone())
+                {
+                    return Err(McpError::InvalidInput(format!(
+                        "tool `{name}` marks both `{existing}` and `{key}` as default"
+                    )));
+                }
+                defaults.insert(name, key.clone());
+            }
+            tools.insert(key, tool);
         }
 
-        Ok(ToolMap { tools })
+        defaults.extend(pinned);
+        Ok(ToolMap {
+            tools,
+            defaults,
+            disabled,
+            tool_defaults: config.defaults.clone(),
+            host_features,
+        })
     }
 
-    /// Retrieve a tool by name.
+    /// Retrieve a tool by exact key (`name` or `name@version`) or by its default version.
     pub fn get(&self, name: &str) -> Result<&ToolRef, McpError> {
+        if let Some(tool) = self.tools.get(name) {
+            return Ok(tool);
+        }
+        if let Some(tool) = self.defaults.get(name).and_then(|key| self.tools.get(key)) {
+            return Ok(tool);
+        }
+        match self.disabled.get(name) {
+            Some(reason) => Err(McpError::ToolDisabled {
+                name: name.to_string(),
+                reason: reason.clone(),
+            }),
+            None => Err(McpError::tool_not_found(name.to_string())),
+        }
+    }
+
+    /// Combine `other` into this map, resolving keys present in both with `policy`.
+    ///
+    /// Disabled entries take part in conflict detection, so preferring a map that
+    /// disables a tool keeps it disabled. Default-version pointers follow the same policy.
+    pub fn merge(mut self, other: ToolMap, policy: ConflictPolicy) -> Result<ToolMap, McpError> {
+        let incoming = other
+            .tools
+            .keys()
+            .chain(other.disabled.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in incoming {
+            let conflict = self.tools.contains_key(&key) || self.disabled.contains_key(&key);
+            if conflict {
+                match policy {
+                    ConflictPolicy::Error => {
+                        return Err(McpError::InvalidInput(format!(
+                            "tool `{key}` is defined in both tool maps"
+                        )));
+                    }
+                    ConflictPolicy::PreferLeft => continue,
+                    ConflictPolicy::PreferRight => {
+                        self.tools.shift_remove(&key);
+                        self.disabled.shift_remove(&key);
+                    }
+                }
+            }
+            if let Some(tool) = other.tools.get(&key) {
+                self.tools.insert(key, tool.clone());
+            } else if let Some(reason) = other.disabled.get(&key) {
+                self.disabled.insert(key, reason.clone());
+            }
+        }
+
+        for (name, key) in other.defaults {
+            match self.defaults.get(&name) {
+                Some(existing) if *existing == key => {}
+                Some(_) if policy == ConflictPolicy::Error => {
+                    return Err(McpError::InvalidInput(format!(
+                        "tool `{name}` has a different default version in each tool map"
+                    )));
+                }
+                Some(_) if policy == ConflictPolicy::PreferLeft => {}
+                _ => {
+                    self.defaults.insert(name, key);
+                }
+            }
+        }
+        let tools = &self.tools;
+        self.defaults.retain(|_, key| tools.contains_key(key));
+
+        Ok(self)
+    }
+
+    /// Copy of this map with every tool moved under the namespace `prefix`, so
+    /// `crm/create_lead` becomes `prefix/crm/create_lead`.
+    pub fn prefixed(&self, prefix: &str) -> ToolMap {
+        let under = |key: &str| format!("{prefix}/{key}");
+        let tools = self
+            .tools
+            .iter()
+            .map(|(key, tool)| {
+                let mut tool = tool.clone();
+                tool.namespace = Some(match &tool.namespace {
+                    Some(namespace) => under(namespace),
+                    None => prefix.to_string(),
+                });
+                (under(key), tool)
+            })
+            .collect();
+        let rekey = |entries: &IndexMap<String, String>, value_is_key: bool| {
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let value = if value_is_key {
+                        under(value)
+                    } else {
+                        value.clone()
+                    };
+                    (under(key), value)
+                })
+                .collect()
+        };
+        ToolMap {
+            tools,
+            defaults: rekey(&self.defaults, true),
+            disabled: rekey(&self.disabled, false),
+            tool_defaults: self.tool_defaults.clone(),
+            host_features: self.host_features.clone(),
+        }
+    }
+
+    /// Register `tool`, replacing any tool with the same key. Returns the replaced tool.
+    ///
+    /// The tool is checked and completed like one read by
+    /// [`from_config_with_features`](Self::from_config_with_features): it inherits the
+    /// config's defaults, and tools with `enabled: false` or a missing host feature are
+    /// recorded as disabled instead. A versioned tool becomes the default version when it
+    /// sets `default_version` or when the current default is not pinned.
+    pub fn insert(&mut self, tool: ToolRef) -> Result<Option<ToolRef>, McpError> {
+        let key = tool.key();
+        let admitted = admit(&tool, self.tool_defaults.as_ref(), &self.host_features)?;
+
+        self.disabled.shift_remove(&key);
+        let tool = match admitted {
+            Ok(tool) => tool,
+            Err(reason) => {
+                self.disabled.insert(key.clone(), reason);
+                let previous = self.tools.shift_remove(&key);
+                self.repoint_default(&previous);
+                return Ok(previous);
+            }
+        };
+
+        if tool.version.is_some() {
+            let name = tool.qualified_name();
+            let current_is_pinned = self
+                .defaults
+                .get(&name)
+                .and_then(|current| self.tools.get(current))
+                .is_some_and(|current| current.default_version && current.key() != key);
+            if tool.default_version || !current_is_pinned {
+                self.defaults.insert(name, key.clone());
+            }
+        }
+        Ok(self.tools.insert(key, tool))
+    }
+
+    /// Unregister the tool addressed by `name` (exact key or default version).
+    pub fn remove(&mut self, name: &str) -> Option<ToolRef> {
+        let key = if self.tools.contains_key(name) {
+            name.to_string()
+        } else {
+            self.defaults.get(name)?.clone()
+        };
+        let removed = self.tools.shift_remove(&key);
+        self.repoint_default(&removed);
+        removed
+    }
+
+    /// Point the default version of `removed` at a remaining version, if any.
+    fn repoint_default(&mut self, removed: &Option<ToolRef>) {
+        let Some(removed) = removed.as_ref().filter(|tool| tool.version.is_some()) else {
+            return;
+        };
+        let name = removed.qualified_name();
+        if self.defaults.get(&name) != Some(&removed.key()) {
+            return;
+        }
+        let replacement = self
+            .versions(&name)
+            .find(|tool| tool.default_version)
+            .or_else(|| self.versions(&name).last())
+            .map(ToolRef::key);
+        match replacement {
+            Some(key) => self.defaults.insert(name, key),
+            None => self.defaults.shift_remove(&name),
+        };
+    }
+
+    /// Look up a registered tool by its exact key, without default-version fallback.
+    pub(crate) fn entry(&self, key: &str) -> Option<&ToolRef> {
+        self.tools.get(key)
+    }
+
+    /// Keys of tools that are configured but not registered, with the reason.
+    pub fn disabled(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.disabled.iter()
+    }
+
+    /// Iterate over all registered versions of a tool.
+    pub fn versions<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a ToolRef> + 'a {
         self.tools
-            .get(name)
-            .ok_or_else(|| McpError::tool_not_found(name.to_string()))
+            .values()
+            .filter(move |tool| tool.version.is_some() && tool.qualified_name() == name)
     }
 
     /// Iterate over desired tool references.
     pub fn iter(&self) -> impl Iterator<Item = (&String, &ToolRef)> {
         self.tools.iter()
     }
+
+    /// Iterate over the tools registered under `namespace`.
+    pub fn iter_namespace<'a>(
+        &'a self,
+        namespace: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a ToolRef)> + 'a {
+        self.tools
+            .iter()
+            .filter(move |(_, tool)| tool.namespace.as_deref() == Some(namespace))
+    }
+
+    /// Iterate over the tools carrying label `key` with exactly `value`.
+    pub fn iter_by_label<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a ToolRef)> + 'a {
+        self.tools
+            .iter()
+            .filter(move |(_, tool)| tool.has_label(key, value))
+    }
+
+    /// Iterate over the tools matching every requirement of `selector`.
+    pub fn select<'a>(
+        &'a self,
+        selector: &'a LabelSelector,
+    ) -> impl Iterator<Item = (&'a String, &'a ToolRef)> + 'a {
+        self.tools
+            .iter()
+            .filter(move |(_, tool)| selector.matches(tool))
+    }
+
+    /// Distinct namespaces in declaration order.
+    pub fn namespaces(&self) -> Vec<&str> {
+        let mut namespaces = Vec::new();
+        for namespace in self
+            .tools
+            .values()
+            .filter_map(|tool| tool.namespace.as_deref())
+        {
+            if !namespaces.contains(&namespace) {
+                namespaces.push(namespace);
+            }
+        }
+        namespaces
+    }
+}
+
+/// Validate `tool` and fill in `defaults`. Tools that are disabled or need a feature
+/// missing from `host_features` come back as `Err` with the reason.
+fn admit(
+    tool: &ToolRef,
+    defaults: Option<&ToolDefaults>,
+    host_features: &[String],
+) -> Result<Result<ToolRef, String>, McpError> {
+    if tool.component.is_empty() && tool.source.is_none() {
+        return Err(McpError::InvalidInput(format!(
+            "tool `{}` needs either `component` or `source`",
+            tool.key()
+        )));
+    }
+    if !tool.is_enabled() {
+        return Ok(Err("disabled in config".to_string()));
+    }
+    if let Some(feature) = tool
+        .requires_features
+        .iter()
+        .find(|feature| !host_features.contains(*feature))
+    {
+        return Ok(Err(format!("requires host feature `{feature}`")));
+    }
+    let mut tool = tool.clone();
+    if let Some(defaults) = defaults {
+        defaults.apply(&mut tool);
+    }
+    Ok(Ok(tool))
+}
+
+/// How [`ToolMap::merge`] resolves a key present in both maps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Fail the merge.
+    Error,
+    /// Keep the entry from the map being merged into.
+    PreferLeft,
+    /// Take the entry from the map being merged in.
+    PreferRight,
+}
+
+/// Conjunction of label requirements used by [`ToolMap::select`].
+///
+/// The textual form is a comma-separated list of `key=value`, `key!=value`, `key`
+/// (label present), and `!key` (label absent), e.g. `audience=agent,!admin`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<LabelRequirement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LabelRequirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+    Absent(String),
+}
+
+impl LabelSelector {
+    /// Selector matching every tool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require label `key` to equal `value`.
+    pub fn equals(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.requirements
+            .push(LabelRequirement::Equals(key.into(), value.into()));
+        self
+    }
+
+    /// Require label `key` to be missing or differ from `value`.
+    pub fn not_equals(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.requirements
+            .push(LabelRequirement::NotEquals(key.into(), value.into()));
+        self
+    }
+
+    /// Require label `key` to be present.
+    pub fn exists(mut self, key: impl Into<String>) -> Self {
+        self.requirements.push(LabelRequirement::Exists(key.into()));
+        self
+    }
+
+    /// Require label `key` to be absent.
+    pub fn absent(mut self, key: impl Into<String>) -> Self {
+        self.requirements.push(LabelRequirement::Absent(key.into()));
+        self
+    }
+
+    /// Whether `tool` satisfies every requirement.
+    pub fn matches(&self, tool: &ToolRef) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| match requirement {
+                LabelRequirement::Equals(key, value) => tool.has_label(key, value),
+                LabelRequirement::NotEquals(key, value) => !tool.has_label(key, value),
+                LabelRequirement::Exists(key) => tool.labels.contains_key(key),
+                LabelRequirement::Absent(key) => !tool.labels.contains_key(key),
+            })
+    }
+}
+
+impl LabelRequirement {
+    fn key(&self) -> &str {
+        match self {
+            LabelRequirement::Equals(key, _)
+            | LabelRequirement::NotEquals(key, _)
+            | LabelRequirement::Exists(key)
+            | LabelRequirement::Absent(key) => key,
+        }
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = McpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut requirements = Vec::new();
+        for term in s.split(',').map(str::trim).filter(|term| !term.is_empty()) {
+            let requirement = if let Some((key, value)) = term.split_once("!=") {
+                LabelRequirement::NotEquals(key.trim().into(), value.trim().into())
+            } else if let Some((key, value)) = term.split_once('=') {
+                LabelRequirement::Equals(key.trim().into(), value.trim().into())
+            } else if let Some(key) = term.strip_prefix('!') {
+                LabelRequirement::Absent(key.trim().into())
+            } else {
+                LabelRequirement::Exists(term.into())
+            };
+            if requirement.key().is_empty() {
+                return Err(McpError::InvalidInput(format!(
+                    "invalid label selector term `{term}`"
+                )));
+            }
+            requirements.push(requirement);
+        }
+        Ok(LabelSelector { requirements })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versioned(version: &str, default_version: bool) -> ToolRef {
+        ToolRef {
+            version: Some(version.into()),
+            default_version,
+            ..ToolRef::new("summarize", format!("./summarize-{version}.wasm"), "run")
+        }
+    }
+
+    #[test]
+    fn resolves_exact_and_default_versions() {
+        let config = ToolMapConfig {
+            tools: vec![versioned("1", true), versioned("2", false)],
+            ..Default::default()
+        };
+        let map = ToolMap::from_config(&config).unwrap();
+
+        assert_eq!(
+            map.get("summarize@2").unwrap().component,
+            "./summarize-2.wasm"
+        );
+        assert_eq!(map.get("summarize").unwrap().version.as_deref(), Some("1"));
+        assert_eq!(map.versions("summarize").count(), 2);
+        assert!(map.get("summarize@3").is_err());
+    }
+
+    #[test]
+    fn defaults_to_last_declared_version() {
+        let config = ToolMapConfig {
+            tools: vec![versioned("1", false), versioned("2", false)],
+            ..Default::default()
+        };
+        let map = ToolMap::from_config(&config).unwrap();
+
+        assert_eq!(map.get("summarize").unwrap().version.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn prefixes_keys_namespaces_and_defaults() {
+        let config = ToolMapConfig {
+            tools: vec![
+                versioned("1.0.0", false),
+                ToolRef {
+                    namespace: Some("crm".into()),
+                    ..ToolRef::new("create_lead", "./crm.wasm", "run")
+                },
+                ToolRef {
+                    enabled: Some(false),
+                    ..ToolRef::new("deploy", "./deploy.wasm", "run")
+                },
+            ],
+            ..Default::default()
+        };
+        let map = ToolMap::from_config(&config).unwrap().prefixed("acme");
+
+        let keys = map.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["acme/summarize@1.0.0", "acme/crm/create_lead"]);
+        for (key, tool) in map.iter() {
+            assert_eq!(*key, tool.key());
+        }
+        assert_eq!(map.get("acme/summarize").unwrap().name, "summarize");
+        assert!(matches!(
+            map.get("acme/deploy"),
+            Err(McpError::ToolDisabled { .. })
+        ));
+        assert!(map.get("summarize").is_err());
+    }
+
+    #[test]
+    fn filters_by_namespace() {
+        let lead = ToolRef {
+            namespace: Some("crm".into()),
+            ..ToolRef::new("create_lead", "./lead.wasm", "run")
+        };
+        let config = ToolMapConfig {
+            tools: vec![lead, ToolRef::new("echo", "./echo.wasm", "run")],
+            ..Default::default()
+        };
+        let map = ToolMap::from_config(&config).unwrap();
+
+        assert!(map.get("crm/create_lead").is_ok());
+        assert!(map.get("create_lead").is_err());
+        let crm = map.iter_namespace("crm").map(|(key, _)| key.as_str());
+        assert_eq!(crm.collect::<Vec<_>>(), ["crm/create_lead"]);
+        assert_eq!(map.namespaces(), ["crm"]);
+    }
+
+    #[test]
+    fn skips_disabled_and_unsupported_tools() {
+        let off = ToolRef {
+            enabled: Some(false),
+            ..ToolRef::new("off", "./off.wasm", "run")
+        };
+        let fetch = ToolRef {
+            requires_features: vec!["http".into()],
+            ..ToolRef::new("fetch", "./fetch.wasm", "run")
+        };
+        let config = ToolMapConfig {
+            tools: vec![off, fetch, ToolRef::new("echo", "./echo.wasm", "run")],
+            ..Default::default()
+        };
+
+        let map = ToolMap::from_config(&config).unwrap();
+        assert_eq!(map.iter().count(), 1);
+        assert!(matches!(
+            map.get("off"),
+            Err(McpError::ToolDisabled { reason, .. }) if reason == "disabled in config"
+        ));
+        assert!(matches!(
+            map.get("fetch"),
+            Err(McpError::ToolDisabled { reason, .. }) if reason.contains("`http`")
+        ));
+        assert!(matches!(map.get("nope"), Err(McpError::ToolNotFound(_))));
+
+        let map = ToolMap::from_config_with_features(&config, &["http"]).unwrap();
+        assert!(map.get("fetch").is_ok());
+    }
+
+    #[test]
+    fn selects_tools_by_label() {
+        let labeled = |name: &str, labels: &[(&str, &str)]| ToolRef {
+            labels: labels
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            ..ToolRef::new(name, format!("./{name}.wasm"), "run")
+        };
+        let config = ToolMapConfig {
+            tools: vec![
+                labeled("search", &[("audience", "agent")]),
+                labeled("purge", &[("audience", "agent"), ("admin", "true")]),
+                labeled("report", &[]),
+            ],
+            ..Default::default()
+        };
+        let map = ToolMap::from_config(&config).unwrap();
+
+        let agent = map.iter_by_label("audience", "agent").count();
+        assert_eq!(agent, 2);
+
+        let selector: LabelSelector = "audience=agent, !admin".parse().unwrap();
+        let safe = map.select(&selector).map(|(key, _)| key.as_str());
+        assert_eq!(safe.collect::<Vec<_>>(), ["search"]);
+
+        assert!("=agent".parse::<LabelSelector>().is_err());
+    }
+
+    #[test]
+    fn merges_with_conflict_policy() {
+        let builtin = || {
+            ToolMap::from_config(&ToolMapConfig {
+                tools: vec![
+                    ToolRef::new("echo", "./builtin-echo.wasm", "run"),
+                    ToolRef::new("search", "./search.wasm", "run"),
+                ],
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        let tenant = || {
+            ToolMap::from_config(&ToolMapConfig {
+                tools: vec![
+                    ToolRef::new("echo", "./tenant-echo.wasm", "run"),
+                    ToolRef::new("crm", "./crm.wasm", "run"),
+                ],
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        assert!(builtin().merge(tenant(), ConflictPolicy::Error).is_err());
+
+        let left = builtin()
+            .merge(tenant(), ConflictPolicy::PreferLeft)
+            .unwrap();
+        assert_eq!(left.get("echo").unwrap().component, "./builtin-echo.wasm");
+        assert_eq!(left.iter().count(), 3);
+
+        let right = builtin()
+            .merge(tenant(), ConflictPolicy::PreferRight)
+            .unwrap();
+        assert_eq!(right.get("echo").unwrap().component, "./tenant-echo.wasm");
+        assert!(right.get("crm").is_ok());
+    }
+
+    #[test]
+    fn insert_and_remove_track_default_versions() {
+        let mut map = ToolMap::from_config(&ToolMapConfig::default()).unwrap();
+        let version = |v: &str, pinned: bool| ToolRef {
+            version: Some(v.into()),
+            default_version: pinned,
+            ..ToolRef::new("echo", format!("./echo-{v}.wasm"), "run")
+        };
+
+        assert!(map.insert(version("1", true)).unwrap().is_none());
+        map.insert(version("2", false)).unwrap();
+        assert_eq!(map.get("echo").unwrap().version.as_deref(), Some("1"));
+
+        assert!(map.remove("echo").is_some());
+        assert_eq!(map.get("echo").unwrap().version.as_deref(), Some("2"));
+
+        map.remove("echo@2");
+        assert!(map.get("echo").is_err());
+        assert!(map.insert(ToolRef::new("bad", "", "run")).is_err());
+    }
+
+    #[test]
+    fn insert_applies_defaults_and_host_features() {
+        let config = ToolMapConfig {
+            defaults: Some(ToolDefaults {
+                max_retries: Some(3),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut map = ToolMap::from_config_with_features(&config, &["kv"]).unwrap();
+
+        map.insert(ToolRef::new("echo", "./echo.wasm", "run"))
+            .unwrap();
+        assert_eq!(map.get("echo").unwrap().max_retries, Some(3));
+
+        let fetch = ToolRef {
+            requires_features: vec!["http".into()],
+            ..ToolRef::new("fetch", "./fetch.wasm", "run")
+        };
+        assert!(map.insert(fetch).unwrap().is_none());
+        assert!(matches!(
+            map.get("fetch"),
+            Err(McpError::ToolDisabled { reason, .. }) if reason.contains("`http`")
+        ));
+    }
 }
@@ -1,33 +1,195 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use mcp_exec::ExecError;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Value, json};
 use thiserror::Error;
 
+use crate::binary::{Attachment, BinaryOutput};
+use crate::retry::RetryPolicy;
+use crate::usage::ResourceUsage;
+
+pub use mcp_exec::{ErrorCode, ErrorKind, ErrorReport};
+
 /// Reference to a tool stored in the [`ToolMapConfig`](ToolMapConfig).
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct ToolRef {
     pub name: String,
+    /// Human/LLM-readable summary of what the tool does.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Filesystem path of the component; ignored when `source` is set.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub component: String,
     pub entry: String,
-    #[serde(default)]
+    /// Where the component artifact is fetched from, overriding `component`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ToolSource>,
+    /// Namespace grouping related tools; namespaced tools are addressed as `namespace/name`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Version label; versioned tools are addressable as `name@version`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Marks this version as the one resolved by the bare tool name.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub default_version: bool,
+    /// Expected SHA-256 digest (hex) of the component file, checked before execution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Per-attempt timeout; kept for compatibility, `attempt_timeout_ms` takes precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub timeout_ms: Option<u64>,
-    #[serde(default)]
+    /// Upper bound on a single attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt_timeout_ms: Option<u64>,
+    /// Upper bound on the whole invocation, including retries and backoff.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub max_retries: Option<u32>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub retry_backoff_ms: Option<u64>,
+    /// How retry delays grow from `retry_backoff_ms`; exponential when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Stop retrying once this much time has passed since the first attempt started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retry_duration_ms: Option<u64>,
+    /// Pass an idempotency key, stable across retries, as `_meta.idempotency_key` in
+    /// object payloads so the tool can deduplicate side effects.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub inject_idempotency_key: bool,
+    /// Pass the tenant of the invocation (id and environment) as `_meta.tenant` in
+    /// object payloads.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub inject_tenant: bool,
+    /// Let the tool request LLM completions from the hosting agent (MCP sampling).
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub allow_sampling: bool,
+    /// Disabled tools stay in the config but cannot be invoked; enabled when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Host features (e.g. `http`) that must be available for the tool to be registered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires_features: Vec<String>,
+    /// Tenants that may invoke the tool; empty allows every tenant and anonymous
+    /// callers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_tenants: Vec<String>,
+    /// Tenants that may not invoke the tool, even if listed in `allowed_tenants`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_tenants: Vec<String>,
+    /// Free-form labels used to select subsets of tools (e.g. `audience: agent`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// JSON Schema of the payload the tool accepts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<Value>,
+    /// JSON Schema of the payload the tool returns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Sample invocations shown to agents and UIs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ToolExample>,
+}
+
+/// Sample invocation documented alongside a tool.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ToolExample {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl ToolRef {
+    /// Entry point assumed for tools that do not name one explicitly.
+    pub const DEFAULT_ENTRY: &'static str = "tool_invoke";
+
+    /// Create a tool reference with default execution hints.
+    pub fn new(
+        name: impl Into<String>,
+        component: impl Into<String>,
+        entry: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            component: component.into(),
+            entry: entry.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the tool carries label `key` with exactly `value`.
+    pub fn has_label(&self, key: &str, value: &str) -> bool {
+        self.labels.get(key).is_some_and(|label| label == value)
+    }
+
+    /// Name including the namespace prefix, e.g. `crm/create_lead`.
+    pub fn qualified_name(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{namespace}/{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Key under which the tool is registered in a [`ToolMap`](crate::ToolMap).
+    pub fn key(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{version}", self.qualified_name()),
+            None => self.qualified_name(),
+        }
+    }
+
+    /// Whether `tenant` (`None` for anonymous callers) may invoke the tool.
+    pub fn allows_tenant(&self, tenant: Option<&str>) -> bool {
+        match tenant {
+            Some(tenant) => {
+                !self.denied_tenants.iter().any(|denied| denied == tenant)
+                    && (self.allowed_tenants.is_empty()
+                        || self.allowed_tenants.iter().any(|allowed| allowed == tenant))
+            }
+            None => self.allowed_tenants.is_empty(),
+        }
+    }
+
     /// Resolve the component path to a [`PathBuf`], if it is a filesystem path.
     pub fn component_path(&self) -> PathBuf {
         PathBuf::from(&self.component)
     }
 
-    /// Timeout duration requested for this tool.
+    /// Effective component source, falling back to `component` as a local path.
+    pub fn source(&self) -> ToolSource {
+        self.source
+            .clone()
+            .unwrap_or_else(|| ToolSource::Path(self.component_path()))
+    }
+
+    /// Timeout for a single attempt (`attempt_timeout_ms`, falling back to `timeout_ms`).
     pub fn timeout(&self) -> Option<Duration> {
-        self.timeout_ms.map(Duration::from_millis)
+        self.attempt_timeout_ms
+            .or(self.timeout_ms)
+            .map(Duration::from_millis)
+    }
+
+    /// Timeout for the whole invocation, including retries.
+    pub fn total_timeout(&self) -> Option<Duration> {
+        self.total_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Whether the tool may be registered and invoked (`enabled`, true when unset).
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
     }
 
     /// Maximum retry attempts for this tool.
@@ -39,24 +201,208 @@ impl ToolRef {
     pub fn retry_backoff(&self) -> Duration {
         Duration::from_millis(self.retry_backoff_ms.unwrap_or(200))
     }
+
+    /// Total time budget for retries, if any.
+    pub fn max_retry_duration(&self) -> Option<Duration> {
+        self.max_retry_duration_ms.map(Duration::from_millis)
+    }
+
+    /// Retry delay strategy for this tool.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.unwrap_or_default()
+    }
+}
+
+/// Location of a tool's component artifact.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    /// Component file on the local filesystem.
+    Path(PathBuf),
+    /// Component downloaded over HTTP(S) and cached locally.
+    Url(String),
+    /// Tool served by a remote MCP server; `entry` names the remote tool.
+    Mcp(McpEndpoint),
+}
+
+impl std::fmt::Display for ToolSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolSource::Path(path) => write!(f, "{}", path.display()),
+            ToolSource::Url(url) => f.write_str(url),
+            ToolSource::Mcp(endpoint) => write!(f, "{endpoint}"),
+        }
+    }
+}
+
+/// How to reach a remote MCP server.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "transport", rename_all = "snake_case", deny_unknown_fields)]
+pub enum McpEndpoint {
+    /// Subprocess speaking newline-delimited JSON-RPC on stdin/stdout.
+    Stdio {
+        command: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        env: BTreeMap<String, String>,
+    },
+    /// Server using the streamable HTTP transport.
+    Http {
+        url: String,
+        /// Extra request headers, e.g. `Authorization`.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+impl std::fmt::Display for McpEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            McpEndpoint::Stdio { command, args, .. } => {
+                write!(f, "mcp+stdio:{command}")?;
+                args.iter().try_for_each(|arg| write!(f, " {arg}"))
+            }
+            McpEndpoint::Http { url, .. } => write!(f, "mcp+{url}"),
+        }
+    }
+}
+
+/// Settings inherited by every tool that does not set them itself.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ToolDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retry_duration_ms: Option<u64>,
+}
+
+impl ToolDefaults {
+    /// Fill the unset settings of `tool` from these defaults.
+    pub fn apply(&self, tool: &mut ToolRef) {
+        if tool.attempt_timeout_ms.is_none() && tool.timeout_ms.is_none() {
+            tool.attempt_timeout_ms = self.attempt_timeout_ms;
+        }
+        tool.timeout_ms = tool.timeout_ms.or(self.timeout_ms);
+        tool.total_timeout_ms = tool.total_timeout_ms.or(self.total_timeout_ms);
+        tool.max_retries = tool.max_retries.or(self.max_retries);
+        tool.retry_backoff_ms = tool.retry_backoff_ms.or(self.retry_backoff_ms);
+        tool.retry_policy = tool.retry_policy.or(self.retry_policy);
+        tool.max_retry_duration_ms = tool.max_retry_duration_ms.or(self.max_retry_duration_ms);
+    }
 }
 
 /// Tool map configuration file structure.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct ToolMapConfig {
+    /// Additional config files or directories composed into this one, relative to this file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<PathBuf>,
+    /// Namespace applied to tools in this file (and its includes) that do not set one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Settings inherited by the tools in this file (and its includes).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<ToolDefaults>,
+    /// Directory scanned for `*.component.wasm` files, relative to this file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discover: Option<PathBuf>,
+    #[serde(default)]
     pub tools: Vec<ToolRef>,
+    /// Set on configs whose placeholders, includes, overlays, or secrets were resolved
+    /// while loading. [`save`](Self::save) refuses them: writing them back would put
+    /// secret values on disk and flatten the operator's files.
+    #[serde(skip)]
+    pub resolved: bool,
 }
 
 /// Input payload for a tool invocation.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ToolInput {
     pub payload: Value,
+    /// Id tying the invocation to the user action behind it. It is attached to the
+    /// invocation's spans, events, and audit record, and passed to the guest as
+    /// `_meta.correlation_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Binary data guests read through [`INPUT_INTERFACE`](crate::binary::INPUT_INTERFACE)
+    /// instead of from the payload. Tools served by remote MCP servers take none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
 }
 
-/// Output payload for a tool invocation.
+impl ToolInput {
+    pub fn new(payload: Value) -> Self {
+        Self {
+            payload,
+            correlation_id: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    pub fn with_correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    /// Add `attachment`, replacing an earlier one with the same name.
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments
+            .retain(|existing| existing.name != attachment.name);
+        self.attachments.push(attachment);
+        self
+    }
+}
+
+/// Output payload for a tool invocation, with evidence of how it was produced.
+///
+/// The executor fills in the optional fields; flow engines can persist them per step.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ToolOutput {
     pub payload: Value,
+    /// Wall time of the invocation, including retries and backoff.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Attempts made, counting the one that succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+    /// SHA-256 of the component that ran, as computed and verified by the executor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// Lines the guest wrote to stderr, when the executor captures logs; see
+    /// [`WasixExecutor::with_log_capture`](crate::executor::WasixExecutor::with_log_capture).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+    /// Bytes the tool wrote through [`OUTPUT_INTERFACE`](crate::binary::OUTPUT_INTERFACE).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<BinaryOutput>,
+}
+
+impl ToolOutput {
+    pub fn new(payload: Value) -> Self {
+        Self {
+            payload,
+            duration_ms: None,
+            attempts: None,
+            digest: None,
+            logs: Vec::new(),
+            usage: None,
+            binary: None,
+        }
+    }
 }
 
 /// Errors surfaced by the MCP executor.
@@ -70,10 +416,61 @@ pub enum McpError {
     ExecutionFailed(String),
     #[error("tool `{name}` timed out after {timeout:?}")]
     Timeout { name: String, timeout: Duration },
+    #[error("tool `{name}` timed out after {timeout:?}, retries included")]
+    TotalTimeout { name: String, timeout: Duration },
+    #[error("invocation of `{0}` was cancelled")]
+    Cancelled(String),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("tool `{tool}` is not available to {}", tenant.as_deref().map_or("anonymous callers".to_string(), |tenant| format!("tenant `{tenant}`")))]
+    Forbidden {
+        tool: String,
+        tenant: Option<String>,
+    },
+    #[error("tenant `{tenant}` is rate limited; retry after {retry_after:?}")]
+    RateLimited {
+        tenant: String,
+        retry_after: Duration,
+    },
+    #[error("retry deadline for `{name}` exceeded after {elapsed:?}: {last_error}")]
+    DeadlineExceeded {
+        name: String,
+        elapsed: Duration,
+        last_error: String,
+    },
     #[error("transient failure invoking `{0}`: {1}")]
     Transient(String, String),
     #[error("internal error: {0}")]
     Internal(String),
+    #[error("tool `{name}` is disabled: {reason}")]
+    ToolDisabled { name: String, reason: String },
+    #[error("digest mismatch for tool `{name}`: expected {expected}, got {actual}")]
+    DigestMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("invalid tool map config `{}`: {message}", path.display())]
+    ConfigFile { path: PathBuf, message: String },
+    #[error("invalid remote tool map config `{url}`: {message}")]
+    RemoteConfig { url: String, message: String },
+    #[error("secret `{name}` referenced at {location} is unavailable: {message}")]
+    Secret {
+        name: String,
+        location: String,
+        message: String,
+    },
+    /// An [`ExecError`] from [`exec_with_retries`](crate::exec_with_retries) or
+    /// [`mcp_exec::exec`], with the attempt it failed on when known.
+    #[error("{error}{}", attempt.map_or(String::new(), |attempt| format!(" (attempt {attempt})")))]
+    Exec {
+        component: String,
+        action: Option<String>,
+        attempt: Option<u32>,
+        code: ErrorCode,
+        #[source]
+        error: Box<ExecError>,
+    },
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
@@ -82,15 +479,140 @@ pub enum McpError {
     Json(#[from] serde_json::Error),
 }
 
+impl From<ExecError> for McpError {
+    fn from(err: ExecError) -> Self {
+        McpError::from_exec(err, None)
+    }
+}
+
+impl From<&McpError> for ErrorReport {
+    fn from(err: &McpError) -> Self {
+        err.report()
+    }
+}
+
 impl McpError {
     pub fn tool_not_found(name: impl Into<String>) -> Self {
         McpError::ToolNotFound(name.into())
     }
 
+    pub fn config_file(path: impl Into<PathBuf>, message: impl ToString) -> Self {
+        McpError::ConfigFile {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn remote_config(url: impl Into<String>, message: impl ToString) -> Self {
+        McpError::RemoteConfig {
+            url: url.into(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Stable code of the error; see [`ErrorCode`].
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            McpError::ToolNotFound(_) => ErrorCode::ToolNotFound,
+            McpError::InvalidInput(_) => ErrorCode::InvalidInput,
+            McpError::ExecutionFailed(_) => ErrorCode::RunnerFailed,
+            McpError::Timeout { .. } => ErrorCode::RunnerTimeout,
+            McpError::TotalTimeout { .. } => ErrorCode::RunnerTotalTimeout,
+            McpError::Cancelled(_) => ErrorCode::RunnerCancelled,
+            McpError::Unauthorized(_) => ErrorCode::Unauthorized,
+            McpError::Forbidden { .. } => ErrorCode::Forbidden,
+            McpError::RateLimited { .. } => ErrorCode::RateLimited,
+            McpError::DeadlineExceeded { .. } => ErrorCode::RunnerDeadlineExceeded,
+            McpError::Transient(..) => ErrorCode::RunnerTransient,
+            McpError::ToolDisabled { .. } => ErrorCode::ToolDisabled,
+            McpError::DigestMismatch { .. } => ErrorCode::VerifyDigestMismatch,
+            McpError::ConfigFile { .. } | McpError::RemoteConfig { .. } | McpError::Config(_) => {
+                ErrorCode::ConfigInvalid
+            }
+            McpError::Secret { .. } => ErrorCode::SecretUnavailable,
+            McpError::Exec { code, .. } => code.clone(),
+            McpError::Internal(_) | McpError::Io(_) | McpError::Json(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// Coarse category of the error; see [`ErrorKind`].
+    pub fn kind(&self) -> ErrorKind {
+        self.code().kind()
+    }
+
+    /// Serializable form of the error; see [`ErrorReport`].
+    pub fn report(&self) -> ErrorReport {
+        let (component, details) = match self {
+            McpError::ToolNotFound(name)
+            | McpError::Cancelled(name)
+            | McpError::Transient(name, _)
+            | McpError::Timeout { name, .. }
+            | McpError::TotalTimeout { name, .. }
+            | McpError::ToolDisabled { name, .. } => (Some(name), Value::Null),
+            McpError::Forbidden { tool, tenant } => (Some(tool), json!({ "tenant": tenant })),
+            McpError::RateLimited {
+                tenant,
+                retry_after,
+            } => (
+                None,
+                json!({
+                    "tenant": tenant,
+                    "retry_after_ms": retry_after.as_millis() as u64,
+                }),
+            ),
+            McpError::DeadlineExceeded { name, elapsed, .. } => (
+                Some(name),
+                json!({ "elapsed_ms": elapsed.as_millis() as u64 }),
+            ),
+            McpError::DigestMismatch {
+                name,
+                expected,
+                actual,
+            } => (
+                Some(name),
+                json!({ "expected": expected, "actual": actual }),
+            ),
+            McpError::Exec {
+                component, error, ..
+            } => (Some(component), error.report().details),
+            _ => (None, Value::Null),
+        };
+        let code = self.code();
+        ErrorReport {
+            retryable: code.is_retryable(),
+            code,
+            message: self.to_string(),
+            component: component.cloned(),
+            action: match self {
+                McpError::Exec { action, .. } => action.clone(),
+                _ => None,
+            },
+            details,
+        }
+    }
+
+    /// `err` as an [`McpError::Exec`] that records the attempt it failed on.
+    pub fn from_exec(err: ExecError, attempt: Option<u32>) -> Self {
+        McpError::Exec {
+            component: err.component().to_string(),
+            action: err.action().map(str::to_string),
+            attempt,
+            code: err.code(),
+            error: Box::new(err),
+        }
+    }
+
     pub fn timeout(name: impl Into<String>, timeout: Duration) -> Self {
         McpError::Timeout {
             name: name.into(),
             timeout,
         }
     }
+
+    pub fn total_timeout(name: impl Into<String>, timeout: Duration) -> Self {
+        McpError::TotalTimeout {
+            name: name.into(),
+            timeout,
+        }
+    }
 }
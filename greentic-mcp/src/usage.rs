@@ -0,0 +1,553 @@
+//! Per-tenant resource usage, for chargeback and quota enforcement.
+//!
+//! Attach a [`UsageMeter`] with
+//! [`WasixExecutor::with_usage_meter`](crate::executor::WasixExecutor::with_usage_meter)
+//! and every invocation with a tenant adds to that tenant's [`TenantUsage`]. Billing
+//! systems can receive it as [`UsageRecord`]s through a [`UsageSink`], see
+//! [`UsageMeter::spawn_sink`].
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+
+use crate::types::McpError;
+
+/// Fuel given to every guest; what is left after the call is subtracted to get usage.
+pub(crate) const GUEST_FUEL: u64 = u64::MAX;
+
+/// Records kept for a failing sink before the oldest are dropped.
+const MAX_UNSENT_RECORDS: usize = 100_000;
+
+/// Resources used by one tenant.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantUsage {
+    pub invocations: u64,
+    /// Wasm fuel consumed by guests, roughly one unit per instruction executed.
+    pub fuel: u64,
+    /// Wall time of invocations, including retries and backoff.
+    pub wall_time_ms: u64,
+    /// Bytes sent to remote MCP servers. Wasm guests have no network access.
+    pub egress_bytes: u64,
+}
+
+impl TenantUsage {
+    fn add(&mut self, other: &TenantUsage) {
+        self.invocations += other.invocations;
+        self.fuel = self.fuel.saturating_add(other.fuel);
+        self.wall_time_ms += other.wall_time_ms;
+        self.egress_bytes += other.egress_bytes;
+    }
+}
+
+/// Resources used by one invocation, as reported in
+/// [`ToolOutput::usage`](crate::types::ToolOutput::usage).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Wasm fuel consumed by the guest across all attempts.
+    pub fuel: u64,
+    /// Bytes sent to a remote MCP server.
+    pub egress_bytes: u64,
+}
+
+/// Usage totals by tenant id, shared by executor clones.
+#[derive(Debug, Default)]
+pub struct UsageMeter {
+    tenants: Mutex<BTreeMap<String, TenantUsage>>,
+    /// Records taken for a sink and not written yet.
+    unsent: Mutex<Vec<UsageRecord>>,
+}
+
+impl UsageMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Usage of `tenant` since the meter was created or last [`take`](Self::take)n.
+    pub fn usage(&self, tenant: &str) -> Option<TenantUsage> {
+        self.tenants().get(tenant).cloned()
+    }
+
+    /// Usage of every tenant.
+    pub fn snapshot(&self) -> BTreeMap<String, TenantUsage> {
+        self.tenants().clone()
+    }
+
+    /// Usage of every tenant, resetting the totals to zero.
+    pub fn take(&self) -> BTreeMap<String, TenantUsage> {
+        std::mem::take(&mut *self.tenants())
+    }
+
+    /// Every `period`, pass the usage accumulated since the previous export to
+    /// `export`. Periods without invocations are skipped. Usage of a failed export is
+    /// added back and exported with the next period. Must be called from within a
+    /// Tokio runtime; [`shutdown`](UsageExporter::shutdown) the returned exporter to
+    /// export what is left and stop.
+    pub fn spawn_export<F>(self: Arc<Self>, period: Duration, export: F) -> UsageExporter
+    where
+        F: Fn(BTreeMap<String, TenantUsage>) -> Result<(), McpError> + Send + 'static,
+    {
+        UsageExporter::spawn(period, move || {
+            let usage = self.take();
+            if usage.is_empty() {
+                return std::future::ready(());
+            }
+            if let Err(err) = export(usage.clone()) {
+                tracing::warn!(%err, "failed to export usage; keeping it for the next period");
+                for (tenant, usage) in &usage {
+                    self.record(tenant, usage);
+                }
+            }
+            std::future::ready(())
+        })
+    }
+
+    /// Every `period`, pass a [`UsageRecord`] per tenant with invocations in the
+    /// period to `sink`. Records the sink fails to write are kept in the meter and
+    /// written again, ahead of newer ones, every period until it succeeds, so a sink
+    /// may see a record more than once. Must be called from within a Tokio runtime;
+    /// [`shutdown`](UsageExporter::shutdown) the returned exporter to flush and stop.
+    pub fn spawn_sink(self: Arc<Self>, period: Duration, sink: UsageSink) -> UsageExporter {
+        let period_start = Arc::new(AtomicU64::new(unix_millis()));
+        UsageExporter::spawn(period, move || {
+            let meter = self.clone();
+            let sink = sink.clone();
+            let period_start = period_start.clone();
+            async move {
+                let period_end = unix_millis();
+                let start = period_start.swap(period_end, Ordering::Relaxed);
+                meter.queue_records(start, period_end);
+                meter.flush(&sink).await;
+            }
+        })
+    }
+
+    /// Move the current totals into the queue of records waiting for a sink.
+    fn queue_records(&self, period_start_ms: u64, period_end_ms: u64) {
+        let records = self.take().into_iter().map(|(tenant, usage)| UsageRecord {
+            tenant,
+            period_start_ms,
+            period_end_ms,
+            usage,
+        });
+        let mut unsent = self.unsent();
+        unsent.extend(records);
+        let excess = unsent.len().saturating_sub(MAX_UNSENT_RECORDS);
+        if excess > 0 {
+            tracing::warn!(
+                records = excess,
+                "usage sink is behind; dropping the oldest usage records"
+            );
+            unsent.drain(..excess);
+        }
+    }
+
+    /// Write the queued records, removing them once the sink succeeds. They stay
+    /// queued if the write fails or is aborted.
+    async fn flush(&self, sink: &UsageSink) {
+        let batch = self.unsent().clone();
+        if batch.is_empty() {
+            return;
+        }
+        let sent = batch.len();
+        match sink.write(batch).await {
+            Ok(()) => {
+                let mut unsent = self.unsent();
+                let sent = sent.min(unsent.len());
+                unsent.drain(..sent);
+            }
+            Err(err) => {
+                tracing::warn!(%err, records = sent, "failed to write usage records; retrying next period");
+            }
+        }
+    }
+
+    /// Records queued for a sink that have not been written yet.
+    pub fn unsent_records(&self) -> Vec<UsageRecord> {
+        self.unsent().clone()
+    }
+
+    pub(crate) fn record(&self, tenant: &str, usage: &TenantUsage) {
+        self.tenants()
+            .entry(tenant.to_string())
+            .or_default()
+            .add(usage);
+    }
+
+    fn tenants(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, TenantUsage>> {
+        self.tenants.lock().expect("usage meter poisoned")
+    }
+
+    fn unsent(&self) -> std::sync::MutexGuard<'_, Vec<UsageRecord>> {
+        self.unsent.lock().expect("usage meter poisoned")
+    }
+}
+
+/// Background task exporting a [`UsageMeter`], returned by
+/// [`UsageMeter::spawn_export`] and [`UsageMeter::spawn_sink`].
+///
+/// Dropping it leaves the task running. Aborting it stops exporting without losing
+/// queued records, which stay in the meter.
+#[derive(Debug)]
+pub struct UsageExporter {
+    stop: oneshot::Sender<()>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl UsageExporter {
+    /// Run `export` every `period`, and once more when shut down.
+    fn spawn<F, Fut>(period: Duration, export: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            // The first tick completes immediately.
+            interval.tick().await;
+            loop {
+                let last = tokio::select! {
+                    _ = interval.tick() => false,
+                    _ = &mut stopped => true,
+                };
+                export().await;
+                if last {
+                    return;
+                }
+            }
+        });
+        Self { stop, task }
+    }
+
+    /// Export the usage accumulated so far, flushing queued records, and stop.
+    pub async fn shutdown(self) {
+        let _ = self.stop.send(());
+        match self.task.await {
+            Err(err) if err.is_panic() => tracing::warn!(%err, "usage export task panicked"),
+            _ => {}
+        }
+    }
+
+    /// Stop exporting immediately. Records not yet written stay in the meter.
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+}
+
+/// Usage of one tenant over one export period.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub tenant: String,
+    /// Start of the period, in milliseconds since the Unix epoch.
+    pub period_start_ms: u64,
+    /// End of the period, in milliseconds since the Unix epoch.
+    pub period_end_ms: u64,
+    #[serde(flatten)]
+    pub usage: TenantUsage,
+}
+
+type SinkWrite = dyn Fn(Vec<UsageRecord>) -> Pin<Box<dyn Future<Output = Result<(), McpError>> + Send>>
+    + Send
+    + Sync;
+
+/// Destination of the [`UsageRecord`]s of each export period.
+#[derive(Clone)]
+pub struct UsageSink(Arc<SinkWrite>);
+
+impl UsageSink {
+    /// Sink handing each batch to `write`, which cannot fail.
+    pub fn new<F>(write: F) -> Self
+    where
+        F: Fn(Vec<UsageRecord>) + Send + Sync + 'static,
+    {
+        Self::new_async(move |records| {
+            write(records);
+            std::future::ready(Ok(()))
+        })
+    }
+
+    /// Sink awaiting `write` for each batch. A batch whose write fails is written
+    /// again with the next period.
+    pub fn new_async<F, Fut>(write: F) -> Self
+    where
+        F: Fn(Vec<UsageRecord>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), McpError>> + Send + 'static,
+    {
+        Self(Arc::new(move |records| Box::pin(write(records))))
+    }
+
+    /// Append records to the file at `path` as JSON lines, creating it if needed.
+    /// Writes run on Tokio's blocking pool.
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self, McpError> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| {
+                McpError::Internal(format!(
+                    "failed to open usage file `{}`: {err}",
+                    path.display()
+                ))
+            })?;
+        let file = Arc::new(Mutex::new(file));
+        Ok(Self::new_async(move |records| {
+            let file = file.clone();
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    let mut lines = Vec::new();
+                    for record in &records {
+                        serde_json::to_writer(&mut lines, record).expect("usage records serialize");
+                        lines.push(b'\n');
+                    }
+                    let mut file = file.lock().expect("usage file poisoned");
+                    file.write_all(&lines)
+                })
+                .await
+                .map_err(|err| McpError::Internal(format!("usage write task failed: {err}")))?
+                .map_err(|err| McpError::Internal(format!("failed to write usage records: {err}")))
+            }
+        }))
+    }
+
+    /// Send records to `sender`, e.g. for a task forwarding them to a queue.
+    pub fn to_channel(sender: UnboundedSender<UsageRecord>) -> Self {
+        Self::new_async(move |records| {
+            let sent = records
+                .into_iter()
+                .try_for_each(|record| sender.send(record))
+                .map_err(|_| McpError::Internal("usage receiver dropped".into()));
+            std::future::ready(sent)
+        })
+    }
+
+    /// POST the records of each period to `url` as a JSON array. Must be used from
+    /// within a Tokio runtime.
+    pub fn to_http(url: impl Into<String>) -> Self {
+        let url = url.into();
+        let client = reqwest::Client::new();
+        Self::new_async(move |records| {
+            let request = client.post(&url).json(&records);
+            let url = url.clone();
+            async move {
+                request
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .map(drop)
+                    .map_err(|err| {
+                        McpError::Internal(format!(
+                            "failed to export usage records to {url}: {err}"
+                        ))
+                    })
+            }
+        })
+    }
+
+    pub async fn write(&self, records: Vec<UsageRecord>) -> Result<(), McpError> {
+        (self.0)(records).await
+    }
+}
+
+impl std::fmt::Debug for UsageSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UsageSink(..)")
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+/// Fuel and egress of one invocation, added to by each of its attempts.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UsageCounter {
+    fuel: Arc<AtomicU64>,
+    egress_bytes: Arc<AtomicU64>,
+}
+
+impl UsageCounter {
+    pub(crate) fn add_fuel(&self, fuel: u64) {
+        self.fuel.fetch_add(fuel, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_egress(&self, bytes: u64) {
+        self.egress_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn resources(&self) -> ResourceUsage {
+        ResourceUsage {
+            fuel: self.fuel.load(Ordering::Relaxed),
+            egress_bytes: self.egress_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn finish(&self, wall_time: Duration) -> TenantUsage {
+        TenantUsage {
+            invocations: 1,
+            fuel: self.fuel.load(Ordering::Relaxed),
+            wall_time_ms: wall_time.as_millis() as u64,
+            egress_bytes: self.egress_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::executor::{InvokeOptions, WasixExecutor, tests::echo_component};
+    use crate::types::{ToolInput, ToolRef};
+
+    #[tokio::test]
+    async fn meters_invocations_by_tenant() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("echo.wasm");
+        std::fs::write(&path, echo_component()).unwrap();
+        let tool = ToolRef::new("echo", path.to_string_lossy(), "tool-invoke");
+        let meter = Arc::new(UsageMeter::new());
+        let executor = WasixExecutor::new()
+            .unwrap()
+            .with_usage_meter(meter.clone());
+        let input = ToolInput::new(json!({ "text": "hi" }));
+
+        for _ in 0..2 {
+            let call = InvokeOptions {
+                tenant: Some(greentic_types::TenantCtx::new(
+                    "dev".try_into().unwrap(),
+                    "acme".try_into().unwrap(),
+                )),
+                ..InvokeOptions::default()
+            };
+            executor.invoke_with(&tool, &input, call).await.unwrap();
+        }
+        // Anonymous invocations are not metered.
+        executor.invoke(&tool, &input).await.unwrap();
+
+        let usage = meter.usage("acme").unwrap();
+        assert_eq!(usage.invocations, 2);
+        assert!(usage.fuel > 0);
+        assert_eq!(usage.egress_bytes, 0);
+        assert_eq!(meter.take().len(), 1);
+        assert!(meter.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn exports_usage_periodically() {
+        let meter = Arc::new(UsageMeter::new());
+        let (sender, mut exports) = tokio::sync::mpsc::unbounded_channel();
+        let exporter = meter
+            .clone()
+            .spawn_export(Duration::from_millis(10), move |usage| {
+                sender.send(usage).unwrap();
+                Ok(())
+            });
+        let usage = TenantUsage {
+            invocations: 1,
+            wall_time_ms: 5,
+            ..TenantUsage::default()
+        };
+        meter.record("acme", &usage);
+
+        let exported = exports.recv().await.unwrap();
+        assert_eq!(exported, BTreeMap::from([("acme".to_string(), usage)]));
+        assert!(meter.snapshot().is_empty());
+        exporter.abort();
+    }
+
+    #[tokio::test]
+    async fn exports_usage_records_to_a_sink() {
+        let meter = Arc::new(UsageMeter::new());
+        let (sender, mut records) = tokio::sync::mpsc::unbounded_channel();
+        let exporter = meter
+            .clone()
+            .spawn_sink(Duration::from_millis(10), UsageSink::to_channel(sender));
+        let usage = TenantUsage {
+            invocations: 3,
+            fuel: 1_000,
+            ..TenantUsage::default()
+        };
+        meter.record("acme", &usage);
+
+        let record = records.recv().await.unwrap();
+        assert_eq!(record.tenant, "acme");
+        assert_eq!(record.usage, usage);
+        assert!(record.period_start_ms <= record.period_end_ms);
+        let line = serde_json::to_value(&record).unwrap();
+        assert_eq!(line["invocations"], 3);
+        exporter.abort();
+    }
+
+    #[tokio::test]
+    async fn keeps_records_until_the_sink_accepts_them() {
+        let meter = Arc::new(UsageMeter::new());
+        let healthy = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (sender, mut records) = tokio::sync::mpsc::unbounded_channel();
+        let sink = UsageSink::new_async({
+            let healthy = healthy.clone();
+            move |batch| {
+                let result = if healthy.load(Ordering::Relaxed) {
+                    batch
+                        .into_iter()
+                        .for_each(|record| sender.send(record).unwrap());
+                    Ok(())
+                } else {
+                    Err(McpError::Internal("sink unavailable".into()))
+                };
+                std::future::ready(result)
+            }
+        });
+        let exporter = meter
+            .clone()
+            .spawn_sink(Duration::from_secs(3600), sink.clone());
+        meter.record("acme", &TenantUsage::default());
+
+        // A failed flush keeps the record for later.
+        meter.queue_records(0, 1);
+        meter.flush(&sink).await;
+        assert_eq!(meter.unsent_records().len(), 1);
+
+        // Shutting down flushes what is left once the sink recovers.
+        healthy.store(true, Ordering::Relaxed);
+        meter.record("globex", &TenantUsage::default());
+        exporter.shutdown().await;
+        let mut tenants = Vec::new();
+        while let Ok(record) = records.try_recv() {
+            tenants.push(record.tenant);
+        }
+        assert_eq!(tenants, ["acme", "globex"]);
+        assert!(meter.unsent_records().is_empty());
+    }
+
+    #[tokio::test]
+    async fn file_sink_appends_json_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("usage.jsonl");
+        let sink = UsageSink::to_file(&path).unwrap();
+        let record = UsageRecord {
+            tenant: "acme".into(),
+            period_start_ms: 0,
+            period_end_ms: 1,
+            usage: TenantUsage::default(),
+        };
+        sink.write(vec![record.clone()]).await.unwrap();
+        let line = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            serde_json::from_str::<UsageRecord>(line.trim()).unwrap(),
+            record
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Up-front checks that every tool in a [`ToolMap`] can actually be invoked.
+
+use std::path::Path;
+
+use wasmtime::Engine;
+use wasmtime::component::Component;
+use wasmtime::component::types::ComponentItem;
+
+use crate::executor::{load_component, verify_digest};
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolRef, ToolSource};
+
+/// Outcome of [`ToolMap::validate`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    /// Number of tools that were checked.
+    pub checked: usize,
+    /// Problems found, in tool map order.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Whether every checked tool resolved cleanly.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A tool whose component cannot be used as configured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationIssue {
+    /// Key of the tool in the map (`namespace/name@version`).
+    pub key: String,
+    pub problem: ValidationProblem,
+}
+
+/// What went wrong while resolving a tool.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationProblem {
+    /// The component could not be read or fetched from its source.
+    Unavailable(String),
+    /// The component bytes do not match the pinned `sha256`.
+    DigestMismatch { expected: String, actual: String },
+    /// The bytes are not a valid WebAssembly component.
+    InvalidComponent(String),
+    /// The component does not export a function named after the tool's `entry`.
+    MissingEntry(String),
+}
+
+impl ToolMap {
+    /// Resolve every enabled tool the way an executor would at invoke time.
+    ///
+    /// Each component is loaded from its source (fetching remote ones into
+    /// `cache_dir`), checked against its digest pin, compiled with `engine`, and
+    /// inspected for its entry export. Pass an executor's
+    /// [`engine`](crate::WasixExecutor::engine) and
+    /// [`cache_dir`](crate::WasixExecutor::cache_dir) to warm its cache. This blocks on
+    /// I/O and compilation, so async callers should run it on a blocking thread.
+    pub fn validate(&self, engine: &Engine, cache_dir: &Path) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        for (key, tool) in self.iter() {
+            report.checked += 1;
+            if let Err(problem) = check_tool(engine, cache_dir, tool) {
+                report.issues.push(ValidationIssue {
+                    key: key.clone(),
+                    problem,
+                });
+            }
+        }
+        report
+    }
+}
+
+fn check_tool(engine: &Engine, cache_dir: &Path, tool: &ToolRef) -> Result<(), ValidationProblem> {
+    let source = tool.source();
+    // Remote MCP tools have no component; their server is checked when mounted.
+    if matches!(source, ToolSource::Mcp(_)) {
+        return Ok(());
+    }
+    let bytes = load_component(tool, &source, cache_dir)
+        .map_err(|err| ValidationProblem::Unavailable(format!("`{source}`: {err}")))?;
+
+    verify_digest(tool, &bytes).map_err(|err| match err {
+        McpError::DigestMismatch {
+            expected, actual, ..
+        } => ValidationProblem::DigestMismatch { expected, actual },
+        other => ValidationProblem::InvalidComponent(other.to_string()),
+    })?;
+
+    let component = Component::from_binary(engine, &bytes)
+        .map_err(|err| ValidationProblem::InvalidComponent(err.to_string()))?;
+    let has_entry = component
+        .component_type()
+        .exports(engine)
+        .any(|(name, item)| name == tool.entry && matches!(item, ComponentItem::ComponentFunc(_)));
+    if has_entry {
+        Ok(())
+    } else {
+        Err(ValidationProblem::MissingEntry(tool.entry.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ToolMapConfig;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn reports_missing_mismatched_and_invalid_components() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let garbage = tmp.path().join("garbage.wasm");
+        std::fs::write(&garbage, b"not wasm").expect("write");
+        let garbage = garbage.to_string_lossy().into_owned();
+
+        let config = ToolMapConfig {
+            tools: vec![
+                ToolRef::new(
+                    "missing",
+                    tmp.path().join("nope.wasm").to_string_lossy(),
+                    "run",
+                ),
+                ToolRef {
+                    sha256: Some("00".repeat(32)),
+                    ..ToolRef::new("pinned", garbage.clone(), "run")
+                },
+                ToolRef {
+                    sha256: Some(hex::encode(Sha256::digest(b"not wasm"))),
+                    ..ToolRef::new("garbage", garbage, "run")
+                },
+            ],
+            ..Default::default()
+        };
+        let map = ToolMap::from_config(&config).expect("map");
+
+        let report = map.validate(&Engine::default(), tmp.path());
+        assert_eq!(report.checked, 3);
+        assert!(!report.is_ok());
+
+        let problems: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.key.as_str(), &issue.problem))
+            .collect();
+        assert!(matches!(
+            problems[0],
+            ("missing", ValidationProblem::Unavailable(_))
+        ));
+        assert!(matches!(
+            problems[1],
+            ("pinned", ValidationProblem::DigestMismatch { .. })
+        ));
+        assert!(matches!(
+            problems[2],
+            ("garbage", ValidationProblem::InvalidComponent(_))
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+//! Hot reload of a [`ToolMap`] backed by a filesystem watcher.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, RwLock};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::tool_map::ToolMap;
+use crate::types::{McpError, ToolRef};
+
+/// Change emitted when the watched configuration is reloaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ToolMapEvent {
+    /// A tool that was not present before has been registered.
+    Added(ToolRef),
+    /// An existing tool changed one or more of its settings.
+    Updated {
+        previous: Box<ToolRef>,
+        current: Box<ToolRef>,
+    },
+    /// A tool disappeared from the configuration.
+    Removed(ToolRef),
+    /// The configuration could not be reloaded; the previous map stays active.
+    ReloadFailed(String),
+}
+
+/// Watches a tool map configuration file and swaps the active [`ToolMap`] on change.
+pub struct ToolMapWatcher {
+    path: PathBuf,
+    current: Arc<RwLock<Arc<ToolMap>>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl ToolMapWatcher {
+    /// Load `path` and start watching it for changes.
+    ///
+    /// Returns the watcher together with the receiving end of the event channel.
+    pub fn new(path: impl Into<PathBuf>) -> Result<(Self, Receiver<ToolMapEvent>), McpError> {
+        let path = path.into();
+        let initial = crate::load_tool_map(&path)?;
+        let current = Arc::new(RwLock::new(Arc::new(initial)));
+        let (tx, rx) = mpsc::channel();
+
+        // Editors usually replace files via rename, so watch the parent directory
+        // and filter events down to the configuration file itself.
+        let parent = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+        let file_name = path.file_name().map(|name| name.to_os_string());
+
+        let reload_path = path.clone();
+        let reload_state = current.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(err) => {
+                    let _ = tx.send(ToolMapEvent::ReloadFailed(err.to_string()));
+                    return;
+                }
+            };
+            if !is_relevant(&event, file_name.as_deref()) {
+                return;
+            }
+            reload(&reload_path, &reload_state, &tx);
+        })
+        .map_err(|err| McpError::Internal(format!("failed to create watcher: {err}")))?;
+
+        watcher
+            .watch(&parent, RecursiveMode::NonRecursive)
+            .map_err(|err| {
+                McpError::Internal(format!("failed to watch `{}`: {err}", parent.display()))
+            })?;
+
+        Ok((
+            Self {
+                path,
+                current,
+                _watcher: watcher,
+            },
+            rx,
+        ))
+    }
+
+    /// Path of the watched configuration file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Snapshot of the currently active tool map.
+    pub fn current(&self) -> Arc<ToolMap> {
+        self.current.read().expect("tool map lock poisoned").clone()
+    }
+}
+
+fn is_relevant(event: &Event, file_name: Option<&std::ffi::OsStr>) -> bool {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+    event.paths.iter().any(|path| path.file_name() == file_name)
+}
+
+fn reload(path: &Path, state: &RwLock<Arc<ToolMap>>, tx: &Sender<ToolMapEvent>) {
+    let next = match crate::load_tool_map(path) {
+        Ok(map) => map,
+        Err(err) => {
+            tracing::warn!(path = %path.display(), %err, "tool map reload failed");
+            let _ = tx.send(ToolMapEvent::ReloadFailed(err.to_string()));
+            return;
+        }
+    };
+
+    let events = {
+        let mut guard = state.write().expect("tool map lock poisoned");
+        let events = diff_events(&guard, &next);
+        *guard = Arc::new(next);
+        events
+    };
+
+    if !events.is_empty() {
+        tracing::info!(path = %path.display(), changes = events.len(), "tool map reloaded");
+    }
+    for event in events {
+        let _ = tx.send(event);
+    }
+}
+
+fn diff_events(previous: &ToolMap, current: &ToolMap) -> Vec<ToolMapEvent> {
+    let diff = previous.diff(current);
+    let added = diff.added.into_iter().map(ToolMapEvent::Added);
+    let updated = diff
+        .changed
+        .into_iter()
+        .map(|change| ToolMapEvent::Updated {
+            previous: Box::new(change.previous),
+            current: Box::new(change.current),
+        });
+    let removed = diff.removed.into_iter().map(ToolMapEvent::Removed);
+    added.chain(updated).chain(removed).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write_config(path: &Path, tools: &[(&str, u64)]) {
+        let entries = tools
+            .iter()
+            .map(|(name, timeout)| {
+                format!(
+                    r#"{{"name":"{name}","component":"./{name}.wasm","entry":"tool_invoke","timeout_ms":{timeout}}}"#
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        std::fs::write(path, format!(r#"{{"tools":[{entries}]}}"#)).expect("write config");
+    }
+
+    fn next_change(rx: &Receiver<ToolMapEvent>) -> ToolMapEvent {
+        loop {
+            let event = rx
+                .recv_timeout(Duration::from_secs(5))
+                .expect("watcher event");
+            if !matches!(event, ToolMapEvent::ReloadFailed(_)) {
+                return event;
+            }
+        }
+    }
+
+    #[test]
+    fn reload_swaps_map_and_emits_events() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let path = tmp.path().join("toolmap.json");
+        write_config(&path, &[("echo", 100), ("old", 100)]);
+
+        let (watcher, rx) = ToolMapWatcher::new(&path).expect("watcher");
+        assert!(watcher.current().get("old").is_ok());
+
+        write_config(&path, &[("echo", 200), ("new", 100)]);
+
+        let mut seen = Vec::new();
+        while seen.len() < 3 {
+            seen.push(next_change(&rx));
+        }
+
+        assert!(seen.iter().any(|event| matches!(
+            event,
+            ToolMapEvent::Updated { current, .. } if current.timeout_ms == Some(200)
+        )));
+        assert!(
+            seen.iter()
+                .any(|event| matches!(event, ToolMapEvent::Added(tool) if tool.name == "new"))
+        );
+        assert!(
+            seen.iter()
+                .any(|event| matches!(event, ToolMapEvent::Removed(tool) if tool.name == "old"))
+        );
+
+        let current = watcher.current();
+        assert!(current.get("new").is_ok());
+        assert!(current.get("old").is_err());
+    }
+}
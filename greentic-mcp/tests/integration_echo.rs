@@ -1,14 +1,19 @@
-use greentic_mcp::{TestBackend, exec_test_backend, exec_with_retries_backend};
-use mcp_exec::{ExecConfig, ExecRequest, RuntimePolicy, ToolStore, VerifyPolicy};
-use serde_json::json;
+use greentic_mcp::{
+    FlakyEcho, NativeTools, TestBackend, exec_test_backend, exec_with_retries_backend,
+};
+use mcp_exec::{ExecConfig, ExecError, ExecRequest, RuntimePolicy, ToolStore, VerifyPolicy};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Duration;
 use tempfile::tempdir;
 
-fn default_runtime_policy() -> RuntimePolicy {
+/// Policy making up to `max_attempts` attempts, 1ms apart.
+fn runtime_policy(max_attempts: u32) -> RuntimePolicy {
     RuntimePolicy {
         per_call_timeout: Duration::from_secs(10),
-        max_attempts: 1,
-        base_backoff: Duration::from_millis(50),
+        max_attempts,
+        base_backoff: Duration::from_millis(1),
         ..RuntimePolicy::default()
     }
 }
@@ -19,14 +24,49 @@ fn test_exec_config(runtime: RuntimePolicy) -> (ExecConfig, tempfile::TempDir) {
         store: ToolStore::LocalDir(dir.path().into()),
         security: VerifyPolicy::default(),
         runtime,
+        tenant_runtime: Default::default(),
         http_enabled: false,
     };
     (cfg, dir)
 }
 
+fn request(component: &str, args: Value) -> ExecRequest {
+    ExecRequest {
+        component: component.into(),
+        action: "tool-invoke".into(),
+        args,
+        tenant: None,
+        correlation_id: None,
+        identity: None,
+    }
+}
+
+fn tool_error(component: &str, code: &str) -> ExecError {
+    ExecError::tool_error(component, "tool-invoke", code, json!({}))
+}
+
+/// Backend answering with `respond(call, req)`, where `call` counts from 0, and
+/// the counter of calls made so far. Clones share the counter.
+fn counted<F>(
+    respond: F,
+) -> (
+    Arc<AtomicU32>,
+    impl Fn(ExecRequest, &ExecConfig) -> Result<Value, ExecError> + Clone + Send + Sync + 'static,
+)
+where
+    F: Fn(u32, ExecRequest) -> Result<Value, ExecError> + Clone + Send + Sync + 'static,
+{
+    let calls = Arc::new(AtomicU32::new(0));
+    let counter = calls.clone();
+    let backend = move |req: ExecRequest, _: &ExecConfig| {
+        respond(counter.fetch_add(1, Ordering::SeqCst), req)
+    };
+    (calls, backend)
+}
+
 #[tokio::test]
 async fn echo_ok() {
-    let (cfg, _tmp) = test_exec_config(default_runtime_policy());
+    let (cfg, _tmp) = test_exec_config(runtime_policy(1));
     let result = exec_test_backend(TestBackend::NativeEcho, json!({"hello": "world"}), &cfg)
         .expect("tool success");
 
@@ -35,19 +75,20 @@ async fn echo_ok() {
 
 #[tokio::test]
 async fn echo_timeout() {
-    let mut runtime = default_runtime_policy();
-    runtime.per_call_timeout = Duration::from_millis(200);
-    let (cfg, _tmp) = test_exec_config(runtime);
+    let (cfg, _tmp) = test_exec_config(RuntimePolicy {
+        per_call_timeout: Duration::from_millis(200),
+        ..runtime_policy(1)
+    });
 
     let err = exec_test_backend(
-        TestBackend::NativeTimeout(Duration::from_millis(400)),
+        TestBackend::NativeSlow(Duration::from_millis(400)),
         json!({"sleep_ms": 500, "note": "slow"}),
         &cfg,
     )
     .expect_err("should timeout");
 
     match err {
-        mcp_exec::ExecError::Runner { source, .. } => match source {
+        ExecError::Runner { source, .. } => match source {
             mcp_exec::RunnerError::Timeout { .. } => {}
             other => panic!("expected timeout error, got {other:?}"),
         },
@@ -57,24 +98,256 @@ async fn echo_timeout() {
 
 #[tokio::test]
 async fn echo_transient_retries() {
-    let mut runtime = default_runtime_policy();
-    runtime.per_call_timeout = Duration::from_secs(3);
-    runtime.max_attempts = 5;
-    runtime.base_backoff = Duration::from_millis(50);
-    let (cfg, _tmp) = test_exec_config(runtime);
+    let (cfg, _tmp) = test_exec_config(runtime_policy(5));
+    let req = request("echo-flaky", json!({"flaky": true, "message": "hello"}));
 
-    let req = ExecRequest {
-        component: "echo-flaky".into(),
-        action: "tool-invoke".into(),
-        args: json!({"flaky": true, "message": "hello"}),
-        tenant: None,
-    };
-
-    let result = exec_with_retries_backend(req, &cfg, |req, cfg| {
-        exec_test_backend(TestBackend::NativeFlaky, req.args, cfg)
+    let flaky = Arc::new(FlakyEcho::new(2));
+    let attempts = flaky.clone();
+    let result = exec_with_retries_backend(req, &cfg, move |req, cfg| {
+        exec_test_backend(TestBackend::NativeFlaky(flaky.clone()), req.args, cfg)
     })
     .await
     .expect("flaky tool should eventually succeed");
 
     assert_eq!(result, json!({"flaky": true, "message": "hello"}));
+    assert_eq!(attempts.calls(), 3);
+}
+
+#[tokio::test]
+async fn retries_stop_at_the_retry_deadline() {
+    let (cfg, _tmp) = test_exec_config(RuntimePolicy {
+        base_backoff: Duration::from_millis(50),
+        retry_policy: mcp_exec::BackoffStrategy::Fixed.into(),
+        max_retry_duration: Some(Duration::from_millis(120)),
+        ..runtime_policy(10)
+    });
+
+    let (calls, backend) = counted(|_, _| Err(tool_error("echo-flaky", "transient.unavailable")));
+    let err = exec_with_retries_backend(request("echo-flaky", json!({})), &cfg, backend)
+        .await
+        .expect_err("the retry deadline should stop retries");
+
+    match err {
+        ExecError::DeadlineExceeded { last, .. } => {
+            assert!(matches!(*last, ExecError::Tool { .. }));
+        }
+        other => panic!("expected deadline exceeded, got {other:?}"),
+    }
+    let calls = calls.load(Ordering::SeqCst);
+    assert!((2..10).contains(&calls), "made {calls} calls");
+}
+
+#[tokio::test]
+async fn shared_retry_budget_limits_retries_across_calls() {
+    let (cfg, _tmp) = test_exec_config(RuntimePolicy {
+        retry_budget: Some(Arc::new(mcp_exec::RetryBudget::new(0.0, 2))),
+        ..runtime_policy(3)
+    });
+
+    let (calls, backend) = counted(|_, _| Err(tool_error("echo-flaky", "transient.unavailable")));
+    for _ in 0..3 {
+        exec_with_retries_backend(request("echo-flaky", json!({})), &cfg, backend.clone())
+            .await
+            .expect_err("tool always fails");
+    }
+
+    // Three first attempts plus the two retries the budget allows.
+    assert_eq!(calls.load(Ordering::SeqCst), 5);
+}
+
+#[tokio::test]
+async fn custom_classifier_retries_integrator_codes() {
+    let (cfg, _tmp) = test_exec_config(RuntimePolicy {
+        retry_classifier: mcp_exec::RetryClassifier::new().retry_code("rate_limited"),
+        ..runtime_policy(3)
+    });
+
+    let (calls, backend) = counted(|call, req| match call {
+        0 => Err(tool_error("quota", "rate_limited")),
+        _ => Ok(req.args),
+    });
+    let result = exec_with_retries_backend(request("quota", json!({"ok": true})), &cfg, backend)
+        .await
+        .expect("second attempt succeeds");
+
+    assert_eq!(result, json!({"ok": true}));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn retry_observer_sees_attempt_lifecycle() {
+    use mcp_exec::{GiveUpReason, RetryEvent, RetryObserver};
+    use std::sync::Mutex;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = events.clone();
+    let (cfg, _tmp) = test_exec_config(RuntimePolicy {
+        retry_observer: Some(RetryObserver::new(move |event| {
+            sink.lock().unwrap().push(event.clone());
+        })),
+        ..runtime_policy(2)
+    });
+
+    exec_with_retries_backend(request("down", json!({})), &cfg, |_, _| {
+        Err(tool_error("down", "transient.unavailable"))
+    })
+    .await
+    .expect_err("tool always fails");
+
+    let events = events.lock().unwrap();
+    assert!(matches!(
+        events[1],
+        RetryEvent::AttemptFailed { ref class, retryable: true, .. } if class == "transient.unavailable"
+    ));
+    assert!(matches!(
+        events[2],
+        RetryEvent::BackingOff { attempt: 1, .. }
+    ));
+    assert!(matches!(
+        events.last(),
+        Some(RetryEvent::GaveUp {
+            attempts: 2,
+            reason: GiveUpReason::AttemptsExhausted,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn idempotency_key_is_stable_across_retries() {
+    use std::sync::Mutex;
+
+    let (cfg, _tmp) = test_exec_config(RuntimePolicy {
+        inject_idempotency_key: true,
+        ..runtime_policy(3)
+    });
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    let req = ExecRequest {
+        action: "charge".into(),
+        ..request("payments", json!({"amount": 5}))
+    };
+    let result = exec_with_retries_backend(req, &cfg, move |req, _| {
+        let mut seen = sink.lock().unwrap();
+        seen.push(req.args["_meta"]["idempotency_key"].clone());
+        if seen.len() < 3 {
+            Err(ExecError::tool_error(
+                "payments",
+                "charge",
+                "transient.gateway",
+                json!({}),
+            ))
+        } else {
+            Ok(json!({"charged": true}))
+        }
+    })
+    .await
+    .expect("third attempt succeeds");
+
+    assert_eq!(result, json!({"charged": true}));
+    let seen = seen.lock().unwrap();
+    assert!(seen[0].is_string());
+    assert!(seen.iter().all(|key| *key == seen[0]));
+}
+
+#[tokio::test]
+async fn total_timeout_bounds_all_attempts() {
+    let (cfg, _tmp) = test_exec_config(RuntimePolicy {
+        retry_policy: mcp_exec::BackoffStrategy::Fixed.into(),
+        total_timeout: Some(Duration::from_millis(150)),
+        ..runtime_policy(10)
+    });
+
+    // The first attempt fails fast, so the retry deadline lets the second one
+    // start; it is still running when the total timeout elapses.
+    let (calls, backend) = counted(|call, _| {
+        if call > 0 {
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        Err(tool_error("slow", "transient.busy"))
+    });
+    let started = std::time::Instant::now();
+    let err = exec_with_retries_backend(request("slow", json!({})), &cfg, backend)
+        .await
+        .expect_err("total timeout should stop the retries");
+
+    let elapsed = started.elapsed();
+    assert!(
+        (Duration::from_millis(150)..Duration::from_millis(500)).contains(&elapsed),
+        "{elapsed:?}"
+    );
+    assert!(
+        matches!(
+            err,
+            ExecError::Runner {
+                source: mcp_exec::RunnerError::TotalTimeout { elapsed },
+                ..
+            } if elapsed == Duration::from_millis(150)
+        ),
+        "{err:?}"
+    );
+    assert_eq!(err.code(), mcp_exec::ErrorCode::RunnerTotalTimeout);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn registered_closures_run_as_tools() {
+    let (cfg, _tmp) = test_exec_config(runtime_policy(3));
+
+    let calls = AtomicU32::new(0);
+    let tools = NativeTools::new()
+        .with_tool("double", |args| {
+            let n = args["n"].as_i64().ok_or("invalid.n")?;
+            Ok(json!(n * 2))
+        })
+        .with_tool("warming-up", move |args| {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err("transient.cold".into()),
+                _ => Ok(args),
+            }
+        });
+    let map = tools.tool_map().expect("tool map");
+    assert_eq!(map.get("double").expect("registered").component, "double");
+
+    let doubled =
+        exec_with_retries_backend(request("double", json!({"n": 21})), &cfg, tools.backend())
+            .await
+            .expect("double succeeds");
+    assert_eq!(doubled, json!(42));
+    let warmed =
+        exec_with_retries_backend(request("warming-up", json!("hi")), &cfg, tools.backend())
+            .await
+            .expect("retried past the transient error");
+    assert_eq!(warmed, json!("hi"));
+
+    let err = exec_with_retries_backend(request("double", json!({})), &cfg, tools.backend())
+        .await
+        .expect_err("invalid input");
+    assert_eq!(err.code().to_string(), "tool.invalid.n");
+    let result = exec_test_backend(
+        TestBackend::Native(tools.get("double").expect("registered").clone()),
+        json!({"n": 2}),
+        &cfg,
+    );
+    assert_eq!(result.expect("native backend"), json!(4));
+}
+
+#[tokio::test]
+async fn simulated_tools_return_oversized_and_invalid_output() {
+    let (cfg, _tmp) = test_exec_config(runtime_policy(1));
+
+    let output = exec_test_backend(TestBackend::NativeOversized(1 << 20), json!({}), &cfg)
+        .expect("oversized output");
+    assert_eq!(output["data"].as_str().map(str::len), Some(1 << 20));
+
+    let err = exec_test_backend(TestBackend::NativeInvalidJson, json!({}), &cfg)
+        .expect_err("invalid json");
+    assert!(matches!(
+        err,
+        ExecError::Runner {
+            source: mcp_exec::RunnerError::Serde(_),
+            ..
+        }
+    ));
 }